@@ -6,22 +6,19 @@ extern crate nalgebra;
 extern crate nalgebra_glm;
 extern crate image;
 extern crate meshterial;
-//extern crate collada;
 
-use collada::document::ColladaDocument;
-use collada::PrimitiveElement;
 use vulkano::instance::PhysicalDevice;
 use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use nalgebra::Matrix4;
-use nalgebra_glm as glm;
 use std::time::{Duration, Instant};
 use std::path::Path;
 use std::collections::HashMap;
 //use xml;
 
 use meshterial::*;
+use meshterial::loaders::collada;
 use meshterial::utils::*;
 //use renderer::pipelines::color3d::*;
 use meshterial::pipelines::phong::*;
@@ -29,9 +26,9 @@ use meshterial::pipelines::phong::*;
 
 fn main() {
   let mut vkr = VkRenderer::new();
-  vkr
-    .window.set_title("Vulkan Renderer Demo")
-    .expect("Could not set window title.");
+  vkr.set_title("Vulkan Renderer Demo");
+  // Save power while the window is in the background.
+  vkr.set_unfocused_fps_cap(Some(10));
 
   // Before we draw we have to create what is called a pipeline. This is similar to an OpenGL
   // program, but much more specific.
@@ -41,146 +38,73 @@ fn main() {
     .next()
     .expect("no physical device available");
 
-  let doc = ColladaDocument::from_path(Path::new("assets/test.dae"))
+  let model = collada::load(Path::new("assets/test.dae"))
     .expect("Could not load collada file.");
 
-  let eff_lib = doc.get_effect_library();
+  println!("Materials in library: {:#?}", model.materials.keys());
 
-  let mats_to_effs = doc.get_material_to_effect();
+  let stats = model.stats();
+  println!("Loaded model: {}", stats);
 
-  let mut material_vertex_buffers:HashMap<String, Vec<VertexPhong>> = HashMap::new();
-
-  println!("Materials in library: {:#?}", eff_lib.keys());
-
-  if let Some(obj_set) = doc.get_obj_set() {
-    obj_set
-      .objects
-      .iter()
-      .for_each(|obj| {
-        println!("Object name: {}", obj.name);
-        obj
-          .geometry
-          .iter()
-          .for_each(|geom| {
-            geom
-              .mesh
-              .iter()
-              .for_each(|prim| {
-                match prim {
-                  PrimitiveElement::Polylist(_) => {}
-                  PrimitiveElement::Triangles(triangles) => {
-                    let material = triangles
-                      .material
-                      .as_ref()
-                      .expect("No material!")
-                      .clone();
-                    let eff = mats_to_effs.get(&material)
-                      .expect("Could not find material effect");
-
-                    if !material_vertex_buffers.contains_key(eff) {
-                      // Make a new entry!
-                      material_vertex_buffers.insert(eff.clone(), vec![]);
-                    }
-
-                    let buffer = material_vertex_buffers.get_mut(eff)
-                      .expect("This should never happen.");
-
-                    triangles
-                      .vertices
-                      .iter()
-                      .for_each(|(a, b, c)| {
-                        // Add the values pointed to by the indices
-                        // into the vertex buffer
-                        for (vndx, _, may_nndx) in [a, b, c].iter() {
-                          let nndx = may_nndx
-                            .expect("vertex is missing a normal");
-                          let p = obj
-                            .vertices
-                            .get(*vndx)
-                            .expect(&format!("could not get vertex at ndx {}", vndx));
-                          let n = obj
-                            .normals
-                            .get(nndx)
-                            .expect(&format!("could not get vertex at ndx {}", vndx));
-                          buffer.push(VertexPhong{
-                            position: [p.x as f32, p.y as f32, p.z as f32],
-                            normal: [n.x as f32, n.y as f32, n.z as f32]
-                          });
-                        }
-                      });
-                  }
-                }
-              })
-          });
-      });
-  }
+  // Frame whatever was loaded, from the direction the default camera looks.
+  let scene_bounds = stats.bounds.clone();
+  let mut camera = Camera::default();
 
   let mut material_buffers = vec![];
-  for (eff, vertices) in material_vertex_buffers {
+  for (eff, vertices) in model.vertices {
     let buffer = CpuAccessibleBuffer::from_iter(vkr.device.clone(), BufferUsage::all(), vertices.iter().cloned())
       .expect("Could not create material vertex buffer.");
     material_buffers.push((eff, buffer));
   };
 
   // Create the pipeline
-  let phong_pipeline = PhongPipeline::new(
+  let mut phong_pipeline = PhongPipeline::new(
     vkr.render_pass.clone(),
     vkr.device.clone(),
     physical.clone(),
-    eff_lib.keys().cloned().collect()
+    model.materials.keys().cloned().collect()
   );
-
-  // Set the material uniforms on the pipeline.
-  for (name, tech) in eff_lib.iter() {
-    let uniform = phong_pipeline
-      .materials
-      .get(name)
-      .expect(&format!("Could not get material {}", name));
-
-    let material = Material {
-      emission: tech.emission,
-      ambient: tech.ambient,
-      diffuse: tech.diffuse,
-      specular: tech.specular,
-      shininess: tech.shininess
-    };
-
-    let src_uniform = phong_pipeline
-      .material_buffer_pool
-      .next(material)
-      .expect("Could not load material into cpu buffer");
-
-    let dest_uniform = uniform.device_buffer.clone();
-
-    vkr.with_command_builder(|cmds| {
-      cmds
-        .copy_buffer(src_uniform.clone(), dest_uniform)
-        .expect("Could not copy material uniform to device buffer.")
-    });
+  // Draw the renderer's fog, which is off until `vkr.set_fog` is called.
+  phong_pipeline.use_fog(&vkr);
+  // Cut the scene with the renderer's clip plane, which is off until
+  // `vkr.set_clip_plane` is called. Press C to toggle a cutaway.
+  phong_pipeline.use_clip_plane(&vkr);
+
+  // Set the material uniforms on the pipeline, keeping them around so the
+  // inspector can highlight one at a time.
+  let mut material_names:Vec<String> = model.materials.keys().cloned().collect();
+  material_names.sort();
+  let materials:HashMap<String, Material> = model.materials;
+  for (name, material) in materials.iter() {
+    phong_pipeline.set_material(&mut vkr, name, *material);
   }
 
   // Set the light uniform on the pipeline.
-  {
-    let light = Light {
-      position: [0.0, 100.0, 0.0],
-      _dummy0: [0, 0, 0, 0],
-      intensity: [1.0, 1.0, 1.0],
-    };
-    let src = phong_pipeline
-      .light_buffer_pool
-      .next(light)
-      .expect("Could not load light into cpu buffer.");
-    let dest = phong_pipeline.light.device_buffer.clone();
-    vkr.with_command_builder(|cmds| {
-      cmds
-        .copy_buffer(src.clone(), dest)
-        .expect("Could not copy light uniform to device.")
-    });
+  let mut light = Light {
+    position: [0.0, 100.0, 0.0],
+    _dummy0: [0, 0, 0, 0],
+    intensity: [1.0, 1.0, 1.0],
+  };
+  phong_pipeline.set_light(&mut vkr, light);
+
+  // The inspector's state. Arrow keys move the light over the scene and
+  // PageUp/PageDown raise and lower it, Tab cycles which material is
+  // highlighted and W toggles wireframe.
+  let mut input = InputState::new();
+  let light_speed = scene_bounds
+    .as_ref()
+    .map(|bounds| bounds.half_extents().norm())
+    .unwrap_or(100.0);
+  let mut highlighted:Option<usize> = None;
+  let mut wireframe = false;
+  if phong_pipeline.wireframe_pipeline.is_none() {
+    println!("Wireframe is not supported by this device.");
   }
 
   let mut fps = FPSCounter::new();
   let print_at = Duration::from_secs(2);
   let mut last_instant = Instant::now();
+  let mut last_frame = Instant::now();
 
   // Initialization is finally finished!
 
@@ -207,13 +131,11 @@ fn main() {
         //  1.0
         //);
 
-        let aspect = vkr.dimensions[0] as f32 / vkr.dimensions[1] as f32;
-        let projection3d:Matrix4<f32> = Matrix4::new_perspective(
-          aspect,
-          std::f32::consts::FRAC_PI_2,
-          0.01,
-          100.0
-        );
+        camera.set_dimensions(vkr.dimensions);
+        if let Some(bounds) = scene_bounds.as_ref() {
+          camera.frame_bounds(bounds);
+        }
+        let projection3d:Matrix4<f32> = camera.projection();
 
         //let src_buffer2d = vkr
         //  .proj_buffer_pool
@@ -227,38 +149,48 @@ fn main() {
 
         let phong_dest_buffer = phong_pipeline.proj.device_buffer.clone();
 
-        vkr.with_command_builder(|cmds| {
-          cmds
-            .copy_buffer(src_buffer3d.clone(), phong_dest_buffer)
-            .expect("Could not copy color3d projection uniform cpu buffer to the gpu.")
-        });
+        vkr.copy_buffer(src_buffer3d.clone(), phong_dest_buffer);
         // In the future we may also want to do this:
         // https://github.com/vulkano-rs/vulkano-examples/blob/master/src/bin/teapot/main.rs#L265
       }
 
+      // Move the light with the held keys, before the render pass begins.
+      let now = Instant::now();
+      let elapsed = now.duration_since(last_frame);
+      last_frame = now;
+      let step = light_speed * (elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9);
+      let moves = [
+        (Keycode::Left, 0, -step),
+        (Keycode::Right, 0, step),
+        (Keycode::Up, 2, -step),
+        (Keycode::Down, 2, step),
+        (Keycode::PageUp, 1, step),
+        (Keycode::PageDown, 1, -step),
+      ];
+      let mut light_moved = false;
+      for (keycode, axis, delta) in moves.iter() {
+        if input.is_key_down(*keycode) {
+          light.position[*axis] += *delta;
+          light_moved = true;
+        }
+      }
+      if light_moved {
+        phong_pipeline.set_light(&mut vkr, light);
+      }
+
       vkr.begin_rendering();
       // We are now inside the first subpass of the render pass. We can submit
       // draw commands.
 
       let model:Matrix4<f32> = Matrix4::identity();
-      let view:Matrix4<f32> = glm::look_at(
-        &glm::vec3(3.0, 3.0, 4.0),
-        &glm::vec3(0.0, 0.0, 0.0),
-        &glm::vec3(0.0, 1.0, 0.0)
-      );
-      let modelview = model * view;
-      let normal = modelview
-        .pseudo_inverse(1e-10)
-        .transpose();
+      let view:Matrix4<f32> = camera.view();
+      let modelviewnormal = vs::ty::ModelViewNormal::new(&model, &view);
 
-      let mut modelviewnormal = meshterial::pipelines::phong::vs::ty::ModelViewNormal {
-        model: model.into(),
-        view: view.into(),
-        normal: normal.into(),
+      let pipeline = if wireframe {
+        phong_pipeline.wireframe_pipeline.clone().unwrap_or_else(|| phong_pipeline.pipeline.clone())
+      } else {
+        phong_pipeline.pipeline.clone()
       };
-      let mut model:Matrix4<f32> = Matrix4::identity();
-
-      let dynamic_state = vkr.dynamic_state.clone();
       for (eff, buffer) in &material_buffers {
         let eff:&String = eff;
         let material_set = &phong_pipeline
@@ -266,36 +198,85 @@ fn main() {
           .get(eff)
           .expect("Could not find effect in pipeline materials list.")
           .desc_set;
-        vkr.with_command_builder(|cmds| {
-          cmds
-            .draw(
-              phong_pipeline.pipeline.clone(),
-              &dynamic_state,
-              vec!(buffer.clone()),
-              (phong_pipeline.proj.desc_set.clone(), material_set.clone(), phong_pipeline.light.desc_set.clone()),
-              modelviewnormal
-            ).expect("Could not draw 3d geometry in the main loop.")
-        });
+        vkr.draw(
+          pipeline.clone(),
+          buffer.clone(),
+          (phong_pipeline.proj.desc_set.clone(), material_set.clone(), phong_pipeline.light.desc_set.clone()),
+          modelviewnormal
+        );
       }
       vkr.commit_rendering();
     }
 
     // Handling the window events in order to close the program when the user wants to close
     // it.
+    input.begin_frame();
     for event in event_pump.poll_iter() {
+      vkr.handle_event(&event);
+      input.handle_event(&event);
       match event {
         Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
           break 'mainloop;
         },
+        Event::KeyDown { keycode: Some(Keycode::C), repeat: false, .. } => {
+          // Remove the top half of the scene.
+          let cutaway = scene_bounds
+            .as_ref()
+            .map(|bounds| [0.0, -1.0, 0.0, bounds.center().y]);
+          let plane = if vkr.clip_plane().is_some() { None } else { cutaway };
+          vkr.set_clip_plane(plane);
+        },
         _ => {}
       }
     }
 
+    let mut inspector_changed = false;
+    if input.was_key_pressed(Keycode::Tab) && !material_names.is_empty() {
+      // Restore the last highlighted material and highlight the next one, or
+      // none after the last.
+      if let Some(index) = highlighted {
+        let name = &material_names[index];
+        phong_pipeline.set_material(&mut vkr, name, materials[name]);
+      }
+      highlighted = match highlighted {
+        None => Some(0),
+        Some(index) if index + 1 < material_names.len() => Some(index + 1),
+        Some(_) => None,
+      };
+      if let Some(index) = highlighted {
+        let name = &material_names[index];
+        let material = Material {
+          emission: [1.0, 0.0, 1.0, 1.0],
+          .. materials[name]
+        };
+        phong_pipeline.set_material(&mut vkr, name, material);
+      }
+      inspector_changed = true;
+    }
+    if input.was_key_pressed(Keycode::W) && phong_pipeline.wireframe_pipeline.is_some() {
+      wireframe = !wireframe;
+      inspector_changed = true;
+    }
+    let light_keys = [Keycode::Left, Keycode::Right, Keycode::Up, Keycode::Down, Keycode::PageUp, Keycode::PageDown];
+    if light_keys.iter().any(|keycode| input.was_key_pressed(*keycode)) {
+      inspector_changed = true;
+    }
+    if inspector_changed {
+      println!(
+        "Light at [{:.1}, {:.1}, {:.1}], highlighting {}, wireframe {}",
+        light.position[0],
+        light.position[1],
+        light.position[2],
+        highlighted.map(|index| material_names[index].as_str()).unwrap_or("nothing"),
+        if wireframe { "on" } else { "off" }
+      );
+    }
+
     fps.next_frame();
     let this_instant = Instant::now();
     if this_instant.duration_since(last_instant) >= print_at {
       last_instant = this_instant;
-      println!("FPS:{:.2}", fps.current_fps());
+      println!("FPS:{:.2} {}", fps.current_fps(), vkr.last_frame_stats());
     }
   }
 }
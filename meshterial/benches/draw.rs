@@ -0,0 +1,297 @@
+//! Draw call, overdraw and uniform upload throughput, rendered offscreen so
+//! that these run without a window.
+//!
+//! ```text
+//! cargo bench --bench draw
+//! ```
+//!
+//! Each iteration records, submits and waits for one command buffer, so the
+//! numbers are whole frames. Without a vulkan device nothing is measured.
+#[macro_use]
+extern crate criterion;
+#[macro_use]
+extern crate vulkano;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use criterion::Criterion;
+use nalgebra::{Matrix4, Vector3};
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+
+use meshterial::pipelines::depth::DepthPipeline;
+use meshterial::pipelines::phong::{vs, Light, PhongPipelineConfig, VertexPhong};
+use meshterial::pipelines::unlit;
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+use common::{cube, cube_grid, Headless, PhongScene};
+
+
+const DIMENSIONS: [u32; 2] = [256, 256];
+
+/// The number of cubes drawn, or uniforms uploaded, per frame.
+const COUNTS: [usize; 3] = [1, 64, 1024];
+
+/// The number of screen covering layers drawn over each other per frame in
+/// the overdraw benchmarks.
+const LAYERS: [usize; 3] = [1, 8, 32];
+
+/// How far the camera is from the nearest layer.
+const LAYER_DISTANCE: f32 = 2.0;
+
+
+/// A phong scene with the camera far enough back to see a grid of `count`
+/// cubes.
+fn scene(headless: &Headless, count: usize) -> PhongScene {
+  PhongScene::new(headless, (count as f32).sqrt() * 2.0 + 2.0)
+}
+
+
+/// Every cube drawn with its own draw call and push constants.
+fn bench_individual_draws(c: &mut Criterion, headless: &Rc<Headless>) {
+  for &count in COUNTS.iter() {
+    let headless = headless.clone();
+    let scene = scene(&headless, count);
+    let models: Vec<Matrix4<f32>> = cube_grid(count)
+      .iter()
+      .map(Matrix4::new_translation)
+      .collect();
+    c.bench_function(&format!("phong draws/individual/{}", count), move |b| {
+      b.iter(|| {
+        let dynamic_state = headless.dynamic_state();
+        let mut cmds = headless.begin([0.0, 0.0, 0.0, 1.0]);
+        for model in models.iter() {
+          cmds = cmds
+            .draw(
+              scene.pipeline.pipeline.clone(),
+              &dynamic_state,
+              vec![scene.cube.clone() as Arc<BufferAccess + Send + Sync>],
+              scene.sets.clone(),
+              vs::ty::ModelViewNormal::new(model, &Matrix4::identity())
+            ).expect("Could not draw cube.");
+        }
+        let command_buffer = cmds
+          .end_render_pass().expect("Could not end render pass.")
+          .build().expect("Could not build command buffer.");
+        headless.execute(command_buffer);
+      })
+    });
+  }
+}
+
+
+/// The same cubes transformed on the CPU into one vertex buffer and drawn
+/// with a single draw call, which is the best case any instancing or batching
+/// can reach.
+fn bench_batched_draws(c: &mut Criterion, headless: &Rc<Headless>) {
+  for &count in COUNTS.iter() {
+    let headless = headless.clone();
+    let scene = scene(&headless, count);
+    let unit_cube = cube();
+    let mut vertices: Vec<VertexPhong> = vec![];
+    for position in cube_grid(count) {
+      vertices.extend(unit_cube.iter().map(|v| VertexPhong {
+        position: [
+          v.position[0] + position.x,
+          v.position[1] + position.y,
+          v.position[2] + position.z,
+        ],
+        normal: v.normal,
+      }));
+    }
+    let batch = CpuAccessibleBuffer::from_iter(
+      headless.device.clone(),
+      BufferUsage::all(),
+      vertices.into_iter()
+    ).expect("Could not create batched vertex buffer.");
+    c.bench_function(&format!("phong draws/batched/{}", count), move |b| {
+      b.iter(|| {
+        let dynamic_state = headless.dynamic_state();
+        let command_buffer = headless
+          .begin([0.0, 0.0, 0.0, 1.0])
+          .draw(
+            scene.pipeline.pipeline.clone(),
+            &dynamic_state,
+            vec![batch.clone() as Arc<BufferAccess + Send + Sync>],
+            scene.sets.clone(),
+            vs::ty::ModelViewNormal::new(&Matrix4::identity(), &Matrix4::identity())
+          ).expect("Could not draw batched cubes.")
+          .end_render_pass().expect("Could not end render pass.")
+          .build().expect("Could not build command buffer.");
+        headless.execute(command_buffer);
+      })
+    });
+  }
+}
+
+
+/// `count` flattened cubes that each cover the whole image, from the farthest
+/// to the nearest, so that every layer passes the depth test and is shaded
+/// over the one before it.
+fn layers(count: usize) -> Vec<Matrix4<f32>> {
+  (0 .. count)
+    .rev()
+    .map(|i| {
+      Matrix4::new_translation(&Vector3::new(0.0, 0.0, -(i as f32) * 0.25))
+        * Matrix4::new_nonuniform_scaling(&Vector3::new(8.0, 8.0, 0.01))
+    })
+    .collect()
+}
+
+
+/// Layers shaded with phong directly, and with a depth prepass first so that
+/// phong only shades the nearest layer.
+fn bench_depth_prepass(c: &mut Criterion, headless: &Rc<Headless>) {
+  for &count in LAYERS.iter() {
+    let direct_headless = headless.clone();
+    let direct = PhongScene::new(headless, LAYER_DISTANCE);
+    let direct_models = layers(count);
+    c.bench_function(&format!("overdraw/direct/{}", count), move |b| {
+      b.iter(|| {
+        let headless = &direct_headless;
+        let dynamic_state = headless.dynamic_state();
+        let mut cmds = headless.begin([0.0, 0.0, 0.0, 1.0]);
+        for model in direct_models.iter() {
+          cmds = cmds
+            .draw(
+              direct.pipeline.pipeline.clone(),
+              &dynamic_state,
+              vec![direct.cube.clone() as Arc<BufferAccess + Send + Sync>],
+              direct.sets.clone(),
+              vs::ty::ModelViewNormal::new(model, &Matrix4::identity())
+            ).expect("Could not draw layer.");
+        }
+        let command_buffer = cmds
+          .end_render_pass().expect("Could not end render pass.")
+          .build().expect("Could not build command buffer.");
+        headless.execute(command_buffer);
+      })
+    });
+
+    let prepass_headless = headless.clone();
+    let mut config = PhongPipelineConfig::new();
+    config.options.after_depth_prepass = true;
+    let shaded = PhongScene::with_config(headless, LAYER_DISTANCE, config);
+    let depth = DepthPipeline::new(headless.render_pass.clone(), headless.device.clone(), headless.physical());
+    // The same projection as PhongScene's.
+    let projection = Matrix4::new_perspective(1.0, ::std::f32::consts::FRAC_PI_2, 0.1, LAYER_DISTANCE * 4.0)
+      * Matrix4::new_translation(&Vector3::new(0.0, 0.0, -LAYER_DISTANCE));
+    headless.upload(projection, depth.proj.device_buffer.clone());
+    let prepass_models = layers(count);
+    c.bench_function(&format!("overdraw/depth prepass/{}", count), move |b| {
+      b.iter(|| {
+        let headless = &prepass_headless;
+        let dynamic_state = headless.dynamic_state();
+        let mut cmds = headless.begin([0.0, 0.0, 0.0, 1.0]);
+        for model in prepass_models.iter() {
+          cmds = cmds
+            .draw(
+              depth.pipeline.clone(),
+              &dynamic_state,
+              vec![shaded.cube.clone() as Arc<BufferAccess + Send + Sync>],
+              depth.proj.desc_set.clone(),
+              unlit::vs::ty::ModelView {
+                model: (*model).into(),
+                view: Matrix4::identity().into(),
+              }
+            ).expect("Could not draw layer depth.");
+        }
+        for model in prepass_models.iter() {
+          cmds = cmds
+            .draw(
+              shaded.pipeline.pipeline.clone(),
+              &dynamic_state,
+              vec![shaded.cube.clone() as Arc<BufferAccess + Send + Sync>],
+              shaded.sets.clone(),
+              vs::ty::ModelViewNormal::new(model, &Matrix4::identity())
+            ).expect("Could not draw layer.");
+        }
+        let command_buffer = cmds
+          .end_render_pass().expect("Could not end render pass.")
+          .build().expect("Could not build command buffer.");
+        headless.execute(command_buffer);
+      })
+    });
+  }
+}
+
+
+fn light_buffers(headless: &Headless, count: usize) -> Vec<Arc<DeviceLocalBuffer<Light>>> {
+  (0 .. count)
+    .map(|_| {
+      DeviceLocalBuffer::new(
+        headless.device.clone(),
+        BufferUsage::uniform_buffer_transfer_destination(),
+        headless.physical().queue_families()
+      ).expect("Could not create light buffer.")
+    })
+    .collect()
+}
+
+
+fn light(i: usize) -> Light {
+  Light {
+    position: [i as f32, 2.0, 2.0],
+    _dummy0: [0, 0, 0, 0],
+    intensity: [1.0, 1.0, 1.0],
+  }
+}
+
+
+/// Uniform uploads each submitted in their own command buffer, and all
+/// recorded into one command buffer.
+fn bench_uniform_uploads(c: &mut Criterion, headless: &Rc<Headless>) {
+  for &count in COUNTS.iter() {
+    let single_headless = headless.clone();
+    let single_buffers = light_buffers(headless, count);
+    c.bench_function(&format!("uniform uploads/single/{}", count), move |b| {
+      b.iter(|| {
+        for (i, buffer) in single_buffers.iter().enumerate() {
+          single_headless.upload(light(i), buffer.clone());
+        }
+      })
+    });
+
+    let batched_headless = headless.clone();
+    let batched_buffers = light_buffers(headless, count);
+    c.bench_function(&format!("uniform uploads/batched/{}", count), move |b| {
+      b.iter(|| {
+        let headless = &batched_headless;
+        let mut cmds =
+          AutoCommandBufferBuilder::primary_one_time_submit(headless.device.clone(), headless.queue.family())
+            .expect("Could not create a new command buffer builder.");
+        for (i, buffer) in batched_buffers.iter().enumerate() {
+          let src = CpuAccessibleBuffer::from_data(headless.device.clone(), BufferUsage::transfer_source(), light(i))
+            .expect("Could not create upload buffer.");
+          cmds = cmds
+            .copy_buffer(src, buffer.clone())
+            .expect("Could not copy upload buffer.");
+        }
+        headless.execute(cmds.build().expect("Could not build upload command buffer."));
+      })
+    });
+  }
+}
+
+
+fn benches(c: &mut Criterion) {
+  let headless = match Headless::new(DIMENSIONS) {
+    Some(headless) => Rc::new(headless),
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  bench_individual_draws(c, &headless);
+  bench_batched_draws(c, &headless);
+  bench_depth_prepass(c, &headless);
+  bench_uniform_uploads(c, &headless);
+}
+
+
+criterion_group!(draw, benches);
+criterion_main!(draw);
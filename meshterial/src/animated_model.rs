@@ -0,0 +1,144 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use nalgebra::Matrix4;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use super::animation::{Skeleton, SkeletalClip};
+use super::loaders::gltf::{load_skinned, GltfError, GltfPrimitive};
+use super::pipelines::phong::{normal_matrix, PhongPipeline};
+use super::pipelines::phong_skinned::{vs, PhongSkinnedPipeline, VertexSkinned};
+use super::VkRenderer;
+
+
+/// A rigged model and its animations, loaded from a glTF file.
+///
+/// Call `play` to choose an animation, then `update` each frame to advance it
+/// and upload the joint matrices, then `draw`. The skinned pipeline holds one
+/// set of joint matrices, so when drawing more than one model with the same
+/// pipeline each model must be updated right before it is drawn.
+pub struct AnimatedModel {
+  pub vertex_buffer: Arc<CpuAccessibleBuffer<[VertexSkinned]>>,
+  /// The mesh's materials. Set one on the phong pipeline passed to `draw`
+  /// with `GltfPrimitive::to_material`, so double sided materials are lit from
+  /// behind.
+  pub primitives: Vec<GltfPrimitive>,
+  pub skeleton: Skeleton,
+  pub clips: Vec<SkeletalClip>,
+  current: Option<usize>,
+  time: f32,
+}
+
+
+impl AnimatedModel {
+  /// Load the first skinned mesh in a glTF file. See
+  /// `loaders::gltf::load_skinned`.
+  pub fn load(vkr: &VkRenderer, path: &Path) -> Result<AnimatedModel, GltfError> {
+    let gltf = load_skinned(path)?;
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      vkr.device.clone(),
+      BufferUsage::all(),
+      gltf.vertices.into_iter()
+    ).expect("Could not create skinned vertex buffer.");
+
+    Ok(AnimatedModel {
+      vertex_buffer,
+      primitives: gltf.primitives,
+      skeleton: gltf.skeleton,
+      clips: gltf.clips,
+      current: None,
+      time: 0.0,
+    })
+  }
+
+
+  /// The names of all the model's animations.
+  pub fn clip_names<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+    self.clips.iter().map(|clip| clip.name.as_str())
+  }
+
+
+  /// The name of the animation that is playing, if any.
+  pub fn current_clip(&self) -> Option<&str> {
+    self.current.map(|ndx| self.clips[ndx].name.as_str())
+  }
+
+
+  /// Start playing the named animation from the beginning. Animations loop.
+  /// Returns false if there is no animation with that name.
+  pub fn play(&mut self, name: &str) -> bool {
+    match self.clips.iter().position(|clip| clip.name == name) {
+      Some(ndx) => {
+        self.current = Some(ndx);
+        self.time = 0.0;
+        true
+      }
+      None => false
+    }
+  }
+
+
+  /// Stop playing, returning the model to its rest pose.
+  pub fn stop(&mut self) {
+    self.current = None;
+    self.time = 0.0;
+  }
+
+
+  /// The joint matrices of the model's current pose.
+  pub fn joint_matrices(&self) -> Vec<Matrix4<f32>> {
+    let pose = match self.current {
+      Some(ndx) => self.clips[ndx].pose(&self.skeleton, self.time),
+      None => self.skeleton.rest_pose.clone(),
+    };
+    self.skeleton.joint_matrices(&pose)
+  }
+
+
+  /// Advance the current animation by `dt` seconds and upload the resulting
+  /// joint matrices to the pipeline.
+  pub fn update(&mut self, dt: f32, vkr: &mut VkRenderer, pipeline: &PhongSkinnedPipeline) {
+    if let Some(ndx) = self.current {
+      let duration = self.clips[ndx].duration();
+      self.time =
+        if duration > 0.0 {
+          (self.time + dt) % duration
+        } else {
+          0.0
+        };
+    }
+    pipeline.set_joint_matrices(vkr, &self.joint_matrices());
+  }
+
+
+  /// Draw the model using the skinned pipeline, and the material and light of
+  /// a phong pipeline.
+  pub fn draw(
+    &self,
+    vkr: &mut VkRenderer,
+    skinned: &PhongSkinnedPipeline,
+    phong: &PhongPipeline,
+    material: &str,
+    model: &Matrix4<f32>,
+    view: &Matrix4<f32>
+  ) {
+    let material_set = phong
+      .materials
+      .get(material)
+      .expect(&format!("Could not find material {}", material))
+      .desc_set
+      .clone();
+    let mats = vs::ty::ModelViewNormal {
+      model: (*model).into(),
+      view: (*view).into(),
+      normal: normal_matrix(model, view).into(),
+    };
+    let sets = (
+      skinned.proj.desc_set.clone(),
+      material_set,
+      phong.light.desc_set.clone(),
+      skinned.joints.desc_set.clone()
+    );
+    vkr.draw(skinned.pipeline.clone(), self.vertex_buffer.clone(), sets, mats);
+  }
+}
@@ -0,0 +1,401 @@
+//! Keyframe animation sampling.
+//!
+//! An AnimationClip holds a number of tracks, each of which is a list of
+//! time-stamped values. Sampling a clip at a time gives one interpolated value
+//! per track, eg. one joint matrix per joint of a skeleton for the
+//! PhongSkinnedPipeline, or a weight for `VkRenderer::draw_phong_morphed`.
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector3};
+
+
+/// Values that can be interpolated between keyframes.
+pub trait Animatable: Clone {
+  /// Returns this value scaled by `s`.
+  fn scale(&self, s: f32) -> Self;
+  /// Returns the sum of this value and another.
+  fn add(&self, other: &Self) -> Self;
+
+  /// Linearly interpolate between this value and another.
+  fn lerp(&self, other: &Self, t: f32) -> Self {
+    self.scale(1.0 - t).add(&other.scale(t))
+  }
+}
+
+
+impl Animatable for f32 {
+  fn scale(&self, s: f32) -> f32 {
+    self * s
+  }
+
+  fn add(&self, other: &f32) -> f32 {
+    self + other
+  }
+}
+
+
+/// Matrices are interpolated component-wise. This is fine for keyframes that
+/// are close together, but large rotations between two keyframes will shrink
+/// the geometry toward the middle of the interpolation.
+impl Animatable for Matrix4<f32> {
+  fn scale(&self, s: f32) -> Matrix4<f32> {
+    self * s
+  }
+
+  fn add(&self, other: &Matrix4<f32>) -> Matrix4<f32> {
+    self + other
+  }
+}
+
+
+impl Animatable for Vector3<f32> {
+  fn scale(&self, s: f32) -> Vector3<f32> {
+    self * s
+  }
+
+  fn add(&self, other: &Vector3<f32>) -> Vector3<f32> {
+    self + other
+  }
+}
+
+
+/// Quaternions are interpolated component-wise, so they must be normalized
+/// after sampling (see `NodeTransform::matrix`).
+impl Animatable for Quaternion<f32> {
+  fn scale(&self, s: f32) -> Quaternion<f32> {
+    Quaternion::from_vector(self.coords * s)
+  }
+
+  fn add(&self, other: &Quaternion<f32>) -> Quaternion<f32> {
+    Quaternion::from_vector(self.coords + other.coords)
+  }
+}
+
+
+/// How values are interpolated between two keyframes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+  /// Hold the value of the previous keyframe.
+  Step,
+  /// Linearly interpolate between the two keyframes.
+  Linear,
+  /// Interpolate along a Catmull-Rom spline passing through the keyframes.
+  Cubic,
+}
+
+
+/// What happens when a clip is sampled outside of its duration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wrap {
+  /// Hold the first or last keyframe.
+  Clamp,
+  /// Wrap back around to the start of the clip.
+  Loop,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe<T> {
+  pub time: f32,
+  pub value: T,
+}
+
+
+/// A list of keyframes sorted by time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T> {
+  pub interpolation: Interpolation,
+  keyframes: Vec<Keyframe<T>>,
+}
+
+
+impl<T: Animatable> Track<T> {
+  pub fn new(interpolation: Interpolation) -> Track<T> {
+    Track {
+      interpolation,
+      keyframes: vec![],
+    }
+  }
+
+
+  /// Add a keyframe, keeping the keyframes sorted by time.
+  pub fn with_keyframe(mut self, time: f32, value: T) -> Track<T> {
+    let ndx = self
+      .keyframes
+      .iter()
+      .position(|k| k.time > time)
+      .unwrap_or(self.keyframes.len());
+    self.keyframes.insert(ndx, Keyframe { time, value });
+    self
+  }
+
+
+  pub fn keyframes(&self) -> &[Keyframe<T>] {
+    &self.keyframes
+  }
+
+
+  /// The time of the last keyframe.
+  pub fn end_time(&self) -> f32 {
+    self.keyframes.last().map(|k| k.time).unwrap_or(0.0)
+  }
+
+
+  /// Sample the track at the given time. Times before the first keyframe or
+  /// after the last are clamped. Returns None if the track has no keyframes.
+  pub fn sample(&self, time: f32) -> Option<T> {
+    let first = self.keyframes.first()?;
+    let last = self.keyframes.last()?;
+    if time <= first.time {
+      return Some(first.value.clone());
+    }
+    if time >= last.time {
+      return Some(last.value.clone());
+    }
+
+    // The index of the keyframe after `time`. Since time is strictly between
+    // the first and last keyframes this is always in 1..len.
+    let next = self
+      .keyframes
+      .iter()
+      .position(|k| k.time > time)
+      .unwrap_or(self.keyframes.len() - 1);
+    let prev = next - 1;
+    let a = &self.keyframes[prev];
+    let b = &self.keyframes[next];
+    let span = b.time - a.time;
+    let t = if span > 0.0 { (time - a.time) / span } else { 0.0 };
+
+    Some(match self.interpolation {
+      Interpolation::Step => {
+        a.value.clone()
+      }
+      Interpolation::Linear => {
+        a.value.lerp(&b.value, t)
+      }
+      Interpolation::Cubic => {
+        let p0 = &self.keyframes[if prev > 0 { prev - 1 } else { prev }].value;
+        let p3 = &self.keyframes[(next + 1).min(self.keyframes.len() - 1)].value;
+        catmull_rom(p0, &a.value, &b.value, p3, t)
+      }
+    })
+  }
+}
+
+
+/// Interpolate between `p1` and `p2` on the Catmull-Rom spline through all
+/// four points.
+fn catmull_rom<T: Animatable>(p0: &T, p1: &T, p2: &T, p3: &T, t: f32) -> T {
+  let t2 = t * t;
+  let t3 = t2 * t;
+  p0.scale(-0.5 * t3 + t2 - 0.5 * t)
+    .add(&p1.scale(1.5 * t3 - 2.5 * t2 + 1.0))
+    .add(&p2.scale(-1.5 * t3 + 2.0 * t2 + 0.5 * t))
+    .add(&p3.scale(0.5 * t3 - 0.5 * t2))
+}
+
+
+/// A named set of tracks that are played back together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip<T> {
+  pub name: String,
+  pub wrap: Wrap,
+  pub tracks: Vec<Track<T>>,
+}
+
+
+impl<T: Animatable> AnimationClip<T> {
+  pub fn new(name: &str, wrap: Wrap) -> AnimationClip<T> {
+    AnimationClip {
+      name: name.to_string(),
+      wrap,
+      tracks: vec![],
+    }
+  }
+
+
+  pub fn with_track(mut self, track: Track<T>) -> AnimationClip<T> {
+    self.tracks.push(track);
+    self
+  }
+
+
+  /// The length of the clip in seconds, which is the end time of its longest
+  /// track.
+  pub fn duration(&self) -> f32 {
+    self.tracks.iter().fold(0.0, |d, track| d.max(track.end_time()))
+  }
+
+
+  /// Map a time onto the clip's duration according to its wrap mode.
+  pub fn local_time(&self, time: f32) -> f32 {
+    let duration = self.duration();
+    if duration <= 0.0 {
+      return 0.0;
+    }
+    match self.wrap {
+      Wrap::Clamp => time.max(0.0).min(duration),
+      Wrap::Loop => {
+        let t = time % duration;
+        if t < 0.0 { t + duration } else { t }
+      }
+    }
+  }
+
+
+  /// Sample every track at the given time, returning one value per track.
+  /// Returns None if any of the tracks has no keyframes.
+  pub fn sample(&self, time: f32) -> Option<Vec<T>> {
+    let t = self.local_time(time);
+    self
+      .tracks
+      .iter()
+      .map(|track| track.sample(t))
+      .collect()
+  }
+}
+
+
+/// The local transform of a node in a skeleton, decomposed into translation,
+/// rotation and scale.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeTransform {
+  pub translation: Vector3<f32>,
+  pub rotation: Quaternion<f32>,
+  pub scale: Vector3<f32>,
+}
+
+
+impl NodeTransform {
+  pub fn identity() -> NodeTransform {
+    NodeTransform {
+      translation: Vector3::zeros(),
+      rotation: Quaternion::identity(),
+      scale: Vector3::new(1.0, 1.0, 1.0),
+    }
+  }
+
+
+  pub fn matrix(&self) -> Matrix4<f32> {
+    let rotation = UnitQuaternion::new_normalize(self.rotation);
+    Matrix4::new_translation(&self.translation)
+      * rotation.to_homogeneous()
+      * Matrix4::new_nonuniform_scaling(&self.scale)
+  }
+}
+
+
+/// A hierarchy of nodes, some of which are joints that influence a skinned
+/// mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton {
+  /// The parent of each node, if it has one.
+  pub parents: Vec<Option<usize>>,
+  /// The local transform of each node when it is not animated.
+  pub rest_pose: Vec<NodeTransform>,
+  /// The node index of each joint, in the order the joints are indexed by
+  /// VertexSkinned::joint_indices.
+  pub joints: Vec<usize>,
+  /// The inverse bind matrix of each joint.
+  pub inverse_bind_matrices: Vec<Matrix4<f32>>,
+}
+
+
+impl Skeleton {
+  /// Compute the world transform of every node in the given pose.
+  pub fn world_transforms(&self, pose: &[NodeTransform]) -> Vec<Matrix4<f32>> {
+    let mut world:Vec<Option<Matrix4<f32>>> = vec![None; pose.len()];
+    for ndx in 0 .. pose.len() {
+      self.world_transform(ndx, pose, &mut world);
+    }
+    world
+      .into_iter()
+      .map(|m| m.unwrap_or_else(Matrix4::identity))
+      .collect()
+  }
+
+
+  fn world_transform(
+    &self,
+    ndx: usize,
+    pose: &[NodeTransform],
+    world: &mut Vec<Option<Matrix4<f32>>>
+  ) -> Matrix4<f32> {
+    if let Some(m) = world[ndx] {
+      return m;
+    }
+    let local = pose[ndx].matrix();
+    let m = match self.parents[ndx] {
+      Some(parent) => self.world_transform(parent, pose, world) * local,
+      None => local,
+    };
+    world[ndx] = Some(m);
+    m
+  }
+
+
+  /// Compute the matrix of each joint in the given pose, ready to be uploaded
+  /// with `PhongSkinnedPipeline::set_joint_matrices`.
+  pub fn joint_matrices(&self, pose: &[NodeTransform]) -> Vec<Matrix4<f32>> {
+    let world = self.world_transforms(pose);
+    self
+      .joints
+      .iter()
+      .zip(self.inverse_bind_matrices.iter())
+      .map(|(node, inverse_bind)| world[*node] * inverse_bind)
+      .collect()
+  }
+}
+
+
+/// An animation of the nodes of a skeleton. Each track targets one property
+/// of one node. Nodes without a track for a property keep their rest pose.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkeletalClip {
+  pub name: String,
+  pub translations: Vec<(usize, Track<Vector3<f32>>)>,
+  pub rotations: Vec<(usize, Track<Quaternion<f32>>)>,
+  pub scales: Vec<(usize, Track<Vector3<f32>>)>,
+}
+
+
+impl SkeletalClip {
+  pub fn new(name: &str) -> SkeletalClip {
+    SkeletalClip {
+      name: name.to_string(),
+      translations: vec![],
+      rotations: vec![],
+      scales: vec![],
+    }
+  }
+
+
+  /// The length of the clip in seconds.
+  pub fn duration(&self) -> f32 {
+    let t = self.translations.iter().fold(0.0, |d:f32, (_, t)| d.max(t.end_time()));
+    let r = self.rotations.iter().fold(0.0, |d:f32, (_, t)| d.max(t.end_time()));
+    let s = self.scales.iter().fold(0.0, |d:f32, (_, t)| d.max(t.end_time()));
+    t.max(r).max(s)
+  }
+
+
+  /// Sample the clip at the given time, starting from the skeleton's rest
+  /// pose. The time is clamped to the clip's duration.
+  pub fn pose(&self, skeleton: &Skeleton, time: f32) -> Vec<NodeTransform> {
+    let mut pose = skeleton.rest_pose.clone();
+    for (node, track) in &self.translations {
+      if let Some(t) = track.sample(time) {
+        pose[*node].translation = t;
+      }
+    }
+    for (node, track) in &self.rotations {
+      if let Some(r) = track.sample(time) {
+        pose[*node].rotation = r;
+      }
+    }
+    for (node, track) in &self.scales {
+      if let Some(s) = track.sample(time) {
+        pose[*node].scale = s;
+      }
+    }
+    pose
+  }
+}
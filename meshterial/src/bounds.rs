@@ -0,0 +1,205 @@
+//! Bounding volumes.
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use super::pipelines::phong::VertexPhong;
+
+
+/// An axis aligned bounding box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aabb {
+  pub min: Vector3<f32>,
+  pub max: Vector3<f32>,
+}
+
+
+impl Aabb {
+  /// The smallest box containing all of the points. Returns None if there are
+  /// no points.
+  pub fn from_points<I>(points: I) -> Option<Aabb>
+    where I: IntoIterator<Item = [f32; 3]>
+  {
+    let mut points = points.into_iter().map(Vector3::from);
+    let first = points.next()?;
+    Some(points.fold(Aabb { min: first, max: first }, |aabb, p| {
+      Aabb {
+        min: aabb.min.inf(&p),
+        max: aabb.max.sup(&p),
+      }
+    }))
+  }
+
+
+  pub fn center(&self) -> Vector3<f32> {
+    (self.min + self.max) * 0.5
+  }
+
+
+  /// Half of the size of the box along each axis.
+  pub fn half_extents(&self) -> Vector3<f32> {
+    (self.max - self.min) * 0.5
+  }
+
+
+  pub fn contains(&self, point: &Vector3<f32>) -> bool {
+    point.x >= self.min.x && point.x <= self.max.x
+      && point.y >= self.min.y && point.y <= self.max.y
+      && point.z >= self.min.z && point.z <= self.max.z
+  }
+
+
+  /// The smallest box containing this box after it has been transformed.
+  pub fn transformed(&self, transform: &Matrix4<f32>) -> Aabb {
+    let corners = (0 .. 8).map(|i| {
+      let corner = Point3::new(
+        if i & 1 == 0 { self.min.x } else { self.max.x },
+        if i & 2 == 0 { self.min.y } else { self.max.y },
+        if i & 4 == 0 { self.min.z } else { self.max.z },
+      );
+      let p = transform.transform_point(&corner);
+      [p.x, p.y, p.z]
+    });
+    Aabb::from_points(corners).expect("A box always has corners.")
+  }
+}
+
+
+/// A sphere containing some geometry. Cheaper to test against a frustum than
+/// an Aabb, but usually looser.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundingSphere {
+  pub center: Vector3<f32>,
+  pub radius: f32,
+}
+
+
+impl BoundingSphere {
+  /// A sphere containing all of the points, found with Ritter's algorithm.
+  /// It is at most a few percent bigger than the smallest such sphere.
+  /// Returns None if there are no points.
+  pub fn from_points<I>(points: I) -> Option<BoundingSphere>
+    where I: IntoIterator<Item = [f32; 3]>
+  {
+    let points:Vec<Vector3<f32>> = points.into_iter().map(Vector3::from).collect();
+    let first = *points.first()?;
+    let farthest_from = |from: &Vector3<f32>| {
+      points
+        .iter()
+        .cloned()
+        .fold(*from, |far, p| if (p - from).norm_squared() > (far - from).norm_squared() { p } else { far })
+    };
+    // Start with the sphere through two points that are far apart.
+    let a = farthest_from(&first);
+    let b = farthest_from(&a);
+    let mut sphere = BoundingSphere {
+      center: (a + b) * 0.5,
+      radius: (b - a).norm() * 0.5,
+    };
+    // Grow it to include any points outside of it.
+    for p in points.iter() {
+      let distance = (p - sphere.center).norm();
+      if distance > sphere.radius {
+        let radius = (sphere.radius + distance) * 0.5;
+        sphere.center += (p - sphere.center) * ((radius - sphere.radius) / distance);
+        sphere.radius = radius;
+      }
+    }
+    Some(sphere)
+  }
+
+
+  pub fn from_phong_vertices(vertices: &[VertexPhong]) -> Option<BoundingSphere> {
+    BoundingSphere::from_points(vertices.iter().map(|v| v.position))
+  }
+
+
+  /// The sphere around a box's corners.
+  pub fn from_aabb(aabb: &Aabb) -> BoundingSphere {
+    BoundingSphere {
+      center: aabb.center(),
+      radius: aabb.half_extents().norm(),
+    }
+  }
+
+
+  pub fn contains_point(&self, point: &Vector3<f32>) -> bool {
+    (point - self.center).norm_squared() <= self.radius * self.radius
+  }
+
+
+  /// A sphere containing this sphere after it has been transformed. Non
+  /// uniform scales grow the radius by the largest scale.
+  pub fn transformed(&self, transform: &Matrix4<f32>) -> BoundingSphere {
+    let center = transform.transform_point(&Point3::from_coordinates(self.center)).coords;
+    let scale = (0 .. 3)
+      .map(|i| Vector3::new(transform[(0, i)], transform[(1, i)], transform[(2, i)]).norm())
+      .fold(0.0f32, f32::max);
+    BoundingSphere {
+      center,
+      radius: self.radius * scale,
+    }
+  }
+}
+
+
+/// The six planes of a view frustum, pointing inward.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frustum {
+  /// The left, right, bottom, top, near and far planes as `(a, b, c, d)`
+  /// with `ax + by + cz + d >= 0` inside, normalized so that `(a, b, c)` is a
+  /// unit vector.
+  pub planes: [Vector4<f32>; 6],
+}
+
+
+impl Frustum {
+  /// The frustum of a projection * view matrix, eg.
+  /// `Camera::view_projection`. Geometry in world space can then be tested
+  /// against it directly.
+  pub fn from_matrix(m: &Matrix4<f32>) -> Frustum {
+    let row = |i: usize| Vector4::new(m[(i, 0)], m[(i, 1)], m[(i, 2)], m[(i, 3)]);
+    let normalize = |plane: Vector4<f32>| {
+      let len = Vector3::new(plane.x, plane.y, plane.z).norm();
+      if len > 0.0 { plane / len } else { plane }
+    };
+    let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+    Frustum {
+      planes: [
+        normalize(w + x),
+        normalize(w - x),
+        normalize(w + y),
+        normalize(w - y),
+        normalize(w + z),
+        normalize(w - z),
+      ],
+    }
+  }
+
+
+  fn distance(plane: &Vector4<f32>, point: &Vector3<f32>) -> f32 {
+    plane.x * point.x + plane.y * point.y + plane.z * point.z + plane.w
+  }
+
+
+  /// Whether or not any part of the sphere may be inside the frustum. This is
+  /// one dot product per plane, so try it before `intersects_aabb`.
+  pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+    self
+      .planes
+      .iter()
+      .all(|plane| Frustum::distance(plane, &sphere.center) >= -sphere.radius)
+  }
+
+
+  /// Whether or not any part of the box may be inside the frustum.
+  pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+    self.planes.iter().all(|plane| {
+      // The corner farthest along the plane's normal.
+      let corner = Vector3::new(
+        if plane.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+        if plane.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+        if plane.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+      );
+      Frustum::distance(plane, &corner) >= 0.0
+    })
+  }
+}
@@ -0,0 +1,98 @@
+//! A perspective camera.
+use nalgebra::{Matrix4, Point3, Vector3};
+
+use super::bounds::Aabb;
+
+
+/// A perspective camera looking from `position` at `target`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+  pub position: Vector3<f32>,
+  pub target: Vector3<f32>,
+  pub up: Vector3<f32>,
+  /// The vertical field of view in radians.
+  pub fov_y: f32,
+  /// Width divided by height.
+  pub aspect: f32,
+  pub near: f32,
+  pub far: f32,
+}
+
+
+impl Default for Camera {
+  /// The camera used by the example: looking at the origin from (3, 3, 4)
+  /// with a 90 degree field of view.
+  fn default() -> Camera {
+    Camera {
+      position: Vector3::new(3.0, 3.0, 4.0),
+      target: Vector3::zeros(),
+      up: Vector3::y(),
+      fov_y: ::std::f32::consts::FRAC_PI_2,
+      aspect: 4.0 / 3.0,
+      near: 0.01,
+      far: 100.0,
+    }
+  }
+}
+
+
+impl Camera {
+  /// Set the aspect ratio from the dimensions of the framebuffer.
+  pub fn set_dimensions(&mut self, dimensions: [u32; 2]) {
+    if dimensions[1] > 0 {
+      self.aspect = dimensions[0] as f32 / dimensions[1] as f32;
+    }
+  }
+
+
+  pub fn view(&self) -> Matrix4<f32> {
+    Matrix4::look_at_rh(
+      &Point3::from_coordinates(self.position),
+      &Point3::from_coordinates(self.target),
+      &self.up
+    )
+  }
+
+
+  pub fn projection(&self) -> Matrix4<f32> {
+    Matrix4::new_perspective(self.aspect, self.fov_y, self.near, self.far)
+  }
+
+
+  pub fn view_projection(&self) -> Matrix4<f32> {
+    self.projection() * self.view()
+  }
+
+
+  /// The unit vector the camera is looking along.
+  pub fn forward(&self) -> Vector3<f32> {
+    (self.target - self.position).normalize()
+  }
+
+
+  /// Move the camera so the box fills as much of the view as it can while
+  /// staying entirely in it, eg. to frame a freshly loaded mesh. The camera
+  /// keeps looking from the same direction, now at the center of the box,
+  /// and the narrower of the vertical and horizontal fields of view decides
+  /// the distance, so wide boxes fit horizontally too. `far` is pushed out if
+  /// the back of the box would be clipped. Call `set_dimensions` first, since
+  /// the distance depends on the aspect ratio.
+  pub fn frame_bounds(&mut self, aabb: &Aabb) {
+    let center = aabb.center();
+    // The sphere around the box fits in the view from any direction.
+    let radius = aabb.half_extents().norm().max(::std::f32::EPSILON);
+    let fov_x = 2.0 * ((self.fov_y * 0.5).tan() * self.aspect).atan();
+    let fov = self.fov_y.min(fov_x);
+    let distance = radius / (fov * 0.5).sin();
+    let offset = self.position - self.target;
+    let direction =
+      if offset.norm_squared() > 0.0 {
+        offset.normalize()
+      } else {
+        Vector3::z()
+      };
+    self.target = center;
+    self.position = center + direction * distance;
+    self.far = self.far.max(distance + radius);
+  }
+}
@@ -0,0 +1,215 @@
+//! Immediate mode debug drawing. Queue lines, boxes and text during a frame
+//! with `VkRenderer::debug` and draw them all with `VkRenderer::flush_debug`.
+use nalgebra::Vector3;
+
+use super::bounds::Aabb;
+use super::camera::Camera;
+use super::pipelines::color3d::VertexColor3;
+
+
+/// The height of a glyph in font units. Glyphs are 4 units wide.
+const GLYPH_HEIGHT: f32 = 8.0;
+
+/// How far apart glyphs and lines of text are, in font units.
+const GLYPH_ADVANCE: f32 = 6.0;
+const LINE_ADVANCE: f32 = 12.0;
+
+
+/// The strokes of a glyph as polylines separated by spaces, each point being
+/// two digits `xy` in font units from the bottom left. Letters are drawn in
+/// upper case and unknown characters as '?'.
+fn glyph(c: char) -> &'static str {
+  match c.to_ascii_uppercase() {
+    ' ' => "",
+    '0' => "0040480800 0048",
+    '1' => "062820 0040",
+    '2' => "084844040040",
+    '3' => "08484000 0444",
+    '4' => "080444 4840",
+    '5' => "480804444000",
+    '6' => "480800404404",
+    '7' => "084820",
+    '8' => "0040480800 0444",
+    '9' => "440408484000",
+    'A' => "0006284640 0444",
+    'B' => "0008284624422000 0424",
+    'C' => "48080040",
+    'D' => "00082846422000",
+    'E' => "48080040 0424",
+    'F' => "480800 0424",
+    'G' => "480800404424",
+    'H' => "0800 4840 0444",
+    'I' => "0848 2820 0040",
+    'J' => "48400002",
+    'K' => "0800 480440",
+    'L' => "080040",
+    'M' => "0008244840",
+    'N' => "00084048",
+    'O' => "0040480800",
+    'P' => "0008484404",
+    'Q' => "0040480800 2240",
+    'R' => "0008484404 2440",
+    'S' => "480804444000",
+    'T' => "0848 2820",
+    'U' => "08004048",
+    'V' => "082048",
+    'W' => "0800244048",
+    'X' => "0048 0840",
+    'Y' => "082448 2420",
+    'Z' => "08480040",
+    '!' => "2021 2328",
+    '%' => "0048 0708 4041",
+    '\'' => "2826",
+    '(' => "38262230",
+    ')' => "18262210",
+    '*' => "0444 1335 1533",
+    '+' => "0444 2226",
+    ',' => "2110",
+    '-' => "0444",
+    '.' => "2021",
+    '/' => "0048",
+    ':' => "2223 2526",
+    '<' => "460442",
+    '=' => "0343 0545",
+    '>' => "064402",
+    '?' => "060848452423 2021",
+    '[' => "38282030",
+    ']' => "18282010",
+    '_' => "0040",
+    _ => glyph('?'),
+  }
+}
+
+
+/// A text label waiting to be drawn.
+#[derive(Debug, Clone, PartialEq)]
+struct DebugText {
+  position: Vector3<f32>,
+  text: String,
+  color: [f32; 4],
+  size: f32,
+}
+
+
+/// Debug lines, boxes and text queued for the current frame.
+///
+/// Everything is drawn as lines with depth testing, then cleared, by
+/// `VkRenderer::flush_debug`.
+#[derive(Debug, Clone)]
+pub struct DebugDraw {
+  lines: Vec<VertexColor3>,
+  texts: Vec<DebugText>,
+  /// The color of text queued afterward.
+  pub text_color: [f32; 4],
+  /// The height in pixels of text queued afterward.
+  pub text_size: f32,
+}
+
+
+impl Default for DebugDraw {
+  fn default() -> DebugDraw {
+    DebugDraw {
+      lines: vec![],
+      texts: vec![],
+      text_color: [1.0, 1.0, 1.0, 1.0],
+      text_size: 16.0,
+    }
+  }
+}
+
+
+impl DebugDraw {
+  pub fn new() -> DebugDraw {
+    DebugDraw::default()
+  }
+
+
+  pub fn line(&mut self, a: Vector3<f32>, b: Vector3<f32>, color: [f32; 4]) {
+    self.lines.push(VertexColor3 { position: a.into(), color });
+    self.lines.push(VertexColor3 { position: b.into(), color });
+  }
+
+
+  /// The twelve edges of a box.
+  pub fn aabb(&mut self, aabb: &Aabb, color: [f32; 4]) {
+    let corner = |i: usize| Vector3::new(
+      if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+      if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+      if i & 4 == 0 { aabb.min.z } else { aabb.max.z }
+    );
+    for i in 0 .. 8 {
+      // Connect each corner to the corners one axis over from it.
+      for axis in &[1, 2, 4] {
+        if i & axis == 0 {
+          self.line(corner(i), corner(i | axis), color);
+        }
+      }
+    }
+  }
+
+
+  /// Text facing the camera with its first line's bottom left at `position`.
+  /// It stays `text_size` pixels high however far away it is, and each `\n`
+  /// starts a new line below.
+  pub fn text(&mut self, position: Vector3<f32>, text: &str) {
+    self.texts.push(DebugText {
+      position,
+      text: text.to_string(),
+      color: self.text_color,
+      size: self.text_size,
+    });
+  }
+
+
+  pub fn is_empty(&self) -> bool {
+    self.lines.is_empty() && self.texts.is_empty()
+  }
+
+
+  pub fn clear(&mut self) {
+    self.lines.clear();
+    self.texts.clear();
+  }
+
+
+  /// Everything queued as a line list, with text laid out for a camera
+  /// drawing into a framebuffer `screen_height` pixels high. Text behind the
+  /// camera is left out.
+  pub fn vertices(&self, camera: &Camera, screen_height: u32) -> Vec<VertexColor3> {
+    let mut vertices = self.lines.clone();
+    let forward = camera.forward();
+    let right = forward.cross(&camera.up).normalize();
+    let up = right.cross(&forward);
+    // The world size of a pixel at a distance of 1.
+    let pixel = 2.0 * (camera.fov_y * 0.5).tan() / screen_height.max(1) as f32;
+    for text in self.texts.iter() {
+      let distance = (text.position - camera.position).dot(&forward);
+      if distance <= camera.near {
+        continue;
+      }
+      let unit = text.size * pixel * distance / GLYPH_HEIGHT;
+      for (row, line) in text.text.lines().enumerate() {
+        for (column, c) in line.chars().enumerate() {
+          let origin = text.position
+            + right * (column as f32 * GLYPH_ADVANCE * unit)
+            - up * (row as f32 * LINE_ADVANCE * unit);
+          let point = |x: u8, y: u8| {
+            let p = origin
+              + right * ((x - b'0') as f32 * unit)
+              + up * ((y - b'0') as f32 * unit);
+            VertexColor3 { position: p.into(), color: text.color }
+          };
+          for stroke in glyph(c).split(' ') {
+            let digits = stroke.as_bytes();
+            let points = digits.len() / 2;
+            for i in 1 .. points {
+              vertices.push(point(digits[i * 2 - 2], digits[i * 2 - 1]));
+              vertices.push(point(digits[i * 2], digits[i * 2 + 1]));
+            }
+          }
+        }
+      }
+    }
+    vertices
+  }
+}
@@ -0,0 +1,3 @@
+//! Writing meshes back out to files, eg. to inspect the output of merging,
+//! decimation or normal generation in other tools.
+pub mod obj;
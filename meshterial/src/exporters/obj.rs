@@ -0,0 +1,42 @@
+//! Wavefront OBJ files with positions, normals and faces. Materials and
+//! texture coordinates aren't written.
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::super::mesh::Mesh;
+use super::super::pipelines::phong::VertexPhong;
+
+
+/// Write the mesh's triangles to an OBJ file. This reads the vertex buffer.
+pub fn write(path: &Path, mesh: &Mesh<VertexPhong>) -> io::Result<()> {
+  let vertices = mesh
+    .vertex_buffer
+    .read()
+    .expect("Could not read mesh vertex buffer.");
+  let mut writer = BufWriter::new(File::create(path)?);
+  write_triangles(&mut writer, &*vertices)?;
+  writer.flush()
+}
+
+
+/// Write a triangle list as OBJ, with a `v` and a `vn` line for each vertex
+/// and an `f` line for each triangle. Vertices shared by triangles aren't
+/// merged. Floats are written so they read back exactly.
+pub fn write_triangles<W: Write>(writer: &mut W, vertices: &[VertexPhong]) -> io::Result<()> {
+  writeln!(writer, "# {} vertices, {} triangles", vertices.len(), vertices.len() / 3)?;
+  for vertex in vertices {
+    let p = vertex.position;
+    writeln!(writer, "v {} {} {}", p[0], p[1], p[2])?;
+  }
+  for vertex in vertices {
+    let n = vertex.normal;
+    writeln!(writer, "vn {} {} {}", n[0], n[1], n[2])?;
+  }
+  // OBJ indices start at 1.
+  for triangle in 0 .. vertices.len() / 3 {
+    let a = triangle * 3 + 1;
+    writeln!(writer, "f {}//{} {}//{} {}//{}", a, a, a + 1, a + 1, a + 2, a + 2)?;
+  }
+  Ok(())
+}
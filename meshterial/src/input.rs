@@ -0,0 +1,254 @@
+//! Tracking keyboard, mouse and game controller state across frames from
+//! sdl2 events.
+use sdl2::{GameControllerSubsystem, Sdl};
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+
+/// Which keys and mouse buttons are held, and how the mouse moved this frame.
+///
+/// Feed it every event each frame, either all at once with
+/// `update_from_events` or one at a time with `begin_frame` and
+/// `handle_event`.
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+  keys_down: HashSet<Keycode>,
+  keys_pressed: HashSet<Keycode>,
+  buttons_down: HashSet<MouseButton>,
+  buttons_pressed: HashSet<MouseButton>,
+  mouse_position: [i32; 2],
+  mouse_delta: [i32; 2],
+  wheel_delta: [i32; 2],
+  controller_buttons_down: HashSet<Button>,
+  controller_axes: HashMap<Axis, i16>,
+}
+
+
+impl InputState {
+  pub fn new() -> InputState {
+    InputState::default()
+  }
+
+
+  /// Start a new frame and apply all of its events.
+  pub fn update_from_events(&mut self, events: &[Event]) {
+    self.begin_frame();
+    for event in events {
+      self.handle_event(event);
+    }
+  }
+
+
+  /// Forget the presses and mouse movement of the last frame. Held keys and
+  /// buttons stay held.
+  pub fn begin_frame(&mut self) {
+    self.keys_pressed.clear();
+    self.buttons_pressed.clear();
+    self.mouse_delta = [0, 0];
+    self.wheel_delta = [0, 0];
+  }
+
+
+  /// Apply one event. Events that aren't about the keyboard or mouse are
+  /// ignored.
+  pub fn handle_event(&mut self, event: &Event) {
+    match event {
+      Event::KeyDown { keycode: Some(keycode), repeat, .. } => {
+        if !repeat {
+          self.keys_pressed.insert(*keycode);
+        }
+        self.keys_down.insert(*keycode);
+      }
+      Event::KeyUp { keycode: Some(keycode), .. } => {
+        self.keys_down.remove(keycode);
+      }
+      Event::MouseMotion { x, y, xrel, yrel, .. } => {
+        self.mouse_position = [*x, *y];
+        self.mouse_delta[0] += xrel;
+        self.mouse_delta[1] += yrel;
+      }
+      Event::MouseButtonDown { mouse_btn, x, y, .. } => {
+        self.mouse_position = [*x, *y];
+        self.buttons_down.insert(*mouse_btn);
+        self.buttons_pressed.insert(*mouse_btn);
+      }
+      Event::MouseButtonUp { mouse_btn, x, y, .. } => {
+        self.mouse_position = [*x, *y];
+        self.buttons_down.remove(mouse_btn);
+      }
+      Event::MouseWheel { x, y, .. } => {
+        self.wheel_delta[0] += x;
+        self.wheel_delta[1] += y;
+      }
+      // The state of every open controller is merged, so the last event wins.
+      Event::ControllerButtonDown { button, .. } => {
+        self.controller_buttons_down.insert(*button);
+      }
+      Event::ControllerButtonUp { button, .. } => {
+        self.controller_buttons_down.remove(button);
+      }
+      Event::ControllerAxisMotion { axis, value, .. } => {
+        self.controller_axes.insert(*axis, *value);
+      }
+      Event::ControllerDeviceRemoved { .. } => {
+        self.controller_buttons_down.clear();
+        self.controller_axes.clear();
+      }
+      // Releases that happen while the window is unfocused are never seen, so
+      // let go of everything.
+      Event::Window { win_event: WindowEvent::FocusLost, .. } => {
+        self.keys_down.clear();
+        self.buttons_down.clear();
+        self.controller_buttons_down.clear();
+      }
+      _ => {}
+    }
+  }
+
+
+  /// Whether or not the key is held.
+  pub fn is_key_down(&self, keycode: Keycode) -> bool {
+    self.keys_down.contains(&keycode)
+  }
+
+
+  /// Whether or not the key went down this frame. Key repeats don't count.
+  pub fn was_key_pressed(&self, keycode: Keycode) -> bool {
+    self.keys_pressed.contains(&keycode)
+  }
+
+
+  pub fn is_button_down(&self, button: MouseButton) -> bool {
+    self.buttons_down.contains(&button)
+  }
+
+
+  /// Whether or not the button went down this frame.
+  pub fn was_button_pressed(&self, button: MouseButton) -> bool {
+    self.buttons_pressed.contains(&button)
+  }
+
+
+  /// The last known position of the mouse in window coordinates.
+  pub fn mouse_position(&self) -> [i32; 2] {
+    self.mouse_position
+  }
+
+
+  /// How far the mouse moved this frame.
+  pub fn mouse_delta(&self) -> [i32; 2] {
+    self.mouse_delta
+  }
+
+
+  /// How far the mouse wheel scrolled this frame.
+  pub fn wheel_delta(&self) -> [i32; 2] {
+    self.wheel_delta
+  }
+
+
+  /// Whether or not the button is held on any open controller. Controllers
+  /// must be opened with `Controllers` for their events to arrive.
+  pub fn is_controller_button_down(&self, button: Button) -> bool {
+    self.controller_buttons_down.contains(&button)
+  }
+
+
+  /// The position of a controller axis in [-1, 1], or [0, 1] for the
+  /// triggers. No dead zone is applied, so sticks at rest may read slightly
+  /// off of 0.
+  pub fn controller_axis(&self, axis: Axis) -> f32 {
+    let value = self.controller_axes.get(&axis).cloned().unwrap_or(0);
+    (value as f32 / 32767.0).max(-1.0)
+  }
+}
+
+
+/// The game controller subsystem and the controllers it has opened. See
+/// `VkRenderer::enable_controllers`.
+///
+/// sdl2 only sends controller events for controllers that are open, so pass
+/// every event to `handle_event` to open controllers as they are plugged in
+/// and close them as they are unplugged. Controllers that can't be opened are
+/// skipped, see `take_open_errors`.
+pub struct Controllers {
+  pub subsystem: GameControllerSubsystem,
+  /// The open controllers by joystick instance id.
+  open: HashMap<i32, GameController>,
+  /// The joystick indices of controllers that couldn't be opened, and why.
+  open_errors: Vec<(u32, String)>,
+}
+
+
+impl Controllers {
+  /// Init the game controller subsystem and open every connected
+  /// controller.
+  pub fn new(ctx: &Sdl) -> Result<Controllers, String> {
+    let subsystem = ctx.game_controller()?;
+    let mut controllers = Controllers {
+      subsystem,
+      open: HashMap::new(),
+      open_errors: vec![],
+    };
+    let num_joysticks = controllers.subsystem.num_joysticks()?;
+    for index in 0 .. num_joysticks {
+      controllers.open(index);
+    }
+    Ok(controllers)
+  }
+
+
+  fn open(&mut self, joystick_index: u32) {
+    if !self.subsystem.is_game_controller(joystick_index) {
+      return;
+    }
+    match self.subsystem.open(joystick_index) {
+      Ok(controller) => {
+        self.open.insert(controller.instance_id(), controller);
+      }
+      Err(e) => self.open_errors.push((joystick_index, e.to_string())),
+    }
+  }
+
+
+  /// Open and close controllers as they are plugged in and unplugged.
+  pub fn handle_event(&mut self, event: &Event) {
+    match event {
+      Event::ControllerDeviceAdded { which, .. } => {
+        self.open(*which as u32);
+      }
+      Event::ControllerDeviceRemoved { which, .. } => {
+        self.open.remove(&(*which as i32));
+      }
+      _ => {}
+    }
+  }
+
+
+  /// The joystick indices of the controllers that couldn't be opened since
+  /// the last call, and why.
+  pub fn take_open_errors(&mut self) -> Vec<(u32, String)> {
+    mem::replace(&mut self.open_errors, vec![])
+  }
+
+
+  /// The open controllers.
+  pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a GameController> + 'a {
+    self.open.values()
+  }
+
+
+  pub fn len(&self) -> usize {
+    self.open.len()
+  }
+
+
+  pub fn is_empty(&self) -> bool {
+    self.open.is_empty()
+  }
+}
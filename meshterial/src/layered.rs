@@ -0,0 +1,253 @@
+//! Rendering a scene several times with different views into the layers of
+//! one array image, eg. the six faces of a cubemap or the two eyes of a
+//! stereo view.
+//!
+//! Each layer is rendered with its own draws into a 2d image, which is then
+//! copied into the layer. Selecting the layer with `gl_Layer` in a geometry
+//! shader would need a geometry shader variant of every pipeline, so that
+//! isn't done here.
+use vulkano::buffer::BufferAccess;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::DescriptorSetsCollection;
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract};
+use vulkano::image::{Dimensions, ImageUsage, StorageImage};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::pipeline::GraphicsPipelineAbstract;
+use vulkano::pipeline::viewport::{Scissor, Viewport};
+use vulkano::sampler::Filter;
+use vulkano::sync::{now, GpuFuture};
+use nalgebra::Matrix4;
+
+use std::sync::Arc;
+
+use super::VkRenderer;
+
+
+/// An offscreen render pass that draws into the layers of a `Dim2dArray`
+/// image.
+///
+/// Build pipelines against `render_pass`, then either call `render` with one
+/// view-projection matrix per layer, or call `begin_layer`, `draw` and
+/// `end_layer` for each layer yourself followed by `finish`. Like
+/// `PickingPass`, the target has its own command buffer and `finish` waits for
+/// the GPU, so it is meant for captures (eg. a skybox or a shadow cubemap)
+/// rather than every frame.
+pub struct LayeredTarget {
+  pub render_pass: Arc<RenderPassAbstract + Send + Sync>,
+  /// The rendered layers. This can be sampled once `finish` has returned.
+  pub image: Arc<StorageImage<Format>>,
+  pub format: Format,
+  pub dimensions: [u32; 2],
+  pub layers: u32,
+  /// The clear color of each layer.
+  pub clear_color: [f32; 4],
+  layer_image: Arc<AttachmentImage>,
+  framebuffer: Arc<FramebufferAbstract + Send + Sync>,
+  dynamic_state: DynamicState,
+  current_layer: Option<u32>,
+  command_buffer_builder: Option<AutoCommandBufferBuilder>,
+}
+
+
+impl LayeredTarget {
+  pub fn new(vkr: &VkRenderer, dimensions: [u32; 2], layers: u32, format: Format) -> LayeredTarget {
+    let device = vkr.device.clone();
+    let render_pass = Arc::new(
+      single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+          color: {
+            load: Clear,
+            store: Store,
+            format: format,
+            samples: 1,
+          },
+          depth: {
+            load: Clear,
+            store: DontCare,
+            format: Format::D16Unorm,
+            samples: 1,
+          }
+        },
+        pass: {
+          color: [color],
+          depth_stencil: {depth}
+        }
+      ).expect("Could not create layered render pass.")
+    ) as Arc<RenderPassAbstract + Send + Sync>;
+
+    let image = StorageImage::with_usage(
+      device.clone(),
+      Dimensions::Dim2dArray {
+        width: dimensions[0],
+        height: dimensions[1],
+        array_layers: layers,
+      },
+      format,
+      ImageUsage {
+        sampled: true,
+        transfer_destination: true,
+        .. ImageUsage::none()
+      },
+      vkr.device.physical_device().queue_families()
+    ).expect("Could not create layered image.");
+    let layer_image = AttachmentImage::with_usage(
+      device.clone(),
+      dimensions,
+      format,
+      ImageUsage {
+        color_attachment: true,
+        transfer_source: true,
+        .. ImageUsage::none()
+      }
+    ).expect("Could not create layer image.");
+    let depth_image = AttachmentImage::transient(device, dimensions, Format::D16Unorm)
+      .expect("Could not create layer depth image.");
+    let framebuffer = Arc::new(
+      Framebuffer::start(render_pass.clone())
+        .add(layer_image.clone()).expect("Could not add layer image to framebuffer.")
+        .add(depth_image).expect("Could not add depth buffer to framebuffer.")
+        .build().expect("Could not build layer framebuffer.")
+    );
+
+    LayeredTarget {
+      render_pass,
+      image,
+      format,
+      dimensions,
+      layers,
+      clear_color: [0.0, 0.0, 0.0, 1.0],
+      layer_image,
+      framebuffer,
+      dynamic_state: DynamicState {
+        line_width: None,
+        viewports: Some(vec![Viewport {
+          origin: [0.0, 0.0],
+          dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+          depth_range: 0.0 .. 1.0,
+        }]),
+        scissors: Some(vec![Scissor {
+          origin: [0, 0],
+          dimensions,
+        }]),
+      },
+      current_layer: None,
+      command_buffer_builder: None,
+    }
+  }
+
+
+  /// Render every layer by calling `draw_layer` with the target, the index of
+  /// the layer and its view-projection matrix. There must be one matrix per
+  /// layer.
+  pub fn render<F>(&mut self, vkr: &mut VkRenderer, view_projections: &[Matrix4<f32>], mut draw_layer: F)
+    where F: FnMut(&mut LayeredTarget, usize, &Matrix4<f32>)
+  {
+    assert_eq!(
+      view_projections.len(),
+      self.layers as usize,
+      "LayeredTarget::render needs one view-projection matrix per layer."
+    );
+    for (layer, view_projection) in view_projections.iter().enumerate() {
+      self.begin_layer(vkr, layer as u32);
+      draw_layer(self, layer, view_projection);
+      self.end_layer();
+    }
+    self.finish(vkr);
+  }
+
+
+  /// Start drawing into a layer, clearing it.
+  pub fn begin_layer(&mut self, vkr: &VkRenderer, layer: u32) {
+    assert!(layer < self.layers, "Layer {} is out of bounds.", layer);
+    let cmds = match self.command_buffer_builder.take() {
+      Some(cmds) => cmds,
+      None => {
+        AutoCommandBufferBuilder::primary_one_time_submit(vkr.device.clone(), vkr.queue.family())
+          .expect("Could not create a new command buffer builder.")
+      }
+    };
+    self.command_buffer_builder = Some(
+      cmds
+        .begin_render_pass(
+          self.framebuffer.clone(),
+          false,
+          vec![
+            ClearValue::Float(self.clear_color),
+            1f32.into()
+          ]
+        ).expect("Could not begin layer render pass.")
+    );
+    self.current_layer = Some(layer);
+  }
+
+
+  /// Record a draw into the current layer with a pipeline built against
+  /// `render_pass`.
+  pub fn draw<S, Pc>(
+    &mut self,
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    vertex_buffer: Arc<BufferAccess + Send + Sync>,
+    sets: S,
+    push_constants: Pc
+  ) where
+    S: DescriptorSetsCollection
+  {
+    let cmds = self
+      .command_buffer_builder
+      .take()
+      .expect("Could not take layer command builder - maybe 'begin_layer' was not called.")
+      .draw(
+        pipeline,
+        &self.dynamic_state,
+        vec![vertex_buffer],
+        sets,
+        push_constants
+      ).expect("Could not draw into layer.");
+    self.command_buffer_builder = Some(cmds);
+  }
+
+
+  /// Finish drawing the current layer and copy it into `image`.
+  pub fn end_layer(&mut self) {
+    let layer = self
+      .current_layer
+      .take()
+      .expect("Could not get the current layer - maybe 'begin_layer' was not called.");
+    let w = self.dimensions[0] as i32;
+    let h = self.dimensions[1] as i32;
+    let cmds = self
+      .command_buffer_builder
+      .take()
+      .expect("Could not take layer command builder - maybe 'begin_layer' was not called.")
+      .end_render_pass().expect("Could not end layer render pass.")
+      .blit_image(
+        self.layer_image.clone(), [0, 0, 0], [w, h, 1], 0, 0,
+        self.image.clone(), [0, 0, 0], [w, h, 1], layer, 0,
+        1, Filter::Nearest
+      ).expect("Could not copy into layer.");
+    self.command_buffer_builder = Some(cmds);
+  }
+
+
+  /// Submit the layers that were drawn and wait for them to be rendered.
+  pub fn finish(&mut self, vkr: &mut VkRenderer) {
+    let command_buffer = match self.command_buffer_builder.take() {
+      Some(cmds) => cmds.build().expect("Could not build layer command buffer."),
+      None => return,
+    };
+    let after = vkr
+      .previous_frame_end
+      .take()
+      .unwrap_or_else(|| Box::new(now(vkr.device.clone())) as Box<GpuFuture>);
+    after
+      .then_execute(vkr.queue.clone(), command_buffer)
+      .expect("Could not execute layer command buffer.")
+      .then_signal_fence_and_flush()
+      .expect("Could not flush layer command buffer.")
+      .wait(None)
+      .expect("Could not wait for layers.");
+    vkr.previous_frame_end = Some(Box::new(now(vkr.device.clone())) as Box<GpuFuture>);
+  }
+}
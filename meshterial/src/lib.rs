@@ -3,13 +3,44 @@ extern crate vulkano;
 extern crate vulkano_shaders;
 extern crate nalgebra;
 extern crate nalgebra_glm;
+extern crate collada;
+extern crate gltf;
+extern crate serde_json;
+extern crate vk_sys;
+#[cfg(feature = "egui")]
+extern crate egui;
 
 pub use vulkano::image::ImmutableImage;
 pub use vulkano::format::Format;
 pub use nalgebra::{Matrix4, Vector2};
 
+pub mod animated_model;
+pub mod animation;
+pub mod bounds;
+pub mod camera;
+pub mod debug;
+pub mod exporters;
+pub mod input;
+pub mod layered;
+pub mod loaders;
+pub mod lod;
+pub mod mesh;
+pub mod particles;
+pub mod picking;
 pub mod pipelines;
+pub mod ray;
+pub mod render_layers;
+pub mod scene;
+pub mod textures;
 pub mod utils;
 mod vk_renderer;
 
-pub use self::vk_renderer::VkRenderer;
+pub use self::animated_model::AnimatedModel;
+pub use self::camera::Camera;
+pub use self::debug::DebugDraw;
+pub use self::input::InputState;
+pub use self::mesh::{DynamicMesh, Mesh, MeshCache, MeshStats};
+pub use self::pipelines::background::Background;
+pub use self::pipelines::fog::{FogMode, FogParams};
+pub use self::render_layers::RenderLayers;
+pub use self::vk_renderer::{clamp_scissor, DeviceInfo, FormatFeatures, FormatProperties, FormatUsage, Frame, RenderStats, StaticCommands, SurfaceCapabilities, TextureError, VkRenderer, VkRendererError, HDR_FORMAT, MAX_EXTRA_ATTACHMENTS};
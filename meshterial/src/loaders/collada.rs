@@ -0,0 +1,177 @@
+//! Loading meshes and their phong materials from Collada files.
+//!
+//! Only `<triangles>` primitives with normals are read, `<polylist>`s are
+//! skipped. Node transforms are ignored. Effects are double sided if an
+//! exporter's `<extra>` technique says so, the way Blender, 3ds Max and
+//! SketchUp write it.
+use ::collada::document::ColladaDocument;
+use ::collada::PrimitiveElement;
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use super::super::mesh::MeshStats;
+use super::super::pipelines::phong::{Material, VertexPhong};
+
+
+#[derive(Debug)]
+pub enum ColladaError {
+  /// The file could not be read or parsed.
+  Collada(String),
+  /// A primitive has no material.
+  NoMaterial,
+  /// A material refers to an effect that isn't in the effect library.
+  MissingEffect(String),
+  /// A vertex of a primitive has no normal.
+  MissingNormal,
+  /// A primitive refers to a vertex or normal that doesn't exist.
+  BadIndex(usize),
+}
+
+
+impl fmt::Display for ColladaError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ColladaError::Collada(e) => write!(f, "could not load collada: {}", e),
+      ColladaError::NoMaterial => write!(f, "primitive has no material"),
+      ColladaError::MissingEffect(name) => {
+        write!(f, "material '{}' has no effect in the effect library", name)
+      }
+      ColladaError::MissingNormal => write!(f, "vertex is missing a normal"),
+      ColladaError::BadIndex(ndx) => write!(f, "primitive refers to missing index {}", ndx),
+    }
+  }
+}
+
+
+impl Error for ColladaError {}
+
+
+const COLLADA_NS: &str = "http://www.collada.org/2005/11/COLLADASchema";
+
+
+/// The ids of effects with `<double_sided>1</double_sided>` in an `<extra>`
+/// technique, under the effect, its profile or the profile's technique.
+fn double_sided_effects(doc: &ColladaDocument) -> HashSet<String> {
+  let ns = Some(COLLADA_NS);
+  let mut effects = HashSet::new();
+  let library = match doc.root_element.get_child("library_effects", ns) {
+    Some(library) => library,
+    None => return effects,
+  };
+  for effect in library.get_children("effect", ns) {
+    let id = match effect.get_attribute("id", None) {
+      Some(id) => id,
+      None => continue,
+    };
+    let mut parents = vec![effect];
+    for profile in effect.get_children("profile_COMMON", ns) {
+      parents.push(profile);
+      parents.extend(profile.get_children("technique", ns));
+    }
+    let double_sided = parents
+      .iter()
+      .flat_map(|parent| parent.get_children("extra", ns))
+      .flat_map(|extra| extra.get_children("technique", ns))
+      .flat_map(|technique| technique.get_children("double_sided", ns))
+      .any(|flag| flag.content_str().trim() == "1");
+    if double_sided {
+      effects.insert(id.to_string());
+    }
+  }
+  effects
+}
+
+
+/// The contents of a Collada file, grouped by material. Materials are named
+/// after their effects, the way the phong pipeline's materials are.
+#[derive(Clone)]
+pub struct Collada {
+  pub materials: HashMap<String, Material>,
+  /// A non-indexed triangle list for each material that is used.
+  pub vertices: HashMap<String, Vec<VertexPhong>>,
+}
+
+
+impl Collada {
+  /// Counts and bounds of every material's vertices together.
+  pub fn stats(&self) -> MeshStats {
+    self
+      .vertices
+      .values()
+      .map(|vertices| MeshStats::from_triangle_list(vertices, 1))
+      .fold(MeshStats::default(), |total, stats| total.combine(&stats))
+  }
+}
+
+
+/// Load the effects and triangles of a Collada file.
+pub fn load(path: &Path) -> Result<Collada, ColladaError> {
+  let doc = ColladaDocument::from_path(path)
+    .map_err(|e| ColladaError::Collada(e.to_string()))?;
+  let eff_lib = doc.get_effect_library();
+  let mats_to_effs = doc.get_material_to_effect();
+  let double_sided = double_sided_effects(&doc);
+
+  let materials = eff_lib
+    .iter()
+    .map(|(name, tech)| {
+      let material = Material {
+        emission: tech.emission,
+        ambient: tech.ambient,
+        diffuse: tech.diffuse,
+        specular: tech.specular,
+        shininess: tech.shininess,
+        double_sided: if double_sided.contains(name) { 1 } else { 0 }
+      };
+      (name.clone(), material)
+    })
+    .collect();
+
+  let mut vertices:HashMap<String, Vec<VertexPhong>> = HashMap::new();
+  let obj_set = match doc.get_obj_set() {
+    Some(obj_set) => obj_set,
+    None => return Ok(Collada { materials, vertices }),
+  };
+  for obj in obj_set.objects.iter() {
+    for geom in obj.geometry.iter() {
+      for prim in geom.mesh.iter() {
+        let triangles = match prim {
+          PrimitiveElement::Triangles(triangles) => triangles,
+          PrimitiveElement::Polylist(_) => continue,
+        };
+        let material = triangles
+          .material
+          .as_ref()
+          .ok_or(ColladaError::NoMaterial)?;
+        let eff = mats_to_effs
+          .get(material)
+          .ok_or(ColladaError::MissingEffect(material.clone()))?;
+        let buffer = vertices
+          .entry(eff.clone())
+          .or_insert(vec![]);
+        for (a, b, c) in triangles.vertices.iter() {
+          for (vndx, _, may_nndx) in [a, b, c].iter() {
+            let nndx = may_nndx.ok_or(ColladaError::MissingNormal)?;
+            let p = obj
+              .vertices
+              .get(*vndx)
+              .ok_or(ColladaError::BadIndex(*vndx))?;
+            let n = obj
+              .normals
+              .get(nndx)
+              .ok_or(ColladaError::BadIndex(nndx))?;
+            buffer.push(VertexPhong {
+              position: [p.x as f32, p.y as f32, p.z as f32],
+              normal: [n.x as f32, n.y as f32, n.z as f32]
+            });
+          }
+        }
+      }
+    }
+  }
+
+  Ok(Collada { materials, vertices })
+}
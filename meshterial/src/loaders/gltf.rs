@@ -0,0 +1,379 @@
+//! Loading meshes and rigged models from glTF files.
+use ::gltf::animation::util::ReadOutputs;
+use ::gltf::animation::Interpolation as GltfInterpolation;
+use nalgebra::{Matrix4, Quaternion, Vector3};
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+use super::super::animation::{
+  Animatable,
+  Interpolation,
+  NodeTransform,
+  Skeleton,
+  SkeletalClip,
+  Track
+};
+use super::super::mesh::MeshStats;
+use super::super::pipelines::phong::Material;
+use super::super::pipelines::phong_color::VertexPhongColor;
+use super::super::pipelines::phong_skinned::{VertexSkinned, MAX_JOINTS};
+
+
+#[derive(Debug)]
+pub enum GltfError {
+  /// The file could not be read or parsed.
+  Gltf(::gltf::Error),
+  /// The file does not contain a node with both a mesh and a skin.
+  NoSkinnedMesh,
+  /// A primitive of a mesh is missing a required attribute.
+  MissingAttribute(&'static str),
+  /// The skin has more joints than the skinned pipeline supports.
+  TooManyJoints(usize),
+}
+
+
+impl fmt::Display for GltfError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      GltfError::Gltf(e) => write!(f, "could not load gltf: {}", e),
+      GltfError::NoSkinnedMesh => write!(f, "no node has both a mesh and a skin"),
+      GltfError::MissingAttribute(name) => write!(f, "mesh is missing {}", name),
+      GltfError::TooManyJoints(n) => {
+        write!(f, "skin has {} joints but at most {} are supported", n, MAX_JOINTS)
+      }
+    }
+  }
+}
+
+
+impl Error for GltfError {}
+
+
+impl From<::gltf::Error> for GltfError {
+  fn from(e: ::gltf::Error) -> GltfError {
+    GltfError::Gltf(e)
+  }
+}
+
+
+/// The material of one primitive, and the loaded vertices it covers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfPrimitive {
+  /// The index of the material in the file, or None for glTF's default
+  /// material.
+  pub material_index: Option<usize>,
+  pub base_color: [f32; 4],
+  pub emissive: [f32; 3],
+  pub roughness: f32,
+  /// Whether the material is marked double sided, ie. its back faces should
+  /// be lit too.
+  pub double_sided: bool,
+  /// The range of the loaded vertices drawn with this material.
+  pub vertices: Range<usize>,
+}
+
+
+impl GltfPrimitive {
+  fn new(primitive: &::gltf::Primitive, vertices: Range<usize>) -> GltfPrimitive {
+    let material = primitive.material();
+    let pbr = material.pbr_metallic_roughness();
+    GltfPrimitive {
+      material_index: material.index(),
+      base_color: pbr.base_color_factor(),
+      emissive: material.emissive_factor(),
+      roughness: pbr.roughness_factor(),
+      double_sided: material.double_sided(),
+      vertices,
+    }
+  }
+
+
+  /// A phong material approximating this primitive's metallic-roughness one.
+  /// Rougher materials get a dimmer, wider highlight, and double sided
+  /// materials set `Material::double_sided`.
+  pub fn to_material(&self) -> Material {
+    let e = self.emissive;
+    let c = self.base_color;
+    let specular = 1.0 - self.roughness;
+    Material {
+      emission: [e[0], e[1], e[2], 1.0],
+      ambient: [c[0] * 0.1, c[1] * 0.1, c[2] * 0.1, c[3]],
+      diffuse: c,
+      specular: [specular, specular, specular, 1.0],
+      shininess: 2.0 + specular * 126.0,
+      double_sided: if self.double_sided { 1 } else { 0 },
+    }
+  }
+}
+
+
+/// Every mesh in a glTF file as one vertex colored triangle list.
+pub struct ColoredGltf {
+  /// The vertices as a non-indexed triangle list.
+  pub vertices: Vec<VertexPhongColor>,
+  /// Counts and bounds of `vertices`. The material count is the number of
+  /// different materials the file's primitives use.
+  pub stats: MeshStats,
+  /// The material of each primitive, in the order their vertices were loaded.
+  pub primitives: Vec<GltfPrimitive>,
+}
+
+
+/// Load every mesh in a glTF file as one vertex colored, non-indexed triangle
+/// list. Vertices without a COLOR_0 attribute are white.
+///
+/// Node transforms are ignored, so this is best suited to files with a single
+/// mesh, eg. a scanned or vertex painted asset.
+pub fn load_colored(path: &Path) -> Result<ColoredGltf, GltfError> {
+  let (document, buffers, _images) = ::gltf::import(path)?;
+  let get_buffer = |buffer: ::gltf::Buffer| Some(&buffers[buffer.index()].0[..]);
+
+  let mut vertices = vec![];
+  let mut primitives = vec![];
+  let mut materials = HashSet::new();
+  for mesh in document.meshes() {
+    for primitive in mesh.primitives() {
+      materials.insert(primitive.material().index());
+      let start = vertices.len();
+      let reader = primitive.reader(get_buffer);
+      let positions:Vec<[f32; 3]> = reader
+        .read_positions()
+        .ok_or(GltfError::MissingAttribute("POSITION"))?
+        .collect();
+      let normals:Vec<[f32; 3]> = reader
+        .read_normals()
+        .ok_or(GltfError::MissingAttribute("NORMAL"))?
+        .collect();
+      let colors:Vec<[f32; 4]> = match reader.read_colors(0) {
+        Some(colors) => colors.into_rgba_f32().collect(),
+        None => vec![[1.0, 1.0, 1.0, 1.0]; positions.len()],
+      };
+      let indices:Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0 .. positions.len() as u32).collect(),
+      };
+
+      for i in indices {
+        let i = i as usize;
+        vertices.push(VertexPhongColor {
+          position: positions[i],
+          normal: normals[i],
+          color: colors[i],
+        });
+      }
+      primitives.push(GltfPrimitive::new(&primitive, start .. vertices.len()));
+    }
+  }
+
+  let stats = MeshStats::from_triangle_list(&vertices, materials.len());
+  Ok(ColoredGltf {
+    vertices,
+    stats,
+    primitives,
+  })
+}
+
+
+/// A skinned mesh, its skeleton and its animations.
+pub struct SkinnedGltf {
+  /// The mesh's vertices as a non-indexed triangle list.
+  pub vertices: Vec<VertexSkinned>,
+  /// Counts and bounds of `vertices` in the bind pose. The material count is
+  /// the number of different materials the mesh's primitives use.
+  pub stats: MeshStats,
+  /// The material of each primitive, in the order their vertices were loaded.
+  pub primitives: Vec<GltfPrimitive>,
+  pub skeleton: Skeleton,
+  pub clips: Vec<SkeletalClip>,
+}
+
+
+/// Load the first skinned mesh in a glTF file, along with its skeleton and
+/// all of the file's animations.
+///
+/// The skinned mesh's own node transform is ignored, it is expected to be
+/// placed with the model matrix instead. Cubic spline animations are
+/// approximated with a Catmull-Rom spline through their keyframes.
+pub fn load_skinned(path: &Path) -> Result<SkinnedGltf, GltfError> {
+  let (document, buffers, _images) = ::gltf::import(path)?;
+  let get_buffer = |buffer: ::gltf::Buffer| Some(&buffers[buffer.index()].0[..]);
+
+  let node = document
+    .nodes()
+    .find(|node| node.mesh().is_some() && node.skin().is_some())
+    .ok_or(GltfError::NoSkinnedMesh)?;
+  let mesh = node.mesh().ok_or(GltfError::NoSkinnedMesh)?;
+  let skin = node.skin().ok_or(GltfError::NoSkinnedMesh)?;
+
+  // Vertices
+  let mut vertices = vec![];
+  let mut primitives = vec![];
+  let mut materials = HashSet::new();
+  for primitive in mesh.primitives() {
+    materials.insert(primitive.material().index());
+    let start = vertices.len();
+    let reader = primitive.reader(get_buffer);
+    let positions:Vec<[f32; 3]> = reader
+      .read_positions()
+      .ok_or(GltfError::MissingAttribute("POSITION"))?
+      .collect();
+    let normals:Vec<[f32; 3]> = reader
+      .read_normals()
+      .ok_or(GltfError::MissingAttribute("NORMAL"))?
+      .collect();
+    let joints:Vec<[u16; 4]> = reader
+      .read_joints(0)
+      .ok_or(GltfError::MissingAttribute("JOINTS_0"))?
+      .into_u16()
+      .collect();
+    let weights:Vec<[f32; 4]> = reader
+      .read_weights(0)
+      .ok_or(GltfError::MissingAttribute("WEIGHTS_0"))?
+      .into_f32()
+      .collect();
+    let indices:Vec<u32> = match reader.read_indices() {
+      Some(indices) => indices.into_u32().collect(),
+      None => (0 .. positions.len() as u32).collect(),
+    };
+
+    for i in indices {
+      let i = i as usize;
+      let j = joints[i];
+      vertices.push(VertexSkinned {
+        position: positions[i],
+        normal: normals[i],
+        joint_indices: [j[0] as u32, j[1] as u32, j[2] as u32, j[3] as u32],
+        weights: weights[i],
+      });
+    }
+    primitives.push(GltfPrimitive::new(&primitive, start .. vertices.len()));
+  }
+
+  // Skeleton
+  let num_nodes = document.nodes().count();
+  let mut parents = vec![None; num_nodes];
+  let mut rest_pose = vec![NodeTransform::identity(); num_nodes];
+  for node in document.nodes() {
+    for child in node.children() {
+      parents[child.index()] = Some(node.index());
+    }
+    let (t, r, s) = node.transform().decomposed();
+    rest_pose[node.index()] = NodeTransform {
+      translation: Vector3::from(t),
+      // glTF stores quaternions as [x, y, z, w]
+      rotation: Quaternion::new(r[3], r[0], r[1], r[2]),
+      scale: Vector3::from(s),
+    };
+  }
+
+  let joints:Vec<usize> = skin
+    .joints()
+    .map(|joint| joint.index())
+    .collect();
+  if joints.len() > MAX_JOINTS {
+    return Err(GltfError::TooManyJoints(joints.len()));
+  }
+  let inverse_bind_matrices = match skin.reader(get_buffer).read_inverse_bind_matrices() {
+    Some(mats) => mats.map(Matrix4::from).collect(),
+    None => vec![Matrix4::identity(); joints.len()],
+  };
+
+  let skeleton = Skeleton {
+    parents,
+    rest_pose,
+    joints,
+    inverse_bind_matrices,
+  };
+
+  // Animations
+  let mut clips = vec![];
+  for (ndx, animation) in document.animations().enumerate() {
+    let name = animation
+      .name()
+      .map(|name| name.to_string())
+      .unwrap_or(format!("animation_{}", ndx));
+    let mut clip = SkeletalClip::new(&name);
+
+    for channel in animation.channels() {
+      let target = channel.target().node().index();
+      let interpolation = channel.sampler().interpolation();
+      let reader = channel.reader(get_buffer);
+      let inputs:Vec<f32> = match reader.read_inputs() {
+        Some(inputs) => inputs.collect(),
+        None => continue,
+      };
+
+      match reader.read_outputs() {
+        Some(ReadOutputs::Translations(outputs)) => {
+          let values = keyframe_values(interpolation, outputs.map(Vector3::from).collect());
+          clip.translations.push((target, track(interpolation, &inputs, values)));
+        }
+        Some(ReadOutputs::Rotations(outputs)) => {
+          let values = outputs
+            .into_f32()
+            .map(|r| Quaternion::new(r[3], r[0], r[1], r[2]))
+            .collect();
+          let values = keyframe_values(interpolation, values);
+          clip.rotations.push((target, track(interpolation, &inputs, values)));
+        }
+        Some(ReadOutputs::Scales(outputs)) => {
+          let values = keyframe_values(interpolation, outputs.map(Vector3::from).collect());
+          clip.scales.push((target, track(interpolation, &inputs, values)));
+        }
+        // Morph target weights are not supported by the skinned pipeline.
+        Some(ReadOutputs::MorphTargetWeights(_)) | None => {}
+      }
+    }
+
+    clips.push(clip);
+  }
+
+  let stats = MeshStats::from_triangle_list(&vertices, materials.len());
+  Ok(SkinnedGltf {
+    vertices,
+    stats,
+    primitives,
+    skeleton,
+    clips,
+  })
+}
+
+
+/// Cubic spline outputs store an in-tangent, a value and an out-tangent for
+/// each keyframe. This drops the tangents.
+fn keyframe_values<T>(interpolation: GltfInterpolation, values: Vec<T>) -> Vec<T> {
+  match interpolation {
+    GltfInterpolation::CubicSpline => {
+      values
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| i % 3 == 1)
+        .map(|(_, v)| v)
+        .collect()
+    }
+    _ => values,
+  }
+}
+
+
+fn track<T: Animatable>(
+  interpolation: GltfInterpolation,
+  inputs: &[f32],
+  values: Vec<T>
+) -> Track<T> {
+  let interpolation = match interpolation {
+    GltfInterpolation::Step => Interpolation::Step,
+    GltfInterpolation::Linear => Interpolation::Linear,
+    GltfInterpolation::CubicSpline => Interpolation::Cubic,
+  };
+  inputs
+    .iter()
+    .zip(values.into_iter())
+    .fold(Track::new(interpolation), |track, (time, value)| {
+      track.with_keyframe(*time, value)
+    })
+}
@@ -0,0 +1,7 @@
+//! Loading meshes, point clouds, skeletons and animations from files.
+pub mod collada;
+pub mod gltf;
+pub mod ply;
+
+mod scene;
+pub use self::scene::{load_scene, LoadError, LoadedScene, MaterialLibrary};
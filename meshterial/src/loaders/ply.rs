@@ -0,0 +1,424 @@
+//! Loading meshes and point clouds from PLY files.
+//!
+//! ASCII, binary little endian and binary big endian files are supported.
+//! Vertex positions, normals and colors are read from the `vertex` element and
+//! polygons from the `face` element. Any other elements and properties are
+//! skipped.
+use nalgebra::Vector3;
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::super::bounds::Aabb;
+use super::super::mesh::MeshStats;
+use super::super::pipelines::color3d::VertexColor3;
+use super::super::pipelines::phong::VertexPhong;
+
+
+#[derive(Debug)]
+pub enum PlyError {
+  /// The file could not be read.
+  Io(io::Error),
+  /// The data doesn't start with a PLY header.
+  NotPly,
+  /// A line of the header could not be understood.
+  BadHeader(String),
+  /// The data ends before all of the elements in the header.
+  Truncated,
+  /// A value in an ASCII body could not be parsed.
+  BadValue(String),
+  /// The vertex element is missing a required property.
+  MissingProperty(&'static str),
+  /// A face refers to a vertex that doesn't exist.
+  BadIndex(usize),
+}
+
+
+impl fmt::Display for PlyError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PlyError::Io(e) => write!(f, "could not read ply: {}", e),
+      PlyError::NotPly => write!(f, "not a PLY file"),
+      PlyError::BadHeader(line) => write!(f, "bad PLY header line '{}'", line),
+      PlyError::Truncated => write!(f, "PLY data is truncated"),
+      PlyError::BadValue(value) => write!(f, "bad PLY value '{}'", value),
+      PlyError::MissingProperty(name) => write!(f, "vertex is missing {}", name),
+      PlyError::BadIndex(ndx) => write!(f, "face refers to missing vertex {}", ndx),
+    }
+  }
+}
+
+
+impl Error for PlyError {}
+
+
+impl From<io::Error> for PlyError {
+  fn from(e: io::Error) -> PlyError {
+    PlyError::Io(e)
+  }
+}
+
+
+/// The contents of a PLY file.
+#[derive(Debug, Clone)]
+pub enum Ply {
+  /// The file has faces. They are triangulated as fans into a non-indexed
+  /// triangle list for the phong pipeline. Faces of vertices without normals
+  /// get flat normals.
+  Mesh(Vec<VertexPhong>),
+  /// The file has no faces. Vertices without colors are white.
+  Points(Vec<VertexColor3>),
+}
+
+
+impl Ply {
+  /// Counts and bounds of what was loaded. A mesh has one material, and
+  /// points have no triangles or materials.
+  pub fn stats(&self) -> MeshStats {
+    match self {
+      Ply::Mesh(vertices) => MeshStats::from_triangle_list(vertices, 1),
+      Ply::Points(points) => MeshStats {
+        vertex_count: points.len(),
+        bounds: Aabb::from_points(points.iter().map(|p| p.position)),
+        .. MeshStats::default()
+      },
+    }
+  }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+  Ascii,
+  LittleEndian,
+  BigEndian,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+  I8,
+  U8,
+  I16,
+  U16,
+  I32,
+  U32,
+  F32,
+  F64,
+}
+
+
+impl Scalar {
+  fn parse(name: &str) -> Option<Scalar> {
+    match name {
+      "char" | "int8" => Some(Scalar::I8),
+      "uchar" | "uint8" => Some(Scalar::U8),
+      "short" | "int16" => Some(Scalar::I16),
+      "ushort" | "uint16" => Some(Scalar::U16),
+      "int" | "int32" => Some(Scalar::I32),
+      "uint" | "uint32" => Some(Scalar::U32),
+      "float" | "float32" => Some(Scalar::F32),
+      "double" | "float64" => Some(Scalar::F64),
+      _ => None,
+    }
+  }
+
+
+  fn size(&self) -> usize {
+    match self {
+      Scalar::I8 | Scalar::U8 => 1,
+      Scalar::I16 | Scalar::U16 => 2,
+      Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+      Scalar::F64 => 8,
+    }
+  }
+
+
+  /// The value that represents full intensity in a color channel.
+  fn color_max(&self) -> f64 {
+    match self {
+      Scalar::U8 => 255.0,
+      Scalar::U16 => 65535.0,
+      _ => 1.0,
+    }
+  }
+}
+
+
+#[derive(Debug, Clone)]
+enum Property {
+  Scalar(String, Scalar),
+  /// A list with the type of its length and the type of its items.
+  List(String, Scalar, Scalar),
+}
+
+
+impl Property {
+  fn name(&self) -> &str {
+    match self {
+      Property::Scalar(name, _) | Property::List(name, _, _) => name,
+    }
+  }
+}
+
+
+#[derive(Debug, Clone)]
+struct Element {
+  name: String,
+  count: usize,
+  properties: Vec<Property>,
+}
+
+
+/// Reads values from the body of a PLY file, in either encoding.
+struct Body<'a> {
+  format: Format,
+  bytes: &'a [u8],
+  offset: usize,
+}
+
+
+impl<'a> Body<'a> {
+  fn next_token(&mut self) -> Option<&'a str> {
+    while self.offset < self.bytes.len() && self.bytes[self.offset].is_ascii_whitespace() {
+      self.offset += 1;
+    }
+    let start = self.offset;
+    while self.offset < self.bytes.len() && !self.bytes[self.offset].is_ascii_whitespace() {
+      self.offset += 1;
+    }
+    if start == self.offset {
+      None
+    } else {
+      ::std::str::from_utf8(&self.bytes[start .. self.offset]).ok()
+    }
+  }
+
+
+  fn read(&mut self, scalar: Scalar) -> Result<f64, PlyError> {
+    if self.format == Format::Ascii {
+      let token = self.next_token().ok_or(PlyError::Truncated)?;
+      return token
+        .parse::<f64>()
+        .map_err(|_| PlyError::BadValue(token.to_string()));
+    }
+
+    let size = scalar.size();
+    if self.offset + size > self.bytes.len() {
+      return Err(PlyError::Truncated);
+    }
+    let mut raw = [0u8; 8];
+    raw[.. size].copy_from_slice(&self.bytes[self.offset .. self.offset + size]);
+    self.offset += size;
+    if self.format == Format::BigEndian {
+      raw[.. size].reverse();
+    }
+    let u16_at = |raw: &[u8; 8]| raw[0] as u16 | (raw[1] as u16) << 8;
+    let u32_at = |raw: &[u8; 8]| {
+      raw[0] as u32 | (raw[1] as u32) << 8 | (raw[2] as u32) << 16 | (raw[3] as u32) << 24
+    };
+    Ok(match scalar {
+      Scalar::I8 => raw[0] as i8 as f64,
+      Scalar::U8 => raw[0] as f64,
+      Scalar::I16 => u16_at(&raw) as i16 as f64,
+      Scalar::U16 => u16_at(&raw) as f64,
+      Scalar::I32 => u32_at(&raw) as i32 as f64,
+      Scalar::U32 => u32_at(&raw) as f64,
+      Scalar::F32 => f32::from_bits(u32_at(&raw)) as f64,
+      Scalar::F64 => {
+        let mut bits:u64 = 0;
+        for i in 0 .. 8 {
+          bits |= (raw[i] as u64) << (i * 8);
+        }
+        f64::from_bits(bits)
+      }
+    })
+  }
+}
+
+
+/// Split the header from the body, returning the format, the elements and
+/// the offset of the first byte of the body.
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize), PlyError> {
+  if !bytes.starts_with(b"ply") {
+    return Err(PlyError::NotPly);
+  }
+  let mut format = None;
+  let mut elements:Vec<Element> = vec![];
+  let mut offset = 0;
+  loop {
+    if offset >= bytes.len() {
+      return Err(PlyError::Truncated);
+    }
+    let end = bytes[offset ..]
+      .iter()
+      .position(|b| *b == b'\n')
+      .map(|n| offset + n)
+      .unwrap_or_else(|| bytes.len());
+    let line = String::from_utf8_lossy(&bytes[offset .. end]).trim().to_string();
+    offset = end + 1;
+
+    let words:Vec<&str> = line.split_whitespace().collect();
+    let bad_header = || PlyError::BadHeader(line.clone());
+    match words.first() {
+      None | Some(&"comment") | Some(&"obj_info") => continue,
+      _ => {}
+    }
+    match words.as_slice() {
+      ["ply"] => {}
+      ["format", name, _version] => {
+        format = Some(match *name {
+          "ascii" => Format::Ascii,
+          "binary_little_endian" => Format::LittleEndian,
+          "binary_big_endian" => Format::BigEndian,
+          _ => return Err(bad_header()),
+        });
+      }
+      ["element", name, count] => {
+        elements.push(Element {
+          name: name.to_string(),
+          count: count.parse().map_err(|_| bad_header())?,
+          properties: vec![],
+        });
+      }
+      ["property", "list", len, item, name] => {
+        let len = Scalar::parse(len).ok_or_else(bad_header)?;
+        let item = Scalar::parse(item).ok_or_else(bad_header)?;
+        elements
+          .last_mut()
+          .ok_or_else(bad_header)?
+          .properties
+          .push(Property::List(name.to_string(), len, item));
+      }
+      ["property", ty, name] => {
+        let ty = Scalar::parse(ty).ok_or_else(bad_header)?;
+        elements
+          .last_mut()
+          .ok_or_else(bad_header)?
+          .properties
+          .push(Property::Scalar(name.to_string(), ty));
+      }
+      ["end_header"] => break,
+      _ => return Err(bad_header()),
+    }
+  }
+  let format = format.ok_or_else(|| PlyError::BadHeader("missing format".to_string()))?;
+  Ok((format, elements, offset.min(bytes.len())))
+}
+
+
+/// Parse the contents of a PLY file.
+pub fn parse(bytes: &[u8]) -> Result<Ply, PlyError> {
+  let (format, elements, offset) = parse_header(bytes)?;
+  let mut body = Body { format, bytes, offset };
+
+  let mut positions:Vec<[f32; 3]> = vec![];
+  let mut normals:Vec<[f32; 3]> = vec![];
+  let mut colors:Vec<[f32; 4]> = vec![];
+  let mut faces:Vec<Vec<usize>> = vec![];
+
+  for element in &elements {
+    let find = |name: &str| element.properties.iter().position(|p| p.name() == name);
+    let is_vertex = element.name == "vertex";
+    let is_face = element.name == "face";
+    let position_ndx = [find("x"), find("y"), find("z")];
+    let normal_ndx = [find("nx"), find("ny"), find("nz")];
+    let color_ndx = [
+      find("red").or_else(|| find("r")),
+      find("green").or_else(|| find("g")),
+      find("blue").or_else(|| find("b")),
+      find("alpha").or_else(|| find("a")),
+    ];
+    let face_ndx = find("vertex_indices").or_else(|| find("vertex_index"));
+    if is_vertex && position_ndx.iter().any(Option::is_none) {
+      return Err(PlyError::MissingProperty("x, y or z"));
+    }
+    let has_normals = normal_ndx.iter().all(Option::is_some);
+    let has_colors = color_ndx[.. 3].iter().all(Option::is_some);
+
+    for _ in 0 .. element.count {
+      let mut scalars = vec![0.0; element.properties.len()];
+      let mut color_max = [1.0; 4];
+      for (ndx, property) in element.properties.iter().enumerate() {
+        match property {
+          Property::Scalar(_, ty) => {
+            scalars[ndx] = body.read(*ty)?;
+            if let Some(c) = color_ndx.iter().position(|c| *c == Some(ndx)) {
+              color_max[c] = ty.color_max();
+            }
+          }
+          Property::List(_, len_ty, item_ty) => {
+            let len = body.read(*len_ty)? as usize;
+            let mut items = Vec::with_capacity(len);
+            for _ in 0 .. len {
+              items.push(body.read(*item_ty)? as usize);
+            }
+            if is_face && face_ndx == Some(ndx) {
+              faces.push(items);
+            }
+          }
+        }
+      }
+
+      if is_vertex {
+        let get = |ndx: Option<usize>| ndx.map(|n| scalars[n] as f32).unwrap_or(0.0);
+        positions.push([get(position_ndx[0]), get(position_ndx[1]), get(position_ndx[2])]);
+        if has_normals {
+          normals.push([get(normal_ndx[0]), get(normal_ndx[1]), get(normal_ndx[2])]);
+        }
+        colors.push(if has_colors {
+          let channel = |c: usize| {
+            color_ndx[c]
+              .map(|n| (scalars[n] / color_max[c]) as f32)
+              .unwrap_or(1.0)
+          };
+          [channel(0), channel(1), channel(2), channel(3)]
+        } else {
+          [1.0, 1.0, 1.0, 1.0]
+        });
+      }
+    }
+  }
+
+  if faces.is_empty() {
+    let points = positions
+      .into_iter()
+      .zip(colors)
+      .map(|(position, color)| VertexColor3 { position, color })
+      .collect();
+    return Ok(Ply::Points(points));
+  }
+
+  let mut vertices = vec![];
+  for face in faces {
+    if let Some(ndx) = face.iter().find(|ndx| **ndx >= positions.len()) {
+      return Err(PlyError::BadIndex(*ndx));
+    }
+    for i in 1 .. face.len().saturating_sub(1) {
+      let tri = [face[0], face[i], face[i + 1]];
+      let flat_normal = {
+        let p = |n: usize| Vector3::from(positions[tri[n]]);
+        let normal = (p(1) - p(0)).cross(&(p(2) - p(0)));
+        let normal = normal.try_normalize(1e-12).unwrap_or_else(Vector3::y);
+        [normal.x, normal.y, normal.z]
+      };
+      for ndx in tri.iter() {
+        vertices.push(VertexPhong {
+          position: positions[*ndx],
+          normal: normals.get(*ndx).cloned().unwrap_or(flat_normal),
+        });
+      }
+    }
+  }
+  Ok(Ply::Mesh(vertices))
+}
+
+
+/// Load a PLY file. See `parse`.
+pub fn load(path: &Path) -> Result<Ply, PlyError> {
+  let bytes = fs::read(path)?;
+  parse(&bytes)
+}
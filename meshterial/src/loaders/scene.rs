@@ -0,0 +1,181 @@
+//! Assembling one scene from several files, eg. a level split into authored
+//! assets.
+use nalgebra::Matrix4;
+
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::super::mesh::Mesh;
+use super::super::pipelines::phong::Material;
+use super::super::scene::{Node, PhongItem, Scene};
+use super::super::VkRenderer;
+use super::collada::{self, ColladaError};
+
+
+#[derive(Debug)]
+pub enum LoadError {
+  /// A Collada file could not be loaded.
+  Collada(PathBuf, ColladaError),
+  /// The file's extension isn't one of the supported formats.
+  UnsupportedFormat(PathBuf),
+}
+
+
+impl fmt::Display for LoadError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      LoadError::Collada(path, e) => write!(f, "{}: {}", path.display(), e),
+      LoadError::UnsupportedFormat(path) => {
+        write!(f, "{}: only collada (.dae) scenes are supported", path.display())
+      }
+    }
+  }
+}
+
+
+impl Error for LoadError {}
+
+
+fn same_material(a: &Material, b: &Material) -> bool {
+  a.emission == b.emission
+    && a.ambient == b.ambient
+    && a.diffuse == b.diffuse
+    && a.specular == b.specular
+    && a.shininess == b.shininess
+    && a.double_sided == b.double_sided
+}
+
+
+/// The materials of several files, by their names in the merged scene.
+#[derive(Clone, Default)]
+pub struct MaterialLibrary {
+  materials: Vec<(String, Material)>,
+}
+
+
+impl MaterialLibrary {
+  pub fn new() -> MaterialLibrary {
+    MaterialLibrary::default()
+  }
+
+
+  /// Add a material named `name` in the file with the stem `stem`, returning
+  /// its name in the library.
+  ///
+  /// A material with the same name and definition as one already in the
+  /// library is shared. One that conflicts with an existing definition is
+  /// renamed `stem/name`.
+  pub fn insert(&mut self, stem: &str, name: &str, material: Material) -> String {
+    let existing = self
+      .materials
+      .iter()
+      .find(|(n, _)| n == name)
+      .map(|(_, m)| same_material(m, &material));
+    match existing {
+      None => {
+        self.materials.push((name.to_string(), material));
+        name.to_string()
+      }
+      Some(true) => name.to_string(),
+      Some(false) => {
+        let namespaced = format!("{}/{}", stem, name);
+        println!(
+          "Material '{}' from '{}' conflicts with an earlier one, loading it as '{}'.",
+          name, stem, namespaced
+        );
+        self.insert(stem, &namespaced, material)
+      }
+    }
+  }
+
+
+  pub fn get(&self, name: &str) -> Option<&Material> {
+    self
+      .materials
+      .iter()
+      .find(|(n, _)| n == name)
+      .map(|(_, m)| m)
+  }
+
+
+  /// The library's materials in the order they were first inserted.
+  pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a str, &'a Material)> + 'a {
+    self.materials.iter().map(|(n, m)| (n.as_str(), m))
+  }
+
+
+  pub fn names(&self) -> Vec<String> {
+    self.materials.iter().map(|(n, _)| n.clone()).collect()
+  }
+
+
+  pub fn len(&self) -> usize {
+    self.materials.len()
+  }
+
+
+  pub fn is_empty(&self) -> bool {
+    self.materials.is_empty()
+  }
+}
+
+
+/// A scene loaded from several files and the materials its items use.
+pub struct LoadedScene {
+  /// One root node for each file, in the order they were given, with a child
+  /// for each of the file's materials.
+  pub scene: Scene<PhongItem>,
+  pub materials: MaterialLibrary,
+}
+
+
+/// Load several Collada files into one scene, merging their material
+/// libraries. See `MaterialLibrary::insert` for how colliding names are
+/// handled.
+///
+/// Build the phong pipeline with each of `materials.names()`, eg. with
+/// `PhongPipelineConfig::with_material`, then set each material with
+/// `PhongPipeline::set_material`. Items of files that won't move separately
+/// can be combined further with `Mesh::merge`.
+pub fn load_scene(vkr: &VkRenderer, paths: &[&Path]) -> Result<LoadedScene, LoadError> {
+  let mut materials = MaterialLibrary::new();
+  let mut scene = Scene::new();
+  for path in paths {
+    let is_collada = path
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .map(|ext| ext.eq_ignore_ascii_case("dae"))
+      .unwrap_or(false);
+    if !is_collada {
+      return Err(LoadError::UnsupportedFormat(path.to_path_buf()));
+    }
+    let file = collada::load(path)
+      .map_err(|e| LoadError::Collada(path.to_path_buf(), e))?;
+    let stem = path
+      .file_stem()
+      .map(|stem| stem.to_string_lossy().into_owned())
+      .unwrap_or(String::new());
+
+    // Sort by name so that a file always loads the same way.
+    let mut vertices:Vec<_> = file.vertices.into_iter().collect();
+    vertices.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut node = Node::new(Matrix4::identity());
+    for (effect, vertices) in vertices {
+      let material = match file.materials.get(&effect) {
+        Some(material) => *material,
+        None => {
+          return Err(LoadError::Collada(path.to_path_buf(), ColladaError::MissingEffect(effect)));
+        }
+      };
+      let name = materials.insert(&stem, &effect, material);
+      let item = PhongItem {
+        mesh: Mesh::new(vkr, vertices),
+        material: name,
+      };
+      node = node.with_child(Node::new(Matrix4::identity()).with_item(item));
+    }
+    scene = scene.with_node(node);
+  }
+  Ok(LoadedScene { scene, materials })
+}
@@ -0,0 +1,84 @@
+//! Distance based level of detail selection.
+use nalgebra::{Matrix4, Point3, Vector3};
+
+use super::bounds::Aabb;
+use super::mesh::Mesh;
+
+
+/// Several versions of a mesh at different levels of detail.
+///
+/// Each level has a distance from which it is used. Levels are kept sorted by
+/// distance, so the first level is the most detailed version, used up close.
+/// Add levels with `with_level` to keep them sorted.
+pub struct Lod<V> {
+  levels: Vec<(f32, Mesh<V>)>,
+  /// The bounds of the mesh in model space, used to measure the distance to
+  /// the camera.
+  pub bounds: Aabb,
+}
+
+
+impl<V> Lod<V> {
+  pub fn new(bounds: Aabb) -> Lod<V> {
+    Lod {
+      levels: vec![],
+      bounds,
+    }
+  }
+
+
+  /// Add a level that is used from `distance` onward, until the next level's
+  /// distance.
+  pub fn with_level(mut self, distance: f32, mesh: Mesh<V>) -> Lod<V> {
+    let ndx = self
+      .levels
+      .iter()
+      .position(|(d, _)| *d > distance)
+      .unwrap_or(self.levels.len());
+    self.levels.insert(ndx, (distance, mesh));
+    self
+  }
+
+
+  /// The levels and the distances they are used from, closest first.
+  pub fn levels(&self) -> &[(f32, Mesh<V>)] {
+    &self.levels
+  }
+
+
+  /// The index of the level to use at the given distance. Distances closer
+  /// than the first level's use the first level. Returns None if there are no
+  /// levels.
+  pub fn select_index(&self, distance: f32) -> Option<usize> {
+    if self.levels.is_empty() {
+      return None;
+    }
+    Some(
+      self
+        .levels
+        .iter()
+        .rposition(|(d, _)| *d <= distance)
+        .unwrap_or(0)
+    )
+  }
+
+
+  /// The mesh to draw at the given distance.
+  pub fn select(&self, distance: f32) -> Option<&Mesh<V>> {
+    self.select_index(distance).map(|ndx| &self.levels[ndx].1)
+  }
+
+
+  /// The distance from a camera position to the center of the mesh's bounds,
+  /// after transforming them by `model`.
+  pub fn distance(&self, model: &Matrix4<f32>, camera_position: &Vector3<f32>) -> f32 {
+    let center = model.transform_point(&Point3::from_coordinates(self.bounds.center()));
+    (center.coords - camera_position).norm()
+  }
+
+
+  /// The mesh to draw for a camera at `camera_position`.
+  pub fn select_for(&self, model: &Matrix4<f32>, camera_position: &Vector3<f32>) -> Option<&Mesh<V>> {
+    self.select(self.distance(model, camera_position))
+  }
+}
@@ -0,0 +1,564 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::buffer::cpu_pool::{CpuBufferPool, CpuBufferPoolChunk};
+use vulkano::device::Device;
+use vulkano::memory::pool::StdMemoryPool;
+use nalgebra::{Matrix3, Matrix4, Point3, Vector3, U3};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::Arc;
+
+use super::bounds::{Aabb, BoundingSphere};
+use super::pipelines::color3d::VertexColor3;
+use super::pipelines::phong::VertexPhong;
+use super::pipelines::phong_color::VertexPhongColor;
+use super::pipelines::phong_skinned::VertexSkinned;
+use super::VkRenderer;
+
+
+/// Vertices with a position in model space, for CPU side geometry queries
+/// like `Ray::intersect_mesh`.
+pub trait Positioned {
+  fn position(&self) -> [f32; 3];
+}
+
+
+impl Positioned for VertexPhong {
+  fn position(&self) -> [f32; 3] {
+    self.position
+  }
+}
+
+
+impl Positioned for VertexPhongColor {
+  fn position(&self) -> [f32; 3] {
+    self.position
+  }
+}
+
+
+impl Positioned for VertexSkinned {
+  /// The position in the skeleton's bind pose.
+  fn position(&self) -> [f32; 3] {
+    self.position
+  }
+}
+
+
+impl Positioned for VertexColor3 {
+  fn position(&self) -> [f32; 3] {
+    self.position
+  }
+}
+
+
+/// Vertices that can be moved into another space, for baking transforms into
+/// geometry with `Mesh::merge`.
+pub trait Transformable: Positioned + Sized {
+  /// The vertex transformed by `transform`, with its normal, if it has one,
+  /// transformed by `normal_matrix` and renormalized.
+  fn transformed(&self, transform: &Matrix4<f32>, normal_matrix: &Matrix3<f32>) -> Self;
+}
+
+
+fn transform_position(position: [f32; 3], transform: &Matrix4<f32>) -> [f32; 3] {
+  let p = transform.transform_point(&Point3::from_coordinates(Vector3::from(position)));
+  [p.x, p.y, p.z]
+}
+
+
+fn transform_normal(normal: [f32; 3], normal_matrix: &Matrix3<f32>) -> [f32; 3] {
+  let n = normal_matrix * Vector3::from(normal);
+  n.try_normalize(::std::f32::EPSILON).unwrap_or(n).into()
+}
+
+
+impl Transformable for VertexPhong {
+  fn transformed(&self, transform: &Matrix4<f32>, normal_matrix: &Matrix3<f32>) -> VertexPhong {
+    VertexPhong {
+      position: transform_position(self.position, transform),
+      normal: transform_normal(self.normal, normal_matrix),
+    }
+  }
+}
+
+
+impl Transformable for VertexPhongColor {
+  fn transformed(&self, transform: &Matrix4<f32>, normal_matrix: &Matrix3<f32>) -> VertexPhongColor {
+    VertexPhongColor {
+      position: transform_position(self.position, transform),
+      normal: transform_normal(self.normal, normal_matrix),
+      color: self.color,
+    }
+  }
+}
+
+
+impl Transformable for VertexColor3 {
+  fn transformed(&self, transform: &Matrix4<f32>, _: &Matrix3<f32>) -> VertexColor3 {
+    VertexColor3 {
+      position: transform_position(self.position, transform),
+      color: self.color,
+    }
+  }
+}
+
+
+/// Bake each transform into its triangle list's vertices and concatenate
+/// them, see `Mesh::merge`. Transforms that mirror the geometry also reverse
+/// each triangle's winding, so the triangles keep facing the same way.
+pub fn merge_vertices<V: Transformable>(parts: &[(&[V], Matrix4<f32>)]) -> Vec<V> {
+  let mut merged = Vec::with_capacity(parts.iter().map(|part| part.0.len()).sum());
+  for (vertices, transform) in parts.iter() {
+    let linear: Matrix3<f32> = transform.fixed_slice::<U3, U3>(0, 0).into_owned();
+    let normal_matrix = linear
+      .try_inverse()
+      .map(|inverse| inverse.transpose())
+      .unwrap_or(linear);
+    let mirrored = linear.determinant() < 0.0;
+    for triangle in vertices.chunks(3) {
+      let transformed = triangle.iter().map(|v| v.transformed(transform, &normal_matrix));
+      if mirrored && triangle.len() == 3 {
+        merged.extend(transformed.rev());
+      } else {
+        merged.extend(transformed);
+      }
+    }
+  }
+  merged
+}
+
+
+/// A vertex buffer of a triangle list that can be drawn with any pipeline that
+/// takes vertices of type `V`.
+pub struct Mesh<V> {
+  pub vertex_buffer: Arc<CpuAccessibleBuffer<[V]>>,
+}
+
+
+impl<V: Send + Sync + 'static> Mesh<V> {
+  /// Create a mesh by uploading the given vertices.
+  pub fn new(vkr: &VkRenderer, vertices: Vec<V>) -> Mesh<V> {
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      vkr.device.clone(),
+      BufferUsage::all(),
+      vertices.into_iter()
+    ).expect("Could not create mesh vertex buffer.");
+    Mesh { vertex_buffer }
+  }
+
+
+  /// The number of vertices in the mesh.
+  pub fn len(&self) -> usize {
+    self.vertex_buffer.len()
+  }
+
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RawMeshError {
+  /// The number of bytes doesn't match the number of vertices.
+  SizeMismatch { expected: usize, actual: usize },
+}
+
+
+impl fmt::Display for RawMeshError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      RawMeshError::SizeMismatch { expected, actual } => {
+        write!(f, "expected {} bytes of vertices but got {}", expected, actual)
+      }
+    }
+  }
+}
+
+
+impl Error for RawMeshError {}
+
+
+impl Mesh<VertexPhong> {
+  /// Create a mesh from interleaved vertex data that is already laid out like
+  /// `VertexPhong` - six native endian f32s per vertex, the position followed
+  /// by the normal - eg. from a memory mapped file or the network. The bytes
+  /// are copied straight into the vertex buffer without being decoded, and
+  /// don't need to be aligned.
+  ///
+  /// Returns an error if `bytes` isn't exactly `vertex_count` vertices long.
+  pub fn from_raw_bytes(
+    device: Arc<Device>,
+    bytes: &[u8],
+    vertex_count: usize
+  ) -> Result<Mesh<VertexPhong>, RawMeshError> {
+    let expected = vertex_count
+      .checked_mul(mem::size_of::<VertexPhong>())
+      .unwrap_or(::std::usize::MAX);
+    if bytes.len() != expected {
+      return Err(RawMeshError::SizeMismatch { expected, actual: bytes.len() });
+    }
+    // VertexPhong is repr(C) and made only of f32s, so any bytes of the right
+    // length are valid vertices. They may not be aligned, eg. when they come
+    // from the middle of a file, so each vertex is read unaligned.
+    let vertices = bytes
+      .chunks(mem::size_of::<VertexPhong>())
+      .map(|chunk| unsafe { ptr::read_unaligned(chunk.as_ptr() as *const VertexPhong) });
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      device,
+      BufferUsage::all(),
+      vertices
+    ).expect("Could not create mesh vertex buffer.");
+    Ok(Mesh { vertex_buffer })
+  }
+}
+
+
+impl<V: Positioned + Send + Sync + 'static> Mesh<V> {
+  /// The box around the mesh's vertices in model space, or None if the mesh
+  /// is empty. This reads the vertex buffer, so compute it once after
+  /// loading rather than every frame.
+  pub fn aabb(&self) -> Option<Aabb> {
+    let vertices = self
+      .vertex_buffer
+      .read()
+      .expect("Could not read mesh vertex buffer.");
+    Aabb::from_points(vertices.iter().map(|v| v.position()))
+  }
+
+
+  /// The sphere around the mesh's vertices in model space, or None if the
+  /// mesh is empty. Like `aabb`, this reads the vertex buffer.
+  pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+    let vertices = self
+      .vertex_buffer
+      .read()
+      .expect("Could not read mesh vertex buffer.");
+    BoundingSphere::from_points(vertices.iter().map(|v| v.position()))
+  }
+
+
+  /// Counts and bounds of the mesh, drawn with one material. Like `aabb`,
+  /// this reads the vertex buffer.
+  pub fn stats(&self) -> MeshStats {
+    let vertices = self
+      .vertex_buffer
+      .read()
+      .expect("Could not read mesh vertex buffer.");
+    MeshStats::from_triangle_list(&*vertices, 1)
+  }
+}
+
+
+/// What was loaded, for logging and for spotting bad imports, eg. a model
+/// with no triangles or with bounds far from the origin.
+///
+/// Displays like `12,456 tris, 37,368 vertices, 3 materials, bounds [-1, 0, -1]
+/// to [1, 2, 1]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshStats {
+  pub vertex_count: usize,
+  pub triangle_count: usize,
+  pub material_count: usize,
+  /// The box around every vertex in model space, or None if there are none.
+  pub bounds: Option<Aabb>,
+}
+
+
+impl MeshStats {
+  /// The stats of a non-indexed triangle list. A trailing partial triangle
+  /// isn't counted.
+  pub fn from_triangle_list<V: Positioned>(vertices: &[V], material_count: usize) -> MeshStats {
+    MeshStats {
+      vertex_count: vertices.len(),
+      triangle_count: vertices.len() / 3,
+      material_count,
+      bounds: Aabb::from_points(vertices.iter().map(|v| v.position())),
+    }
+  }
+
+
+  /// The stats of both together, eg. to total the meshes of a model's
+  /// materials.
+  pub fn combine(&self, other: &MeshStats) -> MeshStats {
+    let bounds = match (&self.bounds, &other.bounds) {
+      (Some(a), Some(b)) => Some(Aabb { min: a.min.inf(&b.min), max: a.max.sup(&b.max) }),
+      (a, b) => a.clone().or_else(|| b.clone()),
+    };
+    MeshStats {
+      vertex_count: self.vertex_count + other.vertex_count,
+      triangle_count: self.triangle_count + other.triangle_count,
+      material_count: self.material_count + other.material_count,
+      bounds,
+    }
+  }
+}
+
+
+/// `n` with commas between each group of three digits.
+fn with_thousands(n: usize) -> String {
+  let digits = n.to_string();
+  let mut s = String::new();
+  for (i, c) in digits.chars().enumerate() {
+    if i > 0 && (digits.len() - i) % 3 == 0 {
+      s.push(',');
+    }
+    s.push(c);
+  }
+  s
+}
+
+
+impl fmt::Display for MeshStats {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{} tris, {} vertices, {} materials, ",
+      with_thousands(self.triangle_count),
+      with_thousands(self.vertex_count),
+      self.material_count
+    )?;
+    match &self.bounds {
+      Some(aabb) => write!(
+        f,
+        "bounds [{}, {}, {}] to [{}, {}, {}]",
+        aabb.min.x, aabb.min.y, aabb.min.z,
+        aabb.max.x, aabb.max.y, aabb.max.z
+      ),
+      None => write!(f, "no bounds"),
+    }
+  }
+}
+
+
+impl<V: Transformable + Clone + Send + Sync + 'static> Mesh<V> {
+  /// Combine meshes into one, baking each one's transform into its vertices,
+  /// so that static geometry sharing a material can be drawn with one draw
+  /// instead of one per mesh. This reads every mesh's vertex buffer.
+  ///
+  /// The merged mesh is drawn with an identity model matrix, and its parts
+  /// can no longer be moved or hidden separately.
+  pub fn merge(vkr: &VkRenderer, meshes: &[(Mesh<V>, Matrix4<f32>)]) -> Mesh<V> {
+    let contents = meshes
+      .iter()
+      .map(|(mesh, _)| {
+        mesh
+          .vertex_buffer
+          .read()
+          .expect("Could not read mesh vertex buffer.")
+      })
+      .collect::<Vec<_>>();
+    let parts = contents
+      .iter()
+      .zip(meshes.iter())
+      .map(|(vertices, (_, transform))| (&vertices[..], *transform))
+      .collect::<Vec<_>>();
+    Mesh::new(vkr, merge_vertices(&parts))
+  }
+}
+
+
+impl<V> Clone for Mesh<V> {
+  fn clone(&self) -> Mesh<V> {
+    Mesh { vertex_buffer: self.vertex_buffer.clone() }
+  }
+}
+
+
+/// Geometry that is rebuilt every frame, eg. immediate mode 2d or debug lines.
+///
+/// Vertices are pushed between `begin` and `finish`, and `finish` copies them
+/// into a chunk of a `CpuBufferPool`. Chunks are recycled once the GPU is done
+/// with them, so unlike creating a new `Mesh` each frame this doesn't allocate
+/// a new buffer every time.
+pub struct DynamicMesh<V> {
+  pool: CpuBufferPool<V>,
+  vertices: Vec<V>,
+}
+
+
+/// The vertices of a DynamicMesh for one frame. See
+/// `VkRenderer::draw_dynamic`.
+pub struct DynamicMeshChunk<V> {
+  /// None if no vertices were pushed.
+  pub vertex_buffer: Option<Arc<CpuBufferPoolChunk<V, Arc<StdMemoryPool>>>>,
+}
+
+
+impl<V: Send + Sync + 'static> DynamicMesh<V> {
+  pub fn new(vkr: &VkRenderer) -> DynamicMesh<V> {
+    DynamicMesh {
+      pool: CpuBufferPool::vertex_buffer(vkr.device.clone()),
+      vertices: vec![],
+    }
+  }
+
+
+  /// Start a new batch of vertices, discarding any that haven't been
+  /// finished.
+  pub fn begin(&mut self) {
+    self.vertices.clear();
+  }
+
+
+  pub fn push(&mut self, vertex: V) {
+    self.vertices.push(vertex);
+  }
+
+
+  pub fn extend<I: IntoIterator<Item = V>>(&mut self, vertices: I) {
+    self.vertices.extend(vertices);
+  }
+
+
+  /// The number of vertices pushed since `begin`.
+  pub fn len(&self) -> usize {
+    self.vertices.len()
+  }
+
+
+  pub fn is_empty(&self) -> bool {
+    self.vertices.is_empty()
+  }
+
+
+  /// Upload the pushed vertices, returning a chunk to draw this frame.
+  pub fn finish(&mut self) -> DynamicMeshChunk<V> {
+    if self.vertices.is_empty() {
+      return DynamicMeshChunk { vertex_buffer: None };
+    }
+    let chunk = self
+      .pool
+      .chunk(self.vertices.drain(..))
+      .expect("Could not allocate dynamic mesh chunk.");
+    DynamicMeshChunk { vertex_buffer: Some(Arc::new(chunk)) }
+  }
+}
+
+
+impl<V> DynamicMeshChunk<V> {
+  /// The number of vertices in the chunk.
+  pub fn len(&self) -> usize {
+    self
+      .vertex_buffer
+      .as_ref()
+      .map(|buffer| buffer.len())
+      .unwrap_or(0)
+  }
+
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+
+/// Meshes loaded from files, kept by path so that loading the same file again
+/// returns the mesh that is already uploaded instead of parsing and
+/// uploading it again. Like the renderer's texture store, but for meshes of
+/// one vertex type.
+///
+/// Paths are canonicalized, so `assets/../assets/cube.ply` and
+/// `assets/cube.ply` are the same mesh. Paths that can't be canonicalized,
+/// eg. because the file doesn't exist, are kept as they are.
+///
+/// ```ignore
+/// let mut cache = MeshCache::new();
+/// let rock = cache.load(Path::new("assets/rock.gltf"), |path| {
+///   gltf::load_colored(path).map(|gltf| Mesh::new(&vkr, gltf.vertices))
+/// })?;
+/// ```
+pub struct MeshCache<V> {
+  meshes: HashMap<PathBuf, Arc<Mesh<V>>>,
+}
+
+
+impl<V> Default for MeshCache<V> {
+  fn default() -> MeshCache<V> {
+    MeshCache::new()
+  }
+}
+
+
+impl<V> MeshCache<V> {
+  pub fn new() -> MeshCache<V> {
+    MeshCache {
+      meshes: HashMap::new(),
+    }
+  }
+
+
+  /// The key a path is kept under.
+  pub fn key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+  }
+
+
+  /// Return the mesh loaded from the path, or call `load` with the
+  /// canonicalized path to load it and keep it. Errors from `load` are
+  /// returned and nothing is kept, so the next call tries again.
+  pub fn load<F, E>(&mut self, path: &Path, load: F) -> Result<Arc<Mesh<V>>, E>
+    where F: FnOnce(&Path) -> Result<Mesh<V>, E>
+  {
+    let key = MeshCache::<V>::key(path);
+    if let Some(mesh) = self.meshes.get(&key) {
+      return Ok(mesh.clone());
+    }
+    let mesh = Arc::new(load(&key)?);
+    self.meshes.insert(key, mesh.clone());
+    Ok(mesh)
+  }
+
+
+  /// The mesh loaded from the path, if there is one.
+  pub fn get(&self, path: &Path) -> Option<Arc<Mesh<V>>> {
+    self.meshes.get(&MeshCache::<V>::key(path)).cloned()
+  }
+
+
+  pub fn contains(&self, path: &Path) -> bool {
+    self.meshes.contains_key(&MeshCache::<V>::key(path))
+  }
+
+
+  /// Forget the mesh loaded from the path, eg. because the file changed, so
+  /// that the next `load` loads it again. Meshes that are still drawn with
+  /// stay alive until their last `Arc` is dropped. Returns the mesh, if
+  /// there was one.
+  pub fn evict(&mut self, path: &Path) -> Option<Arc<Mesh<V>>> {
+    self.meshes.remove(&MeshCache::<V>::key(path))
+  }
+
+
+  /// Forget the meshes that are only held by the cache, keeping the ones
+  /// that are still in use elsewhere. Returns the number forgotten.
+  pub fn evict_unused(&mut self) -> usize {
+    let before = self.meshes.len();
+    self.meshes.retain(|_, mesh| Arc::strong_count(mesh) > 1);
+    before - self.meshes.len()
+  }
+
+
+  /// Forget every mesh.
+  pub fn clear(&mut self) {
+    self.meshes.clear();
+  }
+
+
+  /// The number of meshes kept.
+  pub fn len(&self) -> usize {
+    self.meshes.len()
+  }
+
+
+  pub fn is_empty(&self) -> bool {
+    self.meshes.is_empty()
+  }
+}
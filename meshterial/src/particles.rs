@@ -0,0 +1,177 @@
+//! A simple CPU simulated particle system.
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use nalgebra::{Matrix4, Vector3};
+
+use std::sync::Arc;
+
+use super::pipelines::particles::{vs, ParticlePipeline, VertexParticle};
+use super::VkRenderer;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Particle {
+  pub position: Vector3<f32>,
+  pub velocity: Vector3<f32>,
+  pub color: [f32; 4],
+  /// Seconds left until the particle dies.
+  pub life: f32,
+}
+
+
+/// How new particles are emitted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitConfig {
+  pub origin: Vector3<f32>,
+  pub velocity: Vector3<f32>,
+  /// The largest random change to each component of the velocity.
+  pub velocity_spread: f32,
+  pub color: [f32; 4],
+  /// How long each particle lives, in seconds.
+  pub life: f32,
+}
+
+
+impl Default for EmitConfig {
+  fn default() -> EmitConfig {
+    EmitConfig {
+      origin: Vector3::zeros(),
+      velocity: Vector3::new(0.0, 1.0, 0.0),
+      velocity_spread: 0.5,
+      color: [1.0, 1.0, 1.0, 1.0],
+      life: 2.0,
+    }
+  }
+}
+
+
+/// The corners of the two triangles drawn for each particle.
+const CORNERS: [[f32; 2]; 6] = [
+  [-1.0, -1.0], [1.0, -1.0], [1.0, 1.0],
+  [-1.0, -1.0], [1.0, 1.0], [-1.0, 1.0],
+];
+
+
+/// Particles that are moved and aged on the CPU, and re-uploaded each update.
+pub struct ParticleSystem {
+  pub particles: Vec<Particle>,
+  /// The most particles that can be alive at once. `emit` stops emitting once
+  /// this is reached.
+  pub max_particles: usize,
+  /// Added to each particle's velocity every second.
+  pub gravity: Vector3<f32>,
+  /// The size of each particle in world units.
+  pub size: f32,
+  /// Particles fade out over this many seconds before they die.
+  pub fade_time: f32,
+  vertex_buffer: Option<Arc<CpuAccessibleBuffer<[VertexParticle]>>>,
+  seed: u32,
+}
+
+
+impl ParticleSystem {
+  pub fn new(max_particles: usize) -> ParticleSystem {
+    ParticleSystem {
+      particles: vec![],
+      max_particles,
+      gravity: Vector3::new(0.0, -9.8, 0.0),
+      size: 0.1,
+      fade_time: 0.5,
+      vertex_buffer: None,
+      seed: 0x9e37_79b9,
+    }
+  }
+
+
+  /// A pseudo random number from -1.0 to 1.0.
+  fn random(&mut self) -> f32 {
+    // xorshift32
+    self.seed ^= self.seed << 13;
+    self.seed ^= self.seed >> 17;
+    self.seed ^= self.seed << 5;
+    (self.seed as f32 / ::std::u32::MAX as f32) * 2.0 - 1.0
+  }
+
+
+  /// Emit up to `count` new particles.
+  pub fn emit(&mut self, count: usize, config: &EmitConfig) {
+    let count = count.min(self.max_particles.saturating_sub(self.particles.len()));
+    for _ in 0 .. count {
+      let spread = Vector3::new(self.random(), self.random(), self.random()) * config.velocity_spread;
+      self.particles.push(Particle {
+        position: config.origin,
+        velocity: config.velocity + spread,
+        color: config.color,
+        life: config.life,
+      });
+    }
+  }
+
+
+  /// Age and move the particles by `dt` seconds, remove the dead ones and
+  /// upload the rest.
+  pub fn update(&mut self, dt: f32, vkr: &VkRenderer) {
+    let gravity = self.gravity;
+    for particle in self.particles.iter_mut() {
+      particle.velocity += gravity * dt;
+      particle.position += particle.velocity * dt;
+      particle.life -= dt;
+    }
+    self.particles.retain(|particle| particle.life > 0.0);
+
+    if self.particles.is_empty() {
+      self.vertex_buffer = None;
+      return;
+    }
+
+    let fade_time = self.fade_time;
+    let mut vertices = Vec::with_capacity(self.particles.len() * CORNERS.len());
+    for particle in &self.particles {
+      let mut color = particle.color;
+      if fade_time > 0.0 {
+        color[3] *= (particle.life / fade_time).min(1.0);
+      }
+      for corner in CORNERS.iter() {
+        vertices.push(VertexParticle {
+          position: particle.position.into(),
+          corner: *corner,
+          color,
+        });
+      }
+    }
+    self.vertex_buffer = Some(
+      CpuAccessibleBuffer::from_iter(
+        vkr.device.clone(),
+        BufferUsage::vertex_buffer(),
+        vertices.into_iter()
+      ).expect("Could not create particle vertex buffer.")
+    );
+  }
+
+
+  /// Draw the particles as they were at the last update.
+  pub fn draw(&self, vkr: &mut VkRenderer, pipeline: &ParticlePipeline, view: &Matrix4<f32>) {
+    if let Some(vertex_buffer) = self.vertex_buffer.as_ref() {
+      let billboard = vs::ty::Billboard {
+        view: (*view).into(),
+        size: self.size,
+      };
+      vkr.draw_with(pipeline, vertex_buffer.clone(), pipeline.proj.desc_set.clone(), billboard);
+    }
+  }
+
+
+  /// Draw the particles as they were at the last update as soft particles,
+  /// which fade out near the scene behind them. Soft particles must be
+  /// turned on with `VkRenderer::set_soft_particles`, otherwise nothing is
+  /// drawn. They are drawn when the frame is committed, after everything
+  /// else.
+  pub fn draw_soft(&self, vkr: &mut VkRenderer, view: &Matrix4<f32>) {
+    if let Some(vertex_buffer) = self.vertex_buffer.as_ref() {
+      let billboard = vs::ty::Billboard {
+        view: (*view).into(),
+        size: self.size,
+      };
+      vkr.queue_soft_particles(vertex_buffer.clone(), billboard);
+    }
+  }
+}
@@ -0,0 +1,256 @@
+//! Picking objects by rendering their ids into an offscreen target.
+//!
+//! Each mesh is drawn with an id chosen by the user in place of a color. The id
+//! under the cursor is then read back with `read_id_at` and mapped back to the
+//! user's object. 0 is reserved for "nothing".
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass};
+use vulkano::image::ImageUsage;
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::viewport::{Scissor, Viewport};
+use vulkano::sync::{now, GpuFuture};
+use nalgebra::Matrix4;
+
+use std::sync::Arc;
+
+use super::mesh::Mesh;
+use super::pipelines::phong::VertexPhong;
+use super::VkRenderer;
+
+mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/picking/vert.glsl"
+  }
+}
+
+mod fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/picking/frag.glsl"
+  }
+}
+
+
+/// An offscreen render pass that draws object ids into an `R32Uint` target.
+///
+/// Call `begin`, `draw` each pickable mesh with its id, `end`, and then
+/// `read_id_at`. The pass has its own command buffer, so it can be run
+/// between frames without interfering with the main render pass. Both `end`
+/// and `read_id_at` wait for the GPU, so picking should be done on demand
+/// (eg. on a mouse click) rather than every frame.
+pub struct PickingPass {
+  pub render_pass: Arc<RenderPassAbstract + Send + Sync>,
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  dimensions: [u32; 2],
+  id_image: Option<Arc<AttachmentImage>>,
+  framebuffer: Option<Arc<FramebufferAbstract + Send + Sync>>,
+  dynamic_state: DynamicState,
+  command_buffer_builder: Option<AutoCommandBufferBuilder>,
+}
+
+
+impl PickingPass {
+  pub fn new(vkr: &VkRenderer) -> PickingPass {
+    let device = vkr.device.clone();
+    let render_pass = Arc::new(
+      single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+          id: {
+            load: Clear,
+            store: Store,
+            format: Format::R32Uint,
+            samples: 1,
+          },
+          depth: {
+            load: Clear,
+            store: DontCare,
+            format: Format::D16Unorm,
+            samples: 1,
+          }
+        },
+        pass: {
+          color: [id],
+          depth_stencil: {depth}
+        }
+      ).expect("Could not create picking render pass.")
+    ) as Arc<RenderPassAbstract + Send + Sync>;
+
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexPhong>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .depth_stencil_simple_depth()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    PickingPass {
+      render_pass,
+      pipeline,
+      dimensions: [0, 0],
+      id_image: None,
+      framebuffer: None,
+      dynamic_state: DynamicState::none(),
+      command_buffer_builder: None,
+    }
+  }
+
+
+  /// Start drawing ids, clearing every pixel to 0. The target is resized to
+  /// match the renderer's framebuffers.
+  pub fn begin(&mut self, vkr: &VkRenderer) {
+    if self.framebuffer.is_none() || self.dimensions != vkr.dimensions {
+      self.dimensions = vkr.dimensions;
+      let id_image = AttachmentImage::with_usage(
+        vkr.device.clone(),
+        self.dimensions,
+        Format::R32Uint,
+        ImageUsage {
+          color_attachment: true,
+          transfer_source: true,
+          .. ImageUsage::none()
+        }
+      ).expect("Could not create picking id image.");
+      let depth_image = AttachmentImage::transient(
+        vkr.device.clone(),
+        self.dimensions,
+        Format::D16Unorm
+      ).expect("Could not create picking depth image.");
+      let framebuffer = Arc::new(
+        Framebuffer::start(self.render_pass.clone())
+          .add(id_image.clone()).expect("Could not add id image to framebuffer.")
+          .add(depth_image).expect("Could not add depth buffer to framebuffer.")
+          .build().expect("Could not build picking framebuffer.")
+      );
+      self.id_image = Some(id_image);
+      self.framebuffer = Some(framebuffer);
+      self.dynamic_state = DynamicState {
+        line_width: None,
+        viewports: Some(vec![Viewport {
+          origin: [0.0, 0.0],
+          dimensions: [self.dimensions[0] as f32, self.dimensions[1] as f32],
+          depth_range: 0.0 .. 1.0,
+        }]),
+        scissors: Some(vec![Scissor {
+          origin: [0, 0],
+          dimensions: self.dimensions,
+        }]),
+      };
+    }
+
+    let framebuffer = self.framebuffer.clone().expect("This should never happen");
+    self.command_buffer_builder = Some(
+      AutoCommandBufferBuilder::primary_one_time_submit(vkr.device.clone(), vkr.queue.family())
+        .expect("Could not create a new command buffer builder.")
+        .begin_render_pass(
+          framebuffer,
+          false,
+          vec![
+            [0u32, 0, 0, 0].into(),
+            1f32.into()
+          ]
+        ).expect("Could not begin picking render pass.")
+    );
+  }
+
+
+  /// Draw a mesh's id. `id` should not be 0.
+  pub fn draw(
+    &mut self,
+    mesh: &Mesh<VertexPhong>,
+    id: u32,
+    proj: &Matrix4<f32>,
+    model: &Matrix4<f32>,
+    view: &Matrix4<f32>
+  ) {
+    let pick = vs::ty::Pick {
+      mvp: (proj * view * model).into(),
+      id,
+    };
+    let cmds = self
+      .command_buffer_builder
+      .take()
+      .expect("Could not take picking command builder - maybe 'begin' was not called.")
+      .draw(
+        self.pipeline.clone(),
+        &self.dynamic_state,
+        vec![mesh.vertex_buffer.clone() as Arc<BufferAccess + Send + Sync>],
+        (),
+        pick
+      ).expect("Could not draw picking id.");
+    self.command_buffer_builder = Some(cmds);
+  }
+
+
+  /// Finish drawing ids and wait for them to be rendered.
+  pub fn end(&mut self, vkr: &mut VkRenderer) {
+    let command_buffer = self
+      .command_buffer_builder
+      .take()
+      .expect("Could not take picking command builder - maybe 'begin' was not called.")
+      .end_render_pass().expect("Could not end picking render pass.")
+      .build().expect("Could not build picking command buffer.");
+    let after = vkr
+      .previous_frame_end
+      .take()
+      .unwrap_or_else(|| Box::new(now(vkr.device.clone())) as Box<GpuFuture>);
+    after
+      .then_execute(vkr.queue.clone(), command_buffer)
+      .expect("Could not execute picking command buffer.")
+      .then_signal_fence_and_flush()
+      .expect("Could not flush picking command buffer.")
+      .wait(None)
+      .expect("Could not wait for picking.");
+    vkr.previous_frame_end = Some(Box::new(now(vkr.device.clone())) as Box<GpuFuture>);
+  }
+
+
+  /// The id drawn at a pixel by the last `begin`/`end`, or 0 if nothing was
+  /// drawn there. Pixels outside of the target are clamped to its edge.
+  pub fn read_id_at(&self, vkr: &mut VkRenderer, x: u32, y: u32) -> u32 {
+    let id_image = self
+      .id_image
+      .clone()
+      .expect("Could not get picking id image - maybe 'begin' was not called.");
+    let x = x.min(self.dimensions[0].saturating_sub(1));
+    let y = y.min(self.dimensions[1].saturating_sub(1));
+    let result = CpuAccessibleBuffer::from_iter(
+      vkr.device.clone(),
+      BufferUsage::all(),
+      vec![0u32].into_iter()
+    ).expect("Could not create picking readback buffer.");
+    let command_buffer =
+      AutoCommandBufferBuilder::primary_one_time_submit(vkr.device.clone(), vkr.queue.family())
+        .expect("Could not create a new command buffer builder.")
+        .copy_image_to_buffer_dimensions(id_image, result.clone(), [x, y, 0], [1, 1, 1], 0, 1, 0)
+        .expect("Could not copy picking id.")
+        .build()
+        .expect("Could not build picking readback command buffer.");
+    let after = vkr
+      .previous_frame_end
+      .take()
+      .unwrap_or_else(|| Box::new(now(vkr.device.clone())) as Box<GpuFuture>);
+    after
+      .then_execute(vkr.queue.clone(), command_buffer)
+      .expect("Could not execute picking readback.")
+      .then_signal_fence_and_flush()
+      .expect("Could not flush picking readback.")
+      .wait(None)
+      .expect("Could not wait for picking readback.");
+    vkr.previous_frame_end = Some(Box::new(now(vkr.device.clone())) as Box<GpuFuture>);
+
+    let id = result.read().expect("Could not read picking readback buffer.")[0];
+    id
+  }
+}
@@ -0,0 +1,150 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::Device;
+use vulkano::image::ImageViewAccess;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+
+use std::sync::Arc;
+
+use super::RenderPipeline;
+use super::tonemap::VertexTonemap;
+
+
+mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/tonemap/vert.glsl"
+  }
+}
+
+pub mod gradient_fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/background/gradient_frag.glsl"
+  }
+}
+
+pub mod skybox_fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/background/skybox_frag.glsl"
+  }
+}
+
+
+/// What is drawn behind everything else. See `VkRenderer::set_background`.
+#[derive(Clone)]
+pub enum Background {
+  /// Clear to a flat color.
+  Color([f32; 4]),
+  /// A vertical gradient from the top color to the bottom color.
+  Gradient([f32; 4], [f32; 4]),
+  /// Sample a cubemap, eg. an `ImmutableImage` created with
+  /// `Dimensions::Cubemap`, in the direction of each pixel.
+  Skybox(Arc<ImageViewAccess + Send + Sync>),
+}
+
+
+impl Default for Background {
+  fn default() -> Background {
+    Background::Color([0.0, 0.0, 1.0, 1.0])
+  }
+}
+
+
+/// The pipelines that draw a gradient or skybox background over the whole
+/// framebuffer. Neither tests or writes depth, so they must be drawn before
+/// any geometry.
+pub struct BackgroundPipeline {
+  pub gradient_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub skybox_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// One triangle that covers the whole framebuffer.
+  pub vertex_buffer: Arc<CpuAccessibleBuffer<[VertexTonemap]>>,
+  pub sampler: Arc<Sampler>,
+}
+
+
+impl BackgroundPipeline {
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>
+  ) -> BackgroundPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let gradient_fs = gradient_fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let skybox_fs = skybox_fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let gradient_pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexTonemap>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(gradient_fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+    let skybox_pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexTonemap>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(skybox_fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      device.clone(),
+      BufferUsage::vertex_buffer(),
+      vec![
+        VertexTonemap { position: [-1.0, -1.0] },
+        VertexTonemap { position: [3.0, -1.0] },
+        VertexTonemap { position: [-1.0, 3.0] },
+      ].into_iter()
+    ).expect("Could not create background vertex buffer.");
+
+    let sampler = Sampler::new(
+      device,
+      Filter::Linear, Filter::Linear,
+      MipmapMode::Nearest,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      0.0, 1.0, 0.0, 0.0
+    ).expect("Could not create skybox sampler.");
+
+    BackgroundPipeline {
+      gradient_pipeline,
+      skybox_pipeline,
+      vertex_buffer,
+      sampler,
+    }
+  }
+
+
+  /// A descriptor set that samples the given cubemap.
+  pub fn desc_set_for_skybox(
+    &self,
+    cubemap: Arc<ImageViewAccess + Send + Sync>
+  ) -> Arc<DescriptorSet + Send + Sync> {
+    Arc::new(
+      PersistentDescriptorSet::start(self.skybox_pipeline.clone(), 0)
+        .add_sampled_image(cubemap, self.sampler.clone()).expect("Could not add skybox cubemap.")
+        .build()
+        .expect("Could not build skybox set.")
+    )
+  }
+}
+
+
+impl RenderPipeline for BackgroundPipeline {
+  type Vertex = VertexTonemap;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.gradient_pipeline.clone()
+  }
+}
@@ -0,0 +1,117 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+
+use std::sync::Arc;
+
+use super::RenderPipeline;
+use super::tonemap::VertexTonemap;
+
+
+mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/tonemap/vert.glsl"
+  }
+}
+
+pub mod threshold_fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/bloom/threshold_frag.glsl"
+  }
+}
+
+pub mod blur_fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/bloom/blur_frag.glsl"
+  }
+}
+
+
+/// The post-process pipelines of bloom. See `VkRenderer::set_bloom`.
+///
+/// `threshold_pipeline` keeps the parts of the HDR image brighter than a
+/// threshold and `blur_pipeline` blurs them in one direction at a time. Both
+/// draw `vertex_buffer`, one triangle that covers the whole framebuffer.
+pub struct BloomPipeline {
+  pub threshold_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub blur_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub vertex_buffer: Arc<CpuAccessibleBuffer<[VertexTonemap]>>,
+  /// Filters linearly, so that the bloom images can be smaller than the
+  /// framebuffer.
+  pub sampler: Arc<Sampler>,
+}
+
+
+impl BloomPipeline {
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>
+  ) -> BloomPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let threshold_fs = threshold_fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let blur_fs = blur_fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let threshold_pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexTonemap>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(threshold_fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+    let blur_pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexTonemap>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(blur_fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      device.clone(),
+      BufferUsage::vertex_buffer(),
+      vec![
+        VertexTonemap { position: [-1.0, -1.0] },
+        VertexTonemap { position: [3.0, -1.0] },
+        VertexTonemap { position: [-1.0, 3.0] },
+      ].into_iter()
+    ).expect("Could not create bloom vertex buffer.");
+
+    let sampler = Sampler::new(
+      device,
+      Filter::Linear, Filter::Linear,
+      MipmapMode::Nearest,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      0.0, 1.0, 0.0, 0.0
+    ).expect("Could not create bloom sampler.");
+
+    BloomPipeline {
+      threshold_pipeline,
+      blur_pipeline,
+      vertex_buffer,
+      sampler,
+    }
+  }
+}
+
+
+impl RenderPipeline for BloomPipeline {
+  type Vertex = VertexTonemap;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.threshold_pipeline.clone()
+  }
+}
@@ -0,0 +1,39 @@
+//! A world space clip plane, shared by the phong and color3d pipelines. See
+//! `VkRenderer::set_clip_plane`.
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+
+use std::sync::Arc;
+
+
+/// The `ClipPlane` uniform block of the shaders.
+///
+/// The plane is `[a, b, c, d]` for the plane `a*x + b*y + c*z + d = 0`.
+/// Fragments at world positions where `a*x + b*y + c*z + d < 0` are
+/// discarded, so the normal `[a, b, c]` points toward what is kept. No clip
+/// plane is `[0, 0, 0, 1]`, which keeps everything.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct ClipPlaneUniform {
+  pub plane: [f32; 4],
+}
+
+
+impl From<Option<[f32; 4]>> for ClipPlaneUniform {
+  fn from(plane: Option<[f32; 4]>) -> ClipPlaneUniform {
+    ClipPlaneUniform {
+      plane: plane.unwrap_or([0.0, 0.0, 0.0, 1.0]),
+    }
+  }
+}
+
+
+/// A uniform buffer with no clip plane, which pipelines use until they are
+/// given the renderer's clip plane with `use_clip_plane`.
+pub(crate) fn no_clip_buffer(device: Arc<Device>) -> Arc<CpuAccessibleBuffer<ClipPlaneUniform>> {
+  CpuAccessibleBuffer::from_data(
+    device,
+    BufferUsage::uniform_buffer(),
+    ClipPlaneUniform::from(None)
+  ).expect("Could not create clip plane buffer.")
+}
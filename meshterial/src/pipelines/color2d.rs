@@ -10,6 +10,8 @@ use nalgebra::*;
 
 use std::sync::Arc;
 
+use super::RenderPipeline;
+
 mod vs {
   vulkano_shaders::shader!{
     ty: "vertex",
@@ -59,8 +61,7 @@ impl Color2DPipeline {
         .vertex_shader(vs.main_entry_point(), ())
       // The content of the vertex buffer describes a list of triangles.
         .triangle_list()
-      // Use a resizable viewport set to draw over the entire window
-        .viewports_dynamic_scissors_irrelevant(1)
+        .viewports_dynamic_scissors_dynamic(1)
       // See `vertex_shader`.
         .fragment_shader(fs.main_entry_point(), ())
         .depth_write(false)
@@ -95,3 +96,12 @@ impl Color2DPipeline {
     }
   }
 }
+
+
+impl RenderPipeline for Color2DPipeline {
+  type Vertex = VertexColor2;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
@@ -1,4 +1,4 @@
-use vulkano::buffer::BufferUsage;
+use vulkano::buffer::{BufferAccess, BufferUsage};
 use vulkano::buffer::device_local::DeviceLocalBuffer;
 use vulkano::descriptor::descriptor_set::{PersistentDescriptorSet, DescriptorSet};
 use vulkano::device::Device;
@@ -10,6 +10,12 @@ use nalgebra::*;
 
 use std::sync::Arc;
 
+use super::{PipelineBuildError, PipelineOptions, RenderPipeline, WithPipelineOptions};
+use super::clip::no_clip_buffer;
+use super::fog::no_fog_buffer;
+use super::log_depth::no_log_depth_buffer;
+use super::super::vk_renderer::VkRenderer;
+
 pub mod vs {
   vulkano_shaders::shader!{
     ty: "vertex",
@@ -36,6 +42,15 @@ pub struct Color3DPipeline {
   pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
   pub proj_uniform_device_buffer:Arc<DeviceLocalBuffer<Matrix4<f32>>>,
   pub proj_desc_set: Arc<DescriptorSet + Send + Sync>,
+  /// The fog, clip plane and log depth buffers bound in `proj_desc_set`, kept
+  /// so that `use_fog`, `use_clip_plane` and `use_logarithmic_depth` can each
+  /// replace one of them.
+  fog_buffer: Arc<BufferAccess + Send + Sync>,
+  clip_plane_buffer: Arc<BufferAccess + Send + Sync>,
+  log_depth_buffer: Arc<BufferAccess + Send + Sync>,
+  /// Whether the pipeline was built for reverse-Z, which logarithmic depth
+  /// doesn't support.
+  reverse_z: bool,
 }
 
 
@@ -45,6 +60,33 @@ impl Color3DPipeline {
     device: Arc<Device>,
     physical: PhysicalDevice
   ) -> Color3DPipeline {
+    Color3DPipeline::new_with_options(render_pass, device, physical, &PipelineOptions::default())
+  }
+
+
+  /// Creates a new Color3DPipeline with the given options, panicking if the
+  /// device doesn't support them. See `try_new_with_options`.
+  pub fn new_with_options(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    options: &PipelineOptions
+  ) -> Color3DPipeline {
+    Color3DPipeline::try_new_with_options(render_pass, device, physical, options)
+      .expect("Could not build color3d pipeline.")
+  }
+
+
+  /// Creates a new Color3DPipeline with the given options, or returns an
+  /// error naming the first device feature the options need that isn't
+  /// supported, so that the caller can fall back to other options.
+  pub fn try_new_with_options(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    options: &PipelineOptions
+  ) -> Result<Color3DPipeline, PipelineBuildError> {
+    options.check_supported(&device)?;
     let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
     let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
     let pipeline = Arc::new(
@@ -59,13 +101,11 @@ impl Color3DPipeline {
         .vertex_shader(vs.main_entry_point(), ())
       // The content of the vertex buffer describes a list of triangles.
         .triangle_list()
-      // Use a resizable viewport set to draw over the entire window
-        .viewports_dynamic_scissors_irrelevant(1)
+        .viewports_dynamic_scissors_dynamic(1)
       // See `vertex_shader`.
         .fragment_shader(fs.main_entry_point(), ())
-        .depth_stencil_simple_depth()
-      // Does it blend?
-        .blend_alpha_blending()
+        .with_options(options)
+      // Does it blend? That's `PipelineOptions::blend`.
       // Culling
         .cull_mode_back()
       // We have to indicate which subpass of which render pass this pipeline is going to be used
@@ -78,22 +118,104 @@ impl Color3DPipeline {
 
     // Uniform stuff
     let proj_uniform_device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
-      device,
+      device.clone(),
       BufferUsage::uniform_buffer_transfer_destination(),
       physical.queue_families()
     ).expect("Could not create uniform_device_buffer.");
 
-    let proj_desc_set = Arc::new(
-      PersistentDescriptorSet::start(pipeline.clone(), 0)
-        .add_buffer(proj_uniform_device_buffer.clone()).expect("Could not add uniform_device_buffer.")
-        .build()
-        .expect("Could not build uniform_desc_set.")
+    let fog_buffer: Arc<BufferAccess + Send + Sync> = no_fog_buffer(device.clone());
+    let clip_plane_buffer: Arc<BufferAccess + Send + Sync> = no_clip_buffer(device.clone());
+    let log_depth_buffer: Arc<BufferAccess + Send + Sync> = no_log_depth_buffer(device);
+    let proj_desc_set = Color3DPipeline::proj_desc_set(
+      pipeline.clone(),
+      proj_uniform_device_buffer.clone(),
+      fog_buffer.clone(),
+      clip_plane_buffer.clone(),
+      log_depth_buffer.clone()
     );
 
-    Color3DPipeline {
+    Ok(Color3DPipeline {
       pipeline,
       proj_uniform_device_buffer,
       proj_desc_set,
+      fog_buffer,
+      clip_plane_buffer,
+      log_depth_buffer,
+      reverse_z: options.is_reverse_z(),
+    })
+  }
+
+
+  /// The projection set, which also holds the fog, the clip plane and the
+  /// log depth.
+  fn proj_desc_set(
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    proj_buffer: Arc<DeviceLocalBuffer<Matrix4<f32>>>,
+    fog_buffer: Arc<BufferAccess + Send + Sync>,
+    clip_plane_buffer: Arc<BufferAccess + Send + Sync>,
+    log_depth_buffer: Arc<BufferAccess + Send + Sync>
+  ) -> Arc<DescriptorSet + Send + Sync> {
+    Arc::new(
+      PersistentDescriptorSet::start(pipeline, 0)
+        .add_buffer(proj_buffer).expect("Could not add uniform_device_buffer.")
+        .add_buffer(fog_buffer).expect("Could not add fog buffer.")
+        .add_buffer(clip_plane_buffer).expect("Could not add clip plane buffer.")
+        .add_buffer(log_depth_buffer).expect("Could not add log depth buffer.")
+        .build()
+        .expect("Could not build uniform_desc_set.")
+    )
+  }
+
+
+  fn rebuild_proj_desc_set(&mut self) {
+    self.proj_desc_set = Color3DPipeline::proj_desc_set(
+      self.pipeline.clone(),
+      self.proj_uniform_device_buffer.clone(),
+      self.fog_buffer.clone(),
+      self.clip_plane_buffer.clone(),
+      self.log_depth_buffer.clone()
+    );
+  }
+
+
+  /// Draw the renderer's fog, see `VkRenderer::set_fog`. Pipelines draw
+  /// without fog until this is called. This replaces `proj_desc_set`.
+  pub fn use_fog(&mut self, vkr: &VkRenderer) {
+    self.fog_buffer = vkr.fog_buffer();
+    self.rebuild_proj_desc_set();
+  }
+
+
+  /// Cut geometry with the renderer's clip plane, see
+  /// `VkRenderer::set_clip_plane`. Pipelines draw everything until this is
+  /// called. This replaces `proj_desc_set`.
+  pub fn use_clip_plane(&mut self, vkr: &VkRenderer) {
+    self.clip_plane_buffer = vkr.clip_plane_buffer();
+    self.rebuild_proj_desc_set();
+  }
+
+
+  /// Draw with the renderer's logarithmic depth, see
+  /// `VkRenderer::set_logarithmic_depth`. Pipelines draw with their
+  /// projection's depth until this is called. This replaces `proj_desc_set`.
+  ///
+  /// Logarithmic depth only works with a depth buffer cleared to 1.0, so
+  /// pipelines built for reverse-Z return an error.
+  pub fn use_logarithmic_depth(&mut self, vkr: &VkRenderer) -> Result<(), PipelineBuildError> {
+    if self.reverse_z {
+      return Err(PipelineBuildError::Conflict("use_logarithmic_depth", "depth_compare"));
     }
+    self.log_depth_buffer = vkr.log_depth_buffer();
+    self.rebuild_proj_desc_set();
+    Ok(())
+  }
+}
+
+
+impl RenderPipeline for Color3DPipeline {
+  type Vertex = VertexColor3;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
   }
 }
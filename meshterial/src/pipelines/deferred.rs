@@ -0,0 +1,384 @@
+//! Deferred shading, for scenes with more lights than forward phong shading
+//! can afford.
+//!
+//! Geometry is drawn once into a G-buffer of albedo, normal and position
+//! attachments, then each light is drawn as one fullscreen triangle that
+//! shades every pixel from the G-buffer, so the cost of a light doesn't
+//! depend on how much geometry there is. Both passes are subpasses of
+//! `deferred_render_pass`, so the G-buffer never leaves the GPU's tile memory
+//! on devices that support it.
+//!
+//! Forward phong stays the default. To render deferred, replace the
+//! renderer's render pass before building any pipelines and add the G-buffer
+//! to its framebuffers:
+//!
+//! ```ignore
+//! vkr.render_pass = deferred_render_pass(vkr.device.clone(), vkr.swapchain.format());
+//! let deferred = DeferredPipeline::new(vkr.render_pass.clone(), vkr.device.clone(), physical, materials);
+//! let gbuffer = GBuffer::new(vkr.device.clone(), vkr.dimensions);
+//! vkr.build_framebuffers(&gbuffer.attachments()).expect("Could not build framebuffers.");
+//! let lighting_set = deferred.lighting_set(&gbuffer);
+//! // Each frame:
+//! vkr.begin_rendering();
+//! vkr.draw(deferred.gbuffer_pipeline.clone(), buffer, (proj_set, material_set), modelviewnormal);
+//! vkr.next_subpass();
+//! deferred.draw_lights(&mut vkr, lighting_set.clone(), [0.1, 0.1, 0.1, 1.0], &lights);
+//! vkr.commit_rendering();
+//! ```
+//!
+//! The G-buffer (and `lighting_set`) must be recreated at the new size and
+//! given to `build_framebuffers` again whenever `start_next_frame` reports a
+//! resize, before `begin_rendering`. Deferred rendering can't be
+//! combined with HDR, and since the background pipelines draw into a single
+//! color attachment, only `Background::Color` can be used with it.
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::buffer::cpu_pool::CpuBufferPool;
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::image::ImageViewAccess;
+use vulkano::instance::PhysicalDevice;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::blend::{AttachmentBlend, BlendFactor};
+use nalgebra::Matrix4;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::log_depth::no_log_depth_buffer;
+use super::uniform::{uniform_buffer_usage, UniformDeviceAndDescriptor};
+use super::RenderPipeline;
+use super::phong::VertexPhong;
+use super::tonemap::VertexTonemap;
+use super::super::VkRenderer;
+
+
+mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/phong/vert.glsl"
+  }
+}
+
+pub mod gbuffer_fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/deferred/gbuffer_frag.glsl"
+  }
+}
+
+mod lighting_vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/tonemap/vert.glsl"
+  }
+}
+
+pub mod lighting_fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/deferred/lighting_frag.glsl"
+  }
+}
+
+pub use self::gbuffer_fs::ty::Material as DeferredMaterial;
+pub use self::lighting_fs::ty::DeferredLight;
+
+
+/// The format of the albedo attachment.
+pub const ALBEDO_FORMAT: Format = Format::R8G8B8A8Unorm;
+/// The format of the normal and position attachments.
+pub const GEOMETRY_FORMAT: Format = Format::R16G16B16A16Sfloat;
+
+
+/// A render pass with a G-buffer subpass and a lighting subpass.
+///
+/// Its attachments are the swapchain image, the depth buffer, then albedo,
+/// normal and position, matching `VkRenderer::build_framebuffers` with
+/// `GBuffer::attachments`.
+pub fn deferred_render_pass(
+  device: Arc<Device>,
+  color_format: Format
+) -> Arc<RenderPassAbstract + Send + Sync> {
+  Arc::new(
+    ordered_passes_renderpass!(
+      device,
+      attachments: {
+        color: {
+          load: Clear,
+          store: Store,
+          format: color_format,
+          samples: 1,
+        },
+        depth: {
+          load: Clear,
+          store: Store,
+          format: Format::D16Unorm,
+          samples: 1,
+        },
+        albedo: {
+          load: Clear,
+          store: DontCare,
+          format: ALBEDO_FORMAT,
+          samples: 1,
+        },
+        normal: {
+          load: Clear,
+          store: DontCare,
+          format: GEOMETRY_FORMAT,
+          samples: 1,
+        },
+        position: {
+          load: Clear,
+          store: DontCare,
+          format: GEOMETRY_FORMAT,
+          samples: 1,
+        }
+      },
+      passes: [
+        {
+          color: [albedo, normal, position],
+          depth_stencil: {depth},
+          input: []
+        },
+        {
+          color: [color],
+          depth_stencil: {},
+          input: [albedo, normal, position]
+        }
+      ]
+    ).expect("Could not create deferred render pass.")
+  )
+}
+
+
+/// The albedo, normal and position attachments of the G-buffer.
+pub struct GBuffer {
+  pub albedo: Arc<AttachmentImage>,
+  pub normal: Arc<AttachmentImage>,
+  pub position: Arc<AttachmentImage>,
+}
+
+
+impl GBuffer {
+  pub fn new(device: Arc<Device>, dimensions: [u32; 2]) -> GBuffer {
+    GBuffer {
+      albedo: AttachmentImage::transient_input_attachment(device.clone(), dimensions, ALBEDO_FORMAT)
+        .expect("Could not create albedo attachment."),
+      normal: AttachmentImage::transient_input_attachment(device.clone(), dimensions, GEOMETRY_FORMAT)
+        .expect("Could not create normal attachment."),
+      position: AttachmentImage::transient_input_attachment(device, dimensions, GEOMETRY_FORMAT)
+        .expect("Could not create position attachment."),
+    }
+  }
+
+
+  /// The attachments in the order `deferred_render_pass` expects, for
+  /// `VkRenderer::build_framebuffers`.
+  pub fn attachments(&self) -> Vec<Arc<ImageViewAccess + Send + Sync>> {
+    vec![
+      self.albedo.clone() as Arc<ImageViewAccess + Send + Sync>,
+      self.normal.clone() as Arc<ImageViewAccess + Send + Sync>,
+      self.position.clone() as Arc<ImageViewAccess + Send + Sync>,
+    ]
+  }
+}
+
+
+/// The G-buffer and lighting pipelines of deferred shading. See the module
+/// docs.
+///
+/// `gbuffer_pipeline` takes the same vertices, push constants and projection
+/// as `PhongPipeline::pipeline`, and a `DeferredMaterial`, which has the same
+/// fields as `Material`. Lights are drawn with `draw_lights`.
+pub struct DeferredPipeline {
+  pub gbuffer_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// Shades with the first light, replacing the clear color.
+  pub lighting_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// Adds the shading of each other light.
+  pub additive_lighting_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// One triangle that covers the whole framebuffer.
+  pub vertex_buffer: Arc<CpuAccessibleBuffer<[VertexTonemap]>>,
+  pub proj: UniformDeviceAndDescriptor<Matrix4<f32>>,
+  pub materials: HashMap<String, UniformDeviceAndDescriptor<DeferredMaterial>>,
+  pub material_buffer_pool: CpuBufferPool<DeferredMaterial>,
+}
+
+
+impl DeferredPipeline {
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    material_names: Vec<String>
+  ) -> DeferredPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let gbuffer_fs = gbuffer_fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let lighting_vs = lighting_vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let lighting_fs = lighting_fs::Shader::load(device.clone()).expect("failed to create shader module");
+
+    let gbuffer_pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexPhong>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(gbuffer_fs.main_entry_point(), ())
+        .depth_stencil_simple_depth()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+    let lighting_pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexTonemap>()
+        .vertex_shader(lighting_vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(lighting_fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 1).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+    let additive_lighting_pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexTonemap>()
+        .vertex_shader(lighting_vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(lighting_fs.main_entry_point(), ())
+        .blend_collective(AttachmentBlend {
+          color_source: BlendFactor::One,
+          color_destination: BlendFactor::One,
+          alpha_source: BlendFactor::One,
+          alpha_destination: BlendFactor::One,
+          .. AttachmentBlend::alpha_blending()
+        })
+        .render_pass(Subpass::from(render_pass, 1).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      device.clone(),
+      BufferUsage::vertex_buffer(),
+      vec![
+        VertexTonemap { position: [-1.0, -1.0] },
+        VertexTonemap { position: [3.0, -1.0] },
+        VertexTonemap { position: [-1.0, 3.0] },
+      ].into_iter()
+    ).expect("Could not create deferred lighting vertex buffer.");
+
+    // Uniform stuff
+    let proj = {
+      let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
+        device.clone(),
+        uniform_buffer_usage(),
+        physical.queue_families()
+      ).expect("Could not create uniform_device_buffer.");
+
+      let desc_set = Arc::new(
+        PersistentDescriptorSet::start(gbuffer_pipeline.clone(), 0)
+          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
+        // The vertex shader is phong's, which reads a log depth. The
+        // g-buffer is always drawn with the projection's depth.
+          .add_buffer(no_log_depth_buffer(device.clone())).expect("Could not add log depth buffer.")
+          .build()
+          .expect("Could not build uniform_desc_set.")
+      );
+
+      UniformDeviceAndDescriptor {
+        device_buffer, desc_set
+      }
+    };
+
+    let mut materials = HashMap::new();
+    for name in material_names {
+      let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
+        device.clone(),
+        uniform_buffer_usage(),
+        physical.queue_families()
+      ).expect("Could not create uniform_device_buffer.");
+
+      let desc_set = Arc::new(
+        PersistentDescriptorSet::start(gbuffer_pipeline.clone(), 1)
+          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
+          .build()
+          .expect("Could not build uniform_desc_set.")
+      );
+
+      materials.insert(name, UniformDeviceAndDescriptor { device_buffer, desc_set });
+    }
+
+    let material_buffer_pool = CpuBufferPool::upload(device);
+
+    DeferredPipeline {
+      gbuffer_pipeline,
+      lighting_pipeline,
+      additive_lighting_pipeline,
+      vertex_buffer,
+      proj,
+      materials,
+      material_buffer_pool,
+    }
+  }
+
+
+  /// A descriptor set that reads the given G-buffer in the lighting pass.
+  /// Like the G-buffer, this must be recreated after a resize.
+  pub fn lighting_set(&self, gbuffer: &GBuffer) -> Arc<DescriptorSet + Send + Sync> {
+    Arc::new(
+      PersistentDescriptorSet::start(self.lighting_pipeline.clone(), 0)
+        .add_image(gbuffer.albedo.clone()).expect("Could not add albedo attachment.")
+        .add_image(gbuffer.normal.clone()).expect("Could not add normal attachment.")
+        .add_image(gbuffer.position.clone()).expect("Could not add position attachment.")
+        .build()
+        .expect("Could not build lighting set.")
+    )
+  }
+
+
+  /// Shade the G-buffer with each of the lights, whose positions are in view
+  /// space. The `ambient` light is added once. This must be recorded in the
+  /// lighting subpass, after `VkRenderer::next_subpass`.
+  pub fn draw_lights(
+    &self,
+    vkr: &mut VkRenderer,
+    lighting_set: Arc<DescriptorSet + Send + Sync>,
+    ambient: [f32; 4],
+    lights: &[DeferredLight]
+  ) {
+    let unlit = DeferredLight {
+      position: [0.0, 0.0, 0.0, 1.0],
+      intensity: [0.0, 0.0, 0.0, 0.0],
+      ambient,
+    };
+    let first = lights.first().cloned().unwrap_or(unlit);
+    vkr.draw(
+      self.lighting_pipeline.clone(),
+      self.vertex_buffer.clone(),
+      lighting_set.clone(),
+      DeferredLight { ambient, .. first }
+    );
+    for light in lights.iter().skip(1) {
+      vkr.draw(
+        self.additive_lighting_pipeline.clone(),
+        self.vertex_buffer.clone(),
+        lighting_set.clone(),
+        DeferredLight { ambient: [0.0; 4], .. *light }
+      );
+    }
+  }
+}
+
+
+impl RenderPipeline for DeferredPipeline {
+  type Vertex = VertexPhong;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.gbuffer_pipeline.clone()
+  }
+}
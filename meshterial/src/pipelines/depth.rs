@@ -0,0 +1,181 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Device;
+use vulkano::instance::PhysicalDevice;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::blend::AttachmentBlend;
+use vulkano::pipeline::depth_stencil::{Compare, DepthStencil};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use nalgebra::*;
+use std::sync::Arc;
+
+pub use super::uniform::*;
+pub use super::phong::VertexPhong;
+pub use super::unlit::vs;
+use super::RenderPipeline;
+use super::tonemap::VertexTonemap;
+
+mod fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/depth/frag.glsl"
+  }
+}
+
+mod clear_vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/tonemap/vert.glsl"
+  }
+}
+
+mod clear_fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/depth/clear_frag.glsl"
+  }
+}
+
+
+/// A graphics pipeline that only writes depth, for a depth prepass.
+///
+/// To reduce overdraw in fill-rate heavy scenes, first draw all the opaque
+/// geometry with this pipeline, then draw it again in the same frame with
+/// pipelines built with `PipelineOptions::after_depth_prepass`. Those only
+/// shade the pixels whose depth matches the prepass, so each pixel is shaded
+/// once. Both passes must use the same transforms.
+///
+/// Push constants are `vs::ty::ModelView`.
+pub struct DepthPipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub proj: UniformDeviceAndDescriptor<Matrix4<f32>>,
+}
+
+
+impl DepthPipeline {
+  /// Creates a new DepthPipeline.
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice
+  ) -> DepthPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexPhong>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .depth_stencil_simple_depth()
+      // Don't touch the color attachment.
+        .blend_collective(AttachmentBlend {
+          mask_red: false,
+          mask_green: false,
+          mask_blue: false,
+          mask_alpha: false,
+          .. AttachmentBlend::pass_through()
+        })
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    // Uniform stuff
+    let proj = {
+      let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
+        device.clone(),
+        uniform_buffer_usage(),
+        physical.queue_families()
+      ).expect("Could not create uniform_device_buffer.");
+
+      let desc_set = Arc::new(
+        PersistentDescriptorSet::start(pipeline.clone(), 0)
+          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
+          .build()
+          .expect("Could not build uniform_desc_set.")
+      );
+
+      UniformDeviceAndDescriptor {
+        device_buffer, desc_set
+      }
+    };
+
+    DepthPipeline {
+      pipeline,
+      proj
+    }
+  }
+}
+
+
+impl RenderPipeline for DepthPipeline {
+  type Vertex = VertexPhong;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
+
+
+/// A pipeline that resets depth to the far plane under the viewport and
+/// scissor rect, leaving color untouched. See `VkRenderer::clear_depth_only`.
+pub struct DepthClearPipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// One triangle that covers the whole framebuffer.
+  pub vertex_buffer: Arc<CpuAccessibleBuffer<[VertexTonemap]>>,
+}
+
+
+impl DepthClearPipeline {
+  /// Creates a new DepthClearPipeline for the first subpass of the render
+  /// pass, which must have a depth attachment.
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>
+  ) -> DepthClearPipeline {
+    let vs = clear_vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = clear_fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexTonemap>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+      // Overwrite whatever depth is there.
+        .depth_stencil(DepthStencil {
+          depth_compare: Compare::Always,
+          .. DepthStencil::simple_depth_test()
+        })
+      // Don't touch the color attachment.
+        .blend_collective(AttachmentBlend {
+          mask_red: false,
+          mask_green: false,
+          mask_blue: false,
+          mask_alpha: false,
+          .. AttachmentBlend::pass_through()
+        })
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      device,
+      BufferUsage::vertex_buffer(),
+      vec![
+        VertexTonemap { position: [-1.0, -1.0] },
+        VertexTonemap { position: [3.0, -1.0] },
+        VertexTonemap { position: [-1.0, 3.0] },
+      ].into_iter()
+    ).expect("Could not create depth clear vertex buffer.");
+
+    DepthClearPipeline {
+      pipeline,
+      vertex_buffer,
+    }
+  }
+}
@@ -0,0 +1,251 @@
+//! Drawing egui's output, for debug UI. Requires the `egui` feature.
+//!
+//! egui's colors are sRGB, and are written to the framebuffer as they are, so
+//! they are only exact on a UNORM swapchain.
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, FixedSizeDescriptorSetsPool};
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::blend::{AttachmentBlend, BlendFactor};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::sampler::SamplerAddressMode;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::sampler::SamplerConfig;
+use super::RenderPipeline;
+use super::super::VkRenderer;
+
+mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/egui/vert.glsl"
+  }
+}
+
+mod fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/egui/frag.glsl"
+  }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct VertexEgui {
+  /// The position in egui points.
+  pub position: [f32; 2],
+  pub uv: [f32; 2],
+  /// Premultiplied sRGBA.
+  pub color: [f32; 4],
+}
+impl_vertex!(VertexEgui, position, uv, color);
+
+
+impl<'a> From<&'a ::egui::epaint::Vertex> for VertexEgui {
+  fn from(v: &'a ::egui::epaint::Vertex) -> VertexEgui {
+    let c = v.color.to_array();
+    VertexEgui {
+      position: [v.pos.x, v.pos.y],
+      uv: [v.uv.x, v.uv.y],
+      color: [
+        c[0] as f32 / 255.0,
+        c[1] as f32 / 255.0,
+        c[2] as f32 / 255.0,
+        c[3] as f32 / 255.0
+      ],
+    }
+  }
+}
+
+
+/// A texture that egui has asked to be uploaded. A copy of the texels is kept
+/// so that partial updates can be applied by re-uploading the whole image.
+struct EguiTexture {
+  size: [usize; 2],
+  rgba: Vec<u8>,
+  desc_set: Arc<DescriptorSet + Send + Sync>,
+}
+
+
+/// A pipeline that draws the meshes tessellated by egui, clipping each one to
+/// its clip rect with the scissor. See `VkRenderer::draw_egui`.
+pub struct EguiPipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub sampler_config: SamplerConfig,
+  image_sampler_desc_pool: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+  textures: HashMap<::egui::TextureId, EguiTexture>,
+}
+
+
+impl EguiPipeline {
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>
+  ) -> EguiPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexEgui>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+      // Use a resizable viewport and scissor set. Both must be dynamic so that
+      // each mesh can be clipped to its clip rect.
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+      // UI is drawn over everything else.
+        .depth_write(false)
+      // egui's output is premultiplied.
+        .blend_collective(AttachmentBlend {
+          color_source: BlendFactor::One,
+          color_destination: BlendFactor::OneMinusSrcAlpha,
+          alpha_source: BlendFactor::OneMinusDstAlpha,
+          alpha_destination: BlendFactor::One,
+          .. AttachmentBlend::alpha_blending()
+        })
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    let image_sampler_desc_pool =
+      FixedSizeDescriptorSetsPool::new(pipeline.clone() as Arc<GraphicsPipelineAbstract + Send + Sync>, 0);
+
+    EguiPipeline {
+      pipeline,
+      sampler_config: SamplerConfig {
+        address_mode: SamplerAddressMode::ClampToEdge,
+        .. SamplerConfig::default()
+      },
+      image_sampler_desc_pool,
+      textures: HashMap::new(),
+    }
+  }
+
+
+  /// Upload new textures and apply partial updates to existing ones.
+  fn set_textures(&mut self, vkr: &mut VkRenderer, delta: &::egui::TexturesDelta) {
+    for (id, image_delta) in delta.set.iter() {
+      let size = image_delta.image.size();
+      let pixels:Vec<::egui::Color32> = match &image_delta.image {
+        ::egui::ImageData::Color(image) => image.pixels.clone(),
+        ::egui::ImageData::Alpha(image) => image.srgba_pixels(1.0).collect(),
+      };
+
+      let (tex_size, mut rgba) = match (image_delta.pos, self.textures.remove(id)) {
+        (Some(_), Some(texture)) => (texture.size, texture.rgba),
+        _ => (size, vec![0; size[0] * size[1] * 4]),
+      };
+      let [x0, y0] = image_delta.pos.unwrap_or([0, 0]);
+      for y in 0 .. size[1] {
+        for x in 0 .. size[0] {
+          if x0 + x >= tex_size[0] || y0 + y >= tex_size[1] {
+            continue;
+          }
+          let offset = ((y0 + y) * tex_size[0] + x0 + x) * 4;
+          rgba[offset .. offset + 4].copy_from_slice(&pixels[y * size[0] + x].to_array());
+        }
+      }
+
+      let image = vkr.upload_texture(
+        rgba.clone(),
+        tex_size[0] as u32,
+        tex_size[1] as u32,
+        Format::R8G8B8A8Unorm
+      );
+      let sampler = self.sampler_config.sampler(vkr.device.clone());
+      let desc_set = Arc::new(
+        self
+          .image_sampler_desc_pool
+          .next()
+          .add_sampled_image(image, sampler).expect("Could not add sampled image.")
+          .build().expect("Could not build the image sampler set.")
+      );
+      self.textures.insert(*id, EguiTexture { size: tex_size, rgba, desc_set });
+    }
+  }
+
+
+  /// Draw one frame of egui output. This should be called between
+  /// `begin_rendering` and `commit_rendering`, after drawing the scene.
+  pub fn draw(&mut self, vkr: &mut VkRenderer, ctx: &::egui::Context, output: ::egui::FullOutput) {
+    self.set_textures(vkr, &output.textures_delta);
+
+    let pixels_per_point = ctx.pixels_per_point();
+    let screen = vs::ty::Screen {
+      size: [
+        vkr.dimensions[0] as f32 / pixels_per_point,
+        vkr.dimensions[1] as f32 / pixels_per_point
+      ],
+    };
+    let prev_scissors = vkr.dynamic_state.scissors.clone();
+
+    for ::egui::ClippedMesh(clip_rect, mesh) in ctx.tessellate(output.shapes) {
+      if mesh.indices.is_empty() {
+        continue;
+      }
+      let desc_set = match self.textures.get(&mesh.texture_id) {
+        Some(texture) => texture.desc_set.clone(),
+        None => continue,
+      };
+
+      let min_x = (clip_rect.min.x * pixels_per_point).round().max(0.0);
+      let min_y = (clip_rect.min.y * pixels_per_point).round().max(0.0);
+      let max_x = (clip_rect.max.x * pixels_per_point).round().min(vkr.dimensions[0] as f32);
+      let max_y = (clip_rect.max.y * pixels_per_point).round().min(vkr.dimensions[1] as f32);
+      if max_x <= min_x || max_y <= min_y {
+        continue;
+      }
+      vkr.set_scissor([
+        min_x as i32,
+        min_y as i32,
+        (max_x - min_x) as i32,
+        (max_y - min_y) as i32
+      ]);
+
+      let vertex_buffer = CpuAccessibleBuffer::from_iter(
+        vkr.device.clone(),
+        BufferUsage::vertex_buffer(),
+        mesh.vertices.iter().map(VertexEgui::from).collect::<Vec<_>>().into_iter()
+      ).expect("Could not create egui vertex buffer.");
+      let index_buffer = CpuAccessibleBuffer::from_iter(
+        vkr.device.clone(),
+        BufferUsage::index_buffer(),
+        mesh.indices.into_iter()
+      ).expect("Could not create egui index buffer.");
+
+      vkr.record_draw(index_buffer.len());
+      let pipeline = self.pipeline.clone();
+      let dynamic_state = vkr.dynamic_state.clone();
+      vkr.with_command_builder(|cmds| {
+        cmds
+          .draw_indexed(
+            pipeline,
+            &dynamic_state,
+            vec![vertex_buffer as Arc<BufferAccess + Send + Sync>],
+            index_buffer,
+            desc_set,
+            screen
+          ).expect("Could not draw egui mesh.")
+      });
+    }
+
+    vkr.dynamic_state.scissors = prev_scissors;
+
+    for id in &output.textures_delta.free {
+      self.textures.remove(id);
+    }
+  }
+}
+
+
+impl RenderPipeline for EguiPipeline {
+  type Vertex = VertexEgui;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
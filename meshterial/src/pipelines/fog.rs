@@ -0,0 +1,93 @@
+//! Distance fog, shared by the phong and color3d pipelines. See
+//! `VkRenderer::set_fog`.
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+
+use std::sync::Arc;
+
+
+/// How the fog thickens with distance from the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FogMode {
+  /// No fog.
+  Off,
+  /// From no fog at `start` to only fog at `end`.
+  Linear,
+  /// `exp(-density * depth)` of the color is left.
+  Exponential,
+  /// `exp(-(density * depth)^2)` of the color is left, which stays clear
+  /// longer up close and then thickens faster than `Exponential`.
+  ExponentialSquared,
+}
+
+
+/// The fog drawn by the phong and color3d pipelines.
+///
+/// Depth is the view-space distance along the camera's axis. Matching `color`
+/// to the clear color or background hides where geometry ends at the far
+/// plane. `VkRenderer::set_background` does this for a flat color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FogParams {
+  pub color: [f32; 4],
+  /// Used by `FogMode::Linear`.
+  pub start: f32,
+  /// Used by `FogMode::Linear`.
+  pub end: f32,
+  /// Used by the exponential modes.
+  pub density: f32,
+  pub mode: FogMode,
+}
+
+
+impl Default for FogParams {
+  fn default() -> FogParams {
+    FogParams {
+      color: [0.0, 0.0, 1.0, 1.0],
+      start: 10.0,
+      end: 100.0,
+      density: 0.02,
+      mode: FogMode::Off,
+    }
+  }
+}
+
+
+/// The `Fog` uniform block of the shaders.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct FogUniform {
+  pub color: [f32; 4],
+  pub start: f32,
+  pub end: f32,
+  pub density: f32,
+  pub mode: i32,
+}
+
+
+impl<'a> From<&'a FogParams> for FogUniform {
+  fn from(params: &'a FogParams) -> FogUniform {
+    FogUniform {
+      color: params.color,
+      start: params.start,
+      end: params.end,
+      density: params.density,
+      mode: match params.mode {
+        FogMode::Off => 0,
+        FogMode::Linear => 1,
+        FogMode::Exponential => 2,
+        FogMode::ExponentialSquared => 3,
+      },
+    }
+  }
+}
+
+
+/// A uniform buffer with the fog turned off, which pipelines sample until
+/// they are given the renderer's fog with `use_fog`.
+pub(crate) fn no_fog_buffer(device: Arc<Device>) -> Arc<CpuAccessibleBuffer<FogUniform>> {
+  CpuAccessibleBuffer::from_data(
+    device,
+    BufferUsage::uniform_buffer(),
+    FogUniform::from(&FogParams::default())
+  ).expect("Could not create fog buffer.")
+}
@@ -0,0 +1,134 @@
+//! A pipeline for your own vertex type and shaders.
+use vulkano::device::Device;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::pipeline::shader::GraphicsEntryPointAbstract;
+use vulkano::pipeline::vertex::Vertex;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use super::{PipelineBuildError, PipelineOptions, RenderPipeline, WithPipelineOptions};
+
+
+/// A graphics pipeline that draws any vertex type with the given shaders, for
+/// vertices with attributes that the built-in pipelines don't have, like
+/// vertex ids, secondary uvs or bone weights.
+///
+/// Define the vertex with `impl_vertex!`, naming each field like the vertex
+/// shader's input of the same location:
+///
+/// ```ignore
+/// #[derive(Debug, Clone)]
+/// struct VertexId {
+///   position: [f32; 3],
+///   id: u32,
+/// }
+/// impl_vertex!(VertexId, position, id);
+///
+/// let vs = my_vs::Shader::load(device.clone()).expect("failed to create shader module");
+/// let fs = my_fs::Shader::load(device.clone()).expect("failed to create shader module");
+/// let pipeline = GenericPipeline::<VertexId>::new(
+///   render_pass,
+///   device.clone(),
+///   vs.main_entry_point(),
+///   fs.main_entry_point()
+/// );
+/// ```
+///
+/// The pipeline owns no uniforms. Build descriptor sets for the shaders'
+/// uniforms from `pipeline` with `PersistentDescriptorSet::start`, the same
+/// way the built-in pipelines do, and draw with `VkRenderer::draw_with`.
+/// Like the built-in 3d pipelines it blends and depth tests with
+/// `PipelineOptions`, with alpha blending by default.
+pub struct GenericPipeline<V> {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  vertex: PhantomData<V>,
+}
+
+
+impl<V> GenericPipeline<V>
+  where V: Vertex
+{
+  pub fn new<Vs, Fs>(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    vs: Vs,
+    fs: Fs
+  ) -> GenericPipeline<V>
+    where
+      Vs: GraphicsEntryPointAbstract<SpecializationConstants = ()>,
+      Vs::PipelineLayout: Clone + Send + Sync + 'static,
+      Fs: GraphicsEntryPointAbstract<SpecializationConstants = ()>,
+      Fs::PipelineLayout: Clone + Send + Sync + 'static
+  {
+    GenericPipeline::new_with_options(render_pass, device, vs, fs, &PipelineOptions::default())
+  }
+
+
+  /// Panics if the options aren't supported by the device. See
+  /// `try_new_with_options`.
+  pub fn new_with_options<Vs, Fs>(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    vs: Vs,
+    fs: Fs,
+    options: &PipelineOptions
+  ) -> GenericPipeline<V>
+    where
+      Vs: GraphicsEntryPointAbstract<SpecializationConstants = ()>,
+      Vs::PipelineLayout: Clone + Send + Sync + 'static,
+      Fs: GraphicsEntryPointAbstract<SpecializationConstants = ()>,
+      Fs::PipelineLayout: Clone + Send + Sync + 'static
+  {
+    GenericPipeline::try_new_with_options(render_pass, device, vs, fs, options)
+      .expect("Could not build generic pipeline.")
+  }
+
+
+  /// Create the pipeline, or return the first option the device doesn't
+  /// support. Still panics if the shaders don't match the vertex type or each
+  /// other.
+  pub fn try_new_with_options<Vs, Fs>(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    vs: Vs,
+    fs: Fs,
+    options: &PipelineOptions
+  ) -> Result<GenericPipeline<V>, PipelineBuildError>
+    where
+      Vs: GraphicsEntryPointAbstract<SpecializationConstants = ()>,
+      Vs::PipelineLayout: Clone + Send + Sync + 'static,
+      Fs: GraphicsEntryPointAbstract<SpecializationConstants = ()>,
+      Fs::PipelineLayout: Clone + Send + Sync + 'static
+  {
+    options.check_supported(&device)?;
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<V>()
+        .vertex_shader(vs, ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs, ())
+        .with_options(options)
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device)
+        .expect("Could not build generic pipeline - do the shaders match the vertex type?")
+    );
+    Ok(GenericPipeline {
+      pipeline,
+      vertex: PhantomData,
+    })
+  }
+}
+
+
+impl<V> RenderPipeline for GenericPipeline<V>
+  where V: Vertex
+{
+  type Vertex = V;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
@@ -0,0 +1,89 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::descriptor::descriptor_set::{PersistentDescriptorSet, DescriptorSet};
+use vulkano::device::Device;
+use vulkano::instance::PhysicalDevice;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+
+use nalgebra::*;
+
+use std::sync::Arc;
+
+use super::color3d::{vs, fs};
+pub use super::color3d::VertexColor3;
+use super::RenderPipeline;
+use super::clip::no_clip_buffer;
+use super::fog::no_fog_buffer;
+
+
+/// A graphics pipeline for drawing colored 3d line lists, eg. for debugging.
+///
+/// The line width is dynamic, so draws with this pipeline must use
+/// `VkRenderer::line_dynamic_state` instead of `VkRenderer::dynamic_state`.
+pub struct Line3DPipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub proj_uniform_device_buffer:Arc<DeviceLocalBuffer<Matrix4<f32>>>,
+  pub proj_desc_set: Arc<DescriptorSet + Send + Sync>,
+}
+
+
+impl Line3DPipeline {
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice
+  ) -> Line3DPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexColor3>()
+        .vertex_shader(vs.main_entry_point(), ())
+      // The content of the vertex buffer describes a list of line segments.
+        .line_list()
+      // Line width is set per draw through the dynamic state.
+        .line_width_dynamic()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .depth_stencil_simple_depth()
+      // Does it blend?
+        .blend_alpha_blending()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    // Uniform stuff
+    let proj_uniform_device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
+      device.clone(),
+      BufferUsage::uniform_buffer_transfer_destination(),
+      physical.queue_families()
+    ).expect("Could not create uniform_device_buffer.");
+
+    let proj_desc_set = Arc::new(
+      PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_buffer(proj_uniform_device_buffer.clone()).expect("Could not add uniform_device_buffer.")
+      // The color3d shaders are fogged and clipped, but debug lines never are.
+        .add_buffer(no_fog_buffer(device.clone())).expect("Could not add fog buffer.")
+        .add_buffer(no_clip_buffer(device)).expect("Could not add clip plane buffer.")
+        .build()
+        .expect("Could not build uniform_desc_set.")
+    );
+
+    Line3DPipeline {
+      pipeline,
+      proj_uniform_device_buffer,
+      proj_desc_set,
+    }
+  }
+}
+
+
+impl RenderPipeline for Line3DPipeline {
+  type Vertex = VertexColor3;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
@@ -0,0 +1,40 @@
+//! A logarithmic depth buffer, shared by the phong and color3d pipelines. See
+//! `VkRenderer::set_logarithmic_depth`.
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+
+use std::sync::Arc;
+
+
+/// The `LogDepth` uniform block of the shaders.
+///
+/// With a `far` greater than 0 the vertex shaders replace the projection's
+/// depth with `log2(1 + w) / log2(1 + far)`, where `w` is the distance in
+/// front of the camera, which spreads the precision of the depth buffer
+/// evenly over orders of magnitude instead of bunching it up at the near
+/// plane. A `far` of 0 leaves the projection's depth as it is.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct LogDepthUniform {
+  pub far: f32,
+}
+
+
+impl From<Option<f32>> for LogDepthUniform {
+  fn from(far: Option<f32>) -> LogDepthUniform {
+    LogDepthUniform {
+      far: far.map(|far| far.max(0.0)).unwrap_or(0.0),
+    }
+  }
+}
+
+
+/// A uniform buffer with logarithmic depth turned off, which pipelines use
+/// until they are given the renderer's with `use_logarithmic_depth`.
+pub(crate) fn no_log_depth_buffer(device: Arc<Device>) -> Arc<CpuAccessibleBuffer<LogDepthUniform>> {
+  CpuAccessibleBuffer::from_data(
+    device,
+    BufferUsage::uniform_buffer(),
+    LogDepthUniform::from(None)
+  ).expect("Could not create log depth buffer.")
+}
@@ -1,5 +1,32 @@
+pub mod background;
+pub mod bloom;
+pub mod clip;
 pub mod color2d;
 pub mod color3d;
+pub mod deferred;
+pub mod depth;
+#[cfg(feature = "egui")]
+pub mod egui;
+pub mod fog;
+pub mod generic;
+pub mod lines;
+pub mod log_depth;
+pub mod particles;
 pub mod texture2d;
+pub mod tonemap;
 pub mod phong;
+pub mod phong_color;
+pub mod phong_skinned;
+pub mod sampler;
+pub mod ssao;
+pub mod storage;
+pub mod unlit;
 pub mod uniform;
+
+mod options;
+mod render_pipeline;
+pub use self::options::{
+  BlendMode, CompareOp, FrontFace, PipelineBuildError, PipelineOptions, Topology, WithPipelineOptions,
+  PRIMITIVE_RESTART_INDEX
+};
+pub use self::render_pipeline::RenderPipeline;
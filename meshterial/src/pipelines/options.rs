@@ -0,0 +1,349 @@
+use vulkano::device::Device;
+
+use std::error::Error;
+use std::fmt;
+use vulkano::pipeline::GraphicsPipelineBuilder;
+use vulkano::pipeline::blend::{AttachmentBlend, BlendFactor};
+use vulkano::pipeline::depth_stencil::{Compare, DepthStencil};
+
+
+/// Which winding order of a triangle's vertices, as seen on screen, makes it
+/// face the camera. The 3d pipelines take it in `PipelineOptions` and the
+/// others in `new_with_front_face`. `Line3DPipeline` doesn't, since line
+/// segments have no winding. Only `Color3DPipeline` culls back faces, so in
+/// the others this only changes `gl_FrontFacing` for their fragment shaders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrontFace {
+  /// Vulkan's default, and what glTF and most OpenGL era tools export.
+  CounterClockwise,
+  /// What many DirectX era tools export.
+  Clockwise,
+}
+
+
+impl Default for FrontFace {
+  fn default() -> FrontFace {
+    FrontFace::CounterClockwise
+  }
+}
+
+
+/// How a fragment's depth is compared with the depth buffer to decide whether
+/// it's drawn. The fragment is drawn when `fragment <op> stored` is true.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+  Never,
+  /// The default, for a depth buffer cleared to 1.0.
+  Less,
+  /// For decals drawn onto geometry that's already in the depth buffer.
+  Equal,
+  /// For a skybox drawn at the far plane.
+  LessOrEqual,
+  /// For reverse-Z, with a depth buffer cleared to 0.0.
+  Greater,
+  NotEqual,
+  GreaterOrEqual,
+  Always,
+}
+
+
+impl Default for CompareOp {
+  fn default() -> CompareOp {
+    CompareOp::Less
+  }
+}
+
+
+impl From<CompareOp> for Compare {
+  fn from(op: CompareOp) -> Compare {
+    match op {
+      CompareOp::Never => Compare::Never,
+      CompareOp::Less => Compare::Less,
+      CompareOp::Equal => Compare::Equal,
+      CompareOp::LessOrEqual => Compare::LessOrEqual,
+      CompareOp::Greater => Compare::Greater,
+      CompareOp::NotEqual => Compare::NotEqual,
+      CompareOp::GreaterOrEqual => Compare::GreaterOrEqual,
+      CompareOp::Always => Compare::Always,
+    }
+  }
+}
+
+
+/// How a pipeline's fragments are combined with the color that is already in
+/// the framebuffer.
+///
+/// The phong, color3d and generic pipelines take theirs from
+/// `PipelineOptions::blend` and default to `Alpha`. Phong's
+/// `transparent_pipeline` always uses `Alpha` and its `facing_pipeline`
+/// always uses `None`. `ParticlePipeline` uses `Alpha` unless built with
+/// `new_with_blend`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlendMode {
+  /// Replace the color, ignoring alpha. The cheapest, for opaque geometry.
+  None,
+  /// Mix the color over what's behind it by alpha.
+  Alpha,
+  /// Add the color, weighted by alpha, to what's behind it, for glowing
+  /// things like fire, sparks and light shafts. The result doesn't depend on
+  /// the order things are drawn in, so additive particles don't need
+  /// sorting.
+  Additive,
+  /// Multiply what's behind by the color, for darkening decals and blob
+  /// shadows. White leaves it as it is. Alpha is ignored.
+  Multiply,
+}
+
+
+impl Default for BlendMode {
+  fn default() -> BlendMode {
+    BlendMode::Alpha
+  }
+}
+
+
+impl From<BlendMode> for AttachmentBlend {
+  fn from(mode: BlendMode) -> AttachmentBlend {
+    match mode {
+      BlendMode::None => AttachmentBlend::pass_through(),
+      BlendMode::Alpha => AttachmentBlend::alpha_blending(),
+      // Additive and multiplied colors keep the alpha that is already there.
+      BlendMode::Additive => AttachmentBlend {
+        color_source: BlendFactor::SrcAlpha,
+        color_destination: BlendFactor::One,
+        alpha_source: BlendFactor::Zero,
+        alpha_destination: BlendFactor::One,
+        .. AttachmentBlend::alpha_blending()
+      },
+      BlendMode::Multiply => AttachmentBlend {
+        color_source: BlendFactor::DstColor,
+        color_destination: BlendFactor::Zero,
+        alpha_source: BlendFactor::Zero,
+        alpha_destination: BlendFactor::One,
+        .. AttachmentBlend::alpha_blending()
+      },
+    }
+  }
+}
+
+
+/// The index used to end one strip or fan and start the next in an index
+/// buffer, with `Topology::TriangleStrip` or `Topology::TriangleFan`.
+pub const PRIMITIVE_RESTART_INDEX: u32 = 0xFFFF_FFFF;
+
+
+/// How vertices are assembled into triangles.
+///
+/// A strip of `n` triangles needs `n + 2` vertices or indices instead of
+/// `3n`, which saves bandwidth for meshes that are naturally rows of quads,
+/// like terrain. A strip of a row is drawn as alternating vertices from the
+/// row's two edges. Several strips can share one index buffer by separating
+/// them with `PRIMITIVE_RESTART_INDEX`, which is enabled for strips and fans.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+  /// Every three vertices are a triangle.
+  TriangleList,
+  /// Every vertex after the first two makes a triangle with the two before it.
+  TriangleStrip,
+  /// Every vertex after the second makes a triangle with the one before it
+  /// and the first.
+  TriangleFan,
+}
+
+
+impl Default for Topology {
+  fn default() -> Topology {
+    Topology::TriangleList
+  }
+}
+
+
+/// Options shared by the built-in 3d pipelines.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PipelineOptions {
+  /// Set this when the geometry drawn with the pipeline has already been
+  /// drawn into the depth buffer with a DepthPipeline earlier in the frame.
+  /// The depth test becomes `Equal` and depth writes are turned off, so each
+  /// pixel is only shaded once.
+  pub after_depth_prepass: bool,
+  /// Turn each fragment's alpha into how many of the pixel's samples it
+  /// covers, so cutout materials like foliage get anti-aliased edges without
+  /// sorting or blending. This replaces discarding fragments below an alpha
+  /// cutoff, which leaves hard edges. It only has an effect in multisampled
+  /// render passes; the renderer's own render passes have one sample.
+  pub alpha_to_coverage: bool,
+  /// Run the fragment shader for at least this fraction of each pixel's
+  /// samples instead of once per pixel, so shaders with fine detail, like
+  /// sharp specular highlights or high frequency textures, are anti-aliased
+  /// inside triangles and not only at their edges. 1.0 shades every sample.
+  /// The fraction is clamped to [0, 1], see `min_sample_shading`. Requires
+  /// the `sample_rate_shading` device feature, see `is_supported`.
+  ///
+  /// This multiplies the fragment shading cost by up to the number of
+  /// samples, eg. 4x with 4x MSAA and a fraction of 1.0, so prefer smaller
+  /// fractions or only set it on pipelines whose shaders alias. Like
+  /// `alpha_to_coverage` it only has an effect in multisampled render passes.
+  pub sample_shading: Option<f32>,
+  /// Clamp fragment depths to the viewport's depth range instead of clipping
+  /// geometry at the near and far planes, eg. so shadow casters behind a
+  /// light's near plane still write depth. Requires the `depth_clamp` device
+  /// feature, see `is_supported`.
+  ///
+  /// For depth bias, see `Frame::with_depth_bias`.
+  pub depth_clamp: bool,
+  /// How fragments are depth tested. Ignored with `after_depth_prepass`,
+  /// which always tests for `Equal`.
+  pub depth_compare: CompareOp,
+  /// Which triangles face the camera. Set this to `Clockwise` when an
+  /// imported mesh looks inside out, instead of flipping its geometry.
+  ///
+  /// Only `Color3DPipeline` culls back faces. The phong pipelines draw both
+  /// sides, so there this decides which side is lit as the back of a double
+  /// sided material, and which side `facing_pipeline` tints as the back.
+  pub front_face: FrontFace,
+  /// How vertices are assembled into triangles. The topology of a pipeline
+  /// applies to every draw with it.
+  pub topology: Topology,
+  /// Draw only the edges of triangles. Requires the `fill_mode_non_solid`
+  /// device feature, see `check_supported`.
+  pub wireframe: bool,
+  /// How fragments are combined with the framebuffer.
+  pub blend: BlendMode,
+}
+
+
+/// An error building a pipeline on the current device.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipelineBuildError {
+  /// An option needs a device feature that isn't supported, named like the
+  /// field of `vulkano::device::Features`.
+  UnsupportedFeature(&'static str),
+  /// Two settings can't be used together, named like the fields or methods
+  /// they come from.
+  Conflict(&'static str, &'static str),
+}
+
+
+impl fmt::Display for PipelineBuildError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PipelineBuildError::UnsupportedFeature(name) => {
+        write!(f, "the device does not support the '{}' feature", name)
+      }
+      PipelineBuildError::Conflict(a, b) => {
+        write!(f, "'{}' can't be used together with '{}'", a, b)
+      }
+    }
+  }
+}
+
+
+impl Error for PipelineBuildError {}
+
+
+impl PipelineOptions {
+  /// Check that the device supports every option that is set, returning the
+  /// first feature that's missing. Pipelines check this before building, since
+  /// building with unsupported options panics inside vulkano.
+  ///
+  /// The renderer enables every feature the device supports, so this only
+  /// depends on the hardware.
+  pub fn check_supported(&self, device: &Device) -> Result<(), PipelineBuildError> {
+    let features = device.enabled_features();
+    if self.depth_clamp && !features.depth_clamp {
+      return Err(PipelineBuildError::UnsupportedFeature("depth_clamp"));
+    }
+    if self.wireframe && !features.fill_mode_non_solid {
+      return Err(PipelineBuildError::UnsupportedFeature("fill_mode_non_solid"));
+    }
+    if self.sample_shading.is_some() && !features.sample_rate_shading {
+      return Err(PipelineBuildError::UnsupportedFeature("sample_rate_shading"));
+    }
+    Ok(())
+  }
+
+
+  /// The fraction of samples to shade with `sample_shading`, clamped to
+  /// [0, 1]. NaN is treated as 0.
+  pub fn min_sample_shading(&self) -> Option<f32> {
+    self.sample_shading.map(|fraction| fraction.max(0.0).min(1.0))
+  }
+
+
+  /// Whether or not depth is tested for reverse-Z, where nearer fragments
+  /// have greater depths.
+  pub fn is_reverse_z(&self) -> bool {
+    match self.depth_compare {
+      CompareOp::Greater | CompareOp::GreaterOrEqual => true,
+      _ => false,
+    }
+  }
+
+
+  /// Whether or not the device supports every option that is set. See
+  /// `check_supported`.
+  pub fn is_supported(&self, device: &Device) -> bool {
+    self.check_supported(device).is_ok()
+  }
+}
+
+
+/// Adds `with_options` to graphics pipeline builders, so PipelineOptions can
+/// be applied in the middle of a builder chain. Pipelines that don't take
+/// every option, like the 2d and unlit ones, take `with_front_face` alone.
+pub trait WithPipelineOptions {
+  fn with_options(self, options: &PipelineOptions) -> Self;
+  fn with_front_face(self, front_face: FrontFace) -> Self;
+}
+
+
+impl<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp> WithPipelineOptions
+  for GraphicsPipelineBuilder<Vdef, Vs, Vss, Tcs, Tcss, Tes, Tess, Gs, Gss, Fs, Fss, Rp>
+{
+  fn with_options(self, options: &PipelineOptions) -> Self {
+    let builder = self.depth_clamp(options.depth_clamp);
+    let builder =
+      if options.alpha_to_coverage {
+        builder.alpha_to_coverage_enabled()
+      } else {
+        builder.alpha_to_coverage_disabled()
+      };
+    let builder = match options.min_sample_shading() {
+      Some(fraction) => builder.sample_shading_enabled(fraction),
+      None => builder.sample_shading_disabled(),
+    };
+    let builder = builder.with_front_face(options.front_face);
+    let builder = match options.topology {
+      Topology::TriangleList => builder.triangle_list(),
+      Topology::TriangleStrip => builder.triangle_strip().primitive_restart(true),
+      Topology::TriangleFan => builder.triangle_fan().primitive_restart(true),
+    };
+    let builder =
+      if options.wireframe {
+        builder.polygon_mode_line()
+      } else {
+        builder.polygon_mode_fill()
+      };
+    let builder = builder.blend_collective(options.blend.into());
+    if options.after_depth_prepass {
+      builder.depth_stencil(DepthStencil {
+        depth_write: false,
+        depth_compare: Compare::Equal,
+        .. DepthStencil::simple_depth_test()
+      })
+    } else {
+      builder.depth_stencil(DepthStencil {
+        depth_compare: options.depth_compare.into(),
+        .. DepthStencil::simple_depth_test()
+      })
+    }
+  }
+
+
+  fn with_front_face(self, front_face: FrontFace) -> Self {
+    match front_face {
+      FrontFace::CounterClockwise => self.front_face_counter_clockwise(),
+      FrontFace::Clockwise => self.front_face_clockwise(),
+    }
+  }
+}
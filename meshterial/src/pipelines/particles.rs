@@ -0,0 +1,198 @@
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Device;
+use vulkano::instance::PhysicalDevice;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::depth_stencil::DepthStencil;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+use nalgebra::*;
+use std::sync::Arc;
+
+pub use super::uniform::*;
+use super::{BlendMode, RenderPipeline};
+
+pub mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/particles/vert.glsl"
+  }
+}
+
+mod fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/particles/frag.glsl"
+  }
+}
+
+/// A fragment shader that fades particles out near the scene's depth.
+pub mod soft_fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/particles/soft_frag.glsl"
+  }
+}
+
+
+/// One corner of a particle's billboarded quad. Every particle is drawn as two
+/// triangles with the same position and different corners.
+#[derive(Debug, Clone)]
+pub struct VertexParticle {
+  pub position: [f32; 3],
+  /// The corner of the quad, from -1.0 to 1.0 on each axis.
+  pub corner: [f32; 2],
+  pub color: [f32; 4]
+}
+impl_vertex!(VertexParticle, position, corner, color);
+
+
+/// A pipeline that draws particles as round, camera facing quads. Push
+/// constants are `vs::ty::Billboard`. See `particles::ParticleSystem`.
+///
+/// Particles are depth tested against the scene but don't write depth, so
+/// they should be drawn after the opaque geometry.
+pub struct ParticlePipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub proj: UniformDeviceAndDescriptor<Matrix4<f32>>,
+}
+
+
+impl ParticlePipeline {
+  /// Creates a new ParticlePipeline that alpha blends.
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice
+  ) -> ParticlePipeline {
+    ParticlePipeline::new_with_blend(render_pass, device, physical, BlendMode::Alpha)
+  }
+
+
+  /// Creates a new ParticlePipeline that blends with the given mode, eg.
+  /// `BlendMode::Additive` for sparks and fire, which don't need to be
+  /// sorted back to front.
+  pub fn new_with_blend(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    blend: BlendMode
+  ) -> ParticlePipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexParticle>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .depth_stencil(DepthStencil {
+          depth_write: false,
+          .. DepthStencil::simple_depth_test()
+        })
+        .blend_collective(blend.into())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    // Uniform stuff
+    let proj = {
+      let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
+        device.clone(),
+        uniform_buffer_usage(),
+        physical.queue_families()
+      ).expect("Could not create uniform_device_buffer.");
+
+      let desc_set = Arc::new(
+        PersistentDescriptorSet::start(pipeline.clone(), 0)
+          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
+          .build()
+          .expect("Could not build uniform_desc_set.")
+      );
+
+      UniformDeviceAndDescriptor {
+        device_buffer, desc_set
+      }
+    };
+
+    ParticlePipeline {
+      pipeline,
+      proj
+    }
+  }
+}
+
+
+/// A variant of `ParticlePipeline` that fades particles out where they near
+/// the opaque scene behind them. See `VkRenderer::set_soft_particles`.
+///
+/// It samples the depth buffer, which can't be done while the depth buffer
+/// is the render pass's depth attachment, so it is built against a render
+/// pass of its own that loads the scene's color and has no depth. The
+/// sampled depth replaces the depth test. Set 0 is the projection like
+/// `ParticlePipeline`, and set 1 is the depth buffer and
+/// `soft_fs::ty::SoftParticles`.
+pub struct SoftParticlePipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// Samples the depth buffer.
+  pub sampler: Arc<Sampler>,
+}
+
+
+impl SoftParticlePipeline {
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>
+  ) -> SoftParticlePipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = soft_fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexParticle>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .blend_alpha_blending()
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    // Depth formats don't have to support linear filtering.
+    let sampler = Sampler::new(
+      device,
+      Filter::Nearest, Filter::Nearest,
+      MipmapMode::Nearest,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      0.0, 1.0, 0.0, 0.0
+    ).expect("Could not create soft particle sampler.");
+
+    SoftParticlePipeline {
+      pipeline,
+      sampler,
+    }
+  }
+}
+
+
+impl RenderPipeline for SoftParticlePipeline {
+  type Vertex = VertexParticle;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
+
+
+impl RenderPipeline for ParticlePipeline {
+  type Vertex = VertexParticle;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
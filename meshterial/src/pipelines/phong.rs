@@ -1,16 +1,25 @@
-use vulkano::buffer::BufferUsage;
+use vulkano::buffer::BufferAccess;
 use vulkano::buffer::cpu_pool::CpuBufferPool;
 use vulkano::buffer::device_local::DeviceLocalBuffer;
-use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
 use vulkano::device::Device;
 use vulkano::instance::PhysicalDevice;
 use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::depth_stencil::DepthStencil;
+use vulkano::pipeline::vertex::TwoBuffersDefinition;
 use vulkano::framebuffer::{RenderPassAbstract, Subpass};
 use nalgebra::*;
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
 
 pub use super::uniform::*;
+use super::{CompareOp, FrontFace, PipelineBuildError, PipelineOptions, RenderPipeline, WithPipelineOptions};
+use super::clip::no_clip_buffer;
+use super::fog::no_fog_buffer;
+use super::log_depth::no_log_depth_buffer;
+use super::super::vk_renderer::VkRenderer;
 
 pub mod vs {
   vulkano_shaders::shader!{
@@ -26,9 +35,33 @@ pub mod fs {
   }
 }
 
-pub use self::fs::ty::{Material, Light};
+/// A vertex shader that blends between a base pose and a morph target.
+pub mod morph_vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/phong/morph_vert.glsl"
+  }
+}
+
+/// A debug fragment shader that colors front faces green and back faces red.
+pub mod facing_fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/phong/facing_frag.glsl"
+  }
+}
 
+/// A `Material` with `double_sided: 1` lights its back faces with flipped
+/// normals, so thin surfaces drawn with one layer of triangles look right from
+/// both sides.
+pub use self::fs::ty::Material;
+pub use self::fs::ty::Light;
+
+/// The layout is fixed so that vertices can be read from raw bytes, see
+/// `Mesh::from_raw_bytes`: six native endian f32s, the position followed by
+/// the normal.
 #[derive(Debug, Clone)]
+#[repr(C)]
 pub struct VertexPhong {
   pub position: [f32; 3],
   pub normal: [f32; 3]
@@ -36,23 +69,344 @@ pub struct VertexPhong {
 impl_vertex!(VertexPhong, position, normal);
 
 
+/// The target pose of a morph target. Each of these corresponds to the
+/// VertexPhong at the same index of the base pose.
+#[derive(Debug, Clone)]
+pub struct VertexMorphTarget {
+  pub target_position: [f32; 3],
+  pub target_normal: [f32; 3]
+}
+impl_vertex!(VertexMorphTarget, target_position, target_normal);
+
+
+impl<'a> From<&'a VertexPhong> for VertexMorphTarget {
+  fn from(v: &'a VertexPhong) -> VertexMorphTarget {
+    VertexMorphTarget {
+      target_position: v.position,
+      target_normal: v.normal
+    }
+  }
+}
+
+
+/// The matrix that transforms normals by `view * model`: its inverse
+/// transpose, so that normals stay perpendicular to non-uniformly scaled
+/// surfaces.
+pub fn normal_matrix(model: &Matrix4<f32>, view: &Matrix4<f32>) -> Matrix4<f32> {
+  (view * model)
+    .pseudo_inverse(1e-10)
+    .transpose()
+}
+
+
+impl vs::ty::ModelViewNormal {
+  /// The push constants of `PhongPipeline::pipeline`, with the normal matrix
+  /// computed from `model` and `view`.
+  pub fn new(model: &Matrix4<f32>, view: &Matrix4<f32>) -> vs::ty::ModelViewNormal {
+    vs::ty::ModelViewNormal {
+      model: (*model).into(),
+      view: (*view).into(),
+      normal: normal_matrix(model, view).into(),
+    }
+  }
+}
+
+
+/// Converts `(model, view)`, see `ModelViewNormal::new`.
+impl<'a> From<(&'a Matrix4<f32>, &'a Matrix4<f32>)> for vs::ty::ModelViewNormal {
+  fn from((model, view): (&'a Matrix4<f32>, &'a Matrix4<f32>)) -> vs::ty::ModelViewNormal {
+    vs::ty::ModelViewNormal::new(model, view)
+  }
+}
+
+
+impl morph_vs::ty::MorphModelViewNormal {
+  /// The push constants of `PhongPipeline::morph_pipeline`, with the normal
+  /// matrix computed from `model` and `view`. `morph_weight` is clamped to
+  /// [0, 1].
+  pub fn new(
+    model: &Matrix4<f32>,
+    view: &Matrix4<f32>,
+    morph_weight: f32
+  ) -> morph_vs::ty::MorphModelViewNormal {
+    morph_vs::ty::MorphModelViewNormal {
+      model: (*model).into(),
+      view: (*view).into(),
+      normal: normal_matrix(model, view).into(),
+      morph_weight: morph_weight.max(0.0).min(1.0),
+    }
+  }
+}
+
+
 pub struct PhongPipeline {
   pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// A variant of `pipeline` that takes a second vertex buffer of
+  /// VertexMorphTarget and blends toward it. It shares the same descriptor
+  /// sets as `pipeline`. See `VkRenderer::draw_phong_morphed`.
+  pub morph_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// A debug variant of `pipeline` that draws front faces green and back faces
+  /// red, to spot flipped winding or inverted normals in imported meshes. It
+  /// shares the same descriptor sets and push constants as `pipeline`, so it
+  /// can be drawn in its place.
+  pub facing_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// A variant of `pipeline` that depth tests without writing depth, for the
+  /// `transparent_materials`. It shares the same descriptor sets and push
+  /// constants as `pipeline`. `VkRenderer::draw_phong` picks it by material.
+  pub transparent_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// A variant of `pipeline` that draws only the edges of triangles, or None
+  /// when the device doesn't support the `fill_mode_non_solid` feature. It
+  /// shares the same descriptor sets and push constants as `pipeline`, so it
+  /// can be drawn in its place.
+  pub wireframe_pipeline: Option<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+  /// The materials that are see-through, whose opacity is their diffuse
+  /// alpha. See `PhongPipelineConfig::with_transparent_material`.
+  pub transparent_materials: HashSet<String>,
   pub proj: UniformDeviceAndDescriptor<Matrix4<f32>>,
   pub light: UniformDeviceAndDescriptor<Light>,
   pub light_buffer_pool: CpuBufferPool<Light>,
   pub materials: HashMap<String, UniformDeviceAndDescriptor<Material>>,
   pub material_buffer_pool: CpuBufferPool<Material>,
+  /// The fog and clip plane buffers bound in `light.desc_set`, kept so that
+  /// `use_fog` and `use_clip_plane` can each replace one of them.
+  fog_buffer: Arc<BufferAccess + Send + Sync>,
+  clip_plane_buffer: Arc<BufferAccess + Send + Sync>,
+  /// The log depth buffer bound in `proj.desc_set`, kept so that
+  /// `use_logarithmic_depth` can replace it.
+  log_depth_buffer: Arc<BufferAccess + Send + Sync>,
+  /// Whether the pipeline was built for reverse-Z, which logarithmic depth
+  /// doesn't support.
+  reverse_z: bool,
+}
+
+
+/// How the specular highlight is computed.
+///
+/// Blinn-Phong uses the half vector between the light and the eye instead of
+/// the reflected light vector, which is cheaper and keeps highlights round at
+/// grazing angles. For the same `shininess` its highlights are about four
+/// times as wide, so to keep a material looking the same when switching from
+/// Phong to Blinn-Phong multiply its shininess by about 4.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpecularModel {
+  Phong,
+  BlinnPhong,
+}
+
+
+impl Default for SpecularModel {
+  fn default() -> SpecularModel {
+    SpecularModel::Phong
+  }
+}
+
+
+/// Which normal the surface is lit with.
+///
+/// `Flat` ignores the mesh's normals and lights each triangle with its face
+/// normal, computed in the fragment shader from the screen-space derivatives
+/// of the position, for a faceted look without duplicating vertices. The
+/// face normal always points toward the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shading {
+  Smooth,
+  Flat,
+}
+
+
+impl Default for Shading {
+  fn default() -> Shading {
+    Shading::Smooth
+  }
+}
+
+
+/// Everything needed to build a PhongPipeline that doesn't require a GPU.
+/// This can be created and validated without a window or device.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhongPipelineConfig {
+  /// The names of the materials to create uniforms for.
+  pub material_names: Vec<String>,
+  pub options: PipelineOptions,
+  /// Used by `pipeline` and `morph_pipeline`, for every material.
+  pub specular_model: SpecularModel,
+  /// Used by `pipeline` and `morph_pipeline`, for every material.
+  pub shading: Shading,
+  /// The names of the materials that are drawn with `transparent_pipeline`.
+  /// Each must also be in `material_names`.
+  pub transparent_materials: Vec<String>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhongPipelineConfigError {
+  /// A material was registered with an empty name.
+  EmptyMaterialName,
+  /// A material was registered more than once.
+  DuplicateMaterial(String),
+  /// A transparent material isn't one of the registered materials.
+  UnknownTransparentMaterial(String),
+  /// The options can't be built on the device.
+  Build(PipelineBuildError),
+}
+
+
+impl fmt::Display for PhongPipelineConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PhongPipelineConfigError::EmptyMaterialName => {
+        write!(f, "a material name is empty")
+      }
+      PhongPipelineConfigError::DuplicateMaterial(name) => {
+        write!(f, "material '{}' was registered more than once", name)
+      }
+      PhongPipelineConfigError::UnknownTransparentMaterial(name) => {
+        write!(f, "transparent material '{}' is not a registered material", name)
+      }
+      PhongPipelineConfigError::Build(e) => write!(f, "{}", e),
+    }
+  }
+}
+
+
+impl Error for PhongPipelineConfigError {}
+
+
+impl From<PipelineBuildError> for PhongPipelineConfigError {
+  fn from(e: PipelineBuildError) -> PhongPipelineConfigError {
+    PhongPipelineConfigError::Build(e)
+  }
+}
+
+
+impl PhongPipelineConfig {
+  pub fn new() -> PhongPipelineConfig {
+    PhongPipelineConfig::default()
+  }
+
+
+  /// Register a material by name.
+  pub fn with_material(mut self, name: &str) -> PhongPipelineConfig {
+    self.material_names.push(name.to_string());
+    self
+  }
+
+
+  /// Register a see-through material by name. Its opacity is its diffuse
+  /// alpha and it's drawn with `transparent_pipeline`. See `Scene::draw` for
+  /// drawing transparent things in the right order.
+  pub fn with_transparent_material(mut self, name: &str) -> PhongPipelineConfig {
+    self.material_names.push(name.to_string());
+    self.transparent_materials.push(name.to_string());
+    self
+  }
+
+
+  /// Use the given specular model. See `SpecularModel`.
+  pub fn with_specular_model(mut self, specular_model: SpecularModel) -> PhongPipelineConfig {
+    self.specular_model = specular_model;
+    self
+  }
+
+
+  /// Use the given shading. See `Shading`.
+  pub fn with_shading(mut self, shading: Shading) -> PhongPipelineConfig {
+    self.shading = shading;
+    self
+  }
+
+
+  /// Use the given winding order for front faces. See `FrontFace`. Phong
+  /// doesn't cull back faces, so this doesn't hide any triangles, it only
+  /// decides which side of each is its back, see
+  /// `PipelineOptions::front_face`.
+  pub fn with_front_face(mut self, front_face: FrontFace) -> PhongPipelineConfig {
+    self.options.front_face = front_face;
+    self
+  }
+
+
+  /// Depth test with the given comparison. See `CompareOp`.
+  pub fn with_depth_compare(mut self, depth_compare: CompareOp) -> PhongPipelineConfig {
+    self.options.depth_compare = depth_compare;
+    self
+  }
+
+
+  fn specialization_constants(&self) -> fs::SpecializationConstants {
+    fs::SpecializationConstants {
+      specular_model: match self.specular_model {
+        SpecularModel::Phong => 0,
+        SpecularModel::BlinnPhong => 1,
+      },
+      flat_shading: match self.shading {
+        Shading::Smooth => 0,
+        Shading::Flat => 1,
+      },
+    }
+  }
+
+
+  /// Check that the config can be used to build a pipeline.
+  pub fn validate(&self) -> Result<(), PhongPipelineConfigError> {
+    let mut seen = HashSet::new();
+    for name in &self.material_names {
+      if name.is_empty() {
+        return Err(PhongPipelineConfigError::EmptyMaterialName);
+      }
+      if !seen.insert(name) {
+        return Err(PhongPipelineConfigError::DuplicateMaterial(name.clone()));
+      }
+    }
+    for name in &self.transparent_materials {
+      if !seen.contains(name) {
+        return Err(PhongPipelineConfigError::UnknownTransparentMaterial(name.clone()));
+      }
+    }
+    Ok(())
+  }
+
+
+  /// Validate the config, check that the device supports its options and
+  /// build a PhongPipeline from it.
+  pub fn build(
+    self,
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+  ) -> Result<PhongPipeline, PhongPipelineConfigError> {
+    self.validate()?;
+    self.options.check_supported(&device)?;
+    Ok(PhongPipeline::from_valid_config(render_pass, device, physical, self))
+  }
 }
 
 
 impl PhongPipeline {
-  /// Creates a new PhongPipeline.
+  /// Creates a new PhongPipeline with a uniform for each of the materials.
+  /// A name that is given more than once gets one uniform. For named errors
+  /// instead, build from a `PhongPipelineConfig`.
   pub fn new(
     render_pass: Arc<RenderPassAbstract + Send + Sync>,
     device: Arc<Device>,
     physical: PhysicalDevice,
     material_names: Vec<String>
+  ) -> PhongPipeline {
+    let mut seen = HashSet::new();
+    let material_names = material_names
+      .into_iter()
+      .filter(|name| seen.insert(name.clone()))
+      .collect();
+    let config = PhongPipelineConfig { material_names, .. PhongPipelineConfig::default() };
+    PhongPipeline::from_valid_config(render_pass, device, physical, config)
+  }
+
+
+  fn from_valid_config(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    config: PhongPipelineConfig
   ) -> PhongPipeline {
     let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
     let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
@@ -68,13 +422,11 @@ impl PhongPipeline {
         .vertex_shader(vs.main_entry_point(), ())
       // The content of the vertex buffer describes a list of triangles.
         .triangle_list()
-      // Use a resizable viewport set to draw over the entire window
-        .viewports_dynamic_scissors_irrelevant(1)
+        .viewports_dynamic_scissors_dynamic(1)
       // See `vertex_shader`.
-        .fragment_shader(fs.main_entry_point(), ())
-        .depth_stencil_simple_depth()
-      // Does it blend?
-        .blend_alpha_blending()
+        .fragment_shader(fs.main_entry_point(), config.specialization_constants())
+        .with_options(&config.options)
+      // Does it blend? That's `PipelineOptions::blend`.
       // Culling
         //.cull_mode_back()
       // We have to indicate which subpass of which render pass this pipeline is going to be used
@@ -85,19 +437,91 @@ impl PhongPipeline {
         .unwrap()
     );
 
+    let morph_vs = morph_vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let morph_pipeline = Arc::new(
+      GraphicsPipeline::start()
+      // The base pose and the morph target come from separate buffers.
+        .vertex_input(TwoBuffersDefinition::<VertexPhong, VertexMorphTarget>::new())
+        .vertex_shader(morph_vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), config.specialization_constants())
+        .with_options(&config.options)
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    let facing_fs = facing_fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let facing_pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexPhong>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(facing_fs.main_entry_point(), ())
+        .with_options(&config.options)
+      // The faces' colors are drawn as they are, whatever the options blend.
+        .blend_pass_through()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    let transparent_pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexPhong>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), config.specialization_constants())
+        .with_options(&config.options)
+      // Transparent things aren't in a depth prepass, and don't hide what is
+      // drawn behind them afterward.
+        .depth_stencil(DepthStencil {
+          depth_write: false,
+          depth_compare: config.options.depth_compare.into(),
+          .. DepthStencil::simple_depth_test()
+        })
+      // Their opacity is their alpha, whatever the options blend.
+        .blend_alpha_blending()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    let wireframe_options = PipelineOptions { wireframe: true, .. config.options.clone() };
+    let wireframe_pipeline: Option<Arc<GraphicsPipelineAbstract + Send + Sync>> =
+      if wireframe_options.is_supported(&device) {
+        Some(Arc::new(
+          GraphicsPipeline::start()
+            .vertex_input_single_buffer::<VertexPhong>()
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_dynamic(1)
+            .fragment_shader(fs.main_entry_point(), config.specialization_constants())
+            .with_options(&wireframe_options)
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone())
+            .unwrap()
+        ))
+      } else {
+        None
+      };
+
     // Uniform stuff
+    let log_depth_buffer: Arc<BufferAccess + Send + Sync> = no_log_depth_buffer(device.clone());
     let proj = {
       let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
         device.clone(),
-        BufferUsage::uniform_buffer_transfer_destination(),
+        uniform_buffer_usage(),
         physical.queue_families()
       ).expect("Could not create uniform_device_buffer.");
 
-      let desc_set = Arc::new(
-        PersistentDescriptorSet::start(pipeline.clone(), 0)
-          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
-          .build()
-          .expect("Could not build uniform_desc_set.")
+      let desc_set = PhongPipeline::proj_desc_set(
+        pipeline.clone(),
+        device_buffer.clone(),
+        log_depth_buffer.clone()
       );
 
       UniformDeviceAndDescriptor {
@@ -105,12 +529,13 @@ impl PhongPipeline {
       }
     };
 
+    let transparent_materials = config.transparent_materials.iter().cloned().collect();
     let mut materials = HashMap::new();
-    for name in material_names {
+    for name in config.material_names {
       let uniform = {
         let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
           device.clone(),
-          BufferUsage::uniform_buffer_transfer_destination(),
+          uniform_buffer_usage(),
           physical.queue_families()
         ).expect("Could not create uniform_device_buffer.");
 
@@ -129,18 +554,20 @@ impl PhongPipeline {
       materials.insert(name, uniform);
     }
 
+    let fog_buffer: Arc<BufferAccess + Send + Sync> = no_fog_buffer(device.clone());
+    let clip_plane_buffer: Arc<BufferAccess + Send + Sync> = no_clip_buffer(device.clone());
     let light = {
       let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
         device.clone(),
-        BufferUsage::uniform_buffer_transfer_destination(),
+        uniform_buffer_usage(),
         physical.queue_families()
       ).expect("Could not create uniform_device_buffer.");
 
-      let desc_set = Arc::new(
-        PersistentDescriptorSet::start(pipeline.clone(), 2)
-          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
-          .build()
-          .expect("Could not build uniform_desc_set.")
+      let desc_set = PhongPipeline::light_desc_set(
+        pipeline.clone(),
+        device_buffer.clone(),
+        fog_buffer.clone(),
+        clip_plane_buffer.clone()
       );
 
       UniformDeviceAndDescriptor {
@@ -154,13 +581,155 @@ impl PhongPipeline {
 
     PhongPipeline {
       pipeline,
+      morph_pipeline,
+      facing_pipeline,
+      transparent_pipeline,
+      wireframe_pipeline,
+      transparent_materials,
       proj,
       materials,
       material_buffer_pool,
       light,
-      light_buffer_pool
+      light_buffer_pool,
+      fog_buffer,
+      clip_plane_buffer,
+      log_depth_buffer,
+      reverse_z: config.options.is_reverse_z(),
+    }
+  }
+
+
+  /// The projection set, which also holds the log depth.
+  fn proj_desc_set(
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    proj_buffer: Arc<DeviceLocalBuffer<Matrix4<f32>>>,
+    log_depth_buffer: Arc<BufferAccess + Send + Sync>
+  ) -> Arc<DescriptorSet + Send + Sync> {
+    Arc::new(
+      PersistentDescriptorSet::start(pipeline, 0)
+        .add_buffer(proj_buffer).expect("Could not add uniform_device_buffer.")
+        .add_buffer(log_depth_buffer).expect("Could not add log depth buffer.")
+        .build()
+        .expect("Could not build uniform_desc_set.")
+    )
+  }
+
+
+  /// The light set, which also holds the fog and the clip plane.
+  fn light_desc_set(
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    light_buffer: Arc<DeviceLocalBuffer<Light>>,
+    fog_buffer: Arc<BufferAccess + Send + Sync>,
+    clip_plane_buffer: Arc<BufferAccess + Send + Sync>
+  ) -> Arc<DescriptorSet + Send + Sync> {
+    Arc::new(
+      PersistentDescriptorSet::start(pipeline, 2)
+        .add_buffer(light_buffer).expect("Could not add uniform_device_buffer.")
+        .add_buffer(fog_buffer).expect("Could not add fog buffer.")
+        .add_buffer(clip_plane_buffer).expect("Could not add clip plane buffer.")
+        .build()
+        .expect("Could not build uniform_desc_set.")
+    )
+  }
+
+
+  fn rebuild_light_desc_set(&mut self) {
+    self.light.desc_set = PhongPipeline::light_desc_set(
+      self.pipeline.clone(),
+      self.light.device_buffer.clone(),
+      self.fog_buffer.clone(),
+      self.clip_plane_buffer.clone()
+    );
+  }
+
+
+  /// Draw the renderer's fog, see `VkRenderer::set_fog`. Pipelines draw
+  /// without fog until this is called. This replaces `light.desc_set`.
+  pub fn use_fog(&mut self, vkr: &VkRenderer) {
+    self.fog_buffer = vkr.fog_buffer();
+    self.rebuild_light_desc_set();
+  }
+
+
+  /// Cut geometry with the renderer's clip plane, see
+  /// `VkRenderer::set_clip_plane`. Pipelines draw everything until this is
+  /// called. This replaces `light.desc_set`.
+  pub fn use_clip_plane(&mut self, vkr: &VkRenderer) {
+    self.clip_plane_buffer = vkr.clip_plane_buffer();
+    self.rebuild_light_desc_set();
+  }
+
+
+  /// Draw with the renderer's logarithmic depth, see
+  /// `VkRenderer::set_logarithmic_depth`. Pipelines draw with their
+  /// projection's depth until this is called. This replaces `proj.desc_set`.
+  ///
+  /// Logarithmic depth only works with a depth buffer cleared to 1.0, so
+  /// pipelines built for reverse-Z return an error.
+  pub fn use_logarithmic_depth(&mut self, vkr: &VkRenderer) -> Result<(), PipelineBuildError> {
+    if self.reverse_z {
+      return Err(PipelineBuildError::Conflict("use_logarithmic_depth", "depth_compare"));
     }
+    self.log_depth_buffer = vkr.log_depth_buffer();
+    self.proj.desc_set = PhongPipeline::proj_desc_set(
+      self.pipeline.clone(),
+      self.proj.device_buffer.clone(),
+      self.log_depth_buffer.clone()
+    );
+    Ok(())
+  }
+
+
+  /// Record an upload of the light. Call it outside of a render pass, eg.
+  /// before `VkRenderer::begin_rendering`.
+  pub fn set_light(&self, vkr: &mut VkRenderer, light: Light) {
+    let src = self
+      .light_buffer_pool
+      .next(light)
+      .expect("Could not load light into cpu buffer.");
+    vkr.copy_buffer(src, self.light.device_buffer.clone());
+  }
+
+
+  /// Record an upload of the named material, like `set_light`.
+  ///
+  /// Panics if the material wasn't registered. See `has_material`.
+  pub fn set_material(&self, vkr: &mut VkRenderer, name: &str, material: Material) {
+    let uniform = self
+      .materials
+      .get(name)
+      .expect(&format!("Could not get material {}", name));
+    let src = self
+      .material_buffer_pool
+      .next(material)
+      .expect("Could not load material into cpu buffer.");
+    vkr.copy_buffer(src, uniform.device_buffer.clone());
   }
 
 
+  /// An iterator over the names of all the registered materials.
+  pub fn material_names<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+    self.materials.keys().map(|name| name.as_str())
+  }
+
+
+  /// Whether or not a material with the given name has been registered.
+  pub fn has_material(&self, name: &str) -> bool {
+    self.materials.contains_key(name)
+  }
+
+
+  /// Whether or not the material was registered as transparent.
+  pub fn is_transparent(&self, name: &str) -> bool {
+    self.transparent_materials.contains(name)
+  }
+}
+
+
+impl RenderPipeline for PhongPipeline {
+  type Vertex = VertexPhong;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
 }
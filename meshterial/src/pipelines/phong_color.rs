@@ -0,0 +1,137 @@
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Device;
+use vulkano::instance::PhysicalDevice;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use nalgebra::*;
+use std::sync::Arc;
+
+pub use super::uniform::*;
+use super::phong::VertexPhong;
+use super::options::{FrontFace, WithPipelineOptions};
+use super::RenderPipeline;
+
+pub mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/phong/color_vert.glsl"
+  }
+}
+
+pub mod fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/phong/color_frag.glsl"
+  }
+}
+
+
+/// A VertexPhong with a color that modulates the lit material color.
+#[derive(Debug, Clone)]
+pub struct VertexPhongColor {
+  pub position: [f32; 3],
+  pub normal: [f32; 3],
+  pub color: [f32; 4]
+}
+impl_vertex!(VertexPhongColor, position, normal, color);
+
+
+/// A VertexPhong without a color is white, so it is lit as if it were drawn
+/// with the PhongPipeline.
+impl<'a> From<&'a VertexPhong> for VertexPhongColor {
+  fn from(v: &'a VertexPhong) -> VertexPhongColor {
+    VertexPhongColor {
+      position: v.position,
+      normal: v.normal,
+      color: [1.0, 1.0, 1.0, 1.0]
+    }
+  }
+}
+
+
+/// A phong pipeline for vertex colored meshes, eg. vertex painted terrain or
+/// scanned meshes. The ambient and diffuse terms are multiplied by each
+/// vertex's color.
+///
+/// Its material and light uniforms have the same layout as the PhongPipeline's,
+/// so a PhongPipeline's material and light descriptor sets can be bound at
+/// sets 1 and 2. Push constants are `vs::ty::ModelViewNormal`.
+pub struct PhongColorPipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub proj: UniformDeviceAndDescriptor<Matrix4<f32>>,
+}
+
+
+impl PhongColorPipeline {
+  /// Creates a new PhongColorPipeline.
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice
+  ) -> PhongColorPipeline {
+    PhongColorPipeline::new_with_front_face(render_pass, device, physical, FrontFace::default())
+  }
+
+
+  /// Creates a new PhongColorPipeline with the given winding order for front faces. See
+  /// `FrontFace`.
+  pub fn new_with_front_face(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    front_face: FrontFace
+  ) -> PhongColorPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexPhongColor>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .with_front_face(front_face)
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .depth_stencil_simple_depth()
+      // Does it blend?
+        .blend_alpha_blending()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    // Uniform stuff
+    let proj = {
+      let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
+        device.clone(),
+        uniform_buffer_usage(),
+        physical.queue_families()
+      ).expect("Could not create uniform_device_buffer.");
+
+      let desc_set = Arc::new(
+        PersistentDescriptorSet::start(pipeline.clone(), 0)
+          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
+          .build()
+          .expect("Could not build uniform_desc_set.")
+      );
+
+      UniformDeviceAndDescriptor {
+        device_buffer, desc_set
+      }
+    };
+
+    PhongColorPipeline {
+      pipeline,
+      proj
+    }
+  }
+}
+
+
+impl RenderPipeline for PhongColorPipeline {
+  type Vertex = VertexPhongColor;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
@@ -0,0 +1,177 @@
+use vulkano::buffer::cpu_pool::CpuBufferPool;
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Device;
+use vulkano::instance::PhysicalDevice;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use nalgebra::*;
+use std::sync::Arc;
+
+pub use super::uniform::*;
+use super::phong::fs;
+use super::options::{FrontFace, WithPipelineOptions};
+use super::RenderPipeline;
+use super::super::VkRenderer;
+
+pub mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/phong/skinned_vert.glsl"
+  }
+}
+
+pub use self::vs::ty::Joints;
+
+/// The maximum number of joints in a skeleton.
+/// This must match MAX_JOINTS in the skinned vertex shader.
+pub const MAX_JOINTS: usize = 64;
+
+
+/// A VertexPhong that is also influenced by up to four joints of a skeleton.
+/// The weights should sum to 1.0.
+#[derive(Debug, Clone)]
+pub struct VertexSkinned {
+  pub position: [f32; 3],
+  pub normal: [f32; 3],
+  pub joint_indices: [u32; 4],
+  pub weights: [f32; 4]
+}
+impl_vertex!(VertexSkinned, position, normal, joint_indices, weights);
+
+
+/// A phong pipeline that transforms each vertex by a weighted blend of joint
+/// matrices before lighting it.
+///
+/// It uses the same fragment shader as the PhongPipeline, so a PhongPipeline's
+/// material and light descriptor sets can be bound at sets 1 and 2. The joint
+/// matrices are bound at set 3.
+pub struct PhongSkinnedPipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub proj: UniformDeviceAndDescriptor<Matrix4<f32>>,
+  pub joints: UniformDeviceAndDescriptor<Joints>,
+  pub joints_buffer_pool: CpuBufferPool<Joints>,
+}
+
+
+impl PhongSkinnedPipeline {
+  /// Creates a new PhongSkinnedPipeline.
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice
+  ) -> PhongSkinnedPipeline {
+    PhongSkinnedPipeline::new_with_front_face(render_pass, device, physical, FrontFace::default())
+  }
+
+
+  /// Creates a new PhongSkinnedPipeline with the given winding order for front faces. See
+  /// `FrontFace`.
+  pub fn new_with_front_face(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    front_face: FrontFace
+  ) -> PhongSkinnedPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexSkinned>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .with_front_face(front_face)
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .depth_stencil_simple_depth()
+      // Does it blend?
+        .blend_alpha_blending()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    // Uniform stuff
+    let proj = {
+      let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
+        device.clone(),
+        uniform_buffer_usage(),
+        physical.queue_families()
+      ).expect("Could not create uniform_device_buffer.");
+
+      let desc_set = Arc::new(
+        PersistentDescriptorSet::start(pipeline.clone(), 0)
+          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
+          .build()
+          .expect("Could not build uniform_desc_set.")
+      );
+
+      UniformDeviceAndDescriptor {
+        device_buffer, desc_set
+      }
+    };
+
+    let joints = {
+      let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
+        device.clone(),
+        uniform_buffer_usage(),
+        physical.queue_families()
+      ).expect("Could not create uniform_device_buffer.");
+
+      let desc_set = Arc::new(
+        PersistentDescriptorSet::start(pipeline.clone(), 3)
+          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
+          .build()
+          .expect("Could not build uniform_desc_set.")
+      );
+
+      UniformDeviceAndDescriptor {
+        device_buffer, desc_set
+      }
+    };
+
+    let joints_buffer_pool = CpuBufferPool::upload(device.clone());
+
+    PhongSkinnedPipeline {
+      pipeline,
+      proj,
+      joints,
+      joints_buffer_pool
+    }
+  }
+
+
+  /// Upload the joint matrices of a skeleton. Joints past the end of
+  /// `matrices` are set to the identity.
+  ///
+  /// Panics if there are more than MAX_JOINTS matrices.
+  pub fn set_joint_matrices(&self, vkr: &mut VkRenderer, matrices: &[Matrix4<f32>]) {
+    assert!(
+      matrices.len() <= MAX_JOINTS,
+      "Too many joint matrices ({}), the maximum is {}", matrices.len(), MAX_JOINTS
+    );
+    let identity:[[f32; 4]; 4] = Matrix4::<f32>::identity().into();
+    let mut joints = Joints {
+      matrices: [identity; MAX_JOINTS]
+    };
+    for (i, mat) in matrices.iter().enumerate() {
+      joints.matrices[i] = (*mat).into();
+    }
+
+    let src = self
+      .joints_buffer_pool
+      .next(joints)
+      .expect("Could not load joint matrices into cpu buffer.");
+    let dest = self.joints.device_buffer.clone();
+    vkr.copy_buffer(src, dest);
+  }
+}
+
+
+impl RenderPipeline for PhongSkinnedPipeline {
+  type Vertex = VertexSkinned;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
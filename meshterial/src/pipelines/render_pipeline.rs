@@ -0,0 +1,14 @@
+use vulkano::pipeline::GraphicsPipelineAbstract;
+use std::sync::Arc;
+
+
+/// Implemented by all of the built-in pipelines, so that code can be written
+/// generically over them. See `VkRenderer::draw_with` and
+/// `VkRenderer::draw_mesh`.
+pub trait RenderPipeline {
+  /// The type of vertex the pipeline draws.
+  type Vertex: Send + Sync + 'static;
+
+  /// The underlying vulkano pipeline.
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync>;
+}
@@ -0,0 +1,154 @@
+use vulkano::device::Device;
+use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+/// The bits of the f32 used as the default max_anisotropy of new
+/// SamplerConfigs. Starts at 1.0, which is no anisotropic filtering.
+static DEFAULT_MAX_ANISOTROPY: AtomicUsize = AtomicUsize::new(0x3f80_0000);
+
+
+/// Set the max_anisotropy used by `SamplerConfig::default()`, eg. to 16.0 to
+/// turn on 16x anisotropic filtering for every texture sampled afterward.
+pub fn set_default_max_anisotropy(level: f32) {
+  DEFAULT_MAX_ANISOTROPY.store(level.to_bits() as usize, Ordering::Relaxed);
+}
+
+
+/// The max_anisotropy used by `SamplerConfig::default()`.
+pub fn default_max_anisotropy() -> f32 {
+  f32::from_bits(DEFAULT_MAX_ANISOTROPY.load(Ordering::Relaxed) as u32)
+}
+
+
+/// How a texture is sampled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplerConfig {
+  pub mag_filter: Filter,
+  pub min_filter: Filter,
+  pub mipmap_mode: MipmapMode,
+  pub address_mode: SamplerAddressMode,
+  /// The level of anisotropic filtering, where 1.0 is off. This is clamped to
+  /// the device's limit, and is ignored if the device doesn't support
+  /// anisotropic filtering. It has no visible effect on textures without
+  /// mipmaps.
+  pub max_anisotropy: f32,
+  /// Added to the mip level the GPU picks. Negative values sharpen textures
+  /// by choosing larger mips, eg. for distant terrain, at the cost of
+  /// shimmering. Positive values soften them. Anisotropic filtering is
+  /// applied after the bias, so with anisotropy on a negative bias is needed
+  /// less, and a large one brings back the aliasing anisotropy removes.
+  pub mip_lod_bias: f32,
+  /// The lowest (largest) mip level that will be sampled.
+  pub min_lod: f32,
+  /// The highest (smallest) mip level that will be sampled. Lowering this
+  /// caps how blurry a texture can get at a distance.
+  pub max_lod: f32,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SamplerConfigError {
+  /// `min_lod` is below 0.0.
+  NegativeMinLod(f32),
+  /// `min_lod` is above `max_lod`.
+  MinLodAboveMaxLod { min_lod: f32, max_lod: f32 },
+  /// The absolute value of `mip_lod_bias` is above the device's limit.
+  MipLodBiasOutOfRange { bias: f32, max: f32 },
+}
+
+
+impl fmt::Display for SamplerConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SamplerConfigError::NegativeMinLod(min_lod) => {
+        write!(f, "min_lod {} is negative", min_lod)
+      }
+      SamplerConfigError::MinLodAboveMaxLod { min_lod, max_lod } => {
+        write!(f, "min_lod {} is above max_lod {}", min_lod, max_lod)
+      }
+      SamplerConfigError::MipLodBiasOutOfRange { bias, max } => {
+        write!(f, "mip_lod_bias {} is outside of the device's range of +/-{}", bias, max)
+      }
+    }
+  }
+}
+
+
+impl Error for SamplerConfigError {}
+
+
+impl Default for SamplerConfig {
+  fn default() -> SamplerConfig {
+    SamplerConfig {
+      mag_filter: Filter::Linear,
+      min_filter: Filter::Linear,
+      mipmap_mode: MipmapMode::Nearest,
+      address_mode: SamplerAddressMode::Repeat,
+      max_anisotropy: default_max_anisotropy(),
+      mip_lod_bias: 0.0,
+      min_lod: 0.0,
+      // Don't cap the mip levels.
+      max_lod: 1000.0,
+    }
+  }
+}
+
+
+impl SamplerConfig {
+  /// The max_anisotropy that will actually be used on the given device.
+  ///
+  /// The renderer enables the `sampler_anisotropy` feature whenever the
+  /// device supports it.
+  pub fn supported_max_anisotropy(&self, device: &Device) -> f32 {
+    if device.enabled_features().sampler_anisotropy {
+      let max = device.physical_device().limits().max_sampler_anisotropy();
+      self.max_anisotropy.max(1.0).min(max)
+    } else {
+      1.0
+    }
+  }
+
+
+  /// Check that the LOD range and bias can be used on the given device.
+  pub fn validate(&self, device: &Device) -> Result<(), SamplerConfigError> {
+    if self.min_lod < 0.0 {
+      return Err(SamplerConfigError::NegativeMinLod(self.min_lod));
+    }
+    if self.min_lod > self.max_lod {
+      return Err(SamplerConfigError::MinLodAboveMaxLod {
+        min_lod: self.min_lod,
+        max_lod: self.max_lod,
+      });
+    }
+    let max = device.physical_device().limits().max_sampler_lod_bias();
+    if self.mip_lod_bias.abs() > max {
+      return Err(SamplerConfigError::MipLodBiasOutOfRange {
+        bias: self.mip_lod_bias,
+        max,
+      });
+    }
+    Ok(())
+  }
+
+
+  /// Create a sampler from this config. Panics if the config is invalid, see
+  /// `validate`.
+  pub fn sampler(&self, device: Arc<Device>) -> Arc<Sampler> {
+    self.validate(&device).expect("Invalid sampler config.");
+    let max_anisotropy = self.supported_max_anisotropy(&device);
+    Sampler::new(
+      device,
+      self.mag_filter, self.min_filter,
+      self.mipmap_mode,
+      self.address_mode, self.address_mode, self.address_mode,
+      self.mip_lod_bias,
+      max_anisotropy,
+      self.min_lod, self.max_lod
+    ).expect("Could not create sampler.")
+  }
+}
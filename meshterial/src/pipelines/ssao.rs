@@ -0,0 +1,134 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+
+use std::sync::Arc;
+
+use super::RenderPipeline;
+use super::tonemap::VertexTonemap;
+
+
+mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/tonemap/vert.glsl"
+  }
+}
+
+pub mod fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/ssao/frag.glsl"
+  }
+}
+
+mod blur_fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/ssao/blur_frag.glsl"
+  }
+}
+
+
+/// The number of hemisphere samples taken per pixel.
+pub const KERNEL_SIZE: usize = 16;
+
+
+/// The two post-process pipelines of screen-space ambient occlusion. See
+/// `VkRenderer::set_ssao`.
+///
+/// `pipeline` draws the occlusion of every pixel from the depth buffer and
+/// `blur_pipeline` smooths it. Both draw `vertex_buffer`, one triangle that
+/// covers the whole framebuffer, into a single channel image.
+pub struct SsaoPipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub blur_pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub vertex_buffer: Arc<CpuAccessibleBuffer<[VertexTonemap]>>,
+  /// Samples the depth buffer and the unblurred occlusion.
+  pub sampler: Arc<Sampler>,
+  /// Tiles the noise texture over the framebuffer.
+  pub noise_sampler: Arc<Sampler>,
+}
+
+
+impl SsaoPipeline {
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>
+  ) -> SsaoPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let blur_fs = blur_fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexTonemap>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+    let blur_pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexTonemap>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(blur_fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      device.clone(),
+      BufferUsage::vertex_buffer(),
+      vec![
+        VertexTonemap { position: [-1.0, -1.0] },
+        VertexTonemap { position: [3.0, -1.0] },
+        VertexTonemap { position: [-1.0, 3.0] },
+      ].into_iter()
+    ).expect("Could not create ssao vertex buffer.");
+
+    // Depth formats don't have to support linear filtering.
+    let sampler = Sampler::new(
+      device.clone(),
+      Filter::Nearest, Filter::Nearest,
+      MipmapMode::Nearest,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      0.0, 1.0, 0.0, 0.0
+    ).expect("Could not create ssao sampler.");
+    let noise_sampler = Sampler::new(
+      device,
+      Filter::Nearest, Filter::Nearest,
+      MipmapMode::Nearest,
+      SamplerAddressMode::Repeat,
+      SamplerAddressMode::Repeat,
+      SamplerAddressMode::Repeat,
+      0.0, 1.0, 0.0, 0.0
+    ).expect("Could not create ssao noise sampler.");
+
+    SsaoPipeline {
+      pipeline,
+      blur_pipeline,
+      vertex_buffer,
+      sampler,
+      noise_sampler,
+    }
+  }
+}
+
+
+impl RenderPipeline for SsaoPipeline {
+  type Vertex = VertexTonemap;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
@@ -0,0 +1,130 @@
+//! Storage buffers, for data that is too big for a uniform buffer or that a
+//! compute shader writes and a later draw reads.
+//!
+//! Uniform buffers are limited by the device's `max_uniform_buffer_range`,
+//! which is only 16KB or 64KB on many devices. Storage buffers are limited by
+//! `max_storage_buffer_range`, usually 128MB or more, so they can hold large
+//! per-instance data or generated geometry. They can also be written by
+//! shaders, where uniform buffers can't. In GLSL they are declared as
+//! `buffer` blocks, eg.
+//!
+//! ```glsl
+//! layout(set = 1, binding = 0) readonly buffer Instances {
+//!   mat4 models[];
+//! } instances;
+//! ```
+use vulkano::buffer::{BufferAccess, BufferSlice, BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::descriptor::pipeline_layout::PipelineLayoutAbstract;
+use vulkano::device::Device;
+use vulkano::instance::QueueFamily;
+
+use std::sync::Arc;
+
+use super::super::vk_renderer::VkRenderer;
+
+
+/// The usage of storage buffers made by `StorageDeviceAndDescriptor`. They can
+/// be uploaded to, read back, and drawn as vertex buffers, so geometry written
+/// by a compute shader can be drawn without a copy.
+pub fn storage_buffer_usage() -> BufferUsage {
+  BufferUsage {
+    storage_buffer: true,
+    vertex_buffer: true,
+    transfer_source: true,
+    transfer_destination: true,
+    .. BufferUsage::none()
+  }
+}
+
+
+/// A descriptor set with one buffer bound as the storage buffer at binding 0
+/// of the layout's set `set`. The set must have no other bindings.
+pub fn storage_desc_set<L, B>(layout: L, set: usize, buffer: B) -> Arc<DescriptorSet + Send + Sync>
+  where
+    L: PipelineLayoutAbstract + Send + Sync + 'static,
+    B: BufferAccess + Send + Sync + 'static
+{
+  Arc::new(
+    PersistentDescriptorSet::start(layout, set)
+      .add_buffer(buffer).expect("Could not add storage buffer.")
+      .build()
+      .expect("Could not build storage buffer set.")
+  )
+}
+
+
+/// A device local array in a storage buffer and a descriptor set binding it,
+/// like `UniformDeviceAndDescriptor` is for uniforms.
+pub struct StorageDeviceAndDescriptor<T> {
+  pub device_buffer: Arc<DeviceLocalBuffer<[T]>>,
+  pub desc_set: Arc<DescriptorSet + Send + Sync>,
+}
+
+
+impl<T> StorageDeviceAndDescriptor<T>
+  where T: Copy + Send + Sync + 'static
+{
+  /// An uninitialized array of `len` elements bound at binding 0 of the
+  /// layout's set `set`. See `storage_desc_set`. Fill it with `upload` or
+  /// with a compute shader.
+  pub fn new<'a, L, I>(
+    device: Arc<Device>,
+    layout: L,
+    set: usize,
+    len: usize,
+    queue_families: I
+  ) -> StorageDeviceAndDescriptor<T>
+    where
+      L: PipelineLayoutAbstract + Send + Sync + 'static,
+      I: IntoIterator<Item = QueueFamily<'a>>
+  {
+    let device_buffer = DeviceLocalBuffer::array(
+      device,
+      len,
+      storage_buffer_usage(),
+      queue_families
+    ).expect("Could not create storage buffer.");
+    let desc_set = storage_desc_set(layout, set, device_buffer.clone());
+    StorageDeviceAndDescriptor {
+      device_buffer,
+      desc_set,
+    }
+  }
+
+
+  /// How many elements the buffer holds.
+  pub fn len(&self) -> usize {
+    self.device_buffer.len()
+  }
+
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+
+  /// Record a copy of `data` into the start of the buffer. `data` must not
+  /// be longer than the buffer.
+  pub fn upload(&self, vkr: &mut VkRenderer, data: &[T]) {
+    assert!(
+      data.len() <= self.len(),
+      "Could not upload {} elements into a storage buffer of {}.",
+      data.len(),
+      self.len()
+    );
+    if data.is_empty() {
+      return;
+    }
+    let src = CpuAccessibleBuffer::from_iter(
+      vkr.device.clone(),
+      BufferUsage::transfer_source(),
+      data.iter().cloned()
+    ).expect("Could not create storage upload buffer.");
+    let dest = BufferSlice::from_typed_buffer_access(self.device_buffer.clone())
+      .slice(0 .. data.len())
+      .expect("Could not slice storage buffer.");
+    vkr.copy_buffer(src, dest);
+  }
+}
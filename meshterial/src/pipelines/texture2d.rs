@@ -8,17 +8,20 @@ use vulkano::descriptor::descriptor_set::{
 use vulkano::device::Device;
 use vulkano::instance::PhysicalDevice;
 use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::depth_stencil::DepthStencil;
 use vulkano::framebuffer::{RenderPassAbstract, Subpass};
-use vulkano::image::immutable::ImmutableImage;
-use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
-use vulkano::format::Format;
+use vulkano::image::ImageViewAccess;
 
 use nalgebra::*;
 
 use std::sync::Arc;
 
+use super::options::{CompareOp, FrontFace, WithPipelineOptions};
+use super::sampler::SamplerConfig;
+use super::RenderPipeline;
 
-mod vs {
+
+pub mod vs {
   vulkano_shaders::shader!{
     ty: "vertex",
     path: "src/shaders/texture2d/vert.glsl"
@@ -48,14 +51,59 @@ pub struct Texture2DPipeline {
   pub proj_desc_set: Arc<DescriptorSet + Send + Sync>,
   pub image_sampler_desc_pool: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
   pub may_tex_desc_set: Option<Arc<DescriptorSet + Send + Sync>>,
+  /// How textures are sampled by descriptor sets made with
+  /// `desc_set_for_texture`.
+  pub sampler_config: SamplerConfig,
 }
 
 
 impl Texture2DPipeline {
+  /// A pipeline that draws over everything, without depth testing.
   pub fn new(
     render_pass: Arc<RenderPassAbstract + Send + Sync>,
     device: Arc<Device>,
     physical: PhysicalDevice
+  ) -> Texture2DPipeline {
+    Texture2DPipeline::new_with_front_face(render_pass, device, physical, FrontFace::default())
+  }
+
+
+  /// Like `new`, with the given winding order for front faces. See
+  /// `FrontFace`.
+  pub fn new_with_front_face(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    front_face: FrontFace
+  ) -> Texture2DPipeline {
+    Texture2DPipeline::with_depth_stencil(render_pass, device, physical, DepthStencil::disabled(), front_face)
+  }
+
+
+  /// A pipeline that depth tests with the given comparison, eg. for sprites
+  /// placed in a 3d scene. Like `new`, it never writes depth, so sprites
+  /// don't hide each other and can be drawn in any order.
+  pub fn new_with_depth_compare(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    depth_compare: CompareOp
+  ) -> Texture2DPipeline {
+    let depth_stencil = DepthStencil {
+      depth_write: false,
+      depth_compare: depth_compare.into(),
+      .. DepthStencil::simple_depth_test()
+    };
+    Texture2DPipeline::with_depth_stencil(render_pass, device, physical, depth_stencil, FrontFace::default())
+  }
+
+
+  fn with_depth_stencil(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    depth_stencil: DepthStencil,
+    front_face: FrontFace
   ) -> Texture2DPipeline {
     let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
     let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
@@ -71,11 +119,11 @@ impl Texture2DPipeline {
         .vertex_shader(vs.main_entry_point(), ())
       // The content of the vertex buffer describes a list of triangles.
         .triangle_list()
-      // Use a resizable viewport set to draw over the entire window
-        .viewports_dynamic_scissors_irrelevant(1)
+        .with_front_face(front_face)
+        .viewports_dynamic_scissors_dynamic(1)
       // See `vertex_shader`.
         .fragment_shader(fs.main_entry_point(), ())
-        .depth_write(false)
+        .depth_stencil(depth_stencil)
       // Does it blend?
         .blend_alpha_blending()
       // We have to indicate which subpass of which render pass this pipeline is going to be used
@@ -108,32 +156,38 @@ impl Texture2DPipeline {
       proj_uniform_device_buffer,
       proj_desc_set,
       image_sampler_desc_pool,
-      may_tex_desc_set: None
+      may_tex_desc_set: None,
+      sampler_config: SamplerConfig::default()
     }
   }
 
 
-  pub fn desc_set_for_texture (
+  /// A descriptor set sampling the texture, which can be a loaded
+  /// `ImmutableImage` or any other sampled image, eg. a `DynamicTexture`'s.
+  pub fn desc_set_for_texture<T> (
     &mut self,
-    texture: Arc<ImmutableImage<Format>>,
+    texture: T,
     device: Arc<Device>,
-  ) -> Arc<DescriptorSet + Send + Sync> {
-    let sampler = Sampler::new(
-      device,
-      Filter::Linear, Filter::Linear,
-      MipmapMode::Nearest,
-      SamplerAddressMode::Repeat, SamplerAddressMode::Repeat, SamplerAddressMode::Repeat,
-      0.0,
-      1.0,
-      0.0, 0.0
-    ).unwrap();
+  ) -> Arc<DescriptorSet + Send + Sync>
+    where T: ImageViewAccess + Send + Sync + 'static
+  {
+    let sampler = self.sampler_config.sampler(device);
 
     Arc::new(
       self
         .image_sampler_desc_pool
         .next()
-        .add_sampled_image(texture.clone(), sampler.clone()).expect("Could not add sampled image.")
+        .add_sampled_image(texture, sampler.clone()).expect("Could not add sampled image.")
         .build().expect("Could not build the image sampler set.")
     )
   }
 }
+
+
+impl RenderPipeline for Texture2DPipeline {
+  type Vertex = VertexUV;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
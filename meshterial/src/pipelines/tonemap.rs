@@ -0,0 +1,137 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::Device;
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::image::ImageViewAccess;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+
+use std::sync::Arc;
+
+use super::RenderPipeline;
+
+
+mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/tonemap/vert.glsl"
+  }
+}
+
+pub mod fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/tonemap/frag.glsl"
+  }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct VertexTonemap {
+  pub position: [f32; 2],
+}
+impl_vertex!(VertexTonemap, position);
+
+
+/// A post-process pipeline that draws an HDR image over the whole framebuffer,
+/// mapping its colors into [0, 1). See `VkRenderer::enable_hdr`.
+pub struct TonemapPipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  /// One triangle that covers the whole framebuffer.
+  pub vertex_buffer: Arc<CpuAccessibleBuffer<[VertexTonemap]>>,
+  pub sampler: Arc<Sampler>,
+  /// Filters linearly, for the bloom image which is smaller than the
+  /// framebuffer.
+  pub bloom_sampler: Arc<Sampler>,
+}
+
+
+impl TonemapPipeline {
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>
+  ) -> TonemapPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexTonemap>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      device.clone(),
+      BufferUsage::vertex_buffer(),
+      vec![
+        VertexTonemap { position: [-1.0, -1.0] },
+        VertexTonemap { position: [3.0, -1.0] },
+        VertexTonemap { position: [-1.0, 3.0] },
+      ].into_iter()
+    ).expect("Could not create tonemap vertex buffer.");
+
+    let sampler = Sampler::new(
+      device.clone(),
+      Filter::Nearest, Filter::Nearest,
+      MipmapMode::Nearest,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      0.0, 1.0, 0.0, 0.0
+    ).expect("Could not create tonemap sampler.");
+    let bloom_sampler = Sampler::new(
+      device,
+      Filter::Linear, Filter::Linear,
+      MipmapMode::Nearest,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      0.0, 1.0, 0.0, 0.0
+    ).expect("Could not create tonemap bloom sampler.");
+
+    TonemapPipeline {
+      pipeline,
+      vertex_buffer,
+      sampler,
+      bloom_sampler,
+    }
+  }
+
+
+  /// A descriptor set that samples the given HDR image, darkened by the red
+  /// channel of `occlusion` and brightened by `bloom`.
+  pub fn desc_set_for_image<O, B>(
+    &self,
+    image: Arc<AttachmentImage>,
+    occlusion: O,
+    bloom: B
+  ) -> Arc<DescriptorSet + Send + Sync>
+    where
+      O: ImageViewAccess + Send + Sync + 'static,
+      B: ImageViewAccess + Send + Sync + 'static
+  {
+    Arc::new(
+      PersistentDescriptorSet::start(self.pipeline.clone(), 0)
+        .add_sampled_image(image, self.sampler.clone()).expect("Could not add hdr image.")
+        .add_sampled_image(occlusion, self.sampler.clone()).expect("Could not add occlusion image.")
+        .add_sampled_image(bloom, self.bloom_sampler.clone()).expect("Could not add bloom image.")
+        .build()
+        .expect("Could not build hdr image set.")
+    )
+  }
+}
+
+
+impl RenderPipeline for TonemapPipeline {
+  type Vertex = VertexTonemap;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
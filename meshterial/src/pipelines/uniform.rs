@@ -1,9 +1,124 @@
+use std::mem;
+use std::slice;
 use std::sync::Arc;
+use vulkano::buffer::{BufferSlice, BufferUsage, CpuAccessibleBuffer};
 use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
 use vulkano::descriptor::descriptor_set::DescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::sync::GpuFuture;
+
+use super::super::vk_renderer::VkRenderer;
+
+
+/// Partial updates copy whole multiples of this many bytes, starting at a
+/// multiple of it.
+pub const UPDATE_ALIGNMENT: usize = 4;
+
+
+/// The usage of the device buffers of the built-in pipelines' uniforms:
+/// uniform buffers that can be copied into and, for `read_back`, out of.
+pub fn uniform_buffer_usage() -> BufferUsage {
+  BufferUsage {
+    transfer_source: true,
+    .. BufferUsage::uniform_buffer_transfer_destination()
+  }
+}
+
+
+/// Copy the value of a device buffer back to the CPU, blocking until it's
+/// read. Work already submitted to the queue finishes first. The buffer must
+/// have been created with transfer source usage, see `uniform_buffer_usage`.
+///
+/// This stalls the GPU, so it's for diagnostics and tests only.
+pub fn read_back_buffer<T>(device: Arc<Device>, queue: Arc<Queue>, buffer: Arc<DeviceLocalBuffer<T>>) -> T
+  where T: Copy + Send + Sync + 'static
+{
+  let dest: Arc<CpuAccessibleBuffer<T>> = unsafe {
+    CpuAccessibleBuffer::uninitialized(device.clone(), BufferUsage::transfer_destination())
+  }.expect("Could not create uniform readback buffer.");
+  AutoCommandBufferBuilder::primary_one_time_submit(device, queue.family())
+    .expect("Could not create a new command buffer builder.")
+    .copy_buffer(buffer, dest.clone())
+    .expect("Could not copy uniform into readback buffer.")
+    .build()
+    .expect("Could not build uniform readback command buffer.")
+    .execute(queue)
+    .expect("Could not execute uniform readback.")
+    .then_signal_fence_and_flush()
+    .expect("Could not flush uniform readback.")
+    .wait(None)
+    .expect("Could not wait for uniform readback.");
+  let value = *dest.read().expect("Could not read uniform readback buffer.");
+  value
+}
 
 
 pub struct UniformDeviceAndDescriptor<T> {
   pub device_buffer: Arc<DeviceLocalBuffer<T>>,
   pub desc_set: Arc<DescriptorSet + Send + Sync>
 }
+
+
+impl<T> UniformDeviceAndDescriptor<T>
+  where T: Copy + Send + Sync + 'static
+{
+  /// Change `value`, which must be what the device buffer currently holds,
+  /// with `update` and record a copy of only the bytes that changed into the
+  /// device buffer. Nothing is copied if nothing changed.
+  ///
+  /// The copied range runs from the first to the last changed byte, widened
+  /// to `UPDATE_ALIGNMENT`, so changing two fields far apart in a struct still
+  /// copies everything between them. `T` must not have implicit padding,
+  /// which holds for the structs generated from shaders since their padding
+  /// is explicit (eg. `_dummy0`). The device buffer must have been created
+  /// with transfer destination usage.
+  pub fn update_region<F>(&self, vkr: &mut VkRenderer, value: &mut T, update: F)
+    where F: FnOnce(&mut T)
+  {
+    let previous = *value;
+    update(value);
+
+    let size = mem::size_of::<T>();
+    let (before, after) = unsafe {
+      (
+        slice::from_raw_parts(&previous as *const T as *const u8, size),
+        slice::from_raw_parts(value as *const T as *const u8, size)
+      )
+    };
+    let first = match (0 .. size).find(|&i| before[i] != after[i]) {
+      Some(first) => first,
+      None => return,
+    };
+    let last = (0 .. size)
+      .rev()
+      .find(|&i| before[i] != after[i])
+      .unwrap_or(first);
+    let start = first / UPDATE_ALIGNMENT * UPDATE_ALIGNMENT;
+    let end = ((last / UPDATE_ALIGNMENT + 1) * UPDATE_ALIGNMENT).min(size);
+
+    let src = CpuAccessibleBuffer::from_iter(
+      vkr.device.clone(),
+      BufferUsage::transfer_source(),
+      after[start .. end].iter().cloned()
+    ).expect("Could not create partial uniform upload buffer.");
+    let dest = unsafe {
+      BufferSlice::from_typed_buffer_access(self.device_buffer.clone())
+        .reinterpret::<[u8]>()
+    }
+      .slice(start .. end)
+      .expect("Could not slice uniform buffer.");
+    vkr.copy_buffer(src, dest);
+  }
+
+
+  /// Copy the uniform's current value back from the GPU, eg. to check that
+  /// an upload happened. This waits for every committed frame to finish, so
+  /// it stalls the GPU and is for diagnostics and tests only. Copies recorded
+  /// in a frame that hasn't been committed yet aren't seen. See
+  /// `read_back_buffer`.
+  pub fn read_back(&self, vkr: &mut VkRenderer) -> T {
+    vkr.wait_for_gpu();
+    read_back_buffer(vkr.device.clone(), vkr.queue.clone(), self.device_buffer.clone())
+  }
+}
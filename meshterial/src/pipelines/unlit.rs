@@ -0,0 +1,140 @@
+use vulkano::buffer::cpu_pool::CpuBufferPool;
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Device;
+use vulkano::instance::PhysicalDevice;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use nalgebra::*;
+use std::sync::Arc;
+
+pub use super::uniform::*;
+pub use super::phong::VertexPhong;
+use super::options::{FrontFace, WithPipelineOptions};
+use super::RenderPipeline;
+
+pub mod vs {
+  vulkano_shaders::shader!{
+    ty: "vertex",
+    path: "src/shaders/unlit/vert.glsl"
+  }
+}
+
+pub mod fs {
+  vulkano_shaders::shader!{
+    ty: "fragment",
+    path: "src/shaders/unlit/frag.glsl"
+  }
+}
+
+pub use self::fs::ty::Color;
+
+
+/// A graphics pipeline that draws geometry in a single flat color, with no
+/// lighting. Useful for silhouettes and for looking at a mesh before its
+/// materials are set up.
+///
+/// It takes the same vertices as the phong pipeline, ignoring the normals.
+pub struct UnlitPipeline {
+  pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+  pub proj: UniformDeviceAndDescriptor<Matrix4<f32>>,
+  pub color: UniformDeviceAndDescriptor<Color>,
+  pub color_buffer_pool: CpuBufferPool<Color>,
+}
+
+
+impl UnlitPipeline {
+  /// Creates a new UnlitPipeline.
+  pub fn new(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice
+  ) -> UnlitPipeline {
+    UnlitPipeline::new_with_front_face(render_pass, device, physical, FrontFace::default())
+  }
+
+
+  /// Creates a new UnlitPipeline with the given winding order for front faces. See
+  /// `FrontFace`.
+  pub fn new_with_front_face(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    physical: PhysicalDevice,
+    front_face: FrontFace
+  ) -> UnlitPipeline {
+    let vs = vs::Shader::load(device.clone()).expect("failed to create shader module");
+    let fs = fs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      GraphicsPipeline::start()
+        .vertex_input_single_buffer::<VertexPhong>()
+        .vertex_shader(vs.main_entry_point(), ())
+        .triangle_list()
+        .with_front_face(front_face)
+        .viewports_dynamic_scissors_dynamic(1)
+        .fragment_shader(fs.main_entry_point(), ())
+        .depth_stencil_simple_depth()
+      // Does it blend?
+        .blend_alpha_blending()
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(device.clone())
+        .unwrap()
+    );
+
+    // Uniform stuff
+    let proj = {
+      let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
+        device.clone(),
+        uniform_buffer_usage(),
+        physical.queue_families()
+      ).expect("Could not create uniform_device_buffer.");
+
+      let desc_set = Arc::new(
+        PersistentDescriptorSet::start(pipeline.clone(), 0)
+          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
+          .build()
+          .expect("Could not build uniform_desc_set.")
+      );
+
+      UniformDeviceAndDescriptor {
+        device_buffer, desc_set
+      }
+    };
+
+    let color = {
+      let device_buffer:Arc<DeviceLocalBuffer<_>> = DeviceLocalBuffer::new(
+        device.clone(),
+        uniform_buffer_usage(),
+        physical.queue_families()
+      ).expect("Could not create uniform_device_buffer.");
+
+      let desc_set = Arc::new(
+        PersistentDescriptorSet::start(pipeline.clone(), 1)
+          .add_buffer(device_buffer.clone()).expect("Could not add uniform_device_buffer.")
+          .build()
+          .expect("Could not build uniform_desc_set.")
+      );
+
+      UniformDeviceAndDescriptor {
+        device_buffer, desc_set
+      }
+    };
+
+    let color_buffer_pool = CpuBufferPool::upload(device.clone());
+
+    UnlitPipeline {
+      pipeline,
+      proj,
+      color,
+      color_buffer_pool
+    }
+  }
+}
+
+
+impl RenderPipeline for UnlitPipeline {
+  type Vertex = VertexPhong;
+
+  fn inner(&self) -> Arc<GraphicsPipelineAbstract + Send + Sync> {
+    self.pipeline.clone()
+  }
+}
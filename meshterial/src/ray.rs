@@ -0,0 +1,120 @@
+//! Rays for CPU picking. See `utils::screen_ray`.
+use nalgebra::{Matrix4, Point3, Vector3};
+
+use super::mesh::{Mesh, Positioned};
+
+
+/// A half line starting at `origin`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ray {
+  pub origin: Vector3<f32>,
+  /// A unit vector.
+  pub direction: Vector3<f32>,
+}
+
+
+/// Where a ray hit a triangle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayHit {
+  /// The distance along the ray to the hit.
+  pub distance: f32,
+  /// The index of the triangle that was hit, ie. the hit triangle's vertices
+  /// are `3 * triangle .. 3 * triangle + 3`.
+  pub triangle: usize,
+  /// The weights of the triangle's three vertices at the hit, which can be
+  /// used to interpolate vertex attributes.
+  pub barycentric: [f32; 3],
+}
+
+
+impl Ray {
+  /// Create a ray. The direction is normalized.
+  pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Ray {
+    Ray {
+      origin,
+      direction: direction.normalize(),
+    }
+  }
+
+
+  pub fn point_at(&self, distance: f32) -> Vector3<f32> {
+    self.origin + self.direction * distance
+  }
+
+
+  /// Intersect the ray with one triangle using the Möller–Trumbore algorithm.
+  /// Both sides of the triangle are hit. Returns the distance to the hit and
+  /// the barycentric weights of `b` and `c`.
+  pub fn intersect_triangle(
+    &self,
+    a: &Vector3<f32>,
+    b: &Vector3<f32>,
+    c: &Vector3<f32>
+  ) -> Option<(f32, f32, f32)> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = self.direction.cross(&edge2);
+    let det = edge1.dot(&p);
+    if det.abs() < 1e-8 {
+      return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = self.origin - a;
+    let u = s.dot(&p) * inv_det;
+    if u < 0.0 || u > 1.0 {
+      return None;
+    }
+    let q = s.cross(&edge1);
+    let v = self.direction.dot(&q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+      return None;
+    }
+    let t = edge2.dot(&q) * inv_det;
+    if t < 0.0 {
+      return None;
+    }
+    Some((t, u, v))
+  }
+
+
+  /// The nearest hit of the ray with a triangle list, after transforming the
+  /// triangles by `model`.
+  pub fn intersect_triangles<I>(&self, positions: I, model: &Matrix4<f32>) -> Option<RayHit>
+    where I: IntoIterator<Item = [f32; 3]>
+  {
+    let transformed:Vec<Vector3<f32>> = positions
+      .into_iter()
+      .map(|p| model.transform_point(&Point3::new(p[0], p[1], p[2])).coords)
+      .collect();
+    let mut nearest:Option<RayHit> = None;
+    for (triangle, tri) in transformed.chunks(3).enumerate() {
+      if tri.len() < 3 {
+        break;
+      }
+      if let Some((distance, u, v)) = self.intersect_triangle(&tri[0], &tri[1], &tri[2]) {
+        if nearest.as_ref().map(|hit| distance < hit.distance).unwrap_or(true) {
+          nearest = Some(RayHit {
+            distance,
+            triangle,
+            barycentric: [1.0 - u - v, u, v],
+          });
+        }
+      }
+    }
+    nearest
+  }
+
+
+  /// The nearest hit of the ray with a mesh drawn with the given model
+  /// matrix. This reads the mesh's vertex buffer, so it should not be called
+  /// while the buffer is being written.
+  pub fn intersect_mesh<V>(&self, mesh: &Mesh<V>, model: &Matrix4<f32>) -> Option<RayHit>
+    where V: Positioned + Send + Sync + 'static
+  {
+    let vertices = mesh
+      .vertex_buffer
+      .read()
+      .expect("Could not read mesh vertex buffer.");
+    self.intersect_triangles(vertices.iter().map(|v| v.position()), model)
+  }
+}
@@ -0,0 +1,159 @@
+//! Named layers of draws within one frame, each drawn over the last and
+//! optionally with its own depth, eg. the 3d scene, then a gizmo that
+//! shouldn't be hidden by the scene, then 2d UI.
+//!
+//! Draws are queued on a layer by name at any time during the frame, and
+//! `RenderLayers::render` draws every layer in the order the layers were
+//! added. Layers composite inside the frame's render pass, so they share
+//! its color and depth attachments.
+use super::vk_renderer::{VkRenderer, VkRendererError};
+
+
+/// The name of the layer that `RenderLayers::new` starts with.
+pub const DEFAULT_LAYER: &str = "default";
+
+
+/// A queued draw.
+type LayerDraw = Box<FnMut(&mut VkRenderer)>;
+
+
+/// One named layer and the draws queued on it this frame.
+pub struct RenderLayer {
+  pub name: String,
+  /// Whether to clear the depth buffer before the layer is drawn, so nothing
+  /// drawn in earlier layers hides it. See `VkRenderer::clear_depth_only`.
+  pub clear_depth: bool,
+  /// The `[x, y, width, height]` scissor rect to draw the layer with, or
+  /// None to draw it with the scissor rect as it was. A scissored layer
+  /// only clears depth inside its rect, and the scissor is reset afterward.
+  pub scissor: Option<[i32; 4]>,
+  draws: Vec<LayerDraw>,
+}
+
+
+/// Layers of draws, drawn in order.
+///
+/// The default is one layer named `DEFAULT_LAYER` that doesn't clear depth,
+/// which draws exactly like drawing straight to the renderer. Add more
+/// layers with `with_layer`:
+///
+/// ```ignore
+/// let mut layers = RenderLayers::new()
+///   .with_layer("gizmos", true)
+///   .with_layer("ui", true);
+/// // each frame
+/// vkr.begin_rendering();
+/// layers.draw("ui", move |vkr| draw_ui(vkr));
+/// layers.draw(DEFAULT_LAYER, move |vkr| draw_scene(vkr));
+/// layers.render(&mut vkr).expect("Could not draw layers.");
+/// vkr.commit_rendering();
+/// ```
+pub struct RenderLayers {
+  layers: Vec<RenderLayer>,
+}
+
+
+impl Default for RenderLayers {
+  fn default() -> RenderLayers {
+    RenderLayers::new()
+  }
+}
+
+
+impl RenderLayers {
+  pub fn new() -> RenderLayers {
+    RenderLayers {
+      layers: vec![],
+    }.with_layer(DEFAULT_LAYER, false)
+  }
+
+
+  /// Add a layer that is drawn after every layer added before it. Panics if
+  /// there already is a layer with the name.
+  pub fn with_layer(mut self, name: &str, clear_depth: bool) -> RenderLayers {
+    self.add_layer(name, clear_depth);
+    self
+  }
+
+
+  /// Like `with_layer`.
+  pub fn add_layer(&mut self, name: &str, clear_depth: bool) {
+    assert!(!self.has_layer(name), "There is already a render layer named '{}'.", name);
+    self.layers.push(RenderLayer {
+      name: name.to_string(),
+      clear_depth,
+      scissor: None,
+      draws: vec![],
+    });
+  }
+
+
+  pub fn has_layer(&self, name: &str) -> bool {
+    self.layers.iter().any(|layer| layer.name == name)
+  }
+
+
+  /// The layer with the name, eg. to change whether it clears depth.
+  pub fn layer_mut(&mut self, name: &str) -> Option<&mut RenderLayer> {
+    self.layers.iter_mut().find(|layer| layer.name == name)
+  }
+
+
+  /// The layers in the order they are drawn.
+  pub fn layers(&self) -> &[RenderLayer] {
+    &self.layers
+  }
+
+
+  /// Queue a draw on the named layer. `draw` is called once by the next
+  /// `render`, inside the render pass. Panics if there is no layer with the
+  /// name.
+  pub fn draw<F>(&mut self, layer: &str, draw: F)
+    where F: FnMut(&mut VkRenderer) + 'static
+  {
+    self
+      .layer_mut(layer)
+      .unwrap_or_else(|| panic!("Could not find render layer '{}'.", layer))
+      .draws
+      .push(Box::new(draw));
+  }
+
+
+  /// The number of draws queued on every layer.
+  pub fn queued_count(&self) -> usize {
+    self.layers.iter().map(|layer| layer.draws.len()).sum()
+  }
+
+
+  /// Draw every layer's queued draws in order, clearing depth before the
+  /// layers that ask for it, and forget the draws. This must be called
+  /// between `VkRenderer::begin_rendering` and `commit_rendering`.
+  ///
+  /// The first layer never needs to clear depth, since `begin_rendering`
+  /// already has, so it doesn't. While the renderer is suspended the draws
+  /// are forgotten without being called.
+  pub fn render(&mut self, vkr: &mut VkRenderer) -> Result<(), VkRendererError> {
+    if vkr.is_suspended() {
+      self.layers.iter_mut().for_each(|layer| layer.draws.clear());
+      return Ok(());
+    }
+    if !vkr.is_in_render_pass() {
+      return Err(VkRendererError::NotInRenderPass);
+    }
+    for (ndx, layer) in self.layers.iter_mut().enumerate() {
+      if let Some(rect) = layer.scissor {
+        vkr.set_scissor(rect);
+      }
+      if layer.clear_depth && ndx > 0 {
+        vkr.clear_depth_only()?;
+      }
+      for mut draw in layer.draws.drain(..) {
+        draw(vkr);
+      }
+      if layer.scissor.is_some() {
+        vkr.reset_scissor();
+      }
+    }
+    Ok(())
+  }
+}
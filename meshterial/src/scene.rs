@@ -0,0 +1,151 @@
+//! A lightweight node hierarchy for things with parented transforms, like a
+//! turret on a tank.
+//!
+//! Nodes hold an item of any type, so the traversal doesn't depend on vulkan.
+//! `Scene<PhongItem>` can be drawn directly with a PhongPipeline.
+use nalgebra::{Matrix4, U1, U3};
+
+use super::mesh::Mesh;
+use super::pipelines::phong::{PhongPipeline, VertexPhong};
+use super::utils::sort_back_to_front;
+use super::VkRenderer;
+
+
+/// A node in a scene, with a transform relative to its parent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node<T> {
+  pub local: Matrix4<f32>,
+  pub item: Option<T>,
+  pub children: Vec<Node<T>>,
+}
+
+
+impl<T> Node<T> {
+  /// Create a node with no item and no children.
+  pub fn new(local: Matrix4<f32>) -> Node<T> {
+    Node {
+      local,
+      item: None,
+      children: vec![],
+    }
+  }
+
+
+  pub fn with_item(mut self, item: T) -> Node<T> {
+    self.item = Some(item);
+    self
+  }
+
+
+  pub fn with_child(mut self, child: Node<T>) -> Node<T> {
+    self.children.push(child);
+    self
+  }
+
+
+  /// Visit this node and its descendants depth first, parents before their
+  /// children, calling `f` with each node and its world transform.
+  pub fn visit<F>(&self, parent: &Matrix4<f32>, f: &mut F)
+    where F: FnMut(&Node<T>, &Matrix4<f32>)
+  {
+    let world = parent * self.local;
+    f(self, &world);
+    for child in &self.children {
+      child.visit(&world, f);
+    }
+  }
+}
+
+
+/// A list of root nodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scene<T> {
+  pub roots: Vec<Node<T>>,
+}
+
+
+impl<T> Scene<T> {
+  pub fn new() -> Scene<T> {
+    Scene { roots: vec![] }
+  }
+
+
+  pub fn with_node(mut self, node: Node<T>) -> Scene<T> {
+    self.roots.push(node);
+    self
+  }
+
+
+  /// Visit every node, parents before their children. See `Node::visit`.
+  pub fn visit<F>(&self, mut f: F)
+    where F: FnMut(&Node<T>, &Matrix4<f32>)
+  {
+    let identity = Matrix4::identity();
+    for root in &self.roots {
+      root.visit(&identity, &mut f);
+    }
+  }
+
+
+  /// Every item in the scene along with its world transform, in draw order.
+  pub fn items(&self) -> Vec<(&T, Matrix4<f32>)> {
+    let mut items = vec![];
+    let identity = Matrix4::identity();
+    for root in &self.roots {
+      collect_items(root, &identity, &mut items);
+    }
+    items
+  }
+}
+
+
+fn collect_items<'a, T>(
+  node: &'a Node<T>,
+  parent: &Matrix4<f32>,
+  items: &mut Vec<(&'a T, Matrix4<f32>)>
+) {
+  let world = parent * node.local;
+  if let Some(item) = node.item.as_ref() {
+    items.push((item, world));
+  }
+  for child in &node.children {
+    collect_items(child, &world, items);
+  }
+}
+
+
+/// A mesh drawn with one of a PhongPipeline's materials.
+#[derive(Clone)]
+pub struct PhongItem {
+  pub mesh: Mesh<VertexPhong>,
+  pub material: String,
+}
+
+
+impl Scene<PhongItem> {
+  /// Draw every item in the scene with the phong pipeline, using each node's
+  /// world transform as the model matrix.
+  ///
+  /// Items with opaque materials are drawn first, in scene order, writing
+  /// depth. Then items with the pipeline's transparent materials are drawn
+  /// from back to front without writing depth, so they blend over everything
+  /// behind them. Transparent items are sorted by their node's origin, so an
+  /// item that overlaps another transparent item may still blend in the wrong
+  /// order. Anything else drawn after this should be transparent too, and
+  /// sorted with this scene's transparent items.
+  pub fn draw(&self, vkr: &mut VkRenderer, pipeline: &PhongPipeline, view: &Matrix4<f32>) {
+    let (mut transparent, opaque): (Vec<_>, Vec<_>) = self
+      .items()
+      .into_iter()
+      .partition(|(item, _)| pipeline.is_transparent(&item.material));
+    for (item, model) in opaque {
+      vkr.draw_phong(pipeline, &item.material, &item.mesh, &model, view);
+    }
+    sort_back_to_front(&mut transparent, view, |(_, model)| {
+      model.fixed_slice::<U3, U1>(0, 3).into_owned()
+    });
+    for (item, model) in transparent {
+      vkr.draw_phong(pipeline, &item.material, &item.mesh, &model, view);
+    }
+  }
+}
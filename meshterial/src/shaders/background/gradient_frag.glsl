@@ -0,0 +1,15 @@
+#version 450
+
+layout(push_constant) uniform Gradient {
+  vec4 top;
+  vec4 bottom;
+} gradient;
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+  // uv.y is 0.0 at the top of the framebuffer.
+  frag_color = mix(gradient.top, gradient.bottom, uv.y);
+}
@@ -0,0 +1,18 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform samplerCube skybox;
+
+layout(push_constant) uniform Skybox {
+  // The inverse of the projection times the view without its translation.
+  mat4 inverse_view_projection;
+} sky;
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+  vec4 far = sky.inverse_view_projection * vec4(uv * 2.0 - 1.0, 1.0, 1.0);
+  vec3 direction = far.xyz / far.w;
+  frag_color = texture(skybox, direction);
+}
@@ -0,0 +1,26 @@
+#version 450
+
+// One direction of a separable 9 tap gaussian blur.
+
+layout(set = 0, binding = 0) uniform sampler2D image;
+
+layout(push_constant) uniform Blur {
+  // (1, 0) blurs horizontally and (0, 1) vertically.
+  vec2 direction;
+} blur;
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 frag_color;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+  vec2 step = blur.direction / vec2(textureSize(image, 0));
+  vec3 sum = texture(image, uv).rgb * weights[0];
+  for (int i = 1; i < 5; i++) {
+    sum += texture(image, uv + step * float(i)).rgb * weights[i];
+    sum += texture(image, uv - step * float(i)).rgb * weights[i];
+  }
+  frag_color = vec4(sum, 1.0);
+}
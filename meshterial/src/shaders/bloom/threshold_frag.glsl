@@ -0,0 +1,21 @@
+#version 450
+
+// Keeps the part of each pixel brighter than the threshold, eg. emissive
+// materials and strong highlights.
+
+layout(set = 0, binding = 0) uniform sampler2D hdr;
+
+layout(push_constant) uniform Threshold {
+  float threshold;
+} params;
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+  vec3 color = texture(hdr, uv).rgb;
+  float brightness = max(color.r, max(color.g, color.b));
+  float excess = max(brightness - params.threshold, 0.0);
+  frag_color = vec4(color * excess / max(brightness, 0.0001), 1.0);
+}
@@ -1,9 +1,44 @@
 #version 450
 
+layout(set = 0, binding = 1) uniform Fog {
+  vec4 color;
+  float start;
+  float end;
+  float density;
+  // 0 off, 1 linear, 2 exponential, 3 exponential squared. See FogMode in
+  // fog.rs.
+  int mode;
+} fog;
+
+// Fragments on the negative side of the plane are discarded. See
+// ClipPlaneUniform in clip.rs.
+layout(set = 0, binding = 2) uniform ClipPlane {
+  vec4 plane;
+} clip;
+
 layout(location = 0) in vec4 in_color;
+layout(location = 1) in float in_depth;
+layout(location = 2) in vec3 in_world_position;
 
 layout(location = 0) out vec4 frag_color;
 
+// Blend a color toward the fog color by its view-space depth.
+vec3 apply_fog(vec3 color, float depth) {
+  float visibility = 1.0;
+  if (fog.mode == 1) {
+    visibility = clamp((fog.end - depth) / max(fog.end - fog.start, 0.0001), 0.0, 1.0);
+  } else if (fog.mode == 2) {
+    visibility = exp(-fog.density * depth);
+  } else if (fog.mode == 3) {
+    float d = fog.density * depth;
+    visibility = exp(-d * d);
+  }
+  return mix(fog.color.rgb, color, visibility);
+}
+
 void main() {
-  frag_color = in_color;
+  if (dot(clip.plane, vec4(in_world_position, 1.0)) < 0.0) {
+    discard;
+  }
+  frag_color = vec4(apply_fog(in_color.rgb, in_depth), in_color.a);
 }
@@ -4,6 +4,10 @@ layout(set = 0, binding = 0) uniform Projection {
   mat4 mat;
 } proj;
 
+layout(set = 0, binding = 3) uniform LogDepth {
+  float far;
+} log_depth;
+
 layout(push_constant) uniform ModelView {
   mat4 model;
   mat4 view;
@@ -13,8 +17,27 @@ layout(location = 0) in vec3 position;
 layout(location = 1) in vec4 color;
 
 layout(location = 0) out vec4 out_color;
+// The view-space depth, for fog.
+layout(location = 1) out float out_depth;
+// For the clip plane, which is in world space.
+layout(location = 2) out vec3 out_world_position;
+
+// Replace the projection's depth with a logarithmic one when the far plane is
+// set, see LogDepthUniform in log_depth.rs. This is done per vertex rather
+// than by writing gl_FragDepth, which would turn off early depth testing.
+vec4 apply_log_depth(vec4 position) {
+  if (log_depth.far > 0.0) {
+    float w = max(position.w, 0.000001);
+    position.z = log2(1.0 + w) / log2(1.0 + log_depth.far) * position.w;
+  }
+  return position;
+}
 
 void main() {
   out_color = color;
-  gl_Position = proj.mat * modelview.view * modelview.model * vec4(position, 1.0);
+  vec4 world_position = modelview.model * vec4(position, 1.0);
+  out_world_position = world_position.xyz;
+  vec4 view_position = modelview.view * world_position;
+  out_depth = abs(view_position.z);
+  gl_Position = apply_log_depth(proj.mat * view_position);
 }
@@ -0,0 +1,38 @@
+#version 450
+
+// Writes the surface of each pixel into the G-buffer for the lighting pass.
+// See pipelines::deferred.
+
+layout(set = 1, binding = 0) uniform Material {
+  vec4 emission;
+  vec4 ambient;
+  vec4 diffuse;
+  vec4 specular;
+  float shininess;
+  // 1 to light back faces as if they faced the camera, for thin surfaces
+  // like paper and leaves. The gbuffer pipeline doesn't cull back faces.
+  int double_sided;
+} material;
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+
+// The diffuse color, with the specular intensity in alpha.
+layout(location = 0) out vec4 out_albedo;
+// The view space normal, with the shininess in w.
+layout(location = 1) out vec4 out_normal;
+// The view space position, with 1.0 in w to mark that something was drawn.
+layout(location = 2) out vec4 out_position;
+
+void main() {
+  out_albedo = vec4(
+    material.diffuse.rgb,
+    dot(material.specular.rgb, vec3(1.0 / 3.0))
+  );
+  vec3 n = normalize(normal);
+  if (material.double_sided == 1 && !gl_FrontFacing) {
+    n = -n;
+  }
+  out_normal = vec4(n, material.shininess);
+  out_position = vec4(position, 1.0);
+}
@@ -0,0 +1,34 @@
+#version 450
+
+// Shades each pixel of the G-buffer with one light. See pipelines::deferred.
+
+layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput albedo;
+layout(input_attachment_index = 1, set = 0, binding = 1) uniform subpassInput normal;
+layout(input_attachment_index = 2, set = 0, binding = 2) uniform subpassInput position;
+
+layout(push_constant) uniform DeferredLight {
+  // In view space.
+  vec4 position;
+  vec4 intensity;
+  vec4 ambient;
+} light;
+
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+  vec4 p = subpassLoad(position);
+  if (p.w == 0.0) {
+    // Nothing was drawn here, so keep the clear color.
+    discard;
+  }
+  vec4 a = subpassLoad(albedo);
+  vec4 n4 = subpassLoad(normal);
+  vec3 n = normalize(n4.xyz);
+  vec3 s = normalize(light.position.xyz - p.xyz);
+  vec3 v = normalize(-p.xyz);
+  vec3 h = normalize(s + v);
+  vec3 diffuse = a.rgb * max(dot(s, n), 0.0);
+  float specular = a.a * pow(max(dot(n, h), 0.0), n4.w);
+  vec3 c = a.rgb * light.ambient.rgb + light.intensity.rgb * (diffuse + specular);
+  frag_color = vec4(c, 1.0);
+}
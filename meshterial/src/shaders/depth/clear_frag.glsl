@@ -0,0 +1,7 @@
+#version 450
+
+// Writes the far depth that the depth attachment is cleared to. Color writes
+// are masked off by the pipeline.
+void main() {
+  gl_FragDepth = 1.0;
+}
@@ -0,0 +1,5 @@
+#version 450
+
+// Depth only, color writes are masked off by the pipeline.
+void main() {
+}
@@ -0,0 +1,20 @@
+#version 450
+
+// Copies one texel of the depth buffer into a buffer that can be read on the
+// CPU. See VkRenderer::read_depth_at.
+
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler2D depth;
+
+layout(set = 0, binding = 1) buffer Result {
+  float depth;
+} result;
+
+layout(push_constant) uniform Pixel {
+  ivec2 position;
+} pixel;
+
+void main() {
+  result.depth = texelFetch(depth, pixel.position, 0).r;
+}
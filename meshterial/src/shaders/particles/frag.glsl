@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec2 corner;
+layout(location = 1) in vec4 color;
+
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+  // Round, soft edged particles.
+  float d = length(corner);
+  if (d > 1.0) {
+    discard;
+  }
+  frag_color = vec4(color.rgb, color.a * (1.0 - d * d));
+}
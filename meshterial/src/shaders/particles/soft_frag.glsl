@@ -0,0 +1,38 @@
+#version 450
+
+// Particles that fade out where they get close to the scene behind them,
+// instead of cutting a hard line where their quad intersects it. They are
+// drawn after the scene pass has ended, so the depth buffer can be sampled,
+// and that sample replaces the depth test.
+
+layout(location = 0) in vec2 corner;
+layout(location = 1) in vec4 color;
+layout(location = 2) in float view_depth;
+
+layout(location = 0) out vec4 frag_color;
+
+layout(set = 1, binding = 0) uniform sampler2D depth;
+
+layout(set = 1, binding = 1) uniform SoftParticles {
+  mat4 inverse_projection;
+  // The distance in view space units over which particles fade out in front
+  // of the scene.
+  float softness;
+} soft;
+
+void main() {
+  float d = length(corner);
+  if (d > 1.0) {
+    discard;
+  }
+  // The depth buffer holds depth after the perspective divide, which isn't
+  // linear in distance. Turn it back into a view space distance with the
+  // inverse projection so it can be compared with the particle's.
+  vec2 uv = gl_FragCoord.xy / vec2(textureSize(depth, 0));
+  vec4 ndc = vec4(uv * 2.0 - 1.0, texture(depth, uv).r, 1.0);
+  vec4 view = soft.inverse_projection * ndc;
+  float scene_depth = -view.z / view.w;
+  // Particles behind the scene are hidden, as the depth test would.
+  float fade = clamp((scene_depth - view_depth) / max(soft.softness, 0.0001), 0.0, 1.0);
+  frag_color = vec4(color.rgb, color.a * (1.0 - d * d) * fade);
+}
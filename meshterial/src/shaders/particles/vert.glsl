@@ -0,0 +1,30 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Projection {
+  mat4 mat;
+} proj;
+
+layout(push_constant) uniform Billboard {
+  mat4 view;
+  // The width and height of each particle in world units.
+  float size;
+} billboard;
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec2 corner;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out vec2 out_corner;
+layout(location = 1) out vec4 out_color;
+// How far in front of the camera the particle is, for soft particles.
+layout(location = 2) out float out_view_depth;
+
+void main() {
+  // Offset the corner in view space so the quad always faces the camera.
+  vec4 pos = billboard.view * vec4(position, 1.0);
+  pos.xy += corner * billboard.size * 0.5;
+  out_corner = corner;
+  out_color = color;
+  out_view_depth = -pos.z;
+  gl_Position = proj.mat * pos;
+}
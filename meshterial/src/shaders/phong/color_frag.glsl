@@ -0,0 +1,38 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform Material {
+  vec4 emission;
+  vec4 ambient;
+  vec4 diffuse;
+  vec4 specular;
+  float shininess;
+  // 1 to light back faces as if they faced the camera, for thin surfaces
+  // like paper and leaves. The phong pipelines don't cull back faces.
+  int double_sided;
+} material;
+
+layout(set = 2, binding = 0) uniform Light {
+  vec3 position;
+  vec3 intensity;
+} light;
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+  vec3 n = normalize( normal );
+  if (material.double_sided == 1 && !gl_FrontFacing) {
+    n = -n;
+  }
+  vec3 s = normalize( light.position - position );
+  vec3 v = normalize( -position );
+  vec3 r = reflect(-s, n);
+  // The vertex color modulates the ambient and diffuse terms, leaving
+  // specular highlights the color of the light.
+  vec4 c = (material.ambient + material.diffuse * max(dot(s, n), 0.0)) * color +
+    material.specular * pow(max(dot(r, v), 0.0), material.shininess);
+  frag_color = vec4(light.intensity, 1.0) * c;
+}
@@ -0,0 +1,28 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Projection {
+  mat4 mat;
+} proj;
+
+layout(push_constant) uniform ModelViewNormal {
+  mat4 model;
+  mat4 view;
+  mat4 normal;
+} mats;
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out vec3 out_position;
+layout(location = 1) out vec3 out_normal;
+layout(location = 2) out vec4 out_color;
+
+void main() {
+  mat4 modelview = mats.view * mats.model;
+  vec4 pos = modelview * vec4(position, 1.0);
+  out_normal = normalize( mats.normal * vec4(normal, 1.0) ).xyz;
+  out_position = pos.xyz;
+  out_color = color;
+  gl_Position = proj.mat * pos;
+}
@@ -0,0 +1,39 @@
+#version 450
+
+// A debug variant of frag.glsl that tints front faces green and back faces
+// red, keeping the lighting so the shape of the mesh is still readable.
+
+layout(set = 1, binding = 0) uniform Material {
+  vec4 emission;
+  vec4 ambient;
+  vec4 diffuse;
+  vec4 specular;
+  float shininess;
+  // 1 to light back faces as if they faced the camera, for thin surfaces
+  // like paper and leaves. The phong pipelines don't cull back faces.
+  int double_sided;
+} material;
+
+layout(set = 2, binding = 0) uniform Light {
+  vec3 position;
+  vec3 intensity;
+} light;
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+  vec3 n = normalize( normal );
+  vec3 s = normalize( light.position - position );
+  vec3 v = normalize( -position );
+  vec3 r = reflect(-s, n);
+  vec4 c = material.ambient +
+    material.diffuse * max(dot(s, n), 0.0) +
+    material.specular * pow(max(dot(r, v), 0.0), material.shininess);
+  vec3 lit = light.intensity * c.rgb;
+  float brightness = 0.25 + 0.75 * clamp(max(lit.r, max(lit.g, lit.b)), 0.0, 1.0);
+  vec3 tint = gl_FrontFacing ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+  frag_color = vec4(tint * brightness, 1.0);
+}
@@ -6,6 +6,9 @@ layout(set = 1, binding = 0) uniform Material {
   vec4 diffuse;
   vec4 specular;
   float shininess;
+  // 1 to light back faces as if they faced the camera, for thin surfaces
+  // like paper and leaves. The phong pipelines don't cull back faces.
+  int double_sided;
   //float index_of_refraction;
 } material;
 
@@ -14,19 +17,79 @@ layout(set = 2, binding = 0) uniform Light {
   vec3 intensity;
 } light;
 
+layout(set = 2, binding = 1) uniform Fog {
+  vec4 color;
+  float start;
+  float end;
+  float density;
+  // 0 off, 1 linear, 2 exponential, 3 exponential squared. See FogMode in
+  // fog.rs.
+  int mode;
+} fog;
+
+// Fragments on the negative side of the plane are discarded, where a point p
+// is on the positive side when dot(plane, vec4(p, 1.0)) >= 0. See
+// ClipPlaneUniform in clip.rs.
+layout(set = 2, binding = 2) uniform ClipPlane {
+  vec4 plane;
+} clip;
+
+// 0 for Phong, 1 for Blinn-Phong. See SpecularModel in phong.rs.
+layout(constant_id = 0) const int specular_model = 0;
+// 0 for smooth, 1 for flat. See Shading in phong.rs.
+layout(constant_id = 1) const int flat_shading = 0;
+
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec3 normal;
+layout(location = 2) in vec3 world_position;
 
 layout(location = 0) out vec4 frag_color;
 
+// Blend a color toward the fog color by its view-space depth.
+vec3 apply_fog(vec3 color, float depth) {
+  float visibility = 1.0;
+  if (fog.mode == 1) {
+    visibility = clamp((fog.end - depth) / max(fog.end - fog.start, 0.0001), 0.0, 1.0);
+  } else if (fog.mode == 2) {
+    visibility = exp(-fog.density * depth);
+  } else if (fog.mode == 3) {
+    float d = fog.density * depth;
+    visibility = exp(-d * d);
+  }
+  return mix(fog.color.rgb, color, visibility);
+}
+
 void main() {
-  vec3 n = normalize( normal );
+  if (dot(clip.plane, vec4(world_position, 1.0)) < 0.0) {
+    discard;
+  }
+  vec3 n;
+  if (flat_shading == 1) {
+    n = normalize(cross(dFdx(position), dFdy(position)));
+    n = dot(n, position) > 0.0 ? -n : n;
+  } else {
+    n = normalize( normal );
+    if (material.double_sided == 1 && !gl_FrontFacing) {
+      n = -n;
+    }
+  }
   vec3 s = normalize( light.position - position );
   vec3 v = normalize( -position );
-  vec3 r = reflect(-s, n);
+  float spec;
+  if (specular_model == 1) {
+    vec3 h = normalize(s + v);
+    spec = pow(max(dot(n, h), 0.0), material.shininess);
+  } else {
+    vec3 r = reflect(-s, n);
+    spec = pow(max(dot(r, v), 0.0), material.shininess);
+  }
   vec4 c = material.ambient +
     material.diffuse * max(dot(s, n), 0.0) +
-    material.specular * pow(max(dot(r, v), 0.0), material.shininess);
-  frag_color = vec4(light.intensity, 1.0) * c;
+    material.specular * spec;
+  // Emission isn't lit, and with HDR and bloom on bright emission glows.
+  frag_color = vec4(light.intensity, 1.0) * c + vec4(material.emission.rgb, 0.0);
+  // The diffuse alpha is the material's opacity.
+  frag_color.a = material.diffuse.a;
+  frag_color.rgb = apply_fog(frag_color.rgb, abs(position.z));
   //frag_color = vec4(n.rgb, 1.0);
 }
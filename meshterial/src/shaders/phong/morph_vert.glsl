@@ -0,0 +1,48 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Projection {
+  mat4 mat;
+} proj;
+
+layout(set = 0, binding = 1) uniform LogDepth {
+  float far;
+} log_depth;
+
+layout(push_constant) uniform MorphModelViewNormal {
+  mat4 model;
+  mat4 view;
+  mat4 normal;
+  float morph_weight;
+} mats;
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec3 target_position;
+layout(location = 3) in vec3 target_normal;
+
+layout(location = 0) out vec3 out_position;
+layout(location = 1) out vec3 out_normal;
+// For the clip plane, which is in world space.
+layout(location = 2) out vec3 out_world_position;
+
+// Replace the projection's depth with a logarithmic one when the far plane is
+// set, see LogDepthUniform in log_depth.rs. This is done per vertex rather
+// than by writing gl_FragDepth, which would turn off early depth testing.
+vec4 apply_log_depth(vec4 position) {
+  if (log_depth.far > 0.0) {
+    float w = max(position.w, 0.000001);
+    position.z = log2(1.0 + w) / log2(1.0 + log_depth.far) * position.w;
+  }
+  return position;
+}
+
+void main() {
+  vec3 morph_position = mix(position, target_position, mats.morph_weight);
+  vec3 morph_normal = mix(normal, target_normal, mats.morph_weight);
+  mat4 modelview = mats.view * mats.model;
+  vec4 pos = modelview * vec4(morph_position, 1.0);
+  out_normal = normalize( mats.normal * vec4(morph_normal, 1.0) ).xyz;
+  out_position = pos.xyz;
+  out_world_position = (mats.model * vec4(morph_position, 1.0)).xyz;
+  gl_Position = apply_log_depth(proj.mat * pos);
+}
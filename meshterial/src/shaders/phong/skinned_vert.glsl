@@ -0,0 +1,44 @@
+#version 450
+
+// Must match MAX_JOINTS in pipelines/phong_skinned.rs
+#define MAX_JOINTS 64
+
+layout(set = 0, binding = 0) uniform Projection {
+  mat4 mat;
+} proj;
+
+layout(set = 3, binding = 0) uniform Joints {
+  mat4 matrices[MAX_JOINTS];
+} joints;
+
+layout(push_constant) uniform ModelViewNormal {
+  mat4 model;
+  mat4 view;
+  mat4 normal;
+} mats;
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in uvec4 joint_indices;
+layout(location = 3) in vec4 weights;
+
+layout(location = 0) out vec3 out_position;
+layout(location = 1) out vec3 out_normal;
+// For the clip plane, which is in world space.
+layout(location = 2) out vec3 out_world_position;
+
+void main() {
+  mat4 skin =
+    weights.x * joints.matrices[joint_indices.x] +
+    weights.y * joints.matrices[joint_indices.y] +
+    weights.z * joints.matrices[joint_indices.z] +
+    weights.w * joints.matrices[joint_indices.w];
+  vec4 skinned_position = skin * vec4(position, 1.0);
+  vec3 skinned_normal = (skin * vec4(normal, 0.0)).xyz;
+  mat4 modelview = mats.view * mats.model;
+  vec4 pos = modelview * skinned_position;
+  out_normal = normalize( mats.normal * vec4(skinned_normal, 1.0) ).xyz;
+  out_position = pos.xyz;
+  out_world_position = (mats.model * skinned_position).xyz;
+  gl_Position = proj.mat * pos;
+}
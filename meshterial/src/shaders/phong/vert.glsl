@@ -4,6 +4,10 @@ layout(set = 0, binding = 0) uniform Projection {
   mat4 mat;
 } proj;
 
+layout(set = 0, binding = 1) uniform LogDepth {
+  float far;
+} log_depth;
+
 layout(push_constant) uniform ModelViewNormal {
   mat4 model;
   mat4 view;
@@ -15,11 +19,25 @@ layout(location = 1) in vec3 normal;
 
 layout(location = 0) out vec3 out_position;
 layout(location = 1) out vec3 out_normal;
+// For the clip plane, which is in world space.
+layout(location = 2) out vec3 out_world_position;
+
+// Replace the projection's depth with a logarithmic one when the far plane is
+// set, see LogDepthUniform in log_depth.rs. This is done per vertex rather
+// than by writing gl_FragDepth, which would turn off early depth testing.
+vec4 apply_log_depth(vec4 position) {
+  if (log_depth.far > 0.0) {
+    float w = max(position.w, 0.000001);
+    position.z = log2(1.0 + w) / log2(1.0 + log_depth.far) * position.w;
+  }
+  return position;
+}
 
 void main() {
-  mat4 modelview = mats.model * mats.view;
+  mat4 modelview = mats.view * mats.model;
   vec4 pos = modelview * vec4(position, 1.0);
   out_normal = normalize( mats.normal * vec4(normal, 1.0) ).xyz;
   out_position = pos.xyz;
-  gl_Position = proj.mat * modelview * pos;
+  out_world_position = (mats.model * vec4(position, 1.0)).xyz;
+  gl_Position = apply_log_depth(proj.mat * pos);
 }
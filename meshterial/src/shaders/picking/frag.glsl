@@ -0,0 +1,9 @@
+#version 450
+
+layout(location = 0) flat in uint id;
+
+layout(location = 0) out uint frag_id;
+
+void main() {
+  frag_id = id;
+}
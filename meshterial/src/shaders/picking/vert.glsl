@@ -0,0 +1,16 @@
+#version 450
+
+layout(push_constant) uniform Pick {
+  // proj * view * model
+  mat4 mvp;
+  uint id;
+} pick;
+
+layout(location = 0) in vec3 position;
+
+layout(location = 0) flat out uint out_id;
+
+void main() {
+  out_id = pick.id;
+  gl_Position = pick.mvp * vec4(position, 1.0);
+}
@@ -0,0 +1,21 @@
+#version 450
+
+// A 4x4 box blur that removes the pattern left by the 4x4 noise texture of the
+// occlusion pass.
+
+layout(set = 0, binding = 0) uniform sampler2D occlusion;
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+  vec2 texel = 1.0 / vec2(textureSize(occlusion, 0));
+  float sum = 0.0;
+  for (int y = -2; y < 2; y++) {
+    for (int x = -2; x < 2; x++) {
+      sum += texture(occlusion, uv + vec2(x, y) * texel).r;
+    }
+  }
+  frag_color = vec4(vec3(sum / 16.0), 1.0);
+}
@@ -0,0 +1,70 @@
+#version 450
+
+// Screen-space ambient occlusion. See VkRenderer::set_ssao.
+//
+// The view space position of each pixel is reconstructed from the depth
+// buffer, and its normal from the derivatives of that position. Samples in a
+// hemisphere around the normal, randomly rotated per pixel by the noise
+// texture, count as occluded when they are behind the depth buffer.
+
+const int KERNEL_SIZE = 16;
+
+layout(set = 0, binding = 0) uniform sampler2D depth;
+layout(set = 0, binding = 1) uniform sampler2D noise;
+
+layout(set = 0, binding = 2) uniform Kernel {
+  vec4 samples[KERNEL_SIZE];
+} kernel;
+
+layout(push_constant) uniform Ssao {
+  mat4 projection;
+  mat4 inverse_projection;
+  vec2 noise_scale;
+  float radius;
+  float intensity;
+} ssao;
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 frag_color;
+
+vec3 view_position(vec2 at) {
+  vec4 ndc = vec4(at * 2.0 - 1.0, texture(depth, at).r, 1.0);
+  vec4 view = ssao.inverse_projection * ndc;
+  return view.xyz / view.w;
+}
+
+void main() {
+  if (texture(depth, uv).r >= 1.0) {
+    // Nothing was drawn here.
+    frag_color = vec4(1.0);
+    return;
+  }
+
+  vec3 position = view_position(uv);
+  vec3 normal = normalize(cross(dFdx(position), dFdy(position)));
+  // The camera is at the origin, so the normal must point back toward it.
+  if (dot(normal, position) > 0.0) {
+    normal = -normal;
+  }
+
+  vec3 random = texture(noise, uv * ssao.noise_scale).xyz * 2.0 - 1.0;
+  vec3 tangent = normalize(random - normal * dot(random, normal));
+  vec3 bitangent = cross(normal, tangent);
+  mat3 tbn = mat3(tangent, bitangent, normal);
+
+  float occlusion = 0.0;
+  for (int i = 0; i < KERNEL_SIZE; i++) {
+    vec3 sample_position = position + tbn * kernel.samples[i].xyz * ssao.radius;
+    vec4 offset = ssao.projection * vec4(sample_position, 1.0);
+    vec2 sample_uv = (offset.xy / offset.w) * 0.5 + 0.5;
+    float sample_depth = view_position(sample_uv).z;
+    // Ignore geometry much further away than the radius, so that silhouettes
+    // don't darken whatever is behind them.
+    float range = smoothstep(0.0, 1.0, ssao.radius / abs(position.z - sample_depth));
+    // The camera looks down -z, so closer geometry has a greater z.
+    occlusion += (sample_depth >= sample_position.z + 0.025 ? 1.0 : 0.0) * range;
+  }
+  occlusion = 1.0 - ssao.intensity * occlusion / float(KERNEL_SIZE);
+  frag_color = vec4(vec3(clamp(occlusion, 0.0, 1.0)), 1.0);
+}
@@ -0,0 +1,23 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler2D hdr;
+// The ambient occlusion, or a white texel when SSAO is off.
+layout(set = 0, binding = 1) uniform sampler2D occlusion;
+// The blurred bright parts of the scene, or a black texel when bloom is off.
+layout(set = 0, binding = 2) uniform sampler2D bloom;
+
+layout(push_constant) uniform Tonemap {
+  float exposure;
+  float bloom_intensity;
+} tonemap;
+
+layout(location = 0) in vec2 uv;
+
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+  vec3 color = texture(hdr, uv).rgb * texture(occlusion, uv).r;
+  color = (color + texture(bloom, uv).rgb * tonemap.bloom_intensity) * tonemap.exposure;
+  // Exponential tonemapping maps [0, inf) into [0, 1).
+  frag_color = vec4(vec3(1.0) - exp(-color), 1.0);
+}
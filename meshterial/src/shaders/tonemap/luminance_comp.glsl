@@ -0,0 +1,27 @@
+#version 450
+
+// Averages the log luminance of a grid of samples of the HDR image into a
+// buffer that can be read on the CPU. See VkRenderer::set_auto_exposure.
+
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler2D hdr;
+
+layout(set = 0, binding = 1) buffer Result {
+  float log_luminance;
+} result;
+
+const int GRID = 16;
+
+void main() {
+  float sum = 0.0;
+  for (int y = 0; y < GRID; y++) {
+    for (int x = 0; x < GRID; x++) {
+      vec2 uv = (vec2(x, y) + 0.5) / float(GRID);
+      vec3 color = textureLod(hdr, uv, 0.0).rgb;
+      float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+      sum += log(luminance + 0.0001);
+    }
+  }
+  result.log_luminance = sum / float(GRID * GRID);
+}
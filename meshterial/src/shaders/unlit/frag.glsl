@@ -0,0 +1,11 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform Color {
+  vec4 color;
+} color;
+
+layout(location = 0) out vec4 frag_color;
+
+void main() {
+  frag_color = color.color;
+}
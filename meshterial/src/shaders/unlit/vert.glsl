@@ -0,0 +1,19 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Projection {
+  mat4 mat;
+} proj;
+
+layout(push_constant) uniform ModelView {
+  mat4 model;
+  mat4 view;
+} modelview;
+
+layout(location = 0) in vec3 position;
+
+void main() {
+  // The same order of operations as the phong shaders, so that a depth
+  // prepass drawn with this shader matches their depth exactly.
+  vec4 pos = modelview.view * modelview.model * vec4(position, 1.0);
+  gl_Position = proj.mat * pos;
+}
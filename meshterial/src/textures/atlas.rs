@@ -0,0 +1,264 @@
+//! Sprite atlases in TexturePacker's JSON format, either the "hash" layout
+//! where `frames` is an object keyed by name or the "array" layout where each
+//! frame has a `filename`.
+use nalgebra::{Matrix4, Vector2};
+use serde_json::Value;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::descriptor::descriptor_set::DescriptorSet;
+use vulkano::format::Format;
+use vulkano::image::ImmutableImage;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::super::pipelines::texture2d::{vs, Texture2DPipeline, VertexUV};
+use super::super::vk_renderer::{TextureError, VkRenderer};
+
+
+#[derive(Debug)]
+pub enum AtlasError {
+  Io(String, io::Error),
+  Json(String),
+  /// The atlas has no `frames`, or they are neither an object nor an array.
+  MissingFrames,
+  /// A frame doesn't have a `frame` rectangle or name.
+  InvalidFrame(String),
+  /// TexturePacker rotated a frame to pack it tighter. Rotated frames aren't
+  /// supported, so turn off "Allow rotation" when packing.
+  RotatedFrame(String),
+  /// The atlas doesn't say which image it belongs to.
+  MissingImage,
+  Texture(TextureError),
+}
+
+
+impl fmt::Display for AtlasError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      AtlasError::Io(path, e) => write!(f, "could not read atlas '{}': {}", path, e),
+      AtlasError::Json(e) => write!(f, "could not parse atlas: {}", e),
+      AtlasError::MissingFrames => write!(f, "atlas has no frames"),
+      AtlasError::InvalidFrame(name) => write!(f, "atlas frame '{}' is invalid", name),
+      AtlasError::RotatedFrame(name) => {
+        write!(f, "atlas frame '{}' is rotated, which is not supported", name)
+      }
+      AtlasError::MissingImage => write!(f, "atlas does not name its image"),
+      AtlasError::Texture(e) => write!(f, "could not load atlas texture: {}", e),
+    }
+  }
+}
+
+
+impl Error for AtlasError {}
+
+
+impl From<TextureError> for AtlasError {
+  fn from(e: TextureError) -> AtlasError {
+    AtlasError::Texture(e)
+  }
+}
+
+
+/// A named rectangle of an atlas' texture, in pixels from its top left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasFrame {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+
+impl AtlasFrame {
+  /// The frame's texture coordinates in a texture of the given size, as
+  /// `[left, top, right, bottom]`.
+  pub fn uv_rect(&self, texture_size: Vector2<u32>) -> [f32; 4] {
+    let w = texture_size.x.max(1) as f32;
+    let h = texture_size.y.max(1) as f32;
+    [
+      self.x as f32 / w,
+      self.y as f32 / h,
+      (self.x + self.width) as f32 / w,
+      (self.y + self.height) as f32 / h,
+    ]
+  }
+}
+
+
+/// The frames of a sprite atlas, without its texture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Atlas {
+  pub frames: HashMap<String, AtlasFrame>,
+  /// The image file from `meta.image`, relative to the atlas file.
+  pub image: Option<String>,
+  /// The size of the image from `meta.size`.
+  pub size: Option<Vector2<u32>>,
+}
+
+
+impl Atlas {
+  /// Parse an atlas from TexturePacker JSON.
+  pub fn parse(json: &str) -> Result<Atlas, AtlasError> {
+    let value: Value = serde_json::from_str(json)
+      .map_err(|e| AtlasError::Json(e.to_string()))?;
+
+    let mut frames = HashMap::new();
+    match value.get("frames") {
+      Some(Value::Object(named)) => {
+        for (name, frame) in named.iter() {
+          frames.insert(name.clone(), Atlas::parse_frame(name, frame)?);
+        }
+      }
+      Some(Value::Array(list)) => {
+        for frame in list.iter() {
+          let name = frame
+            .get("filename")
+            .and_then(Value::as_str)
+            .ok_or_else(|| AtlasError::InvalidFrame(frame.to_string()))?;
+          frames.insert(name.to_string(), Atlas::parse_frame(name, frame)?);
+        }
+      }
+      _ => return Err(AtlasError::MissingFrames),
+    }
+
+    let meta = value.get("meta");
+    let image = meta
+      .and_then(|meta| meta.get("image"))
+      .and_then(Value::as_str)
+      .map(|image| image.to_string());
+    let size = meta
+      .and_then(|meta| meta.get("size"))
+      .and_then(|size| Some(Vector2::new(
+        size.get("w")?.as_u64()? as u32,
+        size.get("h")?.as_u64()? as u32
+      )));
+    Ok(Atlas { frames, image, size })
+  }
+
+
+  fn parse_frame(name: &str, frame: &Value) -> Result<AtlasFrame, AtlasError> {
+    if frame.get("rotated").and_then(Value::as_bool).unwrap_or(false) {
+      return Err(AtlasError::RotatedFrame(name.to_string()));
+    }
+    let rect = frame.get("frame");
+    let field = |key: &str| {
+      rect
+        .and_then(|rect| rect.get(key))
+        .and_then(Value::as_u64)
+        .map(|n| n as u32)
+        .ok_or_else(|| AtlasError::InvalidFrame(name.to_string()))
+    };
+    Ok(AtlasFrame {
+      x: field("x")?,
+      y: field("y")?,
+      width: field("w")?,
+      height: field("h")?,
+    })
+  }
+
+
+  /// Read and parse an atlas file.
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Atlas, AtlasError> {
+    let path = path.as_ref();
+    let json = fs::read_to_string(path)
+      .map_err(|e| AtlasError::Io(path.display().to_string(), e))?;
+    Atlas::parse(&json)
+  }
+}
+
+
+/// An atlas and its texture, for drawing sprites by name with a
+/// `Texture2DPipeline`.
+///
+/// Every sprite is drawn from the same descriptor set, so drawing many
+/// sprites from one sheet doesn't swap textures.
+pub struct SpriteSheet {
+  pub atlas: Atlas,
+  pub texture: Arc<ImmutableImage<Format>>,
+  pub texture_size: Vector2<u32>,
+  pub desc_set: Arc<DescriptorSet + Send + Sync>,
+}
+
+
+impl SpriteSheet {
+  /// Load an atlas file and the image it names, which is looked up relative
+  /// to the atlas file. The texture is cached like any other loaded with
+  /// `VkRenderer::load_texture`.
+  pub fn load<P: AsRef<Path>>(
+    vkr: &mut VkRenderer,
+    pipeline: &mut Texture2DPipeline,
+    atlas_path: P
+  ) -> Result<SpriteSheet, AtlasError> {
+    let atlas_path = atlas_path.as_ref();
+    let atlas = Atlas::open(atlas_path)?;
+    let image = atlas.image.clone().ok_or(AtlasError::MissingImage)?;
+    let image_path = atlas_path
+      .parent()
+      .unwrap_or_else(|| Path::new(""))
+      .join(image)
+      .to_string_lossy()
+      .into_owned();
+    let (texture, texture_size) = vkr.try_load_texture(&image_path)?;
+    let desc_set = pipeline.desc_set_for_texture(texture.clone(), vkr.device.clone());
+    Ok(SpriteSheet {
+      atlas,
+      texture,
+      texture_size,
+      desc_set,
+    })
+  }
+
+
+  /// The texture coordinates of a frame, see `AtlasFrame::uv_rect`.
+  pub fn uv_rect(&self, name: &str) -> Option<[f32; 4]> {
+    self
+      .atlas
+      .frames
+      .get(name)
+      .map(|frame| frame.uv_rect(self.texture_size))
+  }
+
+
+  /// Draw the named frame into `dest`, given as `[x, y, width, height]` in the
+  /// pipeline's coordinates. The top left of the frame is drawn at `(x, y)`.
+  /// Returns false without drawing if the sheet has no such frame.
+  pub fn draw_sprite_named(
+    &self,
+    vkr: &mut VkRenderer,
+    pipeline: &Texture2DPipeline,
+    name: &str,
+    dest: [f32; 4]
+  ) -> bool {
+    let uv = match self.uv_rect(name) {
+      Some(uv) => uv,
+      None => return false,
+    };
+    let (x, y, w, h) = (dest[0], dest[1], dest[2], dest[3]);
+    let tl = VertexUV { position: [x, y], uv: [uv[0], uv[1]] };
+    let tr = VertexUV { position: [x + w, y], uv: [uv[2], uv[1]] };
+    let br = VertexUV { position: [x + w, y + h], uv: [uv[2], uv[3]] };
+    let bl = VertexUV { position: [x, y + h], uv: [uv[0], uv[3]] };
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      vkr.device.clone(),
+      BufferUsage::vertex_buffer(),
+      vec![tl.clone(), tr, br.clone(), tl, br, bl].into_iter()
+    ).expect("Could not create sprite vertex buffer.");
+    let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+    let push_constants = vs::ty::Matrices {
+      model: identity,
+      view: identity,
+    };
+    vkr.draw_with(
+      pipeline,
+      vertex_buffer,
+      (pipeline.proj_desc_set.clone(), self.desc_set.clone()),
+      push_constants
+    );
+    true
+  }
+}
@@ -0,0 +1,265 @@
+//! Reading block compressed textures from DDS files.
+use vulkano::format::Format;
+
+use std::error::Error;
+use std::fmt;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockFormat {
+  /// DXT1
+  Bc1,
+  /// DXT3
+  Bc2,
+  /// DXT5
+  Bc3,
+  Bc7,
+}
+
+
+impl BlockFormat {
+  /// The number of bytes in each 4x4 block of texels.
+  pub fn block_size(&self) -> usize {
+    match self {
+      BlockFormat::Bc1 => 8,
+      BlockFormat::Bc2 | BlockFormat::Bc3 | BlockFormat::Bc7 => 16,
+    }
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DdsError {
+  /// The data doesn't start with a DDS header.
+  NotDds,
+  /// The data ends before the top mip level does.
+  Truncated,
+  /// The DDS contains a pixel format other than BC1, BC2, BC3 or BC7.
+  UnsupportedFormat(String),
+}
+
+
+impl fmt::Display for DdsError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      DdsError::NotDds => write!(f, "not a DDS file"),
+      DdsError::Truncated => write!(f, "DDS data is truncated"),
+      DdsError::UnsupportedFormat(format) => write!(f, "unsupported DDS format {}", format),
+    }
+  }
+}
+
+
+impl Error for DdsError {}
+
+
+/// The top mip level of a block compressed DDS texture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dds {
+  pub width: u32,
+  pub height: u32,
+  pub format: BlockFormat,
+  pub srgb: bool,
+  /// The compressed blocks, row by row.
+  pub blocks: Vec<u8>,
+}
+
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+  bytes[offset] as u32
+    | (bytes[offset + 1] as u32) << 8
+    | (bytes[offset + 2] as u32) << 16
+    | (bytes[offset + 3] as u32) << 24
+}
+
+
+impl Dds {
+  /// Parse a DDS file. Only the top mip level is kept.
+  pub fn parse(bytes: &[u8]) -> Result<Dds, DdsError> {
+    if bytes.len() < 128 || &bytes[0 .. 4] != b"DDS " || read_u32(bytes, 4) != 124 {
+      return Err(DdsError::NotDds);
+    }
+    let height = read_u32(bytes, 12);
+    let width = read_u32(bytes, 16);
+    let four_cc = &bytes[84 .. 88];
+
+    let (format, srgb, data_start) = match four_cc {
+      b"DXT1" => (BlockFormat::Bc1, false, 128),
+      b"DXT3" => (BlockFormat::Bc2, false, 128),
+      b"DXT5" => (BlockFormat::Bc3, false, 128),
+      b"DX10" => {
+        if bytes.len() < 148 {
+          return Err(DdsError::Truncated);
+        }
+        let (format, srgb) = match read_u32(bytes, 128) {
+          71 => (BlockFormat::Bc1, false),
+          72 => (BlockFormat::Bc1, true),
+          74 => (BlockFormat::Bc2, false),
+          75 => (BlockFormat::Bc2, true),
+          77 => (BlockFormat::Bc3, false),
+          78 => (BlockFormat::Bc3, true),
+          98 => (BlockFormat::Bc7, false),
+          99 => (BlockFormat::Bc7, true),
+          dxgi => {
+            return Err(DdsError::UnsupportedFormat(format!("DXGI format {}", dxgi)));
+          }
+        };
+        (format, srgb, 148)
+      }
+      other => {
+        return Err(DdsError::UnsupportedFormat(String::from_utf8_lossy(other).into_owned()));
+      }
+    };
+
+    let blocks_wide = ((width + 3) / 4).max(1) as usize;
+    let blocks_high = ((height + 3) / 4).max(1) as usize;
+    let len = blocks_wide * blocks_high * format.block_size();
+    if bytes.len() < data_start + len {
+      return Err(DdsError::Truncated);
+    }
+
+    Ok(Dds {
+      width,
+      height,
+      format,
+      srgb,
+      blocks: bytes[data_start .. data_start + len].to_vec(),
+    })
+  }
+
+
+  /// The vulkan format of the compressed blocks.
+  pub fn vulkan_format(&self) -> Format {
+    match (self.format, self.srgb) {
+      (BlockFormat::Bc1, false) => Format::BC1_RGBAUnormBlock,
+      (BlockFormat::Bc1, true) => Format::BC1_RGBASrgbBlock,
+      (BlockFormat::Bc2, false) => Format::BC2UnormBlock,
+      (BlockFormat::Bc2, true) => Format::BC2SrgbBlock,
+      (BlockFormat::Bc3, false) => Format::BC3UnormBlock,
+      (BlockFormat::Bc3, true) => Format::BC3SrgbBlock,
+      (BlockFormat::Bc7, false) => Format::BC7UnormBlock,
+      (BlockFormat::Bc7, true) => Format::BC7SrgbBlock,
+    }
+  }
+
+
+  /// Decode the blocks into RGBA8 texels, for devices that can't sample block
+  /// compressed textures. Returns None for BC7, which isn't decoded in
+  /// software.
+  pub fn decode_rgba(&self) -> Option<Vec<u8>> {
+    if self.format == BlockFormat::Bc7 {
+      return None;
+    }
+    let width = self.width as usize;
+    let height = self.height as usize;
+    let blocks_wide = ((width + 3) / 4).max(1);
+    let block_size = self.format.block_size();
+    let mut rgba = vec![0; width * height * 4];
+
+    for (ndx, block) in self.blocks.chunks(block_size).enumerate() {
+      let bx = (ndx % blocks_wide) * 4;
+      let by = (ndx / blocks_wide) * 4;
+      let texels = match self.format {
+        BlockFormat::Bc1 => decode_color_block(block, true),
+        BlockFormat::Bc2 => {
+          let mut texels = decode_color_block(&block[8 ..], false);
+          for i in 0 .. 16 {
+            let nibble = (block[i / 2] >> ((i % 2) * 4)) & 0xf;
+            texels[i][3] = nibble * 17;
+          }
+          texels
+        }
+        _ => {
+          let mut texels = decode_color_block(&block[8 ..], false);
+          let alphas = decode_alpha_block(&block[0 .. 8]);
+          for i in 0 .. 16 {
+            texels[i][3] = alphas[i];
+          }
+          texels
+        }
+      };
+
+      for (i, texel) in texels.iter().enumerate() {
+        let x = bx + i % 4;
+        let y = by + i / 4;
+        if x < width && y < height {
+          let offset = (y * width + x) * 4;
+          rgba[offset .. offset + 4].copy_from_slice(texel);
+        }
+      }
+    }
+
+    Some(rgba)
+  }
+}
+
+
+fn rgb565(c: u16) -> [u8; 4] {
+  let r = ((c >> 11) & 0x1f) as u32;
+  let g = ((c >> 5) & 0x3f) as u32;
+  let b = (c & 0x1f) as u32;
+  [
+    ((r * 255 + 15) / 31) as u8,
+    ((g * 255 + 31) / 63) as u8,
+    ((b * 255 + 15) / 31) as u8,
+    255
+  ]
+}
+
+
+fn mix(a: &[u8; 4], b: &[u8; 4], wa: u32, wb: u32) -> [u8; 4] {
+  let f = |i: usize| ((a[i] as u32 * wa + b[i] as u32 * wb) / (wa + wb)) as u8;
+  [f(0), f(1), f(2), 255]
+}
+
+
+/// Decode the 8 byte color part of a BC1, BC2 or BC3 block into 16 texels.
+/// Only BC1 blocks use the three color + transparent mode.
+fn decode_color_block(block: &[u8], allow_transparent: bool) -> [[u8; 4]; 16] {
+  let c0 = block[0] as u16 | (block[1] as u16) << 8;
+  let c1 = block[2] as u16 | (block[3] as u16) << 8;
+  let p0 = rgb565(c0);
+  let p1 = rgb565(c1);
+  let palette =
+    if c0 > c1 || !allow_transparent {
+      [p0, p1, mix(&p0, &p1, 2, 1), mix(&p0, &p1, 1, 2)]
+    } else {
+      [p0, p1, mix(&p0, &p1, 1, 1), [0, 0, 0, 0]]
+    };
+  let indices = read_u32(block, 4);
+  let mut texels = [[0; 4]; 16];
+  for i in 0 .. 16 {
+    texels[i] = palette[((indices >> (i * 2)) & 0x3) as usize];
+  }
+  texels
+}
+
+
+/// Decode the 8 byte alpha part of a BC3 block into 16 alpha values.
+fn decode_alpha_block(block: &[u8]) -> [u8; 16] {
+  let a0 = block[0] as u32;
+  let a1 = block[1] as u32;
+  let mut palette = [0u8; 8];
+  palette[0] = a0 as u8;
+  palette[1] = a1 as u8;
+  if a0 > a1 {
+    for i in 1 .. 7 {
+      palette[i + 1] = (((7 - i) as u32 * a0 + i as u32 * a1) / 7) as u8;
+    }
+  } else {
+    for i in 1 .. 5 {
+      palette[i + 1] = (((5 - i) as u32 * a0 + i as u32 * a1) / 5) as u8;
+    }
+    palette[6] = 0;
+    palette[7] = 255;
+  }
+  let mut bits:u64 = 0;
+  for i in 0 .. 6 {
+    bits |= (block[2 + i] as u64) << (i * 8);
+  }
+  let mut alphas = [0; 16];
+  for i in 0 .. 16 {
+    alphas[i] = palette[((bits >> (i * 3)) & 0x7) as usize];
+  }
+  alphas
+}
@@ -0,0 +1,195 @@
+//! Textures whose texels can be rewritten after they are created, for video
+//! frames, procedural textures or a software rendered UI.
+//!
+//! Textures loaded with `VkRenderer::load_texture` are immutable images,
+//! which are written once when they are uploaded.
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor::descriptor_set::DescriptorSet;
+use vulkano::device::{Device, DeviceOwned};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImageUsage, StorageImage};
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use super::super::pipelines::texture2d::Texture2DPipeline;
+use super::super::vk_renderer::VkRenderer;
+
+
+/// The format of every `DynamicTexture`, the same as textures loaded with
+/// `VkRenderer::load_texture`. Each texel is four bytes in the order blue,
+/// green, red, alpha.
+pub const DYNAMIC_TEXTURE_FORMAT: Format = Format::B8G8R8A8Unorm;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicTextureError {
+  /// Texels can't be copied inside a render pass, between
+  /// `VkRenderer::begin_rendering` and `commit_rendering`.
+  InRenderPass,
+  /// The `[x, y, width, height]` rect is empty or isn't inside the texture.
+  OutOfBounds([u32; 4]),
+  /// The data isn't four bytes for every texel of the rect, as
+  /// `(expected, given)`.
+  DataSize(usize, usize),
+}
+
+
+impl fmt::Display for DynamicTextureError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      DynamicTextureError::InRenderPass => {
+        write!(f, "texture updates must be recorded outside of the render pass")
+      }
+      DynamicTextureError::OutOfBounds(rect) => {
+        write!(f, "rect {:?} is empty or not inside the texture", rect)
+      }
+      DynamicTextureError::DataSize(expected, given) => {
+        write!(f, "expected {} bytes of texels but was given {}", expected, given)
+      }
+    }
+  }
+}
+
+
+impl Error for DynamicTextureError {}
+
+
+/// A 2d texture that can be sampled like a loaded texture, and whose texels
+/// can be replaced a region at a time with `update_region`.
+///
+/// Updates are staged in a new host visible buffer and copied into the image
+/// with the frame's commands, so they are seen by draws later in the same
+/// frame. The texels are undefined until written, so fill the whole texture
+/// with `update` before sampling it.
+pub struct DynamicTexture {
+  pub image: Arc<StorageImage<Format>>,
+  pub dimensions: [u32; 2],
+}
+
+
+impl DynamicTexture {
+  pub fn new(device: Arc<Device>, dimensions: [u32; 2]) -> DynamicTexture {
+    let image = StorageImage::with_usage(
+      device.clone(),
+      Dimensions::Dim2d {
+        width: dimensions[0],
+        height: dimensions[1],
+      },
+      DYNAMIC_TEXTURE_FORMAT,
+      ImageUsage {
+        sampled: true,
+        transfer_destination: true,
+        .. ImageUsage::none()
+      },
+      device.physical_device().queue_families()
+    ).expect("Could not create dynamic texture image.");
+    DynamicTexture {
+      image,
+      dimensions,
+    }
+  }
+
+
+  /// Replace the texels of the whole texture. See `update_region`.
+  pub fn update(&mut self, data: &[u8], vkr: &mut VkRenderer) -> Result<(), DynamicTextureError> {
+    let rect = [0, 0, self.dimensions[0], self.dimensions[1]];
+    self.update_region(rect, data, vkr)
+  }
+
+
+  /// Record a copy of new texels into the `[x, y, width, height]` rect of
+  /// the texture. This must be called outside of the render pass, eg. before
+  /// `VkRenderer::begin_rendering`.
+  ///
+  /// `data` is the rect's texels row by row from the top, in
+  /// `DYNAMIC_TEXTURE_FORMAT`. Rows are tightly packed: each is exactly
+  /// `width * 4` bytes with no padding, whatever the width of the texture,
+  /// so there is no row alignment to keep. Copy a rect out of a bigger
+  /// buffer one row at a time.
+  pub fn update_region(
+    &mut self,
+    rect: [u32; 4],
+    data: &[u8],
+    vkr: &mut VkRenderer
+  ) -> Result<(), DynamicTextureError> {
+    if vkr.is_in_render_pass() {
+      return Err(DynamicTextureError::InRenderPass);
+    }
+    let staging = self.staging_buffer(vkr.device.clone(), rect, data)?;
+    let image = self.image.clone();
+    vkr.record_buffer_upload();
+    vkr.with_command_builder(|cmds| DynamicTexture::copy_staged(cmds, staging, image, rect));
+    Ok(())
+  }
+
+
+  /// Like `update_region`, but records the copy into a command buffer of
+  /// your own. It must not be inside a render pass.
+  pub fn record_update_region(
+    &mut self,
+    cmds: AutoCommandBufferBuilder,
+    rect: [u32; 4],
+    data: &[u8]
+  ) -> Result<AutoCommandBufferBuilder, DynamicTextureError> {
+    let staging = self.staging_buffer(self.image.device().clone(), rect, data)?;
+    Ok(DynamicTexture::copy_staged(cmds, staging, self.image.clone(), rect))
+  }
+
+
+  /// A descriptor set for drawing the texture with the pipeline, like
+  /// `Texture2DPipeline::desc_set_for_texture` is for loaded textures.
+  pub fn desc_set(&self, pipeline: &mut Texture2DPipeline, device: Arc<Device>) -> Arc<DescriptorSet + Send + Sync> {
+    pipeline.desc_set_for_texture(self.image.clone(), device)
+  }
+
+
+  /// Check the rect and data, and copy the data into a buffer the GPU can
+  /// read from.
+  fn staging_buffer(
+    &self,
+    device: Arc<Device>,
+    rect: [u32; 4],
+    data: &[u8]
+  ) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, DynamicTextureError> {
+    let (x, y, width, height) = (rect[0], rect[1], rect[2], rect[3]);
+    let inside = width > 0
+      && height > 0
+      && x.checked_add(width).map(|right| right <= self.dimensions[0]).unwrap_or(false)
+      && y.checked_add(height).map(|bottom| bottom <= self.dimensions[1]).unwrap_or(false);
+    if !inside {
+      return Err(DynamicTextureError::OutOfBounds(rect));
+    }
+    let expected = width as usize * height as usize * 4;
+    if data.len() != expected {
+      return Err(DynamicTextureError::DataSize(expected, data.len()));
+    }
+    let staging = CpuAccessibleBuffer::from_iter(
+      device,
+      BufferUsage::transfer_source(),
+      data.iter().cloned()
+    ).expect("Could not create texture staging buffer.");
+    Ok(staging)
+  }
+
+
+  fn copy_staged(
+    cmds: AutoCommandBufferBuilder,
+    staging: Arc<CpuAccessibleBuffer<[u8]>>,
+    image: Arc<StorageImage<Format>>,
+    rect: [u32; 4]
+  ) -> AutoCommandBufferBuilder {
+    cmds
+      .copy_buffer_to_image_dimensions(
+        staging,
+        image,
+        [rect[0], rect[1], 0],
+        [rect[2], rect[3], 1],
+        0,
+        1,
+        0
+      ).expect("Could not copy texels into dynamic texture.")
+  }
+}
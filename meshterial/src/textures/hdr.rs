@@ -0,0 +1,164 @@
+//! Reading high dynamic range images from Radiance `.hdr` files, and turning
+//! equirectangular environment maps into cubemaps.
+//!
+//! OpenEXR files are not supported, since the `image` crate can't decode
+//! them.
+use nalgebra::Vector3;
+
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fmt;
+use std::io::BufReader;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HdrError {
+  /// The data isn't a Radiance image the decoder can read.
+  Decode(String),
+}
+
+
+impl fmt::Display for HdrError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      HdrError::Decode(e) => write!(f, "could not decode HDR image: {}", e),
+    }
+  }
+}
+
+
+impl Error for HdrError {}
+
+
+/// A decoded image of linear RGBA float texels, row by row from the top.
+/// Alpha is always 1.0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdrImage {
+  pub width: u32,
+  pub height: u32,
+  pub texels: Vec<[f32; 4]>,
+}
+
+
+impl HdrImage {
+  /// Decode a Radiance `.hdr` file.
+  pub fn parse(bytes: &[u8]) -> Result<HdrImage, HdrError> {
+    let decoder = image::hdr::HDRDecoder::new(BufReader::new(bytes))
+      .map_err(|e| HdrError::Decode(e.to_string()))?;
+    let metadata = decoder.metadata();
+    let texels = decoder
+      .read_image_hdr()
+      .map_err(|e| HdrError::Decode(e.to_string()))?
+      .into_iter()
+      .map(|rgb| [rgb[0], rgb[1], rgb[2], 1.0])
+      .collect();
+    Ok(HdrImage {
+      width: metadata.width,
+      height: metadata.height,
+      texels,
+    })
+  }
+
+
+  fn texel(&self, x: u32, y: u32) -> [f32; 4] {
+    self.texels[(y * self.width + x) as usize]
+  }
+
+
+  /// Bilinearly sample the image as an equirectangular map in the given
+  /// direction, which doesn't need to be normalized. The top row is +Y, the
+  /// bottom row is -Y, and the center of the image is -Z with +X a quarter
+  /// of the way to its right. Columns wrap around.
+  pub fn sample_equirect(&self, direction: &Vector3<f32>) -> [f32; 4] {
+    let d = direction.normalize();
+    let u = 0.5 + d.x.atan2(-d.z) / (2.0 * PI);
+    let v = d.y.max(-1.0).min(1.0).acos() / PI;
+    let x = u * self.width as f32 - 0.5;
+    let y = (v * self.height as f32 - 0.5).max(0.0).min((self.height - 1) as f32);
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let (fx, fy) = (x - x0, y - y0);
+    let width = self.width as i64;
+    let wrap = |x: f32| (((x as i64) % width + width) % width) as u32;
+    let (x0, x1) = (wrap(x0), wrap(x0 + 1.0));
+    let y0 = y0 as u32;
+    let y1 = (y0 + 1).min(self.height - 1);
+    let (a, b, c, e) = (self.texel(x0, y0), self.texel(x1, y0), self.texel(x0, y1), self.texel(x1, y1));
+    let mut out = [0.0; 4];
+    for i in 0 .. 4 {
+      let top = a[i] + (b[i] - a[i]) * fx;
+      let bottom = c[i] + (e[i] - c[i]) * fx;
+      out[i] = top + (bottom - top) * fy;
+    }
+    out
+  }
+}
+
+
+/// The direction through the center of texel `(x, y)` of a cubemap face, in
+/// vulkan's face order +X, -X, +Y, -Y, +Z, -Z.
+pub fn cubemap_direction(face: usize, x: u32, y: u32, size: u32) -> Vector3<f32> {
+  let a = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+  let b = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+  match face {
+    0 => Vector3::new(1.0, -b, -a),
+    1 => Vector3::new(-1.0, -b, a),
+    2 => Vector3::new(a, 1.0, b),
+    3 => Vector3::new(a, -1.0, -b),
+    4 => Vector3::new(a, -b, 1.0),
+    5 => Vector3::new(-a, -b, -1.0),
+    _ => panic!("A cubemap has six faces, not {}.", face + 1),
+  }
+}
+
+
+/// Resample an equirectangular environment map into the six `size` x `size`
+/// faces of a cubemap, in the order an `ImmutableImage` with
+/// `Dimensions::Cubemap` expects them: +X, -X, +Y, -Y, +Z, -Z, each row by
+/// row. See `HdrImage::sample_equirect` for the map's orientation.
+pub fn equirect_to_cubemap(image: &HdrImage, size: u32) -> Vec<[f32; 4]> {
+  let mut texels = Vec::with_capacity(6 * (size * size) as usize);
+  for face in 0 .. 6 {
+    for y in 0 .. size {
+      for x in 0 .. size {
+        texels.push(image.sample_equirect(&cubemap_direction(face, x, y, size)));
+      }
+    }
+  }
+  texels
+}
+
+
+/// The bits of the half precision float nearest to `f`, for uploading to
+/// `R16G16B16A16Sfloat` images. Values too big for a half become infinity
+/// and values too small become zero.
+pub(crate) fn f32_to_f16(f: f32) -> u16 {
+  let bits = f.to_bits();
+  let sign = ((bits >> 16) & 0x8000) as u16;
+  let exponent = ((bits >> 23) & 0xff) as i32;
+  let mantissa = bits & 0x007f_ffff;
+  if exponent == 0xff {
+    // Infinity stays infinity, NaN stays NaN.
+    let nan = if mantissa != 0 { 0x0200 } else { 0 };
+    return sign | 0x7c00 | nan;
+  }
+  let exponent = exponent - 127 + 15;
+  if exponent >= 0x1f {
+    sign | 0x7c00
+  } else if exponent <= 0 {
+    if exponent < -10 {
+      return sign;
+    }
+    // A subnormal half.
+    let mantissa = mantissa | 0x0080_0000;
+    let shift = (14 - exponent) as u32;
+    let half = mantissa >> shift;
+    let round = (mantissa >> (shift - 1)) & 1;
+    sign | (half + round) as u16
+  } else {
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let round = (mantissa >> 12) & 1;
+    // Rounding up can carry into the exponent, which is still right.
+    sign | (half + round) as u16
+  }
+}
@@ -0,0 +1,9 @@
+//! Loading textures from container formats other than the ones the `image`
+//! crate decodes, and generating textures without a file.
+pub mod atlas;
+pub mod dds;
+pub mod dynamic;
+pub mod hdr;
+pub mod procedural;
+
+pub use self::procedural::{checkerboard, solid, uv_grid};
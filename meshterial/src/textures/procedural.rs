@@ -0,0 +1,102 @@
+//! Textures generated on the fly, for placeholder materials and debugging
+//! UVs without loading a file.
+//!
+//! Each `*_image` function makes the texels, and the function of the same
+//! name without `_image` uploads them. Uploaded textures are kept in the
+//! renderer's texture store under a key made from their parameters, so
+//! asking for the same texture again doesn't upload it again. Colors are
+//! RGBA.
+use image::{Rgba, RgbaImage};
+use nalgebra::Vector2;
+use vulkano::format::Format;
+use vulkano::image::ImmutableImage;
+
+use std::sync::Arc;
+
+use super::super::vk_renderer::VkRenderer;
+
+
+/// The number of squares along each side of `checkerboard` and `uv_grid`
+/// textures.
+pub const CHECKERS: u32 = 8;
+
+
+/// The side of one square in a texture of the given size. Textures smaller
+/// than `CHECKERS` get one texel squares.
+fn checker_size(size: u32) -> u32 {
+  (size / CHECKERS).max(1)
+}
+
+
+/// A `size` x `size` image of `CHECKERS` x `CHECKERS` squares, `color_a` in
+/// the top left corner.
+pub fn checkerboard_image(size: u32, color_a: [u8; 4], color_b: [u8; 4]) -> RgbaImage {
+  let checker = checker_size(size);
+  RgbaImage::from_fn(size, size, |x, y| {
+    if (x / checker + y / checker) % 2 == 0 {
+      Rgba(color_a)
+    } else {
+      Rgba(color_b)
+    }
+  })
+}
+
+
+/// A `size` x `size` image of one color.
+pub fn solid_image(size: u32, color: [u8; 4]) -> RgbaImage {
+  RgbaImage::from_pixel(size, size, Rgba(color))
+}
+
+
+/// A `size` x `size` image for spotting stretched or flipped UVs on a mesh.
+///
+/// Red grows with U from left to right and green grows with V from top to
+/// bottom, so (0, 0) is black in the top left corner and (1, 1) is yellow in
+/// the bottom right. White lines divide it into `CHECKERS` x `CHECKERS`
+/// squares, every other one darker, which should look square on the mesh.
+pub fn uv_grid_image(size: u32) -> RgbaImage {
+  let checker = checker_size(size);
+  RgbaImage::from_fn(size, size, |x, y| {
+    if x % checker == 0 || y % checker == 0 {
+      return Rgba([255, 255, 255, 255]);
+    }
+    let u = (x as f32 + 0.5) / size as f32;
+    let v = (y as f32 + 0.5) / size as f32;
+    let shade = if (x / checker + y / checker) % 2 == 0 { 1.0 } else { 0.75 };
+    Rgba([
+      (u * shade * 255.0).round() as u8,
+      (v * shade * 255.0).round() as u8,
+      (0.25 * shade * 255.0).round() as u8,
+      255
+    ])
+  })
+}
+
+
+/// Upload a `checkerboard_image`, or return the one already uploaded with
+/// the same parameters. Panics if it can't be uploaded.
+pub fn checkerboard(
+  vkr: &mut VkRenderer,
+  size: u32,
+  color_a: [u8; 4],
+  color_b: [u8; 4]
+) -> (Arc<ImmutableImage<Format>>, Vector2<u32>) {
+  let key = format!("procedural:checkerboard:{}:{:?}:{:?}", size, color_a, color_b);
+  vkr.load_generated_texture(key, || checkerboard_image(size, color_a, color_b))
+}
+
+
+/// Upload a `solid_image`, or return the one already uploaded with the same
+/// parameters. Panics if it can't be uploaded.
+pub fn solid(vkr: &mut VkRenderer, size: u32, color: [u8; 4]) -> (Arc<ImmutableImage<Format>>, Vector2<u32>) {
+  let key = format!("procedural:solid:{}:{:?}", size, color);
+  vkr.load_generated_texture(key, || solid_image(size, color))
+}
+
+
+/// Upload a `uv_grid_image`, or return the one already uploaded with the
+/// same size. Panics if it can't be uploaded.
+pub fn uv_grid(vkr: &mut VkRenderer, size: u32) -> (Arc<ImmutableImage<Format>>, Vector2<u32>) {
+  let key = format!("procedural:uv_grid:{}", size);
+  vkr.load_generated_texture(key, || uv_grid_image(size))
+}
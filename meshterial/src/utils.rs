@@ -1,5 +1,15 @@
+use nalgebra::{Matrix3, Matrix4, Point3, Vector3, Vector4};
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::mem;
+use std::thread;
 use std::time::{Instant, Duration};
 
+use super::camera::Camera;
+use super::pipelines::phong::VertexPhong;
+use super::ray::Ray;
+
 
 pub struct DurationMeasurement {
   start: Instant,
@@ -74,3 +84,405 @@ impl Default for FPSCounter {
     FPSCounter::new()
   }
 }
+
+
+/// Waits out the rest of each frame to hold a target frame rate.
+///
+/// `thread::sleep` may oversleep by a millisecond or more depending on the
+/// OS scheduler, so the limiter sleeps until shortly before the deadline and
+/// then spins for the remainder. The spin costs a little CPU in exchange for
+/// accurate frame times. Frames that already took longer than the target are
+/// not slowed down further, and the deadline is reset so a slow frame doesn't
+/// cause a burst of fast ones afterward.
+pub struct FrameLimiter {
+  frame_time: Duration,
+  next_frame: Instant,
+}
+
+
+impl FrameLimiter {
+  /// How long before the deadline to stop sleeping and start spinning.
+  const SPIN_TIME: Duration = Duration::from_millis(2);
+
+
+  pub fn new(fps: u32) -> FrameLimiter {
+    let frame_time = Duration::from_nanos(1_000_000_000 / u64::from(fps.max(1)));
+    FrameLimiter {
+      frame_time,
+      next_frame: Instant::now() + frame_time,
+    }
+  }
+
+
+  /// Block until the current frame has taken at least the target frame time.
+  pub fn wait(&mut self) {
+    let now = Instant::now();
+    if now >= self.next_frame {
+      self.next_frame = now + self.frame_time;
+      return;
+    }
+    let remaining = self.next_frame - now;
+    if remaining > FrameLimiter::SPIN_TIME {
+      thread::sleep(remaining - FrameLimiter::SPIN_TIME);
+    }
+    while Instant::now() < self.next_frame {
+      thread::yield_now();
+    }
+    self.next_frame += self.frame_time;
+  }
+}
+
+
+/// The ray from the camera through a point on the screen, given in pixels
+/// from the top left of a framebuffer with the given dimensions. Use it with
+/// `Ray::intersect_mesh` for picking without reading back from the GPU.
+pub fn screen_ray(camera: &Camera, screen_pos: [f32; 2], dimensions: [u32; 2]) -> Ray {
+  let x = 2.0 * screen_pos[0] / dimensions[0].max(1) as f32 - 1.0;
+  let y = 2.0 * screen_pos[1] / dimensions[1].max(1) as f32 - 1.0;
+  let inverse = camera
+    .view_projection()
+    .try_inverse()
+    .unwrap_or_else(::nalgebra::Matrix4::identity);
+  let unproject = |z: f32| {
+    let p = inverse * Vector4::new(x, y, z, 1.0);
+    Point3::new(p.x / p.w, p.y / p.w, p.z / p.w)
+  };
+  let near = unproject(-1.0);
+  let far = unproject(1.0);
+  Ray::new(near.coords, far - near)
+}
+
+
+/// Sort items from farthest to nearest the camera, by the view space depth of
+/// each item's position, for drawing transparent things back to front.
+pub fn sort_back_to_front<T, F>(items: &mut [T], view: &Matrix4<f32>, position: F)
+  where F: Fn(&T) -> Vector3<f32>
+{
+  let depth = |item: &T| view.transform_point(&Point3::from_coordinates(position(item))).z;
+  // The camera looks down -z, so the farthest items have the lowest z.
+  items.sort_by(|a, b| depth(a).partial_cmp(&depth(b)).unwrap_or(Ordering::Equal));
+}
+
+
+/// Options for `decimate_with_options`.
+#[derive(Debug, Clone, Copy)]
+pub struct DecimateOptions {
+  /// Keep vertices on open edges, ones used by only one triangle, where they
+  /// are so that the outline of the mesh doesn't shrink. Seams where vertices
+  /// are split, eg. for hard normals or uv seams, are open edges too.
+  pub preserve_boundary: bool,
+}
+
+
+impl Default for DecimateOptions {
+  fn default() -> DecimateOptions {
+    DecimateOptions {
+      preserve_boundary: true,
+    }
+  }
+}
+
+
+/// Simplify an indexed triangle mesh down to about `target_ratio` of its
+/// triangles, preserving its boundary. See `decimate_with_options`.
+pub fn decimate(
+  vertices: &[VertexPhong],
+  indices: &[u32],
+  target_ratio: f32
+) -> (Vec<VertexPhong>, Vec<u32>) {
+  decimate_with_options(vertices, indices, target_ratio, &DecimateOptions::default())
+}
+
+
+/// Simplify an indexed triangle mesh down to about `target_ratio` (0.0 to
+/// 1.0) of its triangles, eg. to generate the levels of a `Lod`.
+///
+/// Edges are collapsed cheapest first using quadric error metrics (Garland
+/// and Heckbert), which keeps the overall shape by moving each collapsed
+/// vertex to where it is closest to the planes of its original triangles.
+/// Collapses that would flip a triangle are skipped, so fewer triangles than
+/// asked for may be removed. Normals of collapsed vertices are averaged.
+///
+/// Edges are only found through shared indices, so the mesh must be indexed
+/// with shared vertices. An unindexed triangle list has nothing but open
+/// edges. Expand the result into a list for `Mesh::new` with
+/// `indices.iter().map(|&i| vertices[i as usize].clone())`.
+pub fn decimate_with_options(
+  vertices: &[VertexPhong],
+  indices: &[u32],
+  target_ratio: f32,
+  options: &DecimateOptions
+) -> (Vec<VertexPhong>, Vec<u32>) {
+  let mut positions: Vec<Vector3<f64>> = vertices
+    .iter()
+    .map(|v| Vector3::new(v.position[0] as f64, v.position[1] as f64, v.position[2] as f64))
+    .collect();
+  let mut normals: Vec<Vector3<f32>> = vertices
+    .iter()
+    .map(|v| Vector3::new(v.normal[0], v.normal[1], v.normal[2]))
+    .collect();
+  let mut triangles: Vec<[usize; 3]> = indices
+    .chunks(3)
+    .filter(|t| t.len() == 3)
+    .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+    .collect();
+  let target = (triangles.len() as f32 * target_ratio.max(0.0).min(1.0)).ceil() as usize;
+  let mut alive = vec![true; triangles.len()];
+  let mut alive_count = triangles.len();
+
+  let mut vertex_triangles: Vec<Vec<usize>> = vec![vec![]; vertices.len()];
+  let mut quadrics: Vec<Matrix4<f64>> = vec![Matrix4::zeros(); vertices.len()];
+  let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+  for (t, tri) in triangles.iter().enumerate() {
+    let plane = plane_quadric(&positions[tri[0]], &positions[tri[1]], &positions[tri[2]]);
+    for k in 0 .. 3 {
+      vertex_triangles[tri[k]].push(t);
+      if let Some(plane) = plane {
+        quadrics[tri[k]] += plane;
+      }
+      *edges.entry(edge_key(tri[k], tri[(k + 1) % 3])).or_insert(0) += 1;
+    }
+  }
+  let mut locked = vec![false; vertices.len()];
+  if options.preserve_boundary {
+    for (&(a, b), &count) in edges.iter() {
+      if count == 1 {
+        locked[a] = true;
+        locked[b] = true;
+      }
+    }
+  }
+
+  // Collapses are checked against these when they are popped, so that ones
+  // made stale by an earlier collapse are skipped.
+  let mut versions = vec![0u32; vertices.len()];
+  let mut removed = vec![false; vertices.len()];
+  let mut heap: BinaryHeap<Collapse> = edges
+    .keys()
+    .filter(|(a, b)| a != b)
+    .filter_map(|&(a, b)| Collapse::new(a, b, &positions, &quadrics, &locked, &versions))
+    .collect();
+
+  while alive_count > target {
+    let collapse = match heap.pop() {
+      Some(collapse) => collapse,
+      None => break,
+    };
+    let (keep, remove) = (collapse.keep, collapse.remove);
+    if removed[keep]
+      || removed[remove]
+      || versions[keep] != collapse.keep_version
+      || versions[remove] != collapse.remove_version
+      || collapse.flips(&positions, &triangles, &alive, &vertex_triangles) {
+      continue;
+    }
+
+    positions[keep] = collapse.position;
+    quadrics[keep] = quadrics[keep] + quadrics[remove];
+    normals[keep] = (normals[keep] + normals[remove])
+      .try_normalize(::std::f32::EPSILON)
+      .unwrap_or(normals[keep]);
+    removed[remove] = true;
+    for t in mem::replace(&mut vertex_triangles[remove], vec![]) {
+      if !alive[t] {
+        continue;
+      }
+      if triangles[t].contains(&keep) {
+        alive[t] = false;
+        alive_count -= 1;
+      } else {
+        for i in triangles[t].iter_mut() {
+          if *i == remove {
+            *i = keep;
+          }
+        }
+        vertex_triangles[keep].push(t);
+      }
+    }
+    vertex_triangles[keep].retain(|&t| alive[t]);
+    versions[keep] += 1;
+
+    let mut neighbors: Vec<usize> = vertex_triangles[keep]
+      .iter()
+      .flat_map(|&t| triangles[t].iter().cloned())
+      .filter(|&i| i != keep)
+      .collect();
+    neighbors.sort();
+    neighbors.dedup();
+    for neighbor in neighbors {
+      if let Some(collapse) = Collapse::new(keep, neighbor, &positions, &quadrics, &locked, &versions) {
+        heap.push(collapse);
+      }
+    }
+  }
+
+  let mut remap: Vec<Option<u32>> = vec![None; vertices.len()];
+  let mut out_vertices = vec![];
+  let mut out_indices = vec![];
+  for (t, tri) in triangles.iter().enumerate() {
+    if !alive[t] {
+      continue;
+    }
+    for &i in tri.iter() {
+      let ndx = match remap[i] {
+        Some(ndx) => ndx,
+        None => {
+          let ndx = out_vertices.len() as u32;
+          out_vertices.push(VertexPhong {
+            position: [positions[i].x as f32, positions[i].y as f32, positions[i].z as f32],
+            normal: [normals[i].x, normals[i].y, normals[i].z],
+          });
+          remap[i] = Some(ndx);
+          ndx
+        }
+      };
+      out_indices.push(ndx);
+    }
+  }
+  (out_vertices, out_indices)
+}
+
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+  if a < b { (a, b) } else { (b, a) }
+}
+
+
+/// The quadric measuring the squared distance to the plane of a triangle, or
+/// None if the triangle is degenerate.
+fn plane_quadric(a: &Vector3<f64>, b: &Vector3<f64>, c: &Vector3<f64>) -> Option<Matrix4<f64>> {
+  let normal = (b - a).cross(&(c - a)).try_normalize(1e-12)?;
+  let plane = Vector4::new(normal.x, normal.y, normal.z, -normal.dot(a));
+  Some(plane * plane.transpose())
+}
+
+
+fn quadric_error(quadric: &Matrix4<f64>, position: &Vector3<f64>) -> f64 {
+  let p = Vector4::new(position.x, position.y, position.z, 1.0);
+  p.dot(&(quadric * p)).max(0.0)
+}
+
+
+/// The position minimizing the error of a quadric, if there is a single one.
+fn optimal_position(quadric: &Matrix4<f64>) -> Option<Vector3<f64>> {
+  let a: Matrix3<f64> = quadric.fixed_slice::<nalgebra::U3, nalgebra::U3>(0, 0).into_owned();
+  if a.determinant().abs() < 1e-12 {
+    return None;
+  }
+  let b = Vector3::new(quadric[(0, 3)], quadric[(1, 3)], quadric[(2, 3)]);
+  a.try_inverse().map(|inverse| -(inverse * b))
+}
+
+
+/// An edge collapse that merges `remove` into `keep`, moving `keep` to
+/// `position`.
+struct Collapse {
+  cost: f64,
+  keep: usize,
+  remove: usize,
+  position: Vector3<f64>,
+  keep_version: u32,
+  remove_version: u32,
+}
+
+
+impl Collapse {
+  fn new(
+    a: usize,
+    b: usize,
+    positions: &[Vector3<f64>],
+    quadrics: &[Matrix4<f64>],
+    locked: &[bool],
+    versions: &[u32]
+  ) -> Option<Collapse> {
+    let (keep, remove) = match (locked[a], locked[b]) {
+      (true, true) => return None,
+      (false, true) => (b, a),
+      _ => (a, b),
+    };
+    let quadric = quadrics[keep] + quadrics[remove];
+    let position = if locked[keep] {
+      positions[keep]
+    } else {
+      optimal_position(&quadric).unwrap_or_else(|| {
+        let midpoint = (positions[keep] + positions[remove]) * 0.5;
+        let candidates = [positions[keep], positions[remove], midpoint];
+        let mut best = candidates[0];
+        for candidate in candidates.iter() {
+          if quadric_error(&quadric, candidate) < quadric_error(&quadric, &best) {
+            best = *candidate;
+          }
+        }
+        best
+      })
+    };
+    Some(Collapse {
+      cost: quadric_error(&quadric, &position),
+      keep,
+      remove,
+      position,
+      keep_version: versions[keep],
+      remove_version: versions[remove],
+    })
+  }
+
+
+  /// Whether any triangle that survives the collapse would turn over or
+  /// become degenerate.
+  fn flips(
+    &self,
+    positions: &[Vector3<f64>],
+    triangles: &[[usize; 3]],
+    alive: &[bool],
+    vertex_triangles: &[Vec<usize>]
+  ) -> bool {
+    let corner = |i: usize| {
+      if i == self.keep || i == self.remove {
+        self.position
+      } else {
+        positions[i]
+      }
+    };
+    for &v in [self.keep, self.remove].iter() {
+      for &t in vertex_triangles[v].iter() {
+        let tri = triangles[t];
+        if !alive[t] || (tri.contains(&self.keep) && tri.contains(&self.remove)) {
+          continue;
+        }
+        let before = (positions[tri[1]] - positions[tri[0]])
+          .cross(&(positions[tri[2]] - positions[tri[0]]));
+        let after = (corner(tri[1]) - corner(tri[0]))
+          .cross(&(corner(tri[2]) - corner(tri[0])));
+        if after.norm_squared() < 1e-24 || after.dot(&before) < 0.0 {
+          return true;
+        }
+      }
+    }
+    false
+  }
+}
+
+
+impl PartialEq for Collapse {
+  fn eq(&self, other: &Collapse) -> bool {
+    self.cost == other.cost
+  }
+}
+
+
+impl Eq for Collapse {}
+
+
+impl PartialOrd for Collapse {
+  fn partial_cmp(&self, other: &Collapse) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+
+impl Ord for Collapse {
+  /// Reversed, so that `BinaryHeap` pops the cheapest collapse first.
+  fn cmp(&self, other: &Collapse) -> Ordering {
+    other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+  }
+}
@@ -1,16 +1,21 @@
 use vulkano::VulkanObject;
 use vulkano::image::swapchain::SwapchainImage;
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::buffer::device_local::DeviceLocalBuffer;
 use vulkano::buffer::cpu_pool::CpuBufferPool;
 use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, DescriptorSetsCollection};
 use vulkano::device::{Device, Queue};
 use vulkano::instance::{Instance, RawInstanceExtensions, PhysicalDevice};
 use vulkano::image::attachment::AttachmentImage;
-use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract};
-use vulkano::pipeline::viewport::Viewport;
-use vulkano::image::ImmutableImage;
+use vulkano::framebuffer::{FramebufferAbstract, LoadOp, RenderPassAbstract, RenderPassDesc, Subpass};
+use vulkano::pipeline::GraphicsPipelineAbstract;
+use vulkano::pipeline::viewport::{Scissor, Viewport};
+use vulkano::image::{ImageCreationError, ImageViewAccess, ImmutableImage};
 use image::GenericImageView;
 use vulkano::swapchain;
 use vulkano::swapchain::{
+  Capabilities,
   PresentMode,
   Surface,
   SurfaceTransform,
@@ -20,24 +25,142 @@ use vulkano::swapchain::{
   SwapchainCreationError,
 };
 use vulkano::image::Dimensions;
-use vulkano::format::Format;
+use vulkano::format::{AcceptsPixels, ClearValue, Format};
 use vulkano::sync::now;
 use vulkano::sync::GpuFuture;
 
 use sdl2::Sdl;
-use sdl2::video::{WindowContext, Window};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::video::{DisplayMode, FullscreenType, WindowContext, WindowPos, Window};
 
 use nalgebra::{Matrix4, Vector2};
 use nalgebra_glm as glm;
 
+use super::camera::Camera;
+use super::debug::DebugDraw;
+use super::input::Controllers;
+use super::mesh::{DynamicMeshChunk, Mesh};
+use super::pipelines::RenderPipeline;
+use super::pipelines::color3d;
+use super::pipelines::background::{gradient_fs, skybox_fs, Background, BackgroundPipeline};
+use super::pipelines::depth::DepthClearPipeline;
+#[cfg(feature = "egui")]
+use super::pipelines::egui::EguiPipeline;
+use super::pipelines::clip::ClipPlaneUniform;
+use super::pipelines::fog::{FogParams, FogUniform};
+use super::pipelines::log_depth::LogDepthUniform;
+use super::pipelines::particles::vs as particles_vs;
+use super::pipelines::phong::{morph_vs, vs, PhongPipeline, VertexMorphTarget, VertexPhong};
+use super::textures::dds::Dds;
+use super::textures::hdr::{equirect_to_cubemap, f32_to_f16, HdrImage};
+use super::utils::FrameLimiter;
+
+use std::env;
 use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::sync::Arc;
 use std::rc::Rc;
 use std::mem;
-use std::collections::HashMap;
-
+use std::collections::{HashMap, VecDeque};
+
+mod attachments;
+mod bloom;
+mod capture;
+mod debug;
+mod device_info;
+mod error;
+mod format_support;
+mod frame;
+mod gpu_timer;
+mod hdr;
+mod readback;
 mod sendable;
+mod soft_particles;
+mod ssao;
+mod static_commands;
+mod stats;
+mod surface_caps;
+pub use self::device_info::DeviceInfo;
+pub use self::error::{TextureError, VkRendererError};
+pub use self::format_support::{FormatFeatures, FormatProperties, FormatUsage};
+pub use self::frame::Frame;
+pub use self::static_commands::StaticCommands;
+pub use self::stats::RenderStats;
+pub use self::surface_caps::SurfaceCapabilities;
+pub use self::attachments::MAX_EXTRA_ATTACHMENTS;
+pub use self::hdr::HDR_FORMAT;
+use self::bloom::Bloom;
+use self::capture::Capture;
+use self::debug::DebugRenderer;
+use self::gpu_timer::GpuTimer;
+use self::hdr::{AutoExposure, Hdr};
+use self::readback::DepthReader;
 use self::sendable::Sendable;
+use self::soft_particles::SoftParticles;
+use self::ssao::Ssao;
+
+
+/// A decoded texture waiting for `VkRenderer::process_uploads`.
+struct PendingUpload {
+  path: String,
+  data: Vec<u8>,
+  width: u32,
+  height: u32,
+}
+
+
+/// Clamp a number of swapchain images to what the surface supports.
+fn clamp_image_count(count: u32, caps: &Capabilities) -> u32 {
+  let count = count.max(caps.min_image_count);
+  match caps.max_image_count {
+    Some(max) => count.min(max),
+    None => count,
+  }
+}
+
+
+/// The largest rectangle with the given aspect ratio (width / height) centered
+/// in a framebuffer, as `[x, y, width, height]` in pixels. Without an aspect
+/// ratio the rectangle is the whole framebuffer.
+fn letterbox(dimensions: [u32; 2], aspect: Option<f32>) -> [u32; 4] {
+  let (width, height) = (dimensions[0] as f32, dimensions[1] as f32);
+  match aspect {
+    Some(aspect) if aspect > 0.0 && height > 0.0 => {
+      let (w, h) =
+        if width / height > aspect {
+          // Bars on the left and right.
+          (((height * aspect).round() as u32).min(dimensions[0]), dimensions[1])
+        } else {
+          // Bars on the top and bottom.
+          (dimensions[0], ((width / aspect).round() as u32).min(dimensions[1]))
+        };
+      [(dimensions[0] - w) / 2, (dimensions[1] - h) / 2, w, h]
+    }
+    _ => [0, 0, dimensions[0], dimensions[1]],
+  }
+}
+
+
+/// The part of `rect`, as `[x, y, width, height]` in pixels, that is inside a
+/// framebuffer of the given dimensions. Vulkan doesn't allow a scissor with a
+/// negative origin, so a rect hanging off the top or left is cut down to the
+/// part that is on screen, and so is a rect hanging off the bottom or right.
+/// See `VkRenderer::set_scissor`.
+pub fn clamp_scissor(rect: [i32; 4], dimensions: [u32; 2]) -> Scissor {
+  let axis = |origin: i32, extent: i32, max: u32| {
+    let start = (origin as i64).max(0).min(max as i64);
+    let end = (origin as i64 + (extent as i64).max(0)).max(start).min(max as i64);
+    (start as i32, (end - start) as u32)
+  };
+  let (x, width) = axis(rect[0], rect[2], dimensions[0]);
+  let (y, height) = axis(rect[1], rect[3], dimensions[1]);
+  Scissor {
+    origin: [x, y],
+    dimensions: [width, height],
+  }
+}
 
 
 /// The VkRenderer takes care of making the sdl2 context, choosing the vulkan
@@ -54,6 +177,8 @@ pub struct VkRenderer {
   pub swapchain: Arc<Swapchain<Sendable<Rc<WindowContext>>>>,
   pub images: Vec<Arc<SwapchainImage<Sendable<Rc<WindowContext>>>>>,
   pub framebuffers: Option<Vec<Arc<FramebufferAbstract + Send + Sync>>>,
+  /// The depth attachment shared by all of the framebuffers.
+  pub depth_buffer: Option<Arc<AttachmentImage>>,
 
   pub render_pass: Arc<RenderPassAbstract + Send + Sync>,
 
@@ -62,35 +187,129 @@ pub struct VkRenderer {
   pub image_num: Option<usize>,
   pub acquire_future: Option<SwapchainAcquireFuture<Sendable<Rc<WindowContext>>>>,
   pub dynamic_state: DynamicState,
+  /// The width used by pipelines with a dynamic line width.
+  /// See `set_line_width`.
+  pub line_width: f32,
+  /// The aspect ratio the viewport is letterboxed to. See `set_target_aspect`.
+  target_aspect: Option<f32>,
+  /// The viewport's depth range. See `set_viewport_depth_range`.
+  depth_range: (f32, f32),
 
   pub command_buffer_builder: Option<AutoCommandBufferBuilder>,
 
   pub proj_buffer_pool: CpuBufferPool<Matrix4<f32>>,
 
+  /// The stats of the frame being recorded, and of the last one.
+  stats: RenderStats,
+  last_frame_stats: RenderStats,
+  /// Times frames on the GPU, once enabled. See `enable_gpu_timing`.
+  gpu_timer: Option<GpuTimer>,
+
+  /// Created the first time the depth buffer is read.
+  depth_reader: Option<DepthReader>,
+
+  /// Caps the frame rate. See `set_frame_cap`.
+  frame_limiter: Option<FrameLimiter>,
+  /// Caps the frame rate while the window is unfocused. See
+  /// `set_unfocused_fps_cap`.
+  unfocused_limiter: Option<FrameLimiter>,
+  /// Whether the window has focus, as of the last event given to
+  /// `handle_event`.
+  focused: bool,
+
+  /// The offscreen target and tonemap pass. See `enable_hdr`.
+  hdr: Option<Hdr>,
+
+  /// Saves presented frames to disk. See `start_capture`.
+  capture: Option<Capture>,
+  /// Particles that fade out near the scene. See `set_soft_particles`.
+  soft_particles: Option<SoftParticles>,
+
+  /// Whether rendering is paused. See `suspend`.
+  suspended: bool,
+  /// Whether the render pass has begun and not yet ended, between
+  /// `begin_rendering` and `commit_rendering`.
+  in_render_pass: bool,
+  /// Counts the times the framebuffers have been built, so that
+  /// `StaticCommands` recorded for older framebuffers can be detected.
+  framebuffer_generation: u64,
+
+  /// The present mode the swapchain should have. See `set_vsync`.
+  present_mode: PresentMode,
+  /// The number of swapchain images asked for. See `set_image_count`.
+  image_count: Option<u32>,
+
+  /// What is drawn behind everything. See `set_background`.
+  background: Background,
+  /// Created the first time a gradient or skybox is drawn.
+  background_pipeline: Option<BackgroundPipeline>,
+  background_set: Option<Arc<DescriptorSet + Send + Sync>>,
+  /// See `set_skybox_camera`.
+  skybox_inverse_view_projection: Matrix4<f32>,
+  /// Created the first time `clear_depth_only` is called.
+  depth_clear_pipeline: Option<DepthClearPipeline>,
+
+  /// The fog drawn by pipelines that `use_fog`. See `set_fog`.
+  fog: FogParams,
+  fog_buffer: Arc<DeviceLocalBuffer<FogUniform>>,
+  /// Whether `fog` has changed since it was last uploaded to `fog_buffer`.
+  fog_dirty: bool,
+
+  /// The clip plane of pipelines that `use_clip_plane`. See `set_clip_plane`.
+  clip_plane: Option<[f32; 4]>,
+  clip_plane_buffer: Arc<DeviceLocalBuffer<ClipPlaneUniform>>,
+  /// Whether `clip_plane` has changed since it was last uploaded to
+  /// `clip_plane_buffer`.
+  clip_plane_dirty: bool,
+  /// The far plane of pipelines that `use_logarithmic_depth`, or None when
+  /// it's off. See `set_logarithmic_depth`.
+  log_depth: Option<f32>,
+  log_depth_buffer: Arc<DeviceLocalBuffer<LogDepthUniform>>,
+  /// Whether `log_depth` has changed since it was last uploaded to
+  /// `log_depth_buffer`.
+  log_depth_dirty: bool,
+
+  /// Queued debug drawing. See `debug`.
+  debug: DebugRenderer,
+
   /// A store of loaded textures.
   texture_store: HashMap<String, (Arc<ImmutableImage<Format>>, Vector2<u32>)>,
+
+  /// Added to every framebuffer. See `build_framebuffers`.
+  extra_attachments: Vec<Arc<ImageViewAccess + Send + Sync>>,
+
+  /// Decoded textures waiting to be uploaded. See `queue_texture`.
+  pending_uploads: VecDeque<PendingUpload>,
 }
 
 
 impl VkRenderer {
   /// Create and return a Vulkan instance.
   pub fn instance(window: &Window) -> Arc<Instance> {
+    VkRenderer::try_instance(window).expect("failed to create Vulkan instance")
+  }
+
+
+  fn try_instance(window: &Window) -> Result<Arc<Instance>, VkRendererError> {
     // When we create an instance, we have to pass a list of extensions that we want to enable.
     //
     // All the window-drawing functionalities are part of non-core extensions that we need
     // to enable manually. To do so, we ask the `vulkano_win` crate for the list of extensions
     // required to draw to a window.
-    let instance_extensions = window.vulkan_instance_extensions().unwrap();
+    let instance_extensions = window
+      .vulkan_instance_extensions()
+      .map_err(VkRendererError::Sdl)?;
     let raw_instance_extensions = RawInstanceExtensions::new(instance_extensions.iter().map(
       |&v| CString::new(v).unwrap()
     ));
 
     // Now creating the instance.
-    Instance::new(None, raw_instance_extensions, None).expect("failed to create Vulkan instance")
+    Ok(Instance::new(None, raw_instance_extensions, None)?)
   }
 
 
-  /// Create and return a new vulkan based renderer.
+  /// Create and return a new vulkan based renderer, along with its own sdl2
+  /// context and window.
   pub fn new() -> VkRenderer {
     let ctx = sdl2::init().unwrap();
     let window = ctx
@@ -99,9 +318,41 @@ impl VkRenderer {
       .resizable()
       .vulkan()
       .build().unwrap();
+    VkRenderer::from_window(&ctx, window).expect("Could not create renderer.")
+  }
+
+
+  /// Create a renderer that draws to a window made with an existing sdl2
+  /// context, for apps that use sdl2 for other things too. The window must be
+  /// built with `.vulkan()`. The swapchain starts with the surface's minimum
+  /// number of images, see `from_window_with_image_count`.
+  pub fn from_window(ctx: &Sdl, window: Window) -> Result<VkRenderer, VkRendererError> {
+    VkRenderer::from_window_and_image_count(ctx, window, None)
+  }
+
+
+  /// Like `from_window`, but the swapchain is created with `image_count`
+  /// images, clamped to what the surface supports, instead of the minimum.
+  /// Eg. ask for 3 with `PresentMode::Mailbox`. The count is kept when the
+  /// swapchain is recreated. See `set_image_count` for the tradeoff.
+  pub fn from_window_with_image_count(
+    ctx: &Sdl,
+    window: Window,
+    image_count: u32
+  ) -> Result<VkRenderer, VkRendererError> {
+    VkRenderer::from_window_and_image_count(ctx, window, Some(image_count))
+  }
+
+
+  fn from_window_and_image_count(
+    ctx: &Sdl,
+    window: Window,
+    image_count: Option<u32>
+  ) -> Result<VkRenderer, VkRendererError> {
+    let ctx = ctx.clone();
 
     // The first step of any vulkan program is to create an instance.
-    let instance = VkRenderer::instance(&window);
+    let instance = VkRenderer::try_instance(&window)?;
 
     // We then choose which physical device to use.
     //
@@ -121,7 +372,7 @@ impl VkRenderer {
     let physical =
       PhysicalDevice::enumerate(&physical_instance)
         .next()
-        .expect("no physical device available");
+        .ok_or(VkRendererError::NoPhysicalDevice)?;
     // Some little debug infos.
     println!("Using device: {} (type: {:?})", physical.name(), physical.ty());
 
@@ -136,7 +387,9 @@ impl VkRenderer {
     //
     // This returns a `vulkano::swapchain::Surface` object that contains both a cross-platform winit
     // window and a cross-platform Vulkan surface that represents the surface of the window.
-    let surface_handle = window.vulkan_create_surface(instance.internal_object()).unwrap();
+    let surface_handle = window
+      .vulkan_create_surface(instance.internal_object())
+      .map_err(VkRendererError::Sdl)?;
     let window_context = Sendable::new(window.context());
     let surface = Arc::new(unsafe { Surface::from_raw_surface(instance.clone(), surface_handle, window_context) });
 
@@ -153,7 +406,7 @@ impl VkRenderer {
     let queue = physical.queue_families().find(|&q| {
       // We take the first queue that supports drawing to our window.
       q.supports_graphics() && surface.is_supported(q).unwrap_or(false)
-    }).expect("couldn't find a graphical queue family");
+    }).ok_or(VkRendererError::NoQueueFamily)?;
 
     // Now initializing the device. This is probably the most important object of Vulkan.
     //
@@ -164,7 +417,9 @@ impl VkRenderer {
     // - A list of optional features and extensions that our program needs to work correctly.
     //   Some parts of the Vulkan specs are optional and must be enabled manually at device
     //   creation. In this example the only thing we are going to need is the `khr_swapchain`
-    //   extension that allows us to draw to a window.
+    //   extension that allows us to draw to a window. We also enable every feature the device
+    //   supports, so that optional pipeline features (eg. `sampler_anisotropy` for
+    //   SamplerConfig::max_anisotropy, `wide_lines` for set_line_width) can be used.
     //
     // - A list of layers to enable. This is very niche, and you will usually pass `None`.
     //
@@ -181,7 +436,7 @@ impl VkRenderer {
       };
 
       Device::new(physical, physical.supported_features(), &device_ext,
-                  [(queue, 0.5)].iter().cloned()).expect("failed to create device")
+                  [(queue, 0.5)].iter().cloned())?
     };
 
     // Since we can request multiple queues, the `queues` variable is in fact an iterator. In this
@@ -189,10 +444,15 @@ impl VkRenderer {
     // iterator and throw it away.
     let queue = queues.next().unwrap();
 
+    // Set MESHTERIAL_LOG_DEVICE_INFO to print the device's limits and features,
+    // eg. to include them in a bug report.
+    if env::var_os("MESHTERIAL_LOG_DEVICE_INFO").is_some() {
+      println!("{}", DeviceInfo::new(&device));
+    }
+
     // Querying the capabilities of the surface. When we create the swapchain we can only
     // pass values that are allowed by the capabilities.
-    let caps = surface.capabilities(physical)
-      .expect("failed to get surface capabilities");
+    let caps = surface.capabilities(physical)?;
 
     // The dimensions of the surface.
     // This variable needs to be mutable since the viewport can change size.
@@ -210,12 +470,18 @@ impl VkRenderer {
     let format = caps.supported_formats[0].0;
     println!("Choosing format {:?} from {:?}", format, caps.supported_formats);
 
+    // Use the minimum number of images the surface supports unless more
+    // were asked for.
+    let num_images = image_count
+      .map(|count| clamp_image_count(count, &caps))
+      .unwrap_or(caps.min_image_count);
+
     // Please take a look at the docs for the meaning of the parameters we didn't mention.
     let (swapchain, images) =
       Swapchain::new(
         device.clone(),
         surface.clone(),
-        caps.min_image_count,
+        num_images,
         format,
 
         dimensions,
@@ -231,7 +497,7 @@ impl VkRenderer {
         true,
 
         None
-      ).expect("failed to create swapchain");
+      )?;
 
     // Before we can draw on the surface, we have to create what is called a swapchain. Creating
     // a swapchain allocates the color buffers that will contain the image that will ultimately
@@ -276,7 +542,9 @@ impl VkRenderer {
           },
           depth: {
             load: Clear,
-            store: DontCare,
+            // The depth is stored so that it can be read back with
+            // `read_depth_at`.
+            store: Store,
             format: Format::D16Unorm,
             samples: 1,
           }
@@ -286,7 +554,7 @@ impl VkRenderer {
           color: [color],
           depth_stencil: {depth}
         }
-      ).unwrap()
+      )?
     );
 
         // In some situations, the swapchain will become invalid by itself. This includes for example
@@ -307,13 +575,31 @@ impl VkRenderer {
         dimensions: [dimensions[0] as f32, dimensions[1] as f32],
         depth_range: 0.0 .. 1.0,
       }]),
-      scissors: None,
+      scissors: Some(vec![Scissor {
+        origin: [0, 0],
+        dimensions,
+      }]),
     };
 
 
     let proj_buffer_pool = CpuBufferPool::upload(device.clone());
-
-    VkRenderer {
+    let fog_buffer = DeviceLocalBuffer::new(
+      device.clone(),
+      BufferUsage::uniform_buffer_transfer_destination(),
+      Some(queue.family())
+    ).expect("Could not create fog buffer.");
+    let clip_plane_buffer = DeviceLocalBuffer::new(
+      device.clone(),
+      BufferUsage::uniform_buffer_transfer_destination(),
+      Some(queue.family())
+    ).expect("Could not create clip plane buffer.");
+    let log_depth_buffer = DeviceLocalBuffer::new(
+      device.clone(),
+      BufferUsage::uniform_buffer_transfer_destination(),
+      Some(queue.family())
+    ).expect("Could not create log depth buffer.");
+
+    Ok(VkRenderer {
       ctx,
       window,
       instance,
@@ -324,9 +610,13 @@ impl VkRenderer {
       swapchain,
       images,
       framebuffers,
+      depth_buffer: None,
       render_pass,
       recreate_swapchain,
       dynamic_state,
+      line_width: 1.0,
+      target_aspect: None,
+      depth_range: (0.0, 1.0),
 
       previous_frame_end: None,
       image_num: None,
@@ -335,8 +625,51 @@ impl VkRenderer {
 
       proj_buffer_pool,
 
-      texture_store: HashMap::new()
-    }
+      stats: RenderStats::default(),
+      last_frame_stats: RenderStats::default(),
+      gpu_timer: None,
+
+      depth_reader: None,
+
+      frame_limiter: None,
+      unfocused_limiter: None,
+      focused: true,
+
+      hdr: None,
+
+      capture: None,
+      soft_particles: None,
+
+      suspended: false,
+      in_render_pass: false,
+      framebuffer_generation: 0,
+
+      present_mode: PresentMode::Immediate,
+      image_count,
+
+      background: Background::default(),
+      background_pipeline: None,
+      background_set: None,
+      skybox_inverse_view_projection: Matrix4::identity(),
+      depth_clear_pipeline: None,
+
+      fog: FogParams::default(),
+      fog_buffer,
+      fog_dirty: true,
+
+      clip_plane: None,
+      clip_plane_buffer,
+      clip_plane_dirty: true,
+      log_depth: None,
+      log_depth_buffer,
+      log_depth_dirty: true,
+
+      debug: DebugRenderer::new(device.clone()),
+
+      texture_store: HashMap::new(),
+      extra_attachments: vec![],
+      pending_uploads: VecDeque::new(),
+    })
   }
 
 
@@ -346,21 +679,41 @@ impl VkRenderer {
       .next()
       .expect("no device available");
     // Get the new dimensions for the viewport/framebuffers.
-    self.dimensions = self.surface.capabilities(physical)
-      .expect("failed to get surface capabilities")
-      .current_extent.unwrap();
-
-    // Update the dynamic_state with the new dimensions.
-    self.dynamic_state.viewports = Some(vec![Viewport {
-      origin: [0.0, 0.0],
-      dimensions: [
-        self.dimensions[0] as f32,
-        self.dimensions[1] as f32
-      ],
-      depth_range: 0.0 .. 1.0,
-    }]);
-
-    match self.swapchain.recreate_with_dimension(self.dimensions) {
+    let caps = self.surface.capabilities(physical)
+      .expect("failed to get surface capabilities");
+    self.dimensions = caps.current_extent.unwrap();
+
+    // Update the dynamic_state with the new dimensions. Any previous scissor
+    // rect was relative to the old dimensions, so this resets it.
+    self.update_viewport();
+
+    // Changing the present mode or image count needs a whole new swapchain.
+    let image_count = match self.image_count {
+      Some(count) => clamp_image_count(count, &caps),
+      None => self.swapchain.num_images(),
+    };
+    let recreated =
+      if self.swapchain.present_mode() == self.present_mode
+        && self.swapchain.num_images() == image_count {
+        self.swapchain.recreate_with_dimension(self.dimensions)
+      } else {
+        Swapchain::new(
+          self.device.clone(),
+          self.surface.clone(),
+          image_count,
+          self.swapchain.format(),
+          self.dimensions,
+          1,
+          caps.supported_usage_flags,
+          &self.queue,
+          SurfaceTransform::Identity,
+          self.swapchain.composite_alpha(),
+          self.present_mode,
+          true,
+          Some(&self.swapchain)
+        )
+      };
+    match recreated {
       Ok((new_swapchain, new_images)) => {
         mem::replace(&mut self.swapchain, new_swapchain);
         mem::replace(&mut self.images, new_images);
@@ -372,6 +725,284 @@ impl VkRenderer {
   }
 
 
+  /// Init sdl2's game controller subsystem and open every connected
+  /// controller. Controllers are off by default. See `input::Controllers` for
+  /// handling controllers that are plugged in later.
+  pub fn enable_controllers(&self) -> Result<Controllers, String> {
+    Controllers::new(&self.ctx)
+  }
+
+
+  /// Set the title of the window. Titles containing a nul byte can't be set,
+  /// in which case an error is printed and the title is left unchanged.
+  pub fn set_title(&mut self, title: &str) {
+    if let Err(e) = self.window.set_title(title) {
+      println!("Could not set window title: {}", e);
+    }
+  }
+
+
+  /// The size of the window in screen coordinates. On high DPI displays this
+  /// may be smaller than `drawable_size`.
+  pub fn window_size(&self) -> [u32; 2] {
+    let (w, h) = self.window.size();
+    [w, h]
+  }
+
+
+  /// The size of the window's drawable area in pixels, which is the size the
+  /// swapchain is created with.
+  pub fn drawable_size(&self) -> [u32; 2] {
+    let (w, h) = self.window.vulkan_drawable_size();
+    [w, h]
+  }
+
+
+  /// How many displays there are, to pick from with `move_to_display`.
+  pub fn display_count(&self) -> usize {
+    self.window.subsystem().num_video_displays().unwrap_or(1).max(1) as usize
+  }
+
+
+  /// The index of the display the window is on, which is the display its
+  /// center is on when it spans more than one.
+  pub fn current_display(&self) -> usize {
+    self.window.display_index().unwrap_or(0).max(0) as usize
+  }
+
+
+  /// Center the window on another display. A fullscreen window leaves
+  /// fullscreen to move and goes back into it on the new display. Exclusive
+  /// fullscreen, from `set_display_mode`, uses the new display's desktop
+  /// resolution. Displays can have different pixel densities, so the
+  /// swapchain is recreated on the next `start_next_frame` whenever the
+  /// drawable size changes.
+  pub fn move_to_display(&mut self, index: usize) -> Result<(), String> {
+    let count = self.display_count();
+    if index >= count {
+      return Err(format!("display {} does not exist, there are {}", index, count));
+    }
+    let bounds = self.window.subsystem().display_bounds(index as i32)?;
+    let drawable_size = self.drawable_size();
+    let fullscreen = self.window.fullscreen_state();
+    if fullscreen != FullscreenType::Off {
+      self.window.set_fullscreen(FullscreenType::Off)?;
+    }
+    let (w, h) = self.window.size();
+    let x = bounds.x() + (bounds.width() as i32 - w as i32) / 2;
+    let y = bounds.y() + (bounds.height() as i32 - h as i32) / 2;
+    self.window.set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+    if fullscreen != FullscreenType::Off {
+      if fullscreen == FullscreenType::True {
+        let mode = self.window.subsystem().desktop_display_mode(index as i32)?;
+        self.window.set_display_mode(mode)?;
+      }
+      self.window.set_fullscreen(fullscreen)?;
+    }
+    if fullscreen != FullscreenType::Off || self.drawable_size() != drawable_size {
+      self.recreate_swapchain = true;
+    }
+    Ok(())
+  }
+
+
+  /// The fullscreen display modes of the display the window is on, as
+  /// reported by sdl2. These are usually sorted from the largest resolution
+  /// and highest refresh rate down.
+  pub fn display_modes(&self) -> Vec<DisplayMode> {
+    let video = self.window.subsystem();
+    let display = self.window.display_index().unwrap_or(0);
+    let count = video.num_display_modes(display).unwrap_or(0);
+    (0 .. count)
+      .filter_map(|ndx| video.display_mode(display, ndx).ok())
+      .collect()
+  }
+
+
+  /// The display mode the window's display is using right now.
+  pub fn current_display_mode(&self) -> Option<DisplayMode> {
+    let display = self.window.display_index().ok()?;
+    self.window.subsystem().current_display_mode(display).ok()
+  }
+
+
+  /// The display mode of the desktop, eg. to go back to the native
+  /// resolution with `set_display_mode`.
+  pub fn desktop_display_mode(&self) -> Option<DisplayMode> {
+    let display = self.window.display_index().ok()?;
+    self.window.subsystem().desktop_display_mode(display).ok()
+  }
+
+
+  /// Go fullscreen with one of the `display_modes`. The swapchain is
+  /// recreated at the new resolution on the next `start_next_frame`.
+  pub fn set_display_mode(&mut self, mode: DisplayMode) -> Result<(), String> {
+    self.window.set_display_mode(mode)?;
+    self.window.set_fullscreen(FullscreenType::True)?;
+    self.recreate_swapchain = true;
+    Ok(())
+  }
+
+
+  /// Switch between fullscreen, at the window's display mode, and windowed.
+  pub fn set_fullscreen(&mut self, fullscreen: bool) -> Result<(), String> {
+    let ty =
+      if fullscreen {
+        FullscreenType::True
+      } else {
+        FullscreenType::Off
+      };
+    self.window.set_fullscreen(ty)?;
+    self.recreate_swapchain = true;
+    Ok(())
+  }
+
+
+  /// The name, type and driver of the device, and the limits and features that
+  /// the built-in pipelines depend on.
+  pub fn device_info(&self) -> DeviceInfo {
+    DeviceInfo::new(&self.device)
+  }
+
+
+  /// Whether or not the device supports images of the format for the usage,
+  /// with the optimal tiling that vulkano's images use. Check this before
+  /// picking a depth, HDR or compressed format to fall back to another one.
+  pub fn format_supported(&self, format: Format, usage: FormatUsage) -> bool {
+    self.format_properties(format).optimal_tiling.supports(usage)
+  }
+
+
+  /// Everything the device supports for the format.
+  pub fn format_properties(&self, format: Format) -> FormatProperties {
+    FormatProperties::query(self.device.physical_device(), format)
+  }
+
+
+  /// The image counts, sizes, present modes, transforms and composite alpha
+  /// modes the window's surface supports, eg. to only offer the settings
+  /// that are actually available. These can change when the window moves to
+  /// another display.
+  pub fn surface_capabilities(&self) -> SurfaceCapabilities {
+    let caps = self
+      .surface
+      .capabilities(self.device.physical_device())
+      .expect("failed to get surface capabilities");
+    SurfaceCapabilities::new(&caps)
+  }
+
+
+  /// Clip all following draws to the given rectangle, given as
+  /// `[x, y, width, height]` in framebuffer pixels. Parts of the rect outside
+  /// of the framebuffer are cut off, see `clamp_scissor`.
+  ///
+  /// Scissor rects are part of the dynamic state, so this only has an effect
+  /// on pipelines built with both a dynamic viewport *and* a dynamic scissor
+  /// (`viewports_dynamic_scissors_dynamic`), which all of the built-in
+  /// pipelines are. Build custom pipelines the same way to clip them too.
+  pub fn set_scissor(&mut self, rect: [i32; 4]) {
+    self.dynamic_state.scissors = Some(vec![clamp_scissor(rect, self.dimensions)]);
+  }
+
+
+  /// Reset the scissor rect to cover the entire framebuffer, or only the
+  /// `content_rect` when letterboxing.
+  pub fn reset_scissor(&mut self) {
+    self.dynamic_state.scissors = self.content_dynamic_state().scissors;
+  }
+
+
+  /// Letterbox the viewport to the given aspect ratio (width / height), or
+  /// stop letterboxing with None. The viewport is the largest centered
+  /// rectangle with that aspect ratio and the bars around it are left the
+  /// clear color, so a 16:9 scene in a 4:3 window isn't stretched. Set the
+  /// camera's aspect to the same ratio.
+  ///
+  /// This resets the scissor rect, and the viewport follows every resize.
+  pub fn set_target_aspect(&mut self, aspect: Option<f32>) {
+    self.target_aspect = aspect;
+    self.update_viewport();
+  }
+
+
+  pub fn target_aspect(&self) -> Option<f32> {
+    self.target_aspect
+  }
+
+
+  /// Map depths into `min .. max` of the depth buffer instead of all of it,
+  /// eg. draw a first person weapon with `0.0, 0.1` and everything else with
+  /// `0.1, 1.0` so the weapon is never hidden by walls it pokes into. The
+  /// range is kept across resizes until it is set again.
+  ///
+  /// Returns an error, leaving the range unchanged, unless
+  /// `0.0 <= min < max <= 1.0`.
+  pub fn set_viewport_depth_range(&mut self, min: f32, max: f32) -> Result<(), VkRendererError> {
+    if !(0.0 <= min && min < max && max <= 1.0) {
+      return Err(VkRendererError::InvalidDepthRange(min, max));
+    }
+    self.depth_range = (min, max);
+    if let Some(viewports) = self.dynamic_state.viewports.as_mut() {
+      for viewport in viewports.iter_mut() {
+        viewport.depth_range = min .. max;
+      }
+    }
+    Ok(())
+  }
+
+
+  pub fn viewport_depth_range(&self) -> (f32, f32) {
+    self.depth_range
+  }
+
+
+  /// The part of the framebuffer that is drawn to, as `[x, y, width, height]`
+  /// in pixels from its top left. This is the whole framebuffer unless
+  /// letterboxing, see `set_target_aspect`. Use it to map window coordinates,
+  /// eg. of the mouse, into the scene.
+  pub fn content_rect(&self) -> [u32; 4] {
+    letterbox(self.dimensions, self.target_aspect)
+  }
+
+
+  /// Point the viewport at the `content_rect`, with the depth range, and reset
+  /// the scissor.
+  fn update_viewport(&mut self) {
+    self.dynamic_state.viewports = self.content_dynamic_state().viewports;
+    self.reset_scissor();
+  }
+
+
+  /// Set the width of lines drawn with a pipeline that uses a dynamic line
+  /// width, like `Line3DPipeline`.
+  ///
+  /// Widths other than 1.0 require the `wide_lines` device feature. Without it
+  /// only 1.0 is guaranteed, so the width is left at 1.0. With it the width is
+  /// clamped to the device's supported line width range.
+  pub fn set_line_width(&mut self, width: f32) {
+    self.line_width =
+      if self.device.enabled_features().wide_lines {
+        let range = self.device.physical_device().limits().line_width_range();
+        width.max(range[0]).min(range[1])
+      } else {
+        1.0
+      };
+  }
+
+
+  /// Returns a copy of the dynamic state with the line width set, for use
+  /// with pipelines that have a dynamic line width.
+  ///
+  /// This is separate from `dynamic_state` because vulkano rejects a dynamic
+  /// state that sets a line width for pipelines that don't expect one.
+  pub fn line_dynamic_state(&self) -> DynamicState {
+    DynamicState {
+      line_width: Some(self.line_width),
+      .. self.dynamic_state.clone()
+    }
+  }
+
+
   pub fn with_command_builder<T> (&mut self, add_cmds:T)
     where T: FnOnce(AutoCommandBufferBuilder) -> AutoCommandBufferBuilder
   {
@@ -386,11 +1017,135 @@ impl VkRenderer {
   }
 
 
+  /// Turn vsync on or off. On uses `PresentMode::Fifo`, which every device
+  /// supports. Off uses `Immediate` if the surface supports it, falling back
+  /// to `Mailbox` and then to `Fifo`. The swapchain is recreated with the new
+  /// present mode on the next `start_next_frame`.
+  pub fn set_vsync(&mut self, on: bool) {
+    let physical = self.device.physical_device();
+    let modes = self
+      .surface
+      .capabilities(physical)
+      .expect("failed to get surface capabilities")
+      .present_modes;
+    let present_mode =
+      if on {
+        PresentMode::Fifo
+      } else if modes.supports(PresentMode::Immediate) {
+        PresentMode::Immediate
+      } else if modes.supports(PresentMode::Mailbox) {
+        PresentMode::Mailbox
+      } else {
+        PresentMode::Fifo
+      };
+    if present_mode != self.present_mode {
+      self.present_mode = present_mode;
+      self.recreate_swapchain = true;
+    }
+  }
+
+
+  /// Whether or not the swapchain waits for vertical blanks.
+  pub fn is_vsync(&self) -> bool {
+    self.present_mode == PresentMode::Fifo
+  }
+
+
+  /// The present mode the swapchain is, or will next be, created with.
+  /// Together with `image_count` this bounds the presentation latency: with
+  /// `Fifo` a frame can wait behind up to `image_count() - 1` others before
+  /// it is displayed, while `Mailbox` and `Immediate` show the newest frame
+  /// as soon as possible.
+  pub fn present_mode(&self) -> PresentMode {
+    self.present_mode
+  }
+
+
+  /// Ask for a number of swapchain images, clamped to what the surface
+  /// supports. Unless the renderer was made with
+  /// `from_window_with_image_count`, the swapchain starts with the surface's
+  /// minimum, often 2, which is enough for `Fifo` but can stall presentation
+  /// with `Mailbox`, which wants 3. More images give the GPU more room to run ahead of the
+  /// display, at the cost of the memory for another full size image each and,
+  /// with `Fifo`, more latency. The swapchain is recreated with the new count
+  /// on the next `start_next_frame`, and keeps it when it is recreated
+  /// afterward.
+  pub fn set_image_count(&mut self, count: u32) {
+    self.image_count = Some(count);
+    let caps = self
+      .surface
+      .capabilities(self.device.physical_device())
+      .expect("failed to get surface capabilities");
+    if clamp_image_count(count, &caps) != self.swapchain.num_images() {
+      self.recreate_swapchain = true;
+    }
+  }
+
+
+  /// The number of images in the swapchain.
+  pub fn image_count(&self) -> u32 {
+    self.swapchain.num_images()
+  }
+
+
+  /// Pause rendering, eg. while the game is paused or the window is in the
+  /// background. This waits for the GPU to finish all submitted work, and
+  /// then `start_next_frame` returns None until `resume` is called, so no
+  /// swapchain images are acquired.
+  ///
+  /// This should be called between frames, not between `begin_rendering` and
+  /// `commit_rendering`.
+  pub fn suspend(&mut self) {
+    if self.suspended {
+      return;
+    }
+    self.suspended = true;
+    self.wait_for_gpu();
+  }
+
+
+  /// Block until the GPU has finished all submitted work.
+  pub(crate) fn wait_for_gpu(&mut self) {
+    if let Some(previous_frame_end) = self.previous_frame_end.take() {
+      previous_frame_end
+        .then_signal_fence_and_flush()
+        .expect("Could not flush submitted work.")
+        .wait(None)
+        .expect("Could not wait for the GPU.");
+    }
+    self.previous_frame_end = Some(Box::new(now(self.device.clone())) as Box<GpuFuture>);
+  }
+
+
+  /// Resume rendering after `suspend`. The swapchain is recreated on the next
+  /// `start_next_frame`, in case the window changed while rendering was
+  /// paused.
+  pub fn resume(&mut self) {
+    if self.suspended {
+      self.suspended = false;
+      self.recreate_swapchain = true;
+    }
+  }
+
+
+  /// Whether or not rendering is paused with `suspend`.
+  pub fn is_suspended(&self) -> bool {
+    self.suspended
+  }
+
+
   /// End the last frame of rendering and begin the next.
   /// Sets up the command builder.
   /// If Some(true) is returned it means that the framebuffers were resized.
+  /// If they have extra attachments, see `build_framebuffers`, they must be
+  /// built again before `begin_rendering`.
   pub fn start_next_frame(&mut self) -> Option<bool> {
+    if self.suspended {
+      return None;
+    }
     let mut resized = false;
+    self.last_frame_stats = mem::replace(&mut self.stats, RenderStats::default());
+
     // It is important to call this function from time to time, otherwise resources will keep
     // accumulating and you will eventually reach an out of memory error.
     // Calling this function polls various fences in order to determine what the GPU has
@@ -423,23 +1178,13 @@ impl VkRenderer {
     // Because framebuffers contains an Arc on the old swapchain, we need to
     // recreate framebuffers as well.
     if self.framebuffers.is_none() {
-      let depth_buffer = AttachmentImage::transient(
-        self.device.clone(),
-        self.dimensions,
-        Format::D16Unorm
-      ).unwrap();
-      // Make new framebuffers
-      let new_framebuffers:Option<Vec<Arc<_>>> = Some(self.images.iter().map(|image| {
-        let afb = Arc::new(
-          Framebuffer::start(self.render_pass.clone())
-            .add(image.clone()).expect("Could not add image to framebuffer.")
-            .add(depth_buffer.clone()).expect("Could not add depth buffer to framebuffer.")
-            .build().expect("Could not build new framebuffer.")
-        );
-        afb as Arc<FramebufferAbstract + Send + Sync>
-      }).collect::<Vec<_>>());
-      mem::replace(&mut self.framebuffers, new_framebuffers);
-
+      // Extra attachments of the old size can't be reused, so leave the
+      // framebuffers for the caller to build with new ones.
+      let extra_attachments = self.extra_attachments.clone();
+      if self.build_framebuffers(&extra_attachments).is_err() {
+        self.extra_attachments.clear();
+      }
+      self.update_viewport();
       resized = true;
     }
 
@@ -469,9 +1214,144 @@ impl VkRenderer {
   }
 
 
+  /// Build a new depth buffer and one framebuffer per swapchain image, each
+  /// with the swapchain image (or the HDR image, see `enable_hdr`), the depth
+  /// buffer and then `extra_attachments`, in that order, for use with a
+  /// custom `render_pass` that has that many attachments, eg. a G-buffer.
+  /// At most 4 extra attachments can be given.
+  ///
+  /// The extra attachments are kept and reused whenever `start_next_frame`
+  /// rebuilds the framebuffers at the same size, eg. after `enable_hdr`.
+  /// When it reports a resize the framebuffers aren't rebuilt, so make the
+  /// extra attachments again at the new `dimensions` and call this before
+  /// `begin_rendering`, which returns `VkRendererError::NoFramebuffers`
+  /// otherwise. The extra attachments are cleared to transparent black by
+  /// `begin_rendering`, so the render pass must use `load: Clear` for them.
+  /// With no extra attachments this is the default color + depth setup.
+  ///
+  /// Returns an error if an extra attachment isn't `dimensions` in size.
+  pub fn build_framebuffers(
+    &mut self,
+    extra_attachments: &[Arc<ImageViewAccess + Send + Sync>]
+  ) -> Result<(), VkRendererError> {
+    for (index, attachment) in extra_attachments.iter().enumerate() {
+      let size = attachment.dimensions();
+      let actual = [size.width(), size.height()];
+      if actual != self.dimensions {
+        return Err(VkRendererError::AttachmentSize { index, expected: self.dimensions, actual });
+      }
+    }
+    self.extra_attachments = extra_attachments.to_vec();
+    let depth_buffer = AttachmentImage::sampled(
+      self.device.clone(),
+      self.dimensions,
+      Format::D16Unorm
+    ).unwrap();
+    self.depth_buffer = Some(depth_buffer.clone());
+    // Make new framebuffers
+    let new_framebuffers = match self.hdr.as_mut() {
+      Some(hdr) => {
+        hdr.framebuffers(self.device.clone(), self.dimensions, &self.images, depth_buffer, extra_attachments)
+      }
+      None => {
+        let render_pass = self.render_pass.clone();
+        self.images.iter().map(|image| {
+          attachments::framebuffer(render_pass.clone(), image.clone(), depth_buffer.clone(), extra_attachments)
+        }).collect::<Vec<_>>()
+      }
+    };
+    if let Some(soft_particles) = self.soft_particles.as_mut() {
+      let color_images: Vec<Arc<ImageViewAccess + Send + Sync>> = match self.hdr.as_ref() {
+        Some(hdr) => {
+          let image = hdr
+            .image()
+            .expect("Could not get hdr image - maybe the framebuffers were not created.");
+          vec![image as Arc<ImageViewAccess + Send + Sync>; self.images.len()]
+        }
+        None => {
+          self.images
+            .iter()
+            .map(|image| image.clone() as Arc<ImageViewAccess + Send + Sync>)
+            .collect()
+        }
+      };
+      let depth_buffer = self
+        .depth_buffer
+        .clone()
+        .expect("This should never happen.");
+      soft_particles.resize(color_images, depth_buffer);
+    }
+    self.framebuffers = Some(new_framebuffers);
+    // Anything recorded for the old framebuffers is stale.
+    self.framebuffer_generation += 1;
+    Ok(())
+  }
+
+
   /// Starts a rendering. If None is returned, skip rendering this frame and pick
   /// it back up later. If Some(true) is returned it means that the framebuffers were resized.
   pub fn begin_rendering(&mut self) {
+    let clear_values = self.default_clear_values();
+    self
+      .begin_rendering_with_clear_values(clear_values)
+      .expect("Could not begin rendering.");
+  }
+
+
+  /// The values `begin_rendering` clears the attachments with: the
+  /// background color, a depth of 1.0 and then transparent black for each of
+  /// the extra attachments.
+  pub fn default_clear_values(&self) -> Vec<ClearValue> {
+    let clear_color =
+      match self.background {
+        Background::Color(color) => color,
+        _ => [0.0, 0.0, 0.0, 1.0],
+      };
+    let mut clear_values = vec![
+      clear_color.into(),
+      1f32.into()
+    ];
+    for _ in &self.extra_attachments {
+      clear_values.push([0.0, 0.0, 0.0, 0.0].into());
+    }
+    clear_values
+  }
+
+
+  /// Like `begin_rendering`, but with the given clear values. Use this with
+  /// custom render passes whose attachments differ from the defaults, see
+  /// `default_clear_values`.
+  ///
+  /// Give one value for each attachment that is loaded with `Clear`, in
+  /// attachment order, and the other attachments get `ClearValue::None`. Or
+  /// give one value for every attachment, with `ClearValue::None` for those
+  /// that aren't cleared, since that is how vulkan reads them.
+  ///
+  /// Returns an error if the number of clear values is neither of those, if
+  /// an attachment that is loaded with `Clear` is given `ClearValue::None`,
+  /// or if the framebuffers' extra attachments haven't been made again after
+  /// a resize, see `build_framebuffers`.
+  pub fn begin_rendering_with_clear_values(
+    &mut self,
+    clear_values: Vec<ClearValue>
+  ) -> Result<(), VkRendererError> {
+    if self.suspended {
+      return Ok(());
+    }
+    self.begin_render_pass(clear_values, false)?;
+    self.draw_background();
+    Ok(())
+  }
+
+
+  /// Validate the clear values, upload the fog, clip plane and log depth if
+  /// they changed and begin the render pass. With `secondary` the subpass can
+  /// only execute secondary command buffers, see `execute_static`.
+  fn begin_render_pass(
+    &mut self,
+    clear_values: Vec<ClearValue>,
+    secondary: bool
+  ) -> Result<(), VkRendererError> {
     // In order to draw, we have to build a *command buffer*. The command buffer object holds
     // the list of commands that are going to be executed.
     //
@@ -487,8 +1367,73 @@ impl VkRenderer {
     let fbs = self
       .framebuffers
       .as_ref()
-      .expect("Could not get framebuffers as a ref.")[image_num]
+      .ok_or(VkRendererError::NoFramebuffers)?[image_num]
       .clone();
+    let attachments = fbs.num_attachments();
+    let cleared = fbs
+      .attachment_descs()
+      .filter(|desc| match desc.load {
+        LoadOp::Clear => true,
+        _ => false,
+      })
+      .count();
+    let clear_values =
+      if clear_values.len() == attachments {
+        clear_values
+      } else if clear_values.len() == cleared {
+        // Spread the values over the cleared attachments.
+        let mut values = clear_values.into_iter();
+        fbs
+          .attachment_descs()
+          .map(|desc| match desc.load {
+            LoadOp::Clear => values.next().expect("There is a value for each cleared attachment."),
+            _ => ClearValue::None,
+          })
+          .collect()
+      } else {
+        return Err(VkRendererError::ClearValueCount { cleared, attachments, given: clear_values.len() });
+      };
+    let missing = fbs
+      .attachment_descs()
+      .zip(clear_values.iter())
+      .position(|(desc, value)| match (desc.load, value) {
+        (LoadOp::Clear, ClearValue::None) => true,
+        _ => false,
+      });
+    if let Some(ndx) = missing {
+      return Err(VkRendererError::MissingClearValue(ndx));
+    }
+    // Buffers can't be copied inside a render pass.
+    if self.fog_dirty {
+      let fog = CpuAccessibleBuffer::from_data(
+        self.device.clone(),
+        BufferUsage::transfer_source(),
+        FogUniform::from(&self.fog)
+      ).expect("Could not create fog upload buffer.");
+      let fog_buffer = self.fog_buffer.clone();
+      self.copy_buffer(fog, fog_buffer);
+      self.fog_dirty = false;
+    }
+    if self.clip_plane_dirty {
+      let clip_plane = CpuAccessibleBuffer::from_data(
+        self.device.clone(),
+        BufferUsage::transfer_source(),
+        ClipPlaneUniform::from(self.clip_plane)
+      ).expect("Could not create clip plane upload buffer.");
+      let clip_plane_buffer = self.clip_plane_buffer.clone();
+      self.copy_buffer(clip_plane, clip_plane_buffer);
+      self.clip_plane_dirty = false;
+    }
+    if self.log_depth_dirty {
+      let log_depth = CpuAccessibleBuffer::from_data(
+        self.device.clone(),
+        BufferUsage::transfer_source(),
+        LogDepthUniform::from(self.log_depth)
+      ).expect("Could not create log depth upload buffer.");
+      let log_depth_buffer = self.log_depth_buffer.clone();
+      self.copy_buffer(log_depth, log_depth_buffer);
+      self.log_depth_dirty = false;
+    }
     self.with_command_builder(|cmds| {
       cmds
       // Before we can draw, we have to *enter a render pass*. There are two methods to do
@@ -500,42 +1445,1127 @@ impl VkRenderer {
       // only the attachments that use `load: Clear` appear in the list.
         .begin_render_pass(
           fbs,
-          false,
-          vec![
-            [0.0, 0.0, 1.0, 1.0].into(),
-            1f32.into()
-          ]
+          secondary,
+          clear_values
         ).expect("Could not begin_render_pass.")
     });
+    self.in_render_pass = true;
+    Ok(())
   }
 
 
-  /// Commit a buffer builder to be rendered.
-  pub fn commit_rendering(&mut self) {
-    // We leave the render pass by calling `end_render_pass`. Note that if we had multiple
-    // subpasses we could have called `next_inline` (or `next_secondary`) to jump to the
-    // next subpass.
-    let command_buffer = self
-      .command_buffer_builder
-      .take().expect("Could not take command_buffer - maybe 'begin_rendering' was not called.")
-      .end_render_pass().expect("Could not end_render_pass.")
-      .build().expect("Could not build the command_buffer.");
+  /// Record draws once into `StaticCommands`, to execute each frame with
+  /// `execute_static` instead of recording them again. `f` is given a builder
+  /// for the first subpass of `render_pass` and the current dynamic state,
+  /// and records draws the same way as `with_command_builder`. The
+  /// background is recorded first.
+  ///
+  /// Call this after `start_next_frame`, once the framebuffers exist.
+  pub fn record_static<F>(&mut self, f: F) -> StaticCommands
+    where F: FnOnce(AutoCommandBufferBuilder, &DynamicState) -> AutoCommandBufferBuilder
+  {
+    let subpass = Subpass::from(self.render_pass.clone(), 0)
+      .expect("Could not get the first subpass of the render pass.");
+    // Frames overlap on the GPU, so the same commands can be executing in
+    // more than one of them.
+    let cmds = AutoCommandBufferBuilder::secondary_graphics_simultaneous_use(
+      self.device.clone(),
+      self.queue.family(),
+      subpass
+    ).expect("Could not create a secondary command buffer builder.");
+    let cmds = self.record_background(cmds);
+    let dynamic_state = self.dynamic_state.clone();
+    let commands = f(cmds, &dynamic_state)
+      .build()
+      .expect("Could not build static commands.");
+    StaticCommands {
+      commands: Arc::new(commands),
+      generation: self.framebuffer_generation,
+    }
+  }
 
-    let future = self
-      .previous_frame_end
-      .take().expect("Could not take previous_frame_end.")
-      .join(
-        self
-          .acquire_future
-          .take()
-          .expect("Could not get acquired_future - maybe 'begin_rendering' was not called before 'commit_rendering'.")
-      )
-      .then_execute(self.queue.clone(), command_buffer)
-      .expect("Could not expecute the command_buffer.")
-      // The color output is now expected to contain our geometry. But in order to show it on
-      // the screen, we have to *present* the image by calling `present`.
-      //
-      // This function does not actually present the image immediately. Instead it submits a
+
+  /// Begin rendering the frame by executing `commands`, in place of
+  /// `begin_rendering` and the draws recorded into `commands`. Follow it with
+  /// `commit_rendering` as usual.
+  ///
+  /// Nothing else can be drawn in the render pass of a frame started this
+  /// way, since vulkan doesn't mix secondary command buffers with draws
+  /// recorded inline. Record everything the frame draws into the
+  /// `StaticCommands`.
+  ///
+  /// Returns an error if the framebuffers have been rebuilt since `commands`
+  /// were recorded, in which case nothing is recorded and the frame can
+  /// still be started with `begin_rendering`.
+  pub fn execute_static(&mut self, commands: &StaticCommands) -> Result<(), VkRendererError> {
+    if self.suspended {
+      return Ok(());
+    }
+    if commands.generation != self.framebuffer_generation {
+      return Err(VkRendererError::StaleStaticCommands);
+    }
+    let clear_values = self.default_clear_values();
+    self.begin_render_pass(clear_values, true)?;
+    let secondary = commands.commands.clone();
+    self.with_command_builder(|cmds| unsafe {
+      // vulkano doesn't check or synchronize secondary command buffers yet,
+      // see `StaticCommands` for what that leaves to the caller.
+      cmds
+        .execute_commands(secondary)
+        .expect("Could not execute static commands.")
+    });
+    Ok(())
+  }
+
+
+  /// Move on to the next subpass of a custom `render_pass` with more than one
+  /// subpass, eg. the lighting subpass of `pipelines::deferred`.
+  pub fn next_subpass(&mut self) {
+    self.with_command_builder(|cmds| {
+      cmds
+        .next_subpass(false)
+        .expect("Could not move to the next subpass.")
+    });
+  }
+
+
+  /// Whether or not the render pass has begun and not yet ended, between
+  /// `begin_rendering` and `commit_rendering`. Copies and uploads can only
+  /// be recorded outside of it.
+  pub fn is_in_render_pass(&self) -> bool {
+    self.in_render_pass
+  }
+
+
+  /// Reset the depth under the current viewport and scissor rect to the far
+  /// plane without ending the render pass, so that the next view of a split
+  /// screen, or anything drawn on top like a 3d HUD, isn't hidden by the depth
+  /// of what was drawn before it. Color is left as it is. Set the scissor to
+  /// the next view's rect first to only clear that view.
+  ///
+  /// This must be called between `begin_rendering` and `commit_rendering`,
+  /// with a `render_pass` whose first subpass has a depth attachment.
+  ///
+  /// vulkano doesn't expose `vkCmdClearAttachments`, so this draws one
+  /// triangle over the viewport that writes depth 1.0 and no color. It costs
+  /// about as much as a depth-only fill of the rect - far less than ending
+  /// and beginning another render pass, which on tiled GPUs stores and reloads
+  /// every attachment - but more than the free clear at the start of the
+  /// pass, so one view doesn't need it.
+  pub fn clear_depth_only(&mut self) -> Result<(), VkRendererError> {
+    if self.suspended {
+      return Ok(());
+    }
+    if !self.in_render_pass {
+      return Err(VkRendererError::NotInRenderPass);
+    }
+    let has_depth = Subpass::from(self.render_pass.clone(), 0)
+      .map(|subpass| subpass.has_depth())
+      .unwrap_or(false);
+    if !has_depth {
+      return Err(VkRendererError::NoDepthAttachment);
+    }
+    if self.depth_clear_pipeline.is_none() {
+      self.depth_clear_pipeline = Some(DepthClearPipeline::new(self.render_pass.clone(), self.device.clone()));
+    }
+    let pipeline = self
+      .depth_clear_pipeline
+      .as_ref()
+      .expect("This should never happen.");
+    let graphics_pipeline = pipeline.pipeline.clone();
+    let vertex_buffer = pipeline.vertex_buffer.clone() as Arc<BufferAccess + Send + Sync>;
+    let dynamic_state = self.dynamic_state.clone();
+    self.with_command_builder(|cmds| {
+      cmds
+        .draw(
+          graphics_pipeline,
+          &dynamic_state,
+          vec![vertex_buffer],
+          (),
+          ()
+        ).expect("Could not clear depth.")
+    });
+    Ok(())
+  }
+
+
+  /// A dynamic state that covers the whole framebuffer, ignoring the scissor.
+  fn full_dynamic_state(&self) -> DynamicState {
+    let dimensions = self.dimensions;
+    DynamicState {
+      line_width: None,
+      viewports: Some(vec![Viewport {
+        origin: [0.0, 0.0],
+        dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+        depth_range: 0.0 .. 1.0,
+      }]),
+      scissors: Some(vec![Scissor {
+        origin: [0, 0],
+        dimensions,
+      }]),
+    }
+  }
+
+
+  /// A dynamic state that covers the `content_rect`, ignoring the scissor, so
+  /// that letterbox bars are left the clear color.
+  fn content_dynamic_state(&self) -> DynamicState {
+    let rect = self.content_rect();
+    DynamicState {
+      line_width: None,
+      viewports: Some(vec![Viewport {
+        origin: [rect[0] as f32, rect[1] as f32],
+        dimensions: [rect[2] as f32, rect[3] as f32],
+        depth_range: self.depth_range.0 .. self.depth_range.1,
+      }]),
+      scissors: Some(vec![Scissor {
+        origin: [rect[0] as i32, rect[1] as i32],
+        dimensions: [rect[2], rect[3]],
+      }]),
+    }
+  }
+
+
+  /// Draw the gradient or skybox background, if there is one.
+  fn draw_background(&mut self) {
+    if let Background::Color(_) = self.background {
+      return;
+    }
+    let cmds = self
+      .command_buffer_builder
+      .take()
+      .expect("Could not take command_buffer - maybe 'begin_rendering' was not called.");
+    let cmds = self.record_background(cmds);
+    self.command_buffer_builder = Some(cmds);
+  }
+
+
+  /// Record drawing the gradient or skybox background, if there is one, into
+  /// the given builder.
+  fn record_background(&mut self, cmds: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder {
+    if let Background::Color(_) = self.background {
+      return cmds;
+    }
+    if self.background_pipeline.is_none() {
+      self.background_pipeline = Some(BackgroundPipeline::new(self.render_pass.clone(), self.device.clone()));
+    }
+    let dynamic_state = self.content_dynamic_state();
+    let pipeline = self
+      .background_pipeline
+      .as_ref()
+      .expect("This should never happen.");
+    let vertex_buffer = pipeline.vertex_buffer.clone() as Arc<BufferAccess + Send + Sync>;
+    match &self.background {
+      Background::Color(_) => cmds,
+      Background::Gradient(top, bottom) => {
+        cmds
+          .draw(
+            pipeline.gradient_pipeline.clone(),
+            &dynamic_state,
+            vec![vertex_buffer],
+            (),
+            gradient_fs::ty::Gradient { top: *top, bottom: *bottom }
+          ).expect("Could not draw background gradient.")
+      }
+      Background::Skybox(cubemap) => {
+        let set = self
+          .background_set
+          .get_or_insert_with(|| pipeline.desc_set_for_skybox(cubemap.clone()))
+          .clone();
+        cmds
+          .draw(
+            pipeline.skybox_pipeline.clone(),
+            &dynamic_state,
+            vec![vertex_buffer],
+            set,
+            skybox_fs::ty::Skybox {
+              inverse_view_projection: self.skybox_inverse_view_projection.into(),
+            }
+          ).expect("Could not draw skybox.")
+      }
+    }
+  }
+
+
+  /// Set what is drawn behind everything else at the start of each frame.
+  /// Defaults to a flat blue.
+  ///
+  /// `Background::Color` only changes the clear color. A gradient or skybox
+  /// is drawn over the whole framebuffer right after `begin_rendering`
+  /// clears it, without writing depth, so everything drawn afterward is in
+  /// front of it. A skybox is seen from `set_skybox_camera`.
+  ///
+  /// `Background::Color` also sets the fog color to the same color, so that
+  /// fogged geometry fades into the background. Call `set_fog` afterward to
+  /// use a different fog color.
+  pub fn set_background(&mut self, background: Background) {
+    if let Background::Color(color) = background {
+      self.fog.color = color;
+      self.fog_dirty = true;
+    }
+    self.background = background;
+    self.background_set = None;
+  }
+
+
+  pub fn background(&self) -> &Background {
+    &self.background
+  }
+
+
+  /// Set the fog drawn by the phong and color3d pipelines that have called
+  /// `use_fog`. The fog is uploaded at the start of the next
+  /// `begin_rendering`, so it changes for whole frames only. Fog is off by
+  /// default.
+  ///
+  /// `set_background` with a `Background::Color` sets the fog color to match
+  /// it, replacing the color given here. A gradient or skybox doesn't, so set
+  /// the fog color to match it, eg. the gradient's bottom color, to hide the
+  /// far plane.
+  pub fn set_fog(&mut self, params: FogParams) {
+    self.fog = params;
+    self.fog_dirty = true;
+  }
+
+
+  pub fn fog(&self) -> &FogParams {
+    &self.fog
+  }
+
+
+  /// The uniform buffer holding the fog, which pipelines bind in `use_fog`.
+  pub fn fog_buffer(&self) -> Arc<DeviceLocalBuffer<FogUniform>> {
+    self.fog_buffer.clone()
+  }
+
+
+  /// Set the world space plane `[a, b, c, d]` that the phong and color3d
+  /// pipelines which have called `use_clip_plane` cut geometry with, or None
+  /// to draw everything. Fragments where `a*x + b*y + c*z + d < 0` are
+  /// discarded, so `[0.0, -1.0, 0.0, 1.0]` keeps everything at or below
+  /// `y = 1`, for a cutaway of a building with its upper floors removed.
+  ///
+  /// Like the fog, the plane is uploaded at the start of the next
+  /// `begin_rendering`, so it changes for whole frames only.
+  pub fn set_clip_plane(&mut self, plane: Option<[f32; 4]>) {
+    self.clip_plane = plane;
+    self.clip_plane_dirty = true;
+  }
+
+
+  pub fn clip_plane(&self) -> Option<[f32; 4]> {
+    self.clip_plane
+  }
+
+
+  /// The uniform buffer holding the clip plane, which pipelines bind in
+  /// `use_clip_plane`.
+  pub fn clip_plane_buffer(&self) -> Arc<DeviceLocalBuffer<ClipPlaneUniform>> {
+    self.clip_plane_buffer.clone()
+  }
+
+
+  /// Turn a logarithmic depth buffer on or off for the phong and color3d
+  /// pipelines that have called `use_logarithmic_depth`, with `far` as the
+  /// distance to the camera's far plane.
+  ///
+  /// A projection's own depth puts most of the depth buffer's precision
+  /// right in front of the near plane, so with a huge depth range, like a
+  /// planet seen from orbit, distant surfaces z-fight even with a 32 bit
+  /// depth buffer. Logarithmic depth spreads the precision evenly over
+  /// orders of magnitude. It can't be combined with reverse-Z.
+  ///
+  /// The depth is computed per vertex and interpolated linearly across each
+  /// triangle, so large triangles that reach close to the camera can have
+  /// slightly wrong depths and sort wrongly where they meet other geometry;
+  /// tessellate them more finely if that shows. Computing it per fragment
+  /// would be exact, but writing `gl_FragDepth` turns off early depth testing
+  /// and makes every hidden fragment cost as much as a visible one.
+  ///
+  /// Like the fog, this is uploaded at the start of the next
+  /// `begin_rendering`, so it changes for whole frames only.
+  pub fn set_logarithmic_depth(&mut self, enabled: bool, far: f32) {
+    self.log_depth =
+      if enabled {
+        Some(far)
+      } else {
+        None
+      };
+    self.log_depth_dirty = true;
+  }
+
+
+  /// The far plane of the logarithmic depth buffer, or None when it's off.
+  pub fn logarithmic_depth(&self) -> Option<f32> {
+    self.log_depth
+  }
+
+
+  /// The uniform buffer holding the logarithmic depth, which pipelines bind
+  /// in `use_logarithmic_depth`.
+  pub fn log_depth_buffer(&self) -> Arc<DeviceLocalBuffer<LogDepthUniform>> {
+    self.log_depth_buffer.clone()
+  }
+
+
+  /// Look at the skybox from the camera's orientation. Only the direction the
+  /// camera faces and its projection matter, not its position, so call this
+  /// whenever the camera turns or the window is resized.
+  pub fn set_skybox_camera(&mut self, camera: &Camera) {
+    let mut view = camera.view();
+    view[(0, 3)] = 0.0;
+    view[(1, 3)] = 0.0;
+    view[(2, 3)] = 0.0;
+    self.skybox_inverse_view_projection = (camera.projection() * view)
+      .try_inverse()
+      .unwrap_or(Matrix4::identity());
+  }
+
+
+  /// Lines, boxes and text to draw at the next `flush_debug`, without setting
+  /// up a pipeline or vertex buffers.
+  pub fn debug(&mut self) -> &mut DebugDraw {
+    &mut self.debug.draw
+  }
+
+
+  /// Draw everything queued with `debug` as seen from the camera, then clear
+  /// it. Call this after the rest of the scene so that the lines are depth
+  /// tested against it. Lines are drawn `line_width` wide.
+  pub fn flush_debug(&mut self, camera: &Camera) {
+    if self.debug.draw.is_empty() {
+      return;
+    }
+    let vertices = self.debug.draw.vertices(camera, self.dimensions[1]);
+    let flushed = self.debug.flush(
+      self.device.clone(),
+      self.render_pass.clone(),
+      camera.projection(),
+      vertices
+    );
+    let (pipeline, set, vertex_buffer) = match flushed {
+      Some(flushed) => flushed,
+      None => return,
+    };
+    let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+    let push_constants = color3d::vs::ty::ModelView {
+      model: identity,
+      view: camera.view().into(),
+    };
+    self.record_draw(vertex_buffer.len());
+    let dynamic_state = self.line_dynamic_state();
+    self.with_command_builder(|cmds| {
+      cmds
+        .draw(
+          pipeline,
+          &dynamic_state,
+          vec![vertex_buffer as Arc<BufferAccess + Send + Sync>],
+          set,
+          push_constants
+        ).expect("Could not draw debug lines.")
+    });
+  }
+
+
+  /// Record a draw of one vertex buffer with the current dynamic state,
+  /// counting it in the frame's stats.
+  pub fn draw<V, S, Pc>(
+    &mut self,
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[V]>>,
+    sets: S,
+    push_constants: Pc
+  ) where
+    V: Send + Sync + 'static,
+    S: DescriptorSetsCollection
+  {
+    let len = vertex_buffer.len();
+    self.draw_buffer(pipeline, vertex_buffer, len, sets, push_constants);
+  }
+
+
+  /// Record an indexed draw of one vertex buffer with the current dynamic
+  /// state, counting it in the frame's stats. With a strip or fan topology,
+  /// `PRIMITIVE_RESTART_INDEX` in the index buffer starts a new strip or fan.
+  pub fn draw_indexed<V, S, Pc>(
+    &mut self,
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[V]>>,
+    index_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
+    sets: S,
+    push_constants: Pc
+  ) where
+    V: Send + Sync + 'static,
+    S: DescriptorSetsCollection
+  {
+    self.record_draw(index_buffer.len());
+    let dynamic_state = self.dynamic_state.clone();
+    self.with_command_builder(|cmds| {
+      cmds
+        .draw_indexed(
+          pipeline,
+          &dynamic_state,
+          vec![vertex_buffer as Arc<BufferAccess + Send + Sync>],
+          index_buffer,
+          sets,
+          push_constants
+        ).expect("Could not draw indexed.")
+    });
+  }
+
+
+  /// Record a draw of any kind of vertex buffer holding `len` vertices.
+  fn draw_buffer<S, Pc>(
+    &mut self,
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    vertex_buffer: Arc<BufferAccess + Send + Sync>,
+    len: usize,
+    sets: S,
+    push_constants: Pc
+  ) where
+    S: DescriptorSetsCollection
+  {
+    self.record_draw(len);
+    let dynamic_state = self.dynamic_state.clone();
+    self.with_command_builder(|cmds| {
+      cmds
+        .draw(
+          pipeline,
+          &dynamic_state,
+          vec![vertex_buffer],
+          sets,
+          push_constants
+        ).expect("Could not draw.")
+    });
+  }
+
+
+  /// Like `draw`, but takes one of the built-in pipelines, which checks that
+  /// the vertex buffer holds the pipeline's type of vertex.
+  pub fn draw_with<P, S, Pc>(
+    &mut self,
+    pipeline: &P,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[P::Vertex]>>,
+    sets: S,
+    push_constants: Pc
+  ) where
+    P: RenderPipeline,
+    S: DescriptorSetsCollection
+  {
+    self.draw(pipeline.inner(), vertex_buffer, sets, push_constants);
+  }
+
+
+  /// Draw a mesh with one of the built-in pipelines. The mesh's vertex type
+  /// must be the pipeline's `RenderPipeline::Vertex`, so passing a mesh that
+  /// the pipeline can't read is a compile error instead of a failed draw.
+  pub fn draw_mesh<P, S, Pc>(
+    &mut self,
+    pipeline: &P,
+    mesh: &Mesh<P::Vertex>,
+    sets: S,
+    push_constants: Pc
+  ) where
+    P: RenderPipeline,
+    S: DescriptorSetsCollection
+  {
+    self.draw_with(pipeline, mesh.vertex_buffer.clone(), sets, push_constants);
+  }
+
+
+  /// Draw this frame's chunk of a DynamicMesh with one of the built-in
+  /// pipelines. Nothing is drawn if the chunk is empty.
+  pub fn draw_dynamic<P, S, Pc>(
+    &mut self,
+    pipeline: &P,
+    chunk: &DynamicMeshChunk<P::Vertex>,
+    sets: S,
+    push_constants: Pc
+  ) where
+    P: RenderPipeline,
+    S: DescriptorSetsCollection
+  {
+    if let Some(vertex_buffer) = chunk.vertex_buffer.as_ref() {
+      let len = vertex_buffer.len();
+      self.draw_buffer(pipeline.inner(), vertex_buffer.clone(), len, sets, push_constants);
+    }
+  }
+
+
+  /// Record a copy from one buffer to another, eg. to upload a uniform,
+  /// counting it in the frame's stats.
+  pub fn copy_buffer<S, D, T>(&mut self, src: S, dest: D)
+    where
+      S: TypedBufferAccess<Content = T> + Send + Sync + 'static,
+      D: TypedBufferAccess<Content = T> + Send + Sync + 'static,
+      T: ?Sized
+  {
+    self.record_buffer_upload();
+    self.with_command_builder(|cmds| {
+      cmds
+        .copy_buffer(src, dest)
+        .expect("Could not copy buffer.")
+    });
+  }
+
+
+  /// Count a draw of `vertices` vertices in the frame's stats.
+  pub fn record_draw(&mut self, vertices: usize) {
+    self.stats.draw_calls += 1;
+    self.stats.vertices += vertices;
+  }
+
+
+  /// Count a buffer upload in the frame's stats.
+  pub fn record_buffer_upload(&mut self) {
+    self.stats.buffer_uploads += 1;
+  }
+
+
+  /// The stats of the last frame that was started before the current one.
+  pub fn last_frame_stats(&self) -> &RenderStats {
+    &self.last_frame_stats
+  }
+
+
+  /// How long the GPU took to draw a recent frame, in milliseconds, or None if
+  /// GPU timing isn't enabled, see `enable_gpu_timing`, or no frame has been
+  /// timed yet.
+  ///
+  /// Unlike `utils::FPSCounter`, which measures wall clock time, this is the
+  /// GPU's own time between timestamps written before and after a frame's
+  /// commands. Timestamps are read back without stalling, so this is usually
+  /// the frame before the one just committed: a frame of latency. The time
+  /// includes any wait for the swapchain image, eg. with vsync.
+  pub fn last_gpu_frame_ms(&self) -> Option<f32> {
+    self.gpu_timer.as_ref().and_then(GpuTimer::last_frame_ms)
+  }
+
+
+  /// Start timing frames on the GPU, for `last_gpu_frame_ms`. Timing is off
+  /// by default because it submits two more command buffers every frame.
+  /// Returns false if the device can't time its graphics queue.
+  pub fn enable_gpu_timing(&mut self) -> bool {
+    if self.gpu_timer.is_none() {
+      self.gpu_timer = GpuTimer::new(self.device.clone(), self.queue.clone());
+    }
+    self.gpu_timer.is_some()
+  }
+
+
+  /// Stop timing frames on the GPU. This waits for the GPU to finish, see
+  /// `enable_gpu_timing`.
+  pub fn disable_gpu_timing(&mut self) {
+    self.gpu_timer = None;
+  }
+
+
+  /// Draw one frame of egui output over the scene with an EguiPipeline.
+  /// Requires the `egui` feature.
+  #[cfg(feature = "egui")]
+  pub fn draw_egui(
+    &mut self,
+    pipeline: &mut EguiPipeline,
+    ctx: &::egui::Context,
+    output: ::egui::FullOutput
+  ) {
+    pipeline.draw(self, ctx, output);
+  }
+
+
+  /// Returns the frame currently being rendered. This should be called
+  /// between `begin_rendering` and `commit_rendering`.
+  pub fn frame(&mut self) -> Frame {
+    Frame::new(self)
+  }
+
+
+  /// Render one whole frame: start it, begin rendering, record draws with
+  /// `draw` and commit it, for apps that draw once per loop iteration.
+  /// Returns None without calling `draw` if no frame could be started, see
+  /// `start_next_frame`, or else whether the framebuffers were resized.
+  pub fn render_frame<F>(&mut self, draw: F) -> Option<bool>
+    where F: FnOnce(&mut Frame)
+  {
+    let resized = self.start_next_frame()?;
+    self.begin_rendering();
+    draw(&mut self.frame());
+    self.commit_rendering();
+    Some(resized)
+  }
+
+
+  /// Like `render_frame`, but waits until the GPU has finished the frame
+  /// before returning, eg. for tests and screenshot tools that read the
+  /// result right away.
+  pub fn render_frame_and_wait<F>(&mut self, draw: F) -> Option<bool>
+    where F: FnOnce(&mut Frame)
+  {
+    let resized = self.render_frame(draw)?;
+    self.wait_for_gpu();
+    Some(resized)
+  }
+
+
+  /// Draw a mesh with the phong pipeline and one of its materials.
+  /// Transparent materials are drawn with `transparent_pipeline`, which
+  /// doesn't write depth, so draw them after everything opaque and from back
+  /// to front, eg. with `Scene::draw`.
+  pub fn draw_phong(
+    &mut self,
+    pipeline: &PhongPipeline,
+    material: &str,
+    mesh: &Mesh<VertexPhong>,
+    model: &Matrix4<f32>,
+    view: &Matrix4<f32>
+  ) {
+    let material_set = pipeline
+      .materials
+      .get(material)
+      .expect(&format!("Could not find material {}", material))
+      .desc_set
+      .clone();
+    let mats = vs::ty::ModelViewNormal::new(model, view);
+    let sets = (pipeline.proj.desc_set.clone(), material_set, pipeline.light.desc_set.clone());
+    if pipeline.is_transparent(material) {
+      self.draw(pipeline.transparent_pipeline.clone(), mesh.vertex_buffer.clone(), sets, mats);
+    } else {
+      self.draw_mesh(pipeline, mesh, sets, mats);
+    }
+  }
+
+
+  /// Draw a mesh with the phong pipeline, blending each vertex between its
+  /// base pose and its morph target. A `weight` of 0.0 draws the base pose and
+  /// 1.0 draws the target. `base` and `target` must have the same length.
+  pub fn draw_phong_morphed(
+    &mut self,
+    pipeline: &PhongPipeline,
+    material: &str,
+    base: Arc<CpuAccessibleBuffer<[VertexPhong]>>,
+    target: Arc<CpuAccessibleBuffer<[VertexMorphTarget]>>,
+    weight: f32,
+    model: &Matrix4<f32>,
+    view: &Matrix4<f32>
+  ) {
+    let material_set = pipeline
+      .materials
+      .get(material)
+      .expect(&format!("Could not find material {}", material))
+      .desc_set
+      .clone();
+    let mats = morph_vs::ty::MorphModelViewNormal::new(model, view, weight);
+    let morph_pipeline = pipeline.morph_pipeline.clone();
+    let sets = (pipeline.proj.desc_set.clone(), material_set, pipeline.light.desc_set.clone());
+    self.record_draw(base.len());
+    let dynamic_state = self.dynamic_state.clone();
+    self.with_command_builder(|cmds| {
+      cmds
+        .draw(
+          morph_pipeline,
+          &dynamic_state,
+          vec![
+            base as Arc<BufferAccess + Send + Sync>,
+            target as Arc<BufferAccess + Send + Sync>
+          ],
+          sets,
+          mats
+        ).expect("Could not draw morphed phong geometry.")
+    });
+  }
+
+
+  /// Read the depth of the last rendered frame at a pixel, from 0.0 at the
+  /// near plane to 1.0 at the far plane. Together with the inverse of the
+  /// projection and view matrices this can be unprojected to a world position
+  /// for picking.
+  ///
+  /// This must be called between frames, after `commit_rendering`. It waits
+  /// for the GPU to finish all submitted work, so it should be used for
+  /// occasional picking rather than every frame. Pixels outside of the
+  /// framebuffer are clamped to its edge.
+  pub fn read_depth_at(&mut self, x: u32, y: u32) -> f32 {
+    let x = x.min(self.dimensions[0].saturating_sub(1));
+    let y = y.min(self.dimensions[1].saturating_sub(1));
+    let depth_buffer = self
+      .depth_buffer
+      .clone()
+      .expect("Could not get depth_buffer - maybe nothing has been rendered yet.");
+    if self.depth_reader.is_none() {
+      self.depth_reader = Some(DepthReader::new(self.device.clone()));
+    }
+    let after = self
+      .previous_frame_end
+      .take()
+      .unwrap_or_else(|| Box::new(now(self.device.clone())) as Box<GpuFuture>);
+    let depth = self
+      .depth_reader
+      .as_ref()
+      .expect("This should never happen")
+      .read(self.device.clone(), self.queue.clone(), after, depth_buffer, x, y);
+    self.previous_frame_end = Some(Box::new(now(self.device.clone())) as Box<GpuFuture>);
+    depth
+  }
+
+
+  /// Draw into an `HDR_FORMAT` (R16G16B16A16Sfloat) offscreen image instead
+  /// of the swapchain image, so lit colors can go above 1.0. The image is
+  /// tonemapped onto the swapchain at the end of `commit_rendering`.
+  ///
+  /// This replaces `render_pass`, so it must be called before creating any
+  /// pipelines. Returns an error if the device can't render to or sample
+  /// `HDR_FORMAT` images, in which case the renderer is left unchanged.
+  pub fn enable_hdr(&mut self) -> Result<(), VkRendererError> {
+    let hdr = Hdr::new(self.device.clone(), self.queue.clone(), self.swapchain.format())?;
+    self.render_pass = hdr.scene_render_pass.clone();
+    self.hdr = Some(hdr);
+    // The background pipelines were built against the old render pass.
+    self.background_pipeline = None;
+    self.background_set = None;
+    self.depth_clear_pipeline = None;
+    if let Some(soft_particles) = self.soft_particles.as_mut() {
+      soft_particles.set_format(self.device.clone(), HDR_FORMAT);
+    }
+    self.debug.reset_pipeline();
+    self.framebuffers = None;
+    Ok(())
+  }
+
+
+  /// Whether or not `enable_hdr` has been called.
+  pub fn is_hdr(&self) -> bool {
+    self.hdr.is_some()
+  }
+
+
+  /// Set the exposure that HDR colors are multiplied by before tonemapping.
+  /// Defaults to 1.0. Has no effect unless HDR is enabled. With auto exposure
+  /// on this is the starting point that the exposure adapts from.
+  pub fn set_exposure(&mut self, exposure: f32) {
+    if let Some(hdr) = self.hdr.as_mut() {
+      hdr.exposure = exposure;
+    }
+  }
+
+
+  /// The current exposure, or 1.0 if HDR is not enabled.
+  pub fn exposure(&self) -> f32 {
+    self.hdr.as_ref().map(|hdr| hdr.exposure).unwrap_or(1.0)
+  }
+
+
+  /// Turn automatic exposure on or off. While on, the exposure smoothly
+  /// adapts so that the scene's average luminance is tonemapped to middle
+  /// gray. `adaptation_speed` is in 1/seconds, eg. 1.0 covers about 63% of
+  /// the difference in one second. Has no effect unless HDR is enabled.
+  ///
+  /// The luminance is measured from a 16x16 grid of samples of the HDR image,
+  /// and is read back a frame or more later so that it never stalls.
+  pub fn set_auto_exposure(&mut self, enabled: bool, adaptation_speed: f32) {
+    let device = self.device.clone();
+    if let Some(hdr) = self.hdr.as_mut() {
+      hdr.auto_exposure =
+        if !enabled {
+          None
+        } else {
+          match hdr.auto_exposure.take() {
+            Some(mut auto_exposure) => {
+              auto_exposure.adaptation_speed = adaptation_speed;
+              Some(auto_exposure)
+            }
+            None => Some(AutoExposure::new(device, adaptation_speed)),
+          }
+        };
+    }
+  }
+
+
+  /// Turn screen-space ambient occlusion on or off. While on, crevices and
+  /// corners are darkened by how much of a hemisphere of `radius` (in view
+  /// space units) around each pixel is behind the depth buffer. `intensity`
+  /// is how dark fully occluded pixels get, from 0.0 to 1.0. Has no effect
+  /// unless HDR is enabled, since it is drawn between the scene pass and
+  /// tonemapping.
+  ///
+  /// The occlusion is drawn from the depth buffer alone, with normals
+  /// reconstructed from it, then blurred to hide the noise. It needs the
+  /// scene's projection, see `set_ssao_projection`. Turning it on or off
+  /// recreates the framebuffers, so the next `start_next_frame` reports a
+  /// resize.
+  pub fn set_ssao(&mut self, enabled: bool, radius: f32, intensity: f32) {
+    let device = self.device.clone();
+    let queue = self.queue.clone();
+    if let Some(hdr) = self.hdr.as_mut() {
+      if !enabled {
+        if hdr.ssao.take().is_some() {
+          self.framebuffers = None;
+        }
+        return;
+      }
+      match hdr.ssao.as_mut() {
+        Some(ssao) => {
+          ssao.radius = radius;
+          ssao.intensity = intensity;
+        }
+        None => {
+          hdr.ssao = Some(Ssao::new(device, queue, radius, intensity));
+          self.framebuffers = None;
+        }
+      }
+    }
+  }
+
+
+  /// Set the projection the scene is drawn with, which SSAO needs to
+  /// reconstruct positions from depth. Defaults to the projection of
+  /// `Camera::default()`. Has no effect unless SSAO is on.
+  pub fn set_ssao_projection(&mut self, projection: Matrix4<f32>) {
+    if let Some(ssao) = self.hdr.as_mut().and_then(|hdr| hdr.ssao.as_mut()) {
+      ssao.projection = projection;
+    }
+  }
+
+  /// Turn soft particles on or off. Soft particles fade out over `softness`
+  /// view space units in front of the opaque scene behind them, instead of
+  /// cutting a hard edge where their quads intersect it. Draw them with
+  /// `ParticleSystem::draw_soft`, and set the scene's projection with
+  /// `set_soft_particle_projection`. They are off by default.
+  ///
+  /// Sampling the depth buffer isn't allowed while it is the render pass's
+  /// depth attachment, so soft particles are queued during the frame and
+  /// drawn after the scene pass ends in `commit_rendering`, over everything
+  /// else drawn that frame and before tonemapping. The depth buffer holds
+  /// post-projection depth, which isn't linear in distance, so each sample
+  /// is turned back into a view space distance with the inverse of the
+  /// projection before it is compared with the particle's. Turning them on
+  /// recreates the framebuffers, so the next `start_next_frame` reports a
+  /// resize.
+  pub fn set_soft_particles(&mut self, enabled: bool, softness: f32) {
+    if !enabled {
+      if self.soft_particles.take().is_some() {
+        self.framebuffers = None;
+      }
+      return;
+    }
+    match self.soft_particles.as_mut() {
+      Some(soft_particles) => {
+        soft_particles.softness = softness;
+        soft_particles.set_dirty();
+      }
+      None => {
+        let format = if self.hdr.is_some() { HDR_FORMAT } else { self.swapchain.format() };
+        self.soft_particles = Some(SoftParticles::new(self.device.clone(), format, softness));
+        self.framebuffers = None;
+      }
+    }
+  }
+
+
+  /// Set the projection the scene is drawn with, which soft particles are
+  /// drawn with and need to linearize depth. Defaults to the projection of
+  /// `Camera::default()`. Has no effect unless soft particles are on.
+  pub fn set_soft_particle_projection(&mut self, projection: Matrix4<f32>) {
+    if let Some(soft_particles) = self.soft_particles.as_mut() {
+      soft_particles.projection = projection;
+      soft_particles.set_dirty();
+    }
+  }
+
+
+  /// Whether or not soft particles are on. See `set_soft_particles`.
+  pub fn has_soft_particles(&self) -> bool {
+    self.soft_particles.is_some()
+  }
+
+
+  /// Queue particles to be drawn soft at the end of the frame. Does nothing
+  /// while soft particles are off or rendering is suspended.
+  pub(crate) fn queue_soft_particles(
+    &mut self,
+    vertex_buffer: Arc<BufferAccess + Send + Sync>,
+    billboard: particles_vs::ty::Billboard
+  ) {
+    if self.suspended {
+      return;
+    }
+    if let Some(soft_particles) = self.soft_particles.as_mut() {
+      soft_particles.queue(vertex_buffer, billboard);
+    }
+  }
+
+
+  /// Turn bloom on or off. Pixels brighter than `threshold` glow, with
+  /// `intensity` of the glow added to the scene. This makes bright emissive
+  /// materials and strong highlights bleed into their surroundings. Bloom
+  /// only works while HDR is enabled, since the scene must keep colors above
+  /// 1.0, and is off by default. See `enable_hdr`.
+  pub fn set_bloom(&mut self, enabled: bool, threshold: f32, intensity: f32) {
+    let device = self.device.clone();
+    if let Some(hdr) = self.hdr.as_mut() {
+      if !enabled {
+        if hdr.bloom.take().is_some() {
+          self.framebuffers = None;
+        }
+        return;
+      }
+      match hdr.bloom.as_mut() {
+        Some(bloom) => {
+          bloom.threshold = threshold;
+          bloom.intensity = intensity;
+        }
+        None => {
+          hdr.bloom = Some(Bloom::new(device, threshold, intensity));
+          self.framebuffers = None;
+        }
+      }
+    }
+  }
+
+
+
+  /// Save each of the next `frame_count` presented frames as a numbered PNG
+  /// (`frame_00000.png`, `frame_00001.png`, ...) in `dir`, which is created if
+  /// it doesn't exist. Capturing stops on its own after the last frame. The
+  /// frames can be made into a video with eg.
+  /// `ffmpeg -i frame_%05d.png capture.mp4`.
+  ///
+  /// Each frame is copied off of the GPU at the end of `commit_rendering`,
+  /// which waits for the frame to finish, and is then encoded on a worker
+  /// thread. Returns an error if the directory can't be created or the
+  /// swapchain format isn't 8 bit RGBA or BGRA.
+  pub fn start_capture<P: AsRef<Path>>(&mut self, dir: P, frame_count: u32) -> io::Result<()> {
+    let capture = Capture::new(dir.as_ref(), frame_count, self.swapchain.format())?;
+    self.capture =
+      if capture.is_done() {
+        None
+      } else {
+        Some(capture)
+      };
+    Ok(())
+  }
+
+
+  /// Whether or not frames are still being captured.
+  pub fn is_capturing(&self) -> bool {
+    self.capture.is_some()
+  }
+
+
+  /// Stop capturing early, waiting for the frames captured so far to be
+  /// saved.
+  pub fn stop_capture(&mut self) {
+    self.capture = None;
+  }
+
+
+  /// Cap the frame rate at `fps` frames per second, or remove the cap with
+  /// None. This works with any present mode, eg. to save power while using
+  /// `PresentMode::Immediate`.
+  ///
+  /// The wait happens at the end of `commit_rendering`. It sleeps and then
+  /// spins for the last couple of milliseconds, because `thread::sleep` can
+  /// oversleep by a millisecond or more. See `utils::FrameLimiter`.
+  pub fn set_frame_cap(&mut self, fps: Option<u32>) {
+    self.frame_limiter = fps.map(FrameLimiter::new);
+  }
+
+
+  /// Cap the frame rate at `fps` frames per second while the window is in the
+  /// background, or remove the cap with None, which is the default. The cap
+  /// from `set_frame_cap`, if any, comes back when the window is focused
+  /// again. Focus is tracked from the events given to `handle_event`.
+  pub fn set_unfocused_fps_cap(&mut self, fps: Option<u32>) {
+    self.unfocused_limiter = fps.map(FrameLimiter::new);
+  }
+
+
+  /// Apply one event. Only window focus events are used, see
+  /// `set_unfocused_fps_cap`, so every event can be passed in.
+  pub fn handle_event(&mut self, event: &Event) {
+    match event {
+      Event::Window { win_event: WindowEvent::FocusLost, .. } => {
+        self.focused = false;
+      }
+      Event::Window { win_event: WindowEvent::FocusGained, .. } => {
+        self.focused = true;
+      }
+      _ => {}
+    }
+  }
+
+
+  /// Whether the window has focus, as of the last event given to
+  /// `handle_event`.
+  pub fn is_focused(&self) -> bool {
+    self.focused
+  }
+
+
+  /// Commit a buffer builder to be rendered.
+  pub fn commit_rendering(&mut self) {
+    if self.suspended {
+      return;
+    }
+    // We leave the render pass by calling `end_render_pass`. Note that if we had multiple
+    // subpasses we could have called `next_inline` (or `next_secondary`) to jump to the
+    // next subpass.
+    let mut cmds = self
+      .command_buffer_builder
+      .take().expect("Could not take command_buffer - maybe 'begin_rendering' was not called.")
+      .end_render_pass().expect("Could not end_render_pass.");
+    self.in_render_pass = false;
+    // Soft particles sample the depth buffer, so they are drawn once it is
+    // no longer the depth attachment.
+    if let Some(soft_particles) = self.soft_particles.as_mut() {
+      let image_num = self
+        .image_num
+        .expect("Could not get image_num - maybe 'begin_rendering' was not called before 'commit_rendering'.");
+      cmds = soft_particles.draw(cmds, image_num, &self.dynamic_state);
+    }
+    // With HDR enabled the scene was drawn offscreen, so tonemap it onto the
+    // swapchain image. The tonemap pass always covers the whole framebuffer.
+    let dynamic_state = self.full_dynamic_state();
+    if let Some(hdr) = self.hdr.as_mut() {
+      let image_num = self
+        .image_num
+        .expect("Could not get image_num - maybe 'begin_rendering' was not called before 'commit_rendering'.");
+      cmds = hdr.tonemap(self.device.clone(), cmds, image_num, &dynamic_state);
+    }
+    // While capturing, copy the finished image out before it is presented.
+    let capture_buffer = match self.capture.as_ref() {
+      Some(capture) => {
+        let image_num = self
+          .image_num
+          .expect("Could not get image_num - maybe 'begin_rendering' was not called before 'commit_rendering'.");
+        let buffer = capture.buffer(self.device.clone(), self.dimensions);
+        cmds = cmds
+          .copy_image_to_buffer(self.images[image_num].clone(), buffer.clone())
+          .expect("Could not copy frame to capture buffer.");
+        Some(buffer)
+      }
+      None => None,
+    };
+    let command_buffer = cmds
+      .build().expect("Could not build the command_buffer.");
+
+    if let Some(timer) = self.gpu_timer.as_mut() {
+      timer.begin_frame();
+    }
+    let future = self
+      .previous_frame_end
+      .take().expect("Could not take previous_frame_end.")
+      .join(
+        self
+          .acquire_future
+          .take()
+          .expect("Could not get acquired_future - maybe 'begin_rendering' was not called before 'commit_rendering'.")
+      )
+      .then_execute(self.queue.clone(), command_buffer)
+      .expect("Could not expecute the command_buffer.")
+      // The color output is now expected to contain our geometry. But in order to show it on
+      // the screen, we have to *present* the image by calling `present`.
+      //
+      // This function does not actually present the image immediately. Instead it submits a
       // present command at the end of the queue. This means that it will only be presented once
       // the GPU has finished executing the command buffer that draws the triangle.
       .then_swapchain_present(
@@ -547,9 +2577,28 @@ impl VkRenderer {
           .expect("Could not get image_num - maybe 'begin_rendering' was not called before 'commit_rendering'.")
       )
       .then_signal_fence_and_flush();
+    if let Some(timer) = self.gpu_timer.as_mut() {
+      timer.end_frame();
+    }
 
     match future {
       Ok(future) => {
+        if let Some(buffer) = capture_buffer {
+          future.wait(None).expect("Could not wait for the captured frame.");
+          let dimensions = self.dimensions;
+          let done = self
+            .capture
+            .as_mut()
+            .map(|capture| {
+              capture.save(&buffer, dimensions);
+              capture.is_done()
+            })
+            .unwrap_or(true);
+          if done {
+            // Dropping the capture waits for the last frames to be saved.
+            self.capture = None;
+          }
+        }
         self.previous_frame_end = Some(Box::new(future) as Box<_>);
       }
       Err(vulkano::sync::FlushError::OutOfDate) => {
@@ -562,6 +2611,16 @@ impl VkRenderer {
       }
     }
 
+    let limiter =
+      if self.focused {
+        self.frame_limiter.as_mut()
+      } else {
+        self.unfocused_limiter.as_mut().or(self.frame_limiter.as_mut())
+      };
+    if let Some(limiter) = limiter {
+      limiter.wait();
+    }
+
     // Note that in more complex programs it is likely that one of `acquire_next_image`,
     // `command_buffer::submit`, or `present` will block for some time. This happens when the
     // GPU's queue is full and the driver has to wait until the GPU finished some work.
@@ -572,46 +2631,405 @@ impl VkRenderer {
   }
 
 
-  /// Returns a loaded image and its size.
+  /// Returns a loaded image and its size, panicking if the image can't be
+  /// loaded. See `try_load_texture`.
+  ///
+  /// The texture goes through the upload queue like a queued one, but since
+  /// it is returned it has to be uploaded right away, ignoring the budget of
+  /// `process_uploads`. To spread the uploads of many textures over several
+  /// frames, use `queue_texture` and fetch them with `texture` once they are
+  /// uploaded.
   pub fn load_texture (
     &mut self,
     path: &String,
   ) -> (Arc<ImmutableImage<Format>>, Vector2<u32>) {
+    match self.try_load_texture(path) {
+      Ok(texture) => texture,
+      Err(e) => panic!("{}", e),
+    }
+  }
+
+
+  /// Returns a loaded image and its size, or an error if the file can't be
+  /// read, decoded or uploaded.
+  pub fn try_load_texture (
+    &mut self,
+    path: &String,
+  ) -> Result<(Arc<ImmutableImage<Format>>, Vector2<u32>), TextureError> {
     if self.texture_store.contains_key(path) {
       let stuff = self.texture_store
         .get(path)
         .expect("This should never happen");
-      (stuff.0.clone(), stuff.1)
+      Ok((stuff.0.clone(), stuff.1))
     } else {
-      let dyn_img = image::open(path)
-        .expect(&format!("Could not open image '{}'", path));
-      let width = dyn_img.width();
-      let height = dyn_img.height();
-      println!("Loaded image '{}' is color type {:?}", path, dyn_img.color());
-      let data = dyn_img
-        .to_bgra()
-        .into_raw()
-        .clone();
-
-      let (texture, tex_future) = ImmutableImage::from_iter(
-        data.iter().cloned(),
-        Dimensions::Dim2d {
-          width,
-          height
-        },
-        Format::B8G8R8A8Unorm,
-        self.queue.clone()
-      ).expect("Could not create an immutable image.");
+      // Decode and enqueue it, unless it is already queued, then take it
+      // straight back out of the queue to upload it.
+      self.try_queue_texture(path)?;
+      let upload = self
+        .pending_uploads
+        .iter()
+        .position(|upload| &upload.path == path)
+        .and_then(|ndx| self.pending_uploads.remove(ndx))
+        .expect("A queued texture should be in the queue.");
+      self.upload_pending(upload)
+    }
+  }
+
+
+  /// Decode an image file into BGRA8 texels.
+  fn decode_texture(path: &String) -> Result<PendingUpload, TextureError> {
+    let dyn_img = image::open(path)
+      .map_err(|e| match e {
+        image::ImageError::IoError(e) => TextureError::Io(path.clone(), e),
+        e => TextureError::Decode(path.clone(), e.to_string()),
+      })?;
+    let width = dyn_img.width();
+    let height = dyn_img.height();
+    println!("Loaded image '{}' is color type {:?}", path, dyn_img.color());
+    let data = dyn_img
+      .to_bgra()
+      .into_raw()
+      .clone();
+    Ok(PendingUpload {
+      path: path.clone(),
+      data,
+      width,
+      height,
+    })
+  }
+
+
+  fn upload_pending(
+    &mut self,
+    upload: PendingUpload
+  ) -> Result<(Arc<ImmutableImage<Format>>, Vector2<u32>), TextureError> {
+    let PendingUpload { path, data, width, height } = upload;
+    let texture = self
+      .try_upload_texture(data, width, height, Format::B8G8R8A8Unorm)
+      .map_err(|e| TextureError::Upload(path.clone(), e))?;
+    let dims = glm::vec2(width, height);
+    self.texture_store.insert(path, (texture.clone(), dims));
+    Ok((texture, dims))
+  }
+
+
+  /// Decode a texture now but wait to upload it until `process_uploads`,
+  /// so that streaming many textures doesn't upload them all in one frame.
+  /// Once uploaded, the texture can be fetched with `texture` or
+  /// `load_texture`. Calling `load_texture` on a queued texture uploads it
+  /// right away, ahead of the rest of the queue. Panics if the texture can't be decoded, see
+  /// `try_queue_texture`.
+  pub fn queue_texture(&mut self, path: &String) {
+    if let Err(e) = self.try_queue_texture(path) {
+      panic!("{}", e);
+    }
+  }
+
+
+  /// Like `queue_texture`, but returns an error if the texture can't be read
+  /// or decoded instead of panicking.
+  pub fn try_queue_texture(&mut self, path: &String) -> Result<(), TextureError> {
+    let known = self.texture_store.contains_key(path)
+      || self.pending_uploads.iter().any(|upload| &upload.path == path);
+    if !known {
+      let upload = VkRenderer::decode_texture(path)?;
+      self.pending_uploads.push_back(upload);
+    }
+    Ok(())
+  }
+
+
+  /// Upload at most `max_per_frame` queued textures, in the order they were
+  /// queued. Call this once per frame. Returns the number uploaded. Panics if
+  /// an upload fails, see `try_process_uploads`.
+  pub fn process_uploads(&mut self, max_per_frame: usize) -> usize {
+    match self.try_process_uploads(max_per_frame) {
+      Ok(uploaded) => uploaded,
+      Err(e) => panic!("{}", e),
+    }
+  }
+
+
+  /// Like `process_uploads`, but stops at the first texture that can't be
+  /// uploaded and returns its error. That texture is dropped from the queue,
+  /// the rest stay queued.
+  pub fn try_process_uploads(&mut self, max_per_frame: usize) -> Result<usize, TextureError> {
+    let mut uploaded = 0;
+    while uploaded < max_per_frame {
+      match self.pending_uploads.pop_front() {
+        Some(upload) => {
+          self.upload_pending(upload)?;
+          uploaded += 1;
+        }
+        None => break,
+      }
+    }
+    Ok(uploaded)
+  }
+
+
+  /// The number of queued textures that haven't been uploaded yet.
+  pub fn pending_upload_count(&self) -> usize {
+    self.pending_uploads.len()
+  }
+
+
+  /// A texture that has already been uploaded, and its size.
+  pub fn texture(&self, path: &str) -> Option<(Arc<ImmutableImage<Format>>, Vector2<u32>)> {
+    self
+      .texture_store
+      .get(path)
+      .map(|(texture, dims)| (texture.clone(), *dims))
+  }
+
+
+  /// Returns the texture stored under `key`, or generates and uploads it
+  /// under that key, for textures that don't come from a file. See
+  /// `textures::procedural`.
+  pub(crate) fn load_generated_texture<F>(
+    &mut self,
+    key: String,
+    generate: F
+  ) -> (Arc<ImmutableImage<Format>>, Vector2<u32>)
+    where F: FnOnce() -> image::RgbaImage
+  {
+    if let Some(texture) = self.texture(&key) {
+      return texture;
+    }
+    let rgba = generate();
+    let (width, height) = rgba.dimensions();
+    let data = image::DynamicImage::ImageRgba8(rgba)
+      .to_bgra()
+      .into_raw();
+    match self.upload_pending(PendingUpload { path: key, data, width, height }) {
+      Ok(texture) => texture,
+      Err(e) => panic!("{}", e),
+    }
+  }
+
+
+  /// Returns a loaded block compressed DDS texture (BC1, BC2, BC3 or BC7)
+  /// and its size, panicking if it can't be loaded. See
+  /// `try_load_compressed_texture`.
+  pub fn load_compressed_texture (
+    &mut self,
+    path: &String,
+  ) -> (Arc<ImmutableImage<Format>>, Vector2<u32>) {
+    match self.try_load_compressed_texture(path) {
+      Ok(texture) => texture,
+      Err(e) => panic!("{}", e),
+    }
+  }
 
-      let prev_future = self.previous_frame_end.take();
-      if let Some(future) = prev_future {
-        self.previous_frame_end = Some(Box::new(future.join(tex_future)));
+
+  /// Returns a loaded block compressed DDS texture (BC1, BC2, BC3 or BC7)
+  /// and its size. The compressed blocks are uploaded as they are, without
+  /// decoding, so the texture takes a quarter (BC3, BC7) or an eighth (BC1) of
+  /// the memory of an uncompressed one. Only the top mip level is loaded.
+  ///
+  /// If the device doesn't support the `texture_compression_bc` feature, BC1,
+  /// BC2 and BC3 textures are decoded to RGBA in software instead. BC7 is not,
+  /// so loading a BC7 texture on such a device returns a decode error.
+  pub fn try_load_compressed_texture (
+    &mut self,
+    path: &String,
+  ) -> Result<(Arc<ImmutableImage<Format>>, Vector2<u32>), TextureError> {
+    if let Some(stuff) = self.texture_store.get(path) {
+      return Ok((stuff.0.clone(), stuff.1));
+    }
+
+    let bytes = fs::read(path)
+      .map_err(|e| TextureError::Io(path.clone(), e))?;
+    let dds = Dds::parse(&bytes)
+      .map_err(|e| TextureError::Decode(path.clone(), e.to_string()))?;
+
+    let texture =
+      if self.device.enabled_features().texture_compression_bc {
+        // vulkano checks the length of the staging buffer against the number
+        // of texels times the format's block size, rather than the number of
+        // blocks, so the staging buffer has to be padded up to that length.
+        // Only the blocks are copied into the image.
+        let format = dds.vulkan_format();
+        let texels = (dds.width as usize) * (dds.height as usize);
+        let mut data = dds.blocks.clone();
+        data.resize(data.len().max(texels * dds.format.block_size()), 0);
+        self.try_upload_texture(data, dds.width, dds.height, format)
       } else {
-        self.previous_frame_end = Some(Box::new(tex_future));
+        println!("Device can't sample BC textures, decoding '{}' in software.", path);
+        let data = dds
+          .decode_rgba()
+          .ok_or_else(|| {
+            TextureError::Decode(path.clone(), "the device doesn't support BC7".to_string())
+          })?;
+        let format = if dds.srgb { Format::R8G8B8A8Srgb } else { Format::R8G8B8A8Unorm };
+        self.try_upload_texture(data, dds.width, dds.height, format)
       }
+      .map_err(|e| TextureError::Upload(path.clone(), e))?;
+
+    let dims = glm::vec2(dds.width, dds.height);
+    self.texture_store.insert(path.clone(), (texture.clone(), dims));
+    Ok((texture, dims))
+  }
+
 
-      let dims = glm::vec2(width, height);
-      self.texture_store.insert(path.clone(), (texture.clone(), dims));
-      (texture, dims)
+  /// The float format HDR textures are uploaded in: `R32G32B32A32Sfloat` if
+  /// the device can sample it with linear filtering, otherwise
+  /// `R16G16B16A16Sfloat` if it can sample that, otherwise None.
+  pub fn hdr_texture_format(&self) -> Option<Format> {
+    let full = self.format_properties(Format::R32G32B32A32Sfloat).optimal_tiling;
+    if full.sampled_image && full.sampled_image_filter_linear {
+      Some(Format::R32G32B32A32Sfloat)
+    } else if self.format_supported(Format::R16G16B16A16Sfloat, FormatUsage::Sampled) {
+      Some(Format::R16G16B16A16Sfloat)
+    } else {
+      None
     }
-  }}
+  }
+
+
+  /// Returns a loaded Radiance `.hdr` image and its size, panicking if it
+  /// can't be loaded. See `try_load_hdr_texture`.
+  pub fn load_hdr_texture (
+    &mut self,
+    path: &String,
+  ) -> (Arc<ImmutableImage<Format>>, Vector2<u32>) {
+    match self.try_load_hdr_texture(path) {
+      Ok(texture) => texture,
+      Err(e) => panic!("{}", e),
+    }
+  }
+
+
+  /// Returns a loaded Radiance `.hdr` image and its size. The texels are
+  /// linear floats that aren't clamped to [0, 1], in the
+  /// `hdr_texture_format`. `load_texture` can open `.hdr` files too, but
+  /// clamps them to 8 bits.
+  ///
+  /// Returns an error if the file can't be read or decoded, or the device
+  /// can't sample either float format. OpenEXR isn't supported.
+  pub fn try_load_hdr_texture (
+    &mut self,
+    path: &String,
+  ) -> Result<(Arc<ImmutableImage<Format>>, Vector2<u32>), TextureError> {
+    if let Some(stuff) = self.texture_store.get(path) {
+      return Ok((stuff.0.clone(), stuff.1));
+    }
+    let image = VkRenderer::decode_hdr(path)?;
+    let (width, height) = (image.width, image.height);
+    let texture = self.try_upload_hdr(path, image.texels, Dimensions::Dim2d { width, height })?;
+    let dims = glm::vec2(width, height);
+    self.texture_store.insert(path.clone(), (texture.clone(), dims));
+    Ok((texture, dims))
+  }
+
+
+  /// Load an equirectangular Radiance `.hdr` environment map, eg. a sky
+  /// panorama, as a cubemap with `face_size` x `face_size` faces for
+  /// `Background::Skybox`. The faces are resampled on the CPU with
+  /// `textures::hdr::equirect_to_cubemap`, and aren't kept in the texture
+  /// store. Returns the same errors as `try_load_hdr_texture`.
+  pub fn try_load_hdr_cubemap (
+    &mut self,
+    path: &String,
+    face_size: u32
+  ) -> Result<Arc<ImmutableImage<Format>>, TextureError> {
+    let image = VkRenderer::decode_hdr(path)?;
+    let texels = equirect_to_cubemap(&image, face_size);
+    self.try_upload_hdr(path, texels, Dimensions::Cubemap { size: face_size })
+  }
+
+
+  fn decode_hdr(path: &String) -> Result<HdrImage, TextureError> {
+    let bytes = fs::read(path)
+      .map_err(|e| TextureError::Io(path.clone(), e))?;
+    HdrImage::parse(&bytes)
+      .map_err(|e| TextureError::Decode(path.clone(), e.to_string()))
+  }
+
+
+  /// Upload float texels in the `hdr_texture_format`.
+  fn try_upload_hdr(
+    &mut self,
+    path: &String,
+    texels: Vec<[f32; 4]>,
+    dimensions: Dimensions
+  ) -> Result<Arc<ImmutableImage<Format>>, TextureError> {
+    let format = self
+      .hdr_texture_format()
+      .ok_or_else(|| TextureError::UnsupportedFormat(path.clone(), Format::R16G16B16A16Sfloat))?;
+    let uploaded =
+      if format == Format::R32G32B32A32Sfloat {
+        let data = texels.iter().flat_map(|texel| texel.iter().cloned()).collect::<Vec<f32>>();
+        self.try_upload_image(data, dimensions, format)
+      } else {
+        let data = texels
+          .iter()
+          .flat_map(|texel| texel.iter().map(|c| f32_to_f16(*c)))
+          .collect::<Vec<u16>>();
+        self.try_upload_image(data, dimensions, format)
+      };
+    uploaded.map_err(|e| TextureError::Upload(path.clone(), e))
+  }
+
+
+  /// Upload texel data to a new image, joining the upload with the previous
+  /// frame's future.
+  pub(crate) fn upload_texture(
+    &mut self,
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    format: Format
+  ) -> Arc<ImmutableImage<Format>> {
+    self
+      .try_upload_texture(data, width, height, format)
+      .expect("Could not create an immutable image.")
+  }
+
+
+  /// Like `upload_texture`, but returns an error if the image can't be
+  /// created.
+  pub(crate) fn try_upload_texture<P>(
+    &mut self,
+    data: Vec<P>,
+    width: u32,
+    height: u32,
+    format: Format
+  ) -> Result<Arc<ImmutableImage<Format>>, ImageCreationError>
+    where
+      P: Send + Sync + Clone + 'static,
+      Format: AcceptsPixels<P>
+  {
+    self.try_upload_image(data, Dimensions::Dim2d { width, height }, format)
+  }
+
+
+  /// Like `try_upload_texture`, for images of any dimensions, eg. cubemaps.
+  fn try_upload_image<P>(
+    &mut self,
+    data: Vec<P>,
+    dimensions: Dimensions,
+    format: Format
+  ) -> Result<Arc<ImmutableImage<Format>>, ImageCreationError>
+    where
+      P: Send + Sync + Clone + 'static,
+      Format: AcceptsPixels<P>
+  {
+    let (texture, tex_future) = ImmutableImage::from_iter(
+      data.into_iter(),
+      dimensions,
+      format,
+      self.queue.clone()
+    )?;
+
+    self.record_buffer_upload();
+    let prev_future = self.previous_frame_end.take();
+    if let Some(future) = prev_future {
+      self.previous_frame_end = Some(Box::new(future.join(tex_future)));
+    } else {
+      self.previous_frame_end = Some(Box::new(tex_future));
+    }
+
+    Ok(texture)
+  }
+}
@@ -0,0 +1,65 @@
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::image::ImageViewAccess;
+
+use std::sync::Arc;
+
+
+/// The most extra attachments `VkRenderer::build_framebuffers` accepts.
+pub const MAX_EXTRA_ATTACHMENTS: usize = 4;
+
+
+/// Build a framebuffer with a color attachment, a depth attachment and then
+/// each of the extra attachments, in that order.
+///
+/// vulkano's framebuffer builder changes type with every attachment, so the
+/// extra attachments can't be added in a loop. Instead each supported count
+/// gets its own chain.
+pub(crate) fn framebuffer<C>(
+  render_pass: Arc<RenderPassAbstract + Send + Sync>,
+  color: C,
+  depth_buffer: Arc<AttachmentImage>,
+  extra_attachments: &[Arc<ImageViewAccess + Send + Sync>]
+) -> Arc<FramebufferAbstract + Send + Sync>
+  where C: ImageViewAccess + Send + Sync + 'static
+{
+  let builder = Framebuffer::start(render_pass)
+    .add(color).expect("Could not add image to framebuffer.")
+    .add(depth_buffer).expect("Could not add depth buffer to framebuffer.");
+  let extra = |ndx: usize| extra_attachments[ndx].clone();
+  let msg = "Could not add extra attachment to framebuffer.";
+  match extra_attachments.len() {
+    0 => Arc::new(builder.build().expect("Could not build new framebuffer.")),
+    1 => Arc::new(
+      builder
+        .add(extra(0)).expect(msg)
+        .build().expect("Could not build new framebuffer.")
+    ),
+    2 => Arc::new(
+      builder
+        .add(extra(0)).expect(msg)
+        .add(extra(1)).expect(msg)
+        .build().expect("Could not build new framebuffer.")
+    ),
+    3 => Arc::new(
+      builder
+        .add(extra(0)).expect(msg)
+        .add(extra(1)).expect(msg)
+        .add(extra(2)).expect(msg)
+        .build().expect("Could not build new framebuffer.")
+    ),
+    4 => Arc::new(
+      builder
+        .add(extra(0)).expect(msg)
+        .add(extra(1)).expect(msg)
+        .add(extra(2)).expect(msg)
+        .add(extra(3)).expect(msg)
+        .build().expect("Could not build new framebuffer.")
+    ),
+    n => panic!(
+      "{} extra attachments were given but at most {} are supported.",
+      n,
+      MAX_EXTRA_ATTACHMENTS
+    ),
+  }
+}
@@ -0,0 +1,173 @@
+use vulkano::buffer::BufferAccess;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::Device;
+use vulkano::format::ClearValue;
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::pipeline::viewport::{Scissor, Viewport};
+
+use std::sync::Arc;
+
+use super::super::pipelines::bloom::{blur_fs, threshold_fs, BloomPipeline};
+use super::hdr::HDR_FORMAT;
+
+
+/// Bloom, drawn from the HDR image between the scene pass and tonemapping.
+///
+/// The bright parts of the scene are drawn into a half size image, blurred
+/// horizontally into a second image and then vertically back into the first,
+/// which the tonemap pass adds to the scene.
+pub(crate) struct Bloom {
+  render_pass: Arc<RenderPassAbstract + Send + Sync>,
+  pipeline: BloomPipeline,
+  bright_framebuffer: Option<Arc<FramebufferAbstract + Send + Sync>>,
+  blurred_framebuffer: Option<Arc<FramebufferAbstract + Send + Sync>>,
+  threshold_set: Option<Arc<DescriptorSet + Send + Sync>>,
+  horizontal_set: Option<Arc<DescriptorSet + Send + Sync>>,
+  vertical_set: Option<Arc<DescriptorSet + Send + Sync>>,
+  dynamic_state: DynamicState,
+  /// How bright a pixel must be to bloom. Pixels brighter than 1.0 are the
+  /// ones that tonemapping would otherwise compress.
+  pub threshold: f32,
+  /// How much of the bloom is added to the scene.
+  pub intensity: f32,
+}
+
+
+impl Bloom {
+  pub fn new(device: Arc<Device>, threshold: f32, intensity: f32) -> Bloom {
+    let render_pass = Arc::new(
+      single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+          // Every pixel is overwritten.
+          color: {
+            load: DontCare,
+            store: Store,
+            format: HDR_FORMAT,
+            samples: 1,
+          }
+        },
+        pass: {
+          color: [color],
+          depth_stencil: {}
+        }
+      ).expect("Could not create bloom render pass.")
+    ) as Arc<RenderPassAbstract + Send + Sync>;
+    let pipeline = BloomPipeline::new(render_pass.clone(), device);
+
+    Bloom {
+      render_pass,
+      pipeline,
+      bright_framebuffer: None,
+      blurred_framebuffer: None,
+      threshold_set: None,
+      horizontal_set: None,
+      vertical_set: None,
+      dynamic_state: DynamicState::none(),
+      threshold,
+      intensity,
+    }
+  }
+
+
+  /// Recreate the bloom images for a framebuffer of the given size, returning
+  /// the blurred bloom for the tonemap pass to sample.
+  pub fn resize(
+    &mut self,
+    device: Arc<Device>,
+    dimensions: [u32; 2],
+    hdr_image: Arc<AttachmentImage>
+  ) -> Arc<AttachmentImage> {
+    let size = [(dimensions[0] / 2).max(1), (dimensions[1] / 2).max(1)];
+    let bright = AttachmentImage::sampled(device.clone(), size, HDR_FORMAT)
+      .expect("Could not create bloom image.");
+    let blurred = AttachmentImage::sampled(device, size, HDR_FORMAT)
+      .expect("Could not create blurred bloom image.");
+
+    self.bright_framebuffer = Some(Arc::new(
+      Framebuffer::start(self.render_pass.clone())
+        .add(bright.clone()).expect("Could not add bloom image to framebuffer.")
+        .build().expect("Could not build bloom framebuffer.")
+    ));
+    self.blurred_framebuffer = Some(Arc::new(
+      Framebuffer::start(self.render_pass.clone())
+        .add(blurred.clone()).expect("Could not add blurred bloom image to framebuffer.")
+        .build().expect("Could not build blurred bloom framebuffer.")
+    ));
+    let sampler = self.pipeline.sampler.clone();
+    self.threshold_set = Some(Arc::new(
+      PersistentDescriptorSet::start(self.pipeline.threshold_pipeline.clone(), 0)
+        .add_sampled_image(hdr_image, sampler.clone()).expect("Could not add hdr image.")
+        .build()
+        .expect("Could not build bloom threshold set.")
+    ));
+    self.horizontal_set = Some(Arc::new(
+      PersistentDescriptorSet::start(self.pipeline.blur_pipeline.clone(), 0)
+        .add_sampled_image(bright.clone(), sampler.clone()).expect("Could not add bloom image.")
+        .build()
+        .expect("Could not build horizontal blur set.")
+    ));
+    self.vertical_set = Some(Arc::new(
+      PersistentDescriptorSet::start(self.pipeline.blur_pipeline.clone(), 0)
+        .add_sampled_image(blurred, sampler).expect("Could not add blurred bloom image.")
+        .build()
+        .expect("Could not build vertical blur set.")
+    ));
+    self.dynamic_state = DynamicState {
+      line_width: None,
+      viewports: Some(vec![Viewport {
+        origin: [0.0, 0.0],
+        dimensions: [size[0] as f32, size[1] as f32],
+        depth_range: 0.0 .. 1.0,
+      }]),
+      scissors: Some(vec![Scissor {
+        origin: [0, 0],
+        dimensions: size,
+      }]),
+    };
+    bright
+  }
+
+
+  /// Record the threshold and blur passes. This must be recorded after the
+  /// scene render pass has ended.
+  pub fn bloom(&self, cmds: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder {
+    let expect_msg = "Could not get bloom images - maybe the framebuffers were not created.";
+    let bright_framebuffer = self.bright_framebuffer.clone().expect(expect_msg);
+    let blurred_framebuffer = self.blurred_framebuffer.clone().expect(expect_msg);
+    let vertex_buffer = self.pipeline.vertex_buffer.clone() as Arc<BufferAccess + Send + Sync>;
+    cmds
+      .begin_render_pass(bright_framebuffer.clone(), false, vec![ClearValue::None])
+      .expect("Could not begin bloom threshold render pass.")
+      .draw(
+        self.pipeline.threshold_pipeline.clone(),
+        &self.dynamic_state,
+        vec![vertex_buffer.clone()],
+        self.threshold_set.clone().expect(expect_msg),
+        threshold_fs::ty::Threshold { threshold: self.threshold }
+      ).expect("Could not draw bloom threshold pass.")
+      .end_render_pass().expect("Could not end bloom threshold render pass.")
+      .begin_render_pass(blurred_framebuffer, false, vec![ClearValue::None])
+      .expect("Could not begin horizontal blur render pass.")
+      .draw(
+        self.pipeline.blur_pipeline.clone(),
+        &self.dynamic_state,
+        vec![vertex_buffer.clone()],
+        self.horizontal_set.clone().expect(expect_msg),
+        blur_fs::ty::Blur { direction: [1.0, 0.0] }
+      ).expect("Could not draw horizontal blur pass.")
+      .end_render_pass().expect("Could not end horizontal blur render pass.")
+      .begin_render_pass(bright_framebuffer, false, vec![ClearValue::None])
+      .expect("Could not begin vertical blur render pass.")
+      .draw(
+        self.pipeline.blur_pipeline.clone(),
+        &self.dynamic_state,
+        vec![vertex_buffer],
+        self.vertical_set.clone().expect(expect_msg),
+        blur_fs::ty::Blur { direction: [0.0, 1.0] }
+      ).expect("Could not draw vertical blur pass.")
+      .end_render_pass().expect("Could not end vertical blur render pass.")
+  }
+}
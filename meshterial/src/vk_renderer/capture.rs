@@ -0,0 +1,126 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+use vulkano::format::Format;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::{self, JoinHandle};
+
+
+/// One captured frame, waiting to be encoded.
+struct CapturedFrame {
+  path: PathBuf,
+  rgba: Vec<u8>,
+  dimensions: [u32; 2],
+}
+
+
+/// Saves presented frames as numbered PNGs. See `VkRenderer::start_capture`.
+///
+/// Frames are copied out of the swapchain on the render thread and encoded on
+/// a worker thread, so only the copy stalls rendering.
+pub(crate) struct Capture {
+  dir: PathBuf,
+  /// Whether the swapchain's pixels are BGRA and need to be swizzled.
+  bgra: bool,
+  frames_left: u32,
+  next_frame: u32,
+  sender: Option<Sender<CapturedFrame>>,
+  worker: Option<JoinHandle<()>>,
+}
+
+
+impl Capture {
+  pub fn new(dir: &Path, frame_count: u32, format: Format) -> io::Result<Capture> {
+    let bgra = match format {
+      Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => true,
+      Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb => false,
+      format => {
+        return Err(io::Error::new(
+          io::ErrorKind::Other,
+          format!("swapchain format {:?} can't be captured", format)
+        ));
+      }
+    };
+    fs::create_dir_all(dir)?;
+
+    let (sender, receiver) = channel::<CapturedFrame>();
+    let worker = thread::spawn(move || {
+      for frame in receiver {
+        let result = image::save_buffer(
+          &frame.path,
+          &frame.rgba,
+          frame.dimensions[0],
+          frame.dimensions[1],
+          image::ColorType::RGBA(8)
+        );
+        if let Err(e) = result {
+          println!("Could not save captured frame {:?}: {}", frame.path, e);
+        }
+      }
+    });
+
+    Ok(Capture {
+      dir: dir.to_path_buf(),
+      bgra,
+      frames_left: frame_count,
+      next_frame: 0,
+      sender: Some(sender),
+      worker: Some(worker),
+    })
+  }
+
+
+  pub fn is_done(&self) -> bool {
+    self.frames_left == 0
+  }
+
+
+  /// A buffer big enough to copy one swapchain image into.
+  pub fn buffer(&self, device: Arc<Device>, dimensions: [u32; 2]) -> Arc<CpuAccessibleBuffer<[u8]>> {
+    let len = dimensions[0] as usize * dimensions[1] as usize * 4;
+    CpuAccessibleBuffer::from_iter(
+      device,
+      BufferUsage::all(),
+      (0 .. len).map(|_| 0u8)
+    ).expect("Could not create capture buffer.")
+  }
+
+
+  /// Send a copied frame to the worker thread. The GPU must be done writing
+  /// to the buffer.
+  pub fn save(&mut self, buffer: &CpuAccessibleBuffer<[u8]>, dimensions: [u32; 2]) {
+    if self.is_done() {
+      return;
+    }
+    let mut rgba = buffer
+      .read()
+      .expect("Could not read capture buffer.")
+      .to_vec();
+    if self.bgra {
+      for texel in rgba.chunks_mut(4) {
+        texel.swap(0, 2);
+      }
+    }
+    let path = self.dir.join(format!("frame_{:05}.png", self.next_frame));
+    if let Some(sender) = self.sender.as_ref() {
+      let _ = sender.send(CapturedFrame { path, rgba, dimensions });
+    }
+    self.next_frame += 1;
+    self.frames_left -= 1;
+  }
+}
+
+
+impl Drop for Capture {
+  /// Wait for the worker to finish encoding the frames it was sent.
+  fn drop(&mut self) {
+    self.sender.take();
+    if let Some(worker) = self.worker.take() {
+      let _ = worker.join();
+    }
+  }
+}
@@ -0,0 +1,90 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::buffer::cpu_pool::CpuBufferPool;
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::Device;
+use vulkano::framebuffer::RenderPassAbstract;
+use vulkano::pipeline::GraphicsPipelineAbstract;
+use nalgebra::Matrix4;
+
+use std::sync::Arc;
+
+use super::super::debug::DebugDraw;
+use super::super::pipelines::color3d::VertexColor3;
+use super::super::pipelines::clip::{no_clip_buffer, ClipPlaneUniform};
+use super::super::pipelines::fog::{no_fog_buffer, FogUniform};
+use super::super::pipelines::lines::Line3DPipeline;
+
+
+/// The pipeline and buffers `VkRenderer::flush_debug` draws with.
+///
+/// The projection comes from a host visible buffer so that it can change in
+/// the middle of a render pass without a copy.
+pub(crate) struct DebugRenderer {
+  pub draw: DebugDraw,
+  pipeline: Option<Line3DPipeline>,
+  proj_buffer_pool: CpuBufferPool<Matrix4<f32>>,
+  no_fog: Arc<CpuAccessibleBuffer<FogUniform>>,
+  no_clip: Arc<CpuAccessibleBuffer<ClipPlaneUniform>>,
+}
+
+
+impl DebugRenderer {
+  pub fn new(device: Arc<Device>) -> DebugRenderer {
+    DebugRenderer {
+      draw: DebugDraw::new(),
+      pipeline: None,
+      proj_buffer_pool: CpuBufferPool::uniform_buffer(device.clone()),
+      no_fog: no_fog_buffer(device.clone()),
+      no_clip: no_clip_buffer(device),
+    }
+  }
+
+
+  /// Forget the pipeline, eg. after the render pass has been replaced.
+  pub fn reset_pipeline(&mut self) {
+    self.pipeline = None;
+  }
+
+
+  /// The pipeline, its projection set and the vertex buffer of everything
+  /// queued, which is then cleared. Returns None if nothing was queued.
+  pub fn flush(
+    &mut self,
+    device: Arc<Device>,
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    projection: Matrix4<f32>,
+    vertices: Vec<VertexColor3>
+  ) -> Option<(
+    Arc<GraphicsPipelineAbstract + Send + Sync>,
+    Arc<DescriptorSet + Send + Sync>,
+    Arc<CpuAccessibleBuffer<[VertexColor3]>>
+  )> {
+    self.draw.clear();
+    if vertices.is_empty() {
+      return None;
+    }
+    let vertex_buffer = CpuAccessibleBuffer::from_iter(
+      device.clone(),
+      BufferUsage::vertex_buffer(),
+      vertices.into_iter()
+    ).expect("Could not create debug vertex buffer.");
+    let proj = self
+      .proj_buffer_pool
+      .next(projection)
+      .expect("Could not create debug projection buffer.");
+    if self.pipeline.is_none() {
+      let physical = device.physical_device();
+      self.pipeline = Some(Line3DPipeline::new(render_pass, device.clone(), physical));
+    }
+    let pipeline = self.pipeline.as_ref().expect("This should never happen.");
+    let set = Arc::new(
+      PersistentDescriptorSet::start(pipeline.pipeline.clone(), 0)
+        .add_buffer(proj).expect("Could not add debug projection buffer.")
+        .add_buffer(self.no_fog.clone()).expect("Could not add fog buffer.")
+        .add_buffer(self.no_clip.clone()).expect("Could not add clip plane buffer.")
+        .build()
+        .expect("Could not build debug projection set.")
+    );
+    Some((pipeline.pipeline.clone(), set, vertex_buffer))
+  }
+}
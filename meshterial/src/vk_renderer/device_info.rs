@@ -0,0 +1,85 @@
+use vulkano::device::Device;
+use vulkano::instance::{PhysicalDeviceType, Version};
+
+use std::fmt;
+
+
+/// The name, type and driver of a device, and the limits and features that
+/// the built-in pipelines care about. See `VkRenderer::device_info`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+  pub name: String,
+  pub ty: PhysicalDeviceType,
+  pub api_version: Version,
+  pub driver_version: u32,
+
+  pub max_image_dimension_2d: u32,
+  pub max_push_constants_size: u32,
+  pub max_bound_descriptor_sets: u32,
+  pub max_sampler_anisotropy: f32,
+  pub line_width_range: [f32; 2],
+
+  /// Needed for line widths other than 1.0.
+  pub wide_lines: bool,
+  /// Needed for wireframe and point fill modes.
+  pub fill_mode_non_solid: bool,
+  /// Needed for SamplerConfig::max_anisotropy.
+  pub sampler_anisotropy: bool,
+  pub geometry_shader: bool,
+  /// Needed to upload BC compressed textures without decoding them.
+  pub texture_compression_bc: bool,
+}
+
+
+impl DeviceInfo {
+  /// Query the info of a device. Features are the ones that were enabled when
+  /// the device was created.
+  pub fn new(device: &Device) -> DeviceInfo {
+    let physical = device.physical_device();
+    let limits = physical.limits();
+    let features = device.enabled_features();
+    DeviceInfo {
+      name: physical.name(),
+      ty: physical.ty(),
+      api_version: physical.api_version(),
+      driver_version: physical.driver_version(),
+
+      max_image_dimension_2d: limits.max_image_dimension_2d(),
+      max_push_constants_size: limits.max_push_constants_size(),
+      max_bound_descriptor_sets: limits.max_bound_descriptor_sets(),
+      max_sampler_anisotropy: limits.max_sampler_anisotropy(),
+      line_width_range: limits.line_width_range(),
+
+      wide_lines: features.wide_lines,
+      fill_mode_non_solid: features.fill_mode_non_solid,
+      sampler_anisotropy: features.sampler_anisotropy,
+      geometry_shader: features.geometry_shader,
+      texture_compression_bc: features.texture_compression_bc,
+    }
+  }
+}
+
+
+impl fmt::Display for DeviceInfo {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "{} ({:?})", self.name, self.ty)?;
+    writeln!(
+      f,
+      "  vulkan {}.{}.{}, driver {}",
+      self.api_version.major,
+      self.api_version.minor,
+      self.api_version.patch,
+      self.driver_version
+    )?;
+    writeln!(f, "  max 2d image dimension: {}", self.max_image_dimension_2d)?;
+    writeln!(f, "  max push constants size: {}", self.max_push_constants_size)?;
+    writeln!(f, "  max bound descriptor sets: {}", self.max_bound_descriptor_sets)?;
+    writeln!(f, "  max sampler anisotropy: {}", self.max_sampler_anisotropy)?;
+    writeln!(f, "  line width range: {:?}", self.line_width_range)?;
+    writeln!(f, "  wide_lines: {}", self.wide_lines)?;
+    writeln!(f, "  fill_mode_non_solid: {}", self.fill_mode_non_solid)?;
+    writeln!(f, "  sampler_anisotropy: {}", self.sampler_anisotropy)?;
+    writeln!(f, "  geometry_shader: {}", self.geometry_shader)?;
+    write!(f, "  texture_compression_bc: {}", self.texture_compression_bc)
+  }
+}
@@ -0,0 +1,169 @@
+use vulkano::device::DeviceCreationError;
+use vulkano::format::Format;
+use vulkano::framebuffer::RenderPassCreationError;
+use vulkano::image::ImageCreationError;
+use vulkano::instance::InstanceCreationError;
+use vulkano::swapchain::{CapabilitiesError, SwapchainCreationError};
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+
+/// An error creating a VkRenderer.
+#[derive(Debug)]
+pub enum VkRendererError {
+  /// sdl2 could not create the window, or the vulkan surface for it.
+  Sdl(String),
+  Instance(InstanceCreationError),
+  NoPhysicalDevice,
+  /// No queue family can both draw and present to the window.
+  NoQueueFamily,
+  Device(DeviceCreationError),
+  Capabilities(CapabilitiesError),
+  Swapchain(SwapchainCreationError),
+  RenderPass(RenderPassCreationError),
+  /// The device can't render to or sample images of the format.
+  UnsupportedFormat(Format),
+  /// A viewport depth range isn't within `0.0 ..= 1.0` with its min below its
+  /// max.
+  InvalidDepthRange(f32, f32),
+  /// The number of clear values given to `begin_rendering_with_clear_values`
+  /// matches neither the number of attachments loaded with `Clear` nor the
+  /// number of attachments.
+  ClearValueCount { cleared: usize, attachments: usize, given: usize },
+  /// The attachment with this index is cleared, but was given
+  /// `ClearValue::None`.
+  MissingClearValue(usize),
+  /// A command that must be recorded inside the render pass was called
+  /// outside of `begin_rendering` and `commit_rendering`.
+  NotInRenderPass,
+  /// The render pass has no depth attachment to clear.
+  NoDepthAttachment,
+  /// The `StaticCommands` were recorded for framebuffers that have since
+  /// been rebuilt, and must be recorded again.
+  StaleStaticCommands,
+  /// The extra attachment with this index given to `build_framebuffers` is
+  /// not the size of the swapchain.
+  AttachmentSize { index: usize, expected: [u32; 2], actual: [u32; 2] },
+  /// There are no framebuffers to render to, because the window was resized
+  /// and the extra attachments given to `build_framebuffers` are the old
+  /// size.
+  NoFramebuffers,
+}
+
+
+impl fmt::Display for VkRendererError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      VkRendererError::Sdl(e) => write!(f, "sdl2 error: {}", e),
+      VkRendererError::Instance(e) => write!(f, "could not create vulkan instance: {}", e),
+      VkRendererError::NoPhysicalDevice => write!(f, "no physical device available"),
+      VkRendererError::NoQueueFamily => write!(f, "could not find a graphical queue family"),
+      VkRendererError::Device(e) => write!(f, "could not create device: {}", e),
+      VkRendererError::Capabilities(e) => write!(f, "could not get surface capabilities: {}", e),
+      VkRendererError::Swapchain(e) => write!(f, "could not create swapchain: {}", e),
+      VkRendererError::RenderPass(e) => write!(f, "could not create render pass: {}", e),
+      VkRendererError::UnsupportedFormat(format) => {
+        write!(f, "format {:?} is not supported as a sampled color attachment", format)
+      }
+      VkRendererError::InvalidDepthRange(min, max) => {
+        write!(f, "depth range {} .. {} is not within 0.0 .. 1.0", min, max)
+      }
+      VkRendererError::ClearValueCount { cleared, attachments, given } => {
+        write!(
+          f,
+          "expected {} clear values, one for each attachment loaded with Clear, or {}, one for every attachment, but was given {} - see begin_rendering_with_clear_values",
+          cleared,
+          attachments,
+          given
+        )
+      }
+      VkRendererError::MissingClearValue(ndx) => {
+        write!(f, "attachment {} is cleared but has no clear value", ndx)
+      }
+      VkRendererError::NotInRenderPass => {
+        write!(f, "this must be called between begin_rendering and commit_rendering")
+      }
+      VkRendererError::NoDepthAttachment => write!(f, "the render pass has no depth attachment"),
+      VkRendererError::StaleStaticCommands => {
+        write!(f, "static commands were recorded for framebuffers that have been rebuilt")
+      }
+      VkRendererError::AttachmentSize { index, expected, actual } => {
+        write!(f, "extra attachment {} is {:?} but the framebuffers are {:?}", index, actual, expected)
+      }
+      VkRendererError::NoFramebuffers => {
+        write!(f, "the window was resized - call build_framebuffers with extra attachments of the new size")
+      }
+    }
+  }
+}
+
+
+impl Error for VkRendererError {}
+
+
+impl From<InstanceCreationError> for VkRendererError {
+  fn from(e: InstanceCreationError) -> VkRendererError {
+    VkRendererError::Instance(e)
+  }
+}
+
+
+impl From<DeviceCreationError> for VkRendererError {
+  fn from(e: DeviceCreationError) -> VkRendererError {
+    VkRendererError::Device(e)
+  }
+}
+
+
+impl From<CapabilitiesError> for VkRendererError {
+  fn from(e: CapabilitiesError) -> VkRendererError {
+    VkRendererError::Capabilities(e)
+  }
+}
+
+
+impl From<SwapchainCreationError> for VkRendererError {
+  fn from(e: SwapchainCreationError) -> VkRendererError {
+    VkRendererError::Swapchain(e)
+  }
+}
+
+
+impl From<RenderPassCreationError> for VkRendererError {
+  fn from(e: RenderPassCreationError) -> VkRendererError {
+    VkRendererError::RenderPass(e)
+  }
+}
+
+
+/// An error loading a texture. Each variant has the path of the texture.
+#[derive(Debug)]
+pub enum TextureError {
+  /// The file could not be read.
+  Io(String, io::Error),
+  /// The file could not be decoded.
+  Decode(String, String),
+  /// The decoded texture could not be uploaded to the GPU.
+  Upload(String, ImageCreationError),
+  /// The device can't sample textures of the format the texture needs.
+  UnsupportedFormat(String, Format),
+}
+
+
+impl fmt::Display for TextureError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      TextureError::Io(path, e) => write!(f, "could not read texture '{}': {}", path, e),
+      TextureError::Decode(path, e) => write!(f, "could not decode texture '{}': {}", path, e),
+      TextureError::Upload(path, e) => write!(f, "could not upload texture '{}': {}", path, e),
+      TextureError::UnsupportedFormat(path, format) => {
+        write!(f, "could not upload texture '{}': the device can't sample {:?}", path, format)
+      }
+    }
+  }
+}
+
+
+impl Error for TextureError {}
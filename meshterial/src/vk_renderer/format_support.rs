@@ -0,0 +1,100 @@
+use vk_sys as vk;
+use vulkano::format::Format;
+use vulkano::instance::PhysicalDevice;
+use vulkano::VulkanObject;
+
+use std::mem;
+
+
+/// What an image of some format is used for. See `VkRenderer::format_supported`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormatUsage {
+  ColorAttachment,
+  DepthStencilAttachment,
+  /// Sampled in a shader.
+  Sampled,
+  /// Read and written as a storage image, eg. by a compute shader.
+  Storage,
+}
+
+
+/// What a format can be used for with one kind of tiling or in buffers.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FormatFeatures {
+  pub sampled_image: bool,
+  /// Sampled images can be filtered with `Filter::Linear`.
+  pub sampled_image_filter_linear: bool,
+  pub storage_image: bool,
+  pub color_attachment: bool,
+  /// Color attachments can be blended into.
+  pub color_attachment_blend: bool,
+  pub depth_stencil_attachment: bool,
+  pub blit_src: bool,
+  pub blit_dst: bool,
+  pub vertex_buffer: bool,
+}
+
+
+impl FormatFeatures {
+  fn from_flags(flags: vk::FormatFeatureFlags) -> FormatFeatures {
+    let has = |bit: vk::FormatFeatureFlags| flags & bit != 0;
+    FormatFeatures {
+      sampled_image: has(vk::FORMAT_FEATURE_SAMPLED_IMAGE_BIT),
+      sampled_image_filter_linear: has(vk::FORMAT_FEATURE_SAMPLED_IMAGE_FILTER_LINEAR_BIT),
+      storage_image: has(vk::FORMAT_FEATURE_STORAGE_IMAGE_BIT),
+      color_attachment: has(vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BIT),
+      color_attachment_blend: has(vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BLEND_BIT),
+      depth_stencil_attachment: has(vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT),
+      blit_src: has(vk::FORMAT_FEATURE_BLIT_SRC_BIT),
+      blit_dst: has(vk::FORMAT_FEATURE_BLIT_DST_BIT),
+      vertex_buffer: has(vk::FORMAT_FEATURE_VERTEX_BUFFER_BIT),
+    }
+  }
+
+
+  /// Whether or not the format can be used this way.
+  pub fn supports(&self, usage: FormatUsage) -> bool {
+    match usage {
+      FormatUsage::ColorAttachment => self.color_attachment,
+      FormatUsage::DepthStencilAttachment => self.depth_stencil_attachment,
+      FormatUsage::Sampled => self.sampled_image,
+      FormatUsage::Storage => self.storage_image,
+    }
+  }
+}
+
+
+/// The features a device supports for a format.
+///
+/// Images created by vulkano use optimal tiling, so `optimal_tiling` is
+/// usually the one that matters.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FormatProperties {
+  pub linear_tiling: FormatFeatures,
+  pub optimal_tiling: FormatFeatures,
+  pub buffer: FormatFeatures,
+}
+
+
+impl FormatProperties {
+  /// Query the properties of a format on a physical device. This works
+  /// before a renderer or even a logical device has been created.
+  pub fn query(physical: PhysicalDevice, format: Format) -> FormatProperties {
+    let vk = physical.instance().pointers();
+    let properties = unsafe {
+      let mut properties: vk::FormatProperties = mem::uninitialized();
+      // vulkano's formats are numbered like vulkan's.
+      vk.GetPhysicalDeviceFormatProperties(
+        physical.internal_object(),
+        format as vk::Format,
+        &mut properties
+      );
+      properties
+    };
+    FormatProperties {
+      linear_tiling: FormatFeatures::from_flags(properties.linearTilingFeatures),
+      optimal_tiling: FormatFeatures::from_flags(properties.optimalTilingFeatures),
+      buffer: FormatFeatures::from_flags(properties.bufferFeatures),
+    }
+  }
+}
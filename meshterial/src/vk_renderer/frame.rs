@@ -0,0 +1,108 @@
+use vulkano::pipeline::viewport::Viewport;
+
+use std::ops::{Deref, DerefMut};
+
+use super::{clamp_scissor, VkRenderer};
+
+
+/// A frame that is currently being rendered. Returned by
+/// `VkRenderer::frame` between `begin_rendering` and `commit_rendering`.
+///
+/// A Frame derefs to the renderer it was created from, so anything that can be
+/// done with the renderer can be done with the frame.
+pub struct Frame<'a> {
+  renderer: &'a mut VkRenderer,
+}
+
+
+impl<'a> Frame<'a> {
+  pub fn new(renderer: &'a mut VkRenderer) -> Frame<'a> {
+    Frame { renderer }
+  }
+
+
+  /// Run `f` with the viewport and scissor set to the given sub-region of the
+  /// framebuffer, given as `[x, y, width, height]` in pixels. The previous
+  /// viewport and scissor are restored afterward. Parts of the region outside
+  /// of the framebuffer aren't drawn, see `clamp_scissor`.
+  ///
+  /// This can be called more than once per frame, eg. to draw the same scene
+  /// from two cameras into either half of the window. Because the scissor is
+  /// set to the same region as the viewport, depth writes stay within the
+  /// region. `begin_rendering` clears the depth of the whole framebuffer,
+  /// letterbox bars included, so each region starts at the far plane as long
+  /// as the regions don't overlap. A region drawn over another one, eg. a
+  /// picture in picture view, shares its depth, so call `clear_depth_only`
+  /// at the start of `f` to clear just that region.
+  pub fn with_viewport<F>(&mut self, rect: [i32; 4], f: F)
+    where F: FnOnce(&mut Frame)
+  {
+    let prev_viewports = self.renderer.dynamic_state.viewports.take();
+    let prev_scissors = self.renderer.dynamic_state.scissors.take();
+    let depth_range = prev_viewports
+      .as_ref()
+      .and_then(|vs| vs.first())
+      .map(|v| v.depth_range.clone())
+      .unwrap_or(0.0 .. 1.0);
+
+    self.renderer.dynamic_state.viewports = Some(vec![Viewport {
+      origin: [rect[0] as f32, rect[1] as f32],
+      dimensions: [rect[2] as f32, rect[3] as f32],
+      depth_range,
+    }]);
+    self.renderer.dynamic_state.scissors = Some(vec![clamp_scissor(rect, self.renderer.dimensions)]);
+
+    f(self);
+
+    self.renderer.dynamic_state.viewports = prev_viewports;
+    self.renderer.dynamic_state.scissors = prev_scissors;
+  }
+
+
+  /// Run `f` with the depth of everything it draws offset by about `bias`,
+  /// eg. -0.0005 to draw a decal just in front of the surface it sits on, or a
+  /// small positive bias when rendering shadow casters to avoid shadow acne.
+  ///
+  /// vulkano doesn't expose the rasterizer's depth bias, so this narrows the
+  /// viewport's depth range instead: a negative bias maps depths into
+  /// `[0, 1 + bias]` and a positive one into `[bias, 1]`. The offset is
+  /// constant in depth buffer units rather than scaled by the slope of the
+  /// polygon, and it shrinks toward zero at the near (or far) plane.
+  pub fn with_depth_bias<F>(&mut self, bias: f32, f: F)
+    where F: FnOnce(&mut Frame)
+  {
+    let prev_viewports = self.renderer.dynamic_state.viewports.clone();
+    if let Some(viewports) = self.renderer.dynamic_state.viewports.as_mut() {
+      for viewport in viewports.iter_mut() {
+        let range = viewport.depth_range.clone();
+        let bias = bias.max(-1.0).min(1.0);
+        viewport.depth_range =
+          if bias < 0.0 {
+            range.start .. (range.end + bias * (range.end - range.start))
+          } else {
+            (range.start + bias * (range.end - range.start)) .. range.end
+          };
+      }
+    }
+
+    f(self);
+
+    self.renderer.dynamic_state.viewports = prev_viewports;
+  }
+}
+
+
+impl<'a> Deref for Frame<'a> {
+  type Target = VkRenderer;
+
+  fn deref(&self) -> &VkRenderer {
+    self.renderer
+  }
+}
+
+
+impl<'a> DerefMut for Frame<'a> {
+  fn deref_mut(&mut self) -> &mut VkRenderer {
+    self.renderer
+  }
+}
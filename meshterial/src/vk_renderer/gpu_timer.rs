@@ -0,0 +1,242 @@
+use vk_sys as vk;
+use vulkano::device::{Device, Queue};
+use vulkano::query::{QueryType, UnsafeQueryPool};
+use vulkano::{SynchronizedVulkanObject, VulkanObject};
+
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+
+
+/// How many frames are timed at once. Each frame has its own pair of
+/// timestamps so that one frame can be read back while the next is drawn.
+const SLOTS: usize = 2;
+
+
+/// The command buffers that write a frame's timestamps, and whether their
+/// results have been read yet.
+struct Slot {
+  begin: vk::CommandBuffer,
+  end: vk::CommandBuffer,
+  pending: bool,
+}
+
+
+/// Times frames on the GPU with a pair of timestamp queries each.
+///
+/// `AutoCommandBufferBuilder` can't write timestamps, so they are written by
+/// tiny command buffers of our own, submitted to the same queue right before
+/// and right after each frame's command buffer. Queue submission order means
+/// they bracket the frame's work.
+pub(crate) struct GpuTimer {
+  device: Arc<Device>,
+  queue: Arc<Queue>,
+  query_pool: UnsafeQueryPool,
+  command_pool: vk::CommandPool,
+  slots: Vec<Slot>,
+  frame: usize,
+  /// Nanoseconds per timestamp tick.
+  period: f32,
+  last_frame_ms: Option<f32>,
+}
+
+
+impl GpuTimer {
+  /// Returns None if the queue can't write timestamps.
+  pub fn new(device: Arc<Device>, queue: Arc<Queue>) -> Option<GpuTimer> {
+    let limits = device.physical_device().limits();
+    if limits.timestamp_compute_and_graphics() == 0 {
+      return None;
+    }
+    let period = limits.timestamp_period();
+    let query_pool = UnsafeQueryPool::new(
+      device.clone(),
+      QueryType::Timestamp,
+      (SLOTS * 2) as u32
+    ).ok()?;
+
+    let vk = device.pointers();
+    unsafe {
+      let info = vk::CommandPoolCreateInfo {
+        sType: vk::STRUCTURE_TYPE_COMMAND_POOL_CREATE_INFO,
+        pNext: ptr::null(),
+        flags: vk::COMMAND_POOL_CREATE_RESET_COMMAND_BUFFER_BIT,
+        queueFamilyIndex: queue.family().id(),
+      };
+      let mut command_pool = mem::uninitialized();
+      let result = vk.CreateCommandPool(
+        device.internal_object(),
+        &info,
+        ptr::null(),
+        &mut command_pool
+      );
+      if result != vk::SUCCESS {
+        return None;
+      }
+
+      let info = vk::CommandBufferAllocateInfo {
+        sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO,
+        pNext: ptr::null(),
+        commandPool: command_pool,
+        level: vk::COMMAND_BUFFER_LEVEL_PRIMARY,
+        commandBufferCount: (SLOTS * 2) as u32,
+      };
+      let mut buffers = Vec::with_capacity(SLOTS * 2);
+      let result = vk.AllocateCommandBuffers(
+        device.internal_object(),
+        &info,
+        buffers.as_mut_ptr()
+      );
+      if result != vk::SUCCESS {
+        vk.DestroyCommandPool(device.internal_object(), command_pool, ptr::null());
+        return None;
+      }
+      buffers.set_len(SLOTS * 2);
+
+      let slots = buffers
+        .chunks(2)
+        .map(|pair| Slot { begin: pair[0], end: pair[1], pending: false })
+        .collect();
+      Some(GpuTimer {
+        device,
+        queue,
+        query_pool,
+        command_pool,
+        slots,
+        frame: 0,
+        period,
+        last_frame_ms: None,
+      })
+    }
+  }
+
+
+  /// The GPU time of the most recent frame whose timestamps have been read.
+  pub fn last_frame_ms(&self) -> Option<f32> {
+    self.last_frame_ms
+  }
+
+
+  /// Submit the timestamp that starts a frame. Call this right before the
+  /// frame's command buffer is submitted.
+  pub fn begin_frame(&mut self) {
+    let slot = self.frame % SLOTS;
+    // The previous frame is read if it's done, without waiting.
+    let previous = (self.frame + SLOTS - 1) % SLOTS;
+    self.read(previous, false);
+    // This slot's command buffers are about to be recorded again, so its
+    // last frame must be finished.
+    self.read(slot, true);
+
+    let first = (slot * 2) as u32;
+    let buffer = self.slots[slot].begin;
+    let query_pool = self.query_pool.internal_object();
+    self.submit(buffer, |vk, buffer| unsafe {
+      vk.CmdResetQueryPool(buffer, query_pool, first, 2);
+      // Waiting for everything before keeps the previous frame out.
+      vk.CmdWriteTimestamp(buffer, vk::PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT, query_pool, first);
+    });
+  }
+
+
+  /// Submit the timestamp that ends a frame. Call this right after the
+  /// frame's command buffer is submitted.
+  pub fn end_frame(&mut self) {
+    let slot = self.frame % SLOTS;
+    let query = (slot * 2 + 1) as u32;
+    let buffer = self.slots[slot].end;
+    let query_pool = self.query_pool.internal_object();
+    self.submit(buffer, |vk, buffer| unsafe {
+      vk.CmdWriteTimestamp(buffer, vk::PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT, query_pool, query);
+    });
+    self.slots[slot].pending = true;
+    self.frame += 1;
+  }
+
+
+  /// Record and submit one of our command buffers.
+  fn submit<F>(&self, buffer: vk::CommandBuffer, record: F)
+    where F: FnOnce(&vk::DevicePointers, vk::CommandBuffer)
+  {
+    let vk = self.device.pointers();
+    unsafe {
+      let info = vk::CommandBufferBeginInfo {
+        sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO,
+        pNext: ptr::null(),
+        flags: vk::COMMAND_BUFFER_USAGE_ONE_TIME_SUBMIT_BIT,
+        pInheritanceInfo: ptr::null(),
+      };
+      if vk.BeginCommandBuffer(buffer, &info) != vk::SUCCESS {
+        return;
+      }
+      record(vk, buffer);
+      if vk.EndCommandBuffer(buffer) != vk::SUCCESS {
+        return;
+      }
+      let submit = vk::SubmitInfo {
+        sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
+        pNext: ptr::null(),
+        waitSemaphoreCount: 0,
+        pWaitSemaphores: ptr::null(),
+        pWaitDstStageMask: ptr::null(),
+        commandBufferCount: 1,
+        pCommandBuffers: &buffer,
+        signalSemaphoreCount: 0,
+        pSignalSemaphores: ptr::null(),
+      };
+      let queue = self.queue.internal_object_guard();
+      vk.QueueSubmit(*queue, 1, &submit, 0);
+    }
+  }
+
+
+  /// Read a slot's timestamps if they haven't been read yet, optionally
+  /// waiting for them.
+  fn read(&mut self, slot: usize, wait: bool) {
+    if !self.slots[slot].pending {
+      return;
+    }
+    let mut timestamps = [0u64; 2];
+    let flags =
+      if wait {
+        vk::QUERY_RESULT_64_BIT | vk::QUERY_RESULT_WAIT_BIT
+      } else {
+        vk::QUERY_RESULT_64_BIT
+      };
+    let result = unsafe {
+      self.device.pointers().GetQueryPoolResults(
+        self.device.internal_object(),
+        self.query_pool.internal_object(),
+        (slot * 2) as u32,
+        2,
+        mem::size_of_val(&timestamps),
+        timestamps.as_mut_ptr() as *mut _,
+        mem::size_of::<u64>() as vk::DeviceSize,
+        flags
+      )
+    };
+    if result == vk::SUCCESS {
+      let ticks = timestamps[1].wrapping_sub(timestamps[0]);
+      self.last_frame_ms = Some(ticks as f32 * self.period / 1_000_000.0);
+      self.slots[slot].pending = false;
+    } else if wait {
+      // The results are lost, but the slot can be reused.
+      self.slots[slot].pending = false;
+    }
+  }
+}
+
+
+impl Drop for GpuTimer {
+  fn drop(&mut self) {
+    // Our command buffers may still be running.
+    let _ = self.device.wait();
+    unsafe {
+      self.device.pointers().DestroyCommandPool(
+        self.device.internal_object(),
+        self.command_pool,
+        ptr::null()
+      );
+    }
+  }
+}
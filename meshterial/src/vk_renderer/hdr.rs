@@ -0,0 +1,353 @@
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::image::{Dimensions, ImageViewAccess, ImmutableImage};
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+use vulkano::sync::GpuFuture;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::super::pipelines::tonemap::{fs, TonemapPipeline};
+use super::VkRendererError;
+use super::attachments;
+use super::bloom::Bloom;
+use super::ssao::Ssao;
+
+
+mod luminance_cs {
+  vulkano_shaders::shader!{
+    ty: "compute",
+    path: "src/shaders/tonemap/luminance_comp.glsl"
+  }
+}
+
+
+/// The format of the HDR color attachment.
+pub const HDR_FORMAT: Format = Format::R16G16B16A16Sfloat;
+
+
+/// The offscreen target and tonemapping pass used when HDR is enabled.
+///
+/// The scene is drawn into an `HDR_FORMAT` image with `scene_render_pass`,
+/// which is then tonemapped onto the swapchain image with
+/// `present_render_pass`.
+pub(crate) struct Hdr {
+  pub scene_render_pass: Arc<RenderPassAbstract + Send + Sync>,
+  present_render_pass: Arc<RenderPassAbstract + Send + Sync>,
+  tonemap: TonemapPipeline,
+  desc_set: Option<Arc<DescriptorSet + Send + Sync>>,
+  present_framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+  image: Option<Arc<AttachmentImage>>,
+  pub exposure: f32,
+  pub auto_exposure: Option<AutoExposure>,
+  /// Screen-space ambient occlusion. Its images are made by `framebuffers`.
+  pub ssao: Option<Ssao>,
+  /// Sampled as the occlusion while SSAO is off.
+  no_occlusion: Arc<ImmutableImage<Format>>,
+  /// Glow around bright pixels. Its images are made by `framebuffers`.
+  pub bloom: Option<Bloom>,
+  /// Sampled as the bloom while bloom is off.
+  no_bloom: Arc<ImmutableImage<Format>>,
+}
+
+
+/// Adapts the exposure toward the average luminance of the scene.
+///
+/// Each frame a compute shader averages the log luminance of a grid of
+/// samples of the HDR image into a buffer. The buffer is read back on a later
+/// frame, once the GPU is done with it, so measuring never stalls rendering.
+pub(crate) struct AutoExposure {
+  pipeline: Arc<ComputePipelineAbstract + Send + Sync>,
+  sampler: Arc<Sampler>,
+  pending: Option<Arc<CpuAccessibleBuffer<luminance_cs::ty::Result>>>,
+  last_update: Instant,
+  /// How quickly the exposure adapts, in 1/seconds.
+  pub adaptation_speed: f32,
+  /// The luminance that the average luminance is exposed to.
+  pub key: f32,
+}
+
+
+impl AutoExposure {
+  pub fn new(device: Arc<Device>, adaptation_speed: f32) -> AutoExposure {
+    let cs = luminance_cs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      ComputePipeline::new(device.clone(), &cs.main_entry_point(), &())
+        .expect("Could not create luminance pipeline.")
+    );
+    let sampler = Sampler::new(
+      device,
+      Filter::Linear, Filter::Linear,
+      MipmapMode::Nearest,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      0.0, 1.0, 0.0, 0.0
+    ).expect("Could not create luminance sampler.");
+    AutoExposure {
+      pipeline,
+      sampler,
+      pending: None,
+      last_update: Instant::now(),
+      adaptation_speed,
+      key: 0.18,
+    }
+  }
+
+
+  /// Move `exposure` toward the last measured luminance, if it has been
+  /// measured, and record a new measurement of `image` if none is pending.
+  fn update(
+    &mut self,
+    device: Arc<Device>,
+    image: Arc<AttachmentImage>,
+    exposure: &mut f32,
+    cmds: AutoCommandBufferBuilder
+  ) -> AutoCommandBufferBuilder {
+    let measured = self
+      .pending
+      .as_ref()
+      .and_then(|buffer| buffer.read().ok().map(|result| result.log_luminance));
+    if let Some(log_luminance) = measured {
+      self.pending = None;
+      let now = Instant::now();
+      let dt = now.duration_since(self.last_update);
+      let dt = dt.as_secs() as f32 + dt.subsec_nanos() as f32 / 1_000_000_000.0;
+      self.last_update = now;
+      let target = self.key / log_luminance.exp().max(0.0001);
+      let t = 1.0 - (-dt * self.adaptation_speed).exp();
+      *exposure += (target - *exposure) * t;
+    }
+    if self.pending.is_some() {
+      return cmds;
+    }
+
+    let buffer = CpuAccessibleBuffer::from_data(
+      device,
+      BufferUsage::all(),
+      luminance_cs::ty::Result { log_luminance: 0.0 }
+    ).expect("Could not create luminance buffer.");
+    let set = Arc::new(
+      PersistentDescriptorSet::start(self.pipeline.clone(), 0)
+        .add_sampled_image(image, self.sampler.clone()).expect("Could not add hdr image.")
+        .add_buffer(buffer.clone()).expect("Could not add luminance buffer.")
+        .build()
+        .expect("Could not build luminance set.")
+    );
+    self.pending = Some(buffer);
+    cmds
+      .dispatch([1, 1, 1], self.pipeline.clone(), set, ())
+      .expect("Could not dispatch luminance measurement.")
+  }
+}
+
+
+impl Hdr {
+  /// Create the HDR render passes, checking that the device can both render
+  /// to and sample `HDR_FORMAT` images.
+  pub fn new(
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    swapchain_format: Format
+  ) -> Result<Hdr, VkRendererError> {
+    AttachmentImage::sampled(device.clone(), [1, 1], HDR_FORMAT)
+      .map_err(|_| VkRendererError::UnsupportedFormat(HDR_FORMAT))?;
+
+    let scene_render_pass = Arc::new(
+      single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+          color: {
+            load: Clear,
+            store: Store,
+            format: HDR_FORMAT,
+            samples: 1,
+          },
+          depth: {
+            load: Clear,
+            store: Store,
+            format: Format::D16Unorm,
+            samples: 1,
+          }
+        },
+        pass: {
+          color: [color],
+          depth_stencil: {depth}
+        }
+      )?
+    );
+
+    let present_render_pass = Arc::new(
+      single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+          // Every pixel is overwritten by the tonemap pass.
+          color: {
+            load: DontCare,
+            store: Store,
+            format: swapchain_format,
+            samples: 1,
+          }
+        },
+        pass: {
+          color: [color],
+          depth_stencil: {}
+        }
+      )?
+    );
+
+    let tonemap = TonemapPipeline::new(present_render_pass.clone(), device);
+
+    let (no_occlusion, no_occlusion_future) = ImmutableImage::from_iter(
+      vec![255u8].into_iter(),
+      Dimensions::Dim2d {
+        width: 1,
+        height: 1,
+      },
+      Format::R8Unorm,
+      queue.clone()
+    ).expect("Could not create occlusion image.");
+    let (no_bloom, no_bloom_future) = ImmutableImage::from_iter(
+      vec![0u8, 0, 0, 255].into_iter(),
+      Dimensions::Dim2d {
+        width: 1,
+        height: 1,
+      },
+      Format::R8G8B8A8Unorm,
+      queue
+    ).expect("Could not create bloom image.");
+    no_occlusion_future
+      .join(no_bloom_future)
+      .then_signal_fence_and_flush()
+      .expect("Could not flush placeholder image uploads.")
+      .wait(None)
+      .expect("Could not wait for placeholder image uploads.");
+
+    Ok(Hdr {
+      scene_render_pass,
+      present_render_pass,
+      tonemap,
+      desc_set: None,
+      present_framebuffers: vec![],
+      image: None,
+      exposure: 1.0,
+      auto_exposure: None,
+      ssao: None,
+      no_occlusion,
+      bloom: None,
+      no_bloom,
+    })
+  }
+
+
+  /// Recreate the HDR image at the given size, returning one scene framebuffer
+  /// for each of the swapchain's images.
+  pub fn framebuffers<I>(
+    &mut self,
+    device: Arc<Device>,
+    dimensions: [u32; 2],
+    images: &[I],
+    depth_buffer: Arc<AttachmentImage>,
+    extra_attachments: &[Arc<ImageViewAccess + Send + Sync>]
+  ) -> Vec<Arc<FramebufferAbstract + Send + Sync>>
+    where I: ImageViewAccess + Clone + Send + Sync + 'static
+  {
+    let image = AttachmentImage::sampled(device.clone(), dimensions, HDR_FORMAT)
+      .expect("Could not create hdr image.");
+    let occlusion = match self.ssao.as_mut() {
+      Some(ssao) => ssao.resize(device.clone(), dimensions, depth_buffer.clone()) as Arc<ImageViewAccess + Send + Sync>,
+      None => self.no_occlusion.clone() as Arc<ImageViewAccess + Send + Sync>,
+    };
+    let bloom = match self.bloom.as_mut() {
+      Some(bloom) => bloom.resize(device, dimensions, image.clone()) as Arc<ImageViewAccess + Send + Sync>,
+      None => self.no_bloom.clone() as Arc<ImageViewAccess + Send + Sync>,
+    };
+    self.desc_set = Some(self.tonemap.desc_set_for_image(image.clone(), occlusion, bloom));
+    self.image = Some(image.clone());
+
+    self.present_framebuffers = images
+      .iter()
+      .map(|swapchain_image| {
+        Arc::new(
+          Framebuffer::start(self.present_render_pass.clone())
+            .add(swapchain_image.clone()).expect("Could not add image to framebuffer.")
+            .build().expect("Could not build new framebuffer.")
+        ) as Arc<FramebufferAbstract + Send + Sync>
+      })
+      .collect();
+
+    // Every swapchain image is drawn through the same HDR image.
+    let scene_framebuffer = attachments::framebuffer(
+      self.scene_render_pass.clone(),
+      image,
+      depth_buffer,
+      extra_attachments
+    );
+    vec![scene_framebuffer; images.len()]
+  }
+
+
+  /// The HDR image the scene is drawn into, once the framebuffers have been
+  /// created.
+  pub fn image(&self) -> Option<Arc<AttachmentImage>> {
+    self.image.clone()
+  }
+
+
+  /// Record the tonemap pass that draws the HDR image onto the swapchain
+  /// image `image_num`, first updating the exposure if auto exposure is on,
+  /// drawing the ambient occlusion if SSAO is on and the bloom if bloom is on.
+  /// This must be recorded after the scene render pass has ended.
+  pub fn tonemap(
+    &mut self,
+    device: Arc<Device>,
+    cmds: AutoCommandBufferBuilder,
+    image_num: usize,
+    dynamic_state: &DynamicState
+  ) -> AutoCommandBufferBuilder {
+    let desc_set = self
+      .desc_set
+      .clone()
+      .expect("Could not get hdr image - maybe the framebuffers were not created.");
+    let image = self
+      .image
+      .clone()
+      .expect("Could not get hdr image - maybe the framebuffers were not created.");
+    let cmds = match self.auto_exposure.as_mut() {
+      Some(auto_exposure) => auto_exposure.update(device, image, &mut self.exposure, cmds),
+      None => cmds,
+    };
+    let cmds = match self.ssao.as_ref() {
+      Some(ssao) => ssao.occlude(cmds, dynamic_state),
+      None => cmds,
+    };
+    let cmds = match self.bloom.as_ref() {
+      Some(bloom) => bloom.bloom(cmds),
+      None => cmds,
+    };
+    let push_constants = fs::ty::Tonemap {
+      exposure: self.exposure,
+      bloom_intensity: self.bloom.as_ref().map(|bloom| bloom.intensity).unwrap_or(0.0),
+    };
+    cmds
+      .begin_render_pass(
+        self.present_framebuffers[image_num].clone(),
+        false,
+        vec![ClearValue::None]
+      ).expect("Could not begin tonemap render pass.")
+      .draw(
+        self.tonemap.pipeline.clone(),
+        dynamic_state,
+        vec![self.tonemap.vertex_buffer.clone() as Arc<BufferAccess + Send + Sync>],
+        desc_set,
+        push_constants
+      ).expect("Could not draw tonemap pass.")
+      .end_render_pass().expect("Could not end tonemap render pass.")
+  }
+}
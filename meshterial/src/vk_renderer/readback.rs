@@ -0,0 +1,96 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Queue};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+use vulkano::sync::GpuFuture;
+
+use std::sync::Arc;
+
+mod cs {
+  vulkano_shaders::shader!{
+    ty: "compute",
+    path: "src/shaders/depth/read_comp.glsl"
+  }
+}
+
+
+/// Reads single texels of the depth buffer back to the CPU.
+///
+/// vulkano can't copy the depth aspect of an image into a buffer, so instead a
+/// compute shader samples the depth buffer and writes the texel to a buffer.
+/// Sampling gives the normalized depth for any depth format.
+pub(crate) struct DepthReader {
+  pipeline: Arc<ComputePipelineAbstract + Send + Sync>,
+  sampler: Arc<Sampler>,
+}
+
+
+impl DepthReader {
+  pub fn new(device: Arc<Device>) -> DepthReader {
+    let cs = cs::Shader::load(device.clone()).expect("failed to create shader module");
+    let pipeline = Arc::new(
+      ComputePipeline::new(device.clone(), &cs.main_entry_point(), &())
+        .expect("Could not create depth readback pipeline.")
+    );
+    // Depth formats don't have to support linear filtering.
+    let sampler = Sampler::new(
+      device,
+      Filter::Nearest, Filter::Nearest,
+      MipmapMode::Nearest,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      SamplerAddressMode::ClampToEdge,
+      0.0, 1.0, 0.0, 0.0
+    ).expect("Could not create depth readback sampler.");
+    DepthReader { pipeline, sampler }
+  }
+
+
+  /// Read the depth at a pixel after `after` has finished, blocking until it
+  /// is read.
+  pub fn read<F>(
+    &self,
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    after: F,
+    depth_buffer: Arc<AttachmentImage>,
+    x: u32,
+    y: u32
+  ) -> f32
+    where F: GpuFuture + 'static
+  {
+    let result = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::all(), 0f32)
+      .expect("Could not create depth readback buffer.");
+    let set = Arc::new(
+      PersistentDescriptorSet::start(self.pipeline.clone(), 0)
+        .add_sampled_image(depth_buffer, self.sampler.clone()).expect("Could not add depth buffer.")
+        .add_buffer(result.clone()).expect("Could not add depth readback buffer.")
+        .build()
+        .expect("Could not build depth readback set.")
+    );
+    let pixel = cs::ty::Pixel {
+      position: [x as i32, y as i32],
+    };
+    let command_buffer =
+      AutoCommandBufferBuilder::primary_one_time_submit(device, queue.family())
+        .expect("Could not create a new command buffer builder.")
+        .dispatch([1, 1, 1], self.pipeline.clone(), set, pixel)
+        .expect("Could not dispatch depth readback.")
+        .build()
+        .expect("Could not build depth readback command buffer.");
+
+    after
+      .then_execute(queue, command_buffer)
+      .expect("Could not execute depth readback.")
+      .then_signal_fence_and_flush()
+      .expect("Could not flush depth readback.")
+      .wait(None)
+      .expect("Could not wait for depth readback.");
+
+    let depth = *result.read().expect("Could not read depth readback buffer.");
+    depth
+  }
+}
@@ -0,0 +1,231 @@
+use vulkano::buffer::BufferAccess;
+use vulkano::buffer::cpu_pool::CpuBufferPool;
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::Device;
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract};
+use vulkano::image::ImageViewAccess;
+use vulkano::image::attachment::AttachmentImage;
+use nalgebra::Matrix4;
+
+use std::sync::Arc;
+
+use super::super::camera::Camera;
+use super::super::pipelines::particles::{soft_fs, vs, SoftParticlePipeline};
+use super::super::pipelines::uniform::uniform_buffer_usage;
+
+
+/// Particles drawn after the scene pass has ended, so that they can sample
+/// the depth buffer and fade out near the scene. See
+/// `VkRenderer::set_soft_particles`.
+///
+/// Particle draws are queued during the frame and recorded by `draw` into a
+/// render pass that loads the scene's color image, before tonemapping.
+pub(crate) struct SoftParticles {
+  render_pass: Arc<RenderPassAbstract + Send + Sync>,
+  pipeline: SoftParticlePipeline,
+  /// The format of the scene's color image, which the render pass loads.
+  pub format: Format,
+  proj_buffer: Arc<DeviceLocalBuffer<Matrix4<f32>>>,
+  params_buffer: Arc<DeviceLocalBuffer<soft_fs::ty::SoftParticles>>,
+  proj_pool: CpuBufferPool<Matrix4<f32>>,
+  params_pool: CpuBufferPool<soft_fs::ty::SoftParticles>,
+  proj_set: Arc<DescriptorSet + Send + Sync>,
+  depth_set: Option<Arc<DescriptorSet + Send + Sync>>,
+  framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+  queued: Vec<(Arc<BufferAccess + Send + Sync>, vs::ty::Billboard)>,
+  /// Whether the projection or softness changed since they were uploaded.
+  dirty: bool,
+  /// The distance in view space units over which particles fade out in
+  /// front of the scene.
+  pub softness: f32,
+  /// The projection the scene is drawn with.
+  pub projection: Matrix4<f32>,
+}
+
+
+impl SoftParticles {
+  pub fn new(device: Arc<Device>, format: Format, softness: f32) -> SoftParticles {
+    let (render_pass, pipeline) = SoftParticles::build_pipeline(device.clone(), format);
+    let proj_buffer = DeviceLocalBuffer::new(
+      device.clone(),
+      uniform_buffer_usage(),
+      device.physical_device().queue_families()
+    ).expect("Could not create soft particle projection buffer.");
+    let params_buffer = DeviceLocalBuffer::new(
+      device.clone(),
+      uniform_buffer_usage(),
+      device.physical_device().queue_families()
+    ).expect("Could not create soft particle buffer.");
+    let proj_set = SoftParticles::proj_set(&pipeline, proj_buffer.clone());
+    SoftParticles {
+      render_pass,
+      pipeline,
+      format,
+      proj_buffer,
+      params_buffer,
+      proj_pool: CpuBufferPool::upload(device.clone()),
+      params_pool: CpuBufferPool::upload(device),
+      proj_set,
+      depth_set: None,
+      framebuffers: vec![],
+      queued: vec![],
+      dirty: true,
+      softness,
+      projection: Camera::default().projection(),
+    }
+  }
+
+
+  fn build_pipeline(
+    device: Arc<Device>,
+    format: Format
+  ) -> (Arc<RenderPassAbstract + Send + Sync>, SoftParticlePipeline) {
+    let render_pass = Arc::new(
+      single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+          // Particles are blended over the scene.
+          color: {
+            load: Load,
+            store: Store,
+            format: format,
+            samples: 1,
+          }
+        },
+        pass: {
+          color: [color],
+          depth_stencil: {}
+        }
+      ).expect("Could not create soft particle render pass.")
+    ) as Arc<RenderPassAbstract + Send + Sync>;
+    let pipeline = SoftParticlePipeline::new(render_pass.clone(), device);
+    (render_pass, pipeline)
+  }
+
+
+  fn proj_set(
+    pipeline: &SoftParticlePipeline,
+    proj_buffer: Arc<DeviceLocalBuffer<Matrix4<f32>>>
+  ) -> Arc<DescriptorSet + Send + Sync> {
+    Arc::new(
+      PersistentDescriptorSet::start(pipeline.pipeline.clone(), 0)
+        .add_buffer(proj_buffer).expect("Could not add soft particle projection.")
+        .build()
+        .expect("Could not build soft particle projection set.")
+    )
+  }
+
+
+  /// Rebuild the render pass and pipeline for a scene color image of another
+  /// format, eg. after HDR is enabled. `resize` must be called again before
+  /// drawing.
+  pub fn set_format(&mut self, device: Arc<Device>, format: Format) {
+    let (render_pass, pipeline) = SoftParticles::build_pipeline(device, format);
+    self.proj_set = SoftParticles::proj_set(&pipeline, self.proj_buffer.clone());
+    self.render_pass = render_pass;
+    self.pipeline = pipeline;
+    self.format = format;
+    self.depth_set = None;
+    self.framebuffers = vec![];
+  }
+
+
+  /// Mark the projection and softness to be uploaded before the next draw.
+  pub fn set_dirty(&mut self) {
+    self.dirty = true;
+  }
+
+
+  /// Recreate the framebuffers for the scene's color images, one for each
+  /// swapchain image, and sample the new depth buffer.
+  pub fn resize(
+    &mut self,
+    color_images: Vec<Arc<ImageViewAccess + Send + Sync>>,
+    depth_buffer: Arc<AttachmentImage>
+  ) {
+    self.framebuffers = color_images
+      .into_iter()
+      .map(|image| {
+        Arc::new(
+          Framebuffer::start(self.render_pass.clone())
+            .add(image).expect("Could not add image to soft particle framebuffer.")
+            .build().expect("Could not build soft particle framebuffer.")
+        ) as Arc<FramebufferAbstract + Send + Sync>
+      })
+      .collect();
+    self.depth_set = Some(Arc::new(
+      PersistentDescriptorSet::start(self.pipeline.pipeline.clone(), 1)
+        .add_sampled_image(depth_buffer, self.pipeline.sampler.clone()).expect("Could not add depth buffer.")
+        .add_buffer(self.params_buffer.clone()).expect("Could not add soft particle buffer.")
+        .build()
+        .expect("Could not build soft particle depth set.")
+    ));
+  }
+
+
+  /// Queue particles to be drawn at the end of the frame.
+  pub fn queue(&mut self, vertex_buffer: Arc<BufferAccess + Send + Sync>, billboard: vs::ty::Billboard) {
+    self.queued.push((vertex_buffer, billboard));
+  }
+
+
+  /// Record the queued particles over the scene's color image for swapchain
+  /// image `image_num`. This must be recorded after the scene render pass
+  /// has ended.
+  pub fn draw(
+    &mut self,
+    cmds: AutoCommandBufferBuilder,
+    image_num: usize,
+    dynamic_state: &DynamicState
+  ) -> AutoCommandBufferBuilder {
+    if self.queued.is_empty() {
+      return cmds;
+    }
+    let depth_set = self
+      .depth_set
+      .clone()
+      .expect("Could not get soft particle depth set - maybe the framebuffers were not created.");
+    let mut cmds = cmds;
+    if self.dirty {
+      let proj = self
+        .proj_pool
+        .next(self.projection)
+        .expect("Could not load soft particle projection into cpu buffer.");
+      let inverse_projection = self
+        .projection
+        .try_inverse()
+        .unwrap_or(Matrix4::identity());
+      let params = self
+        .params_pool
+        .next(soft_fs::ty::SoftParticles {
+          inverse_projection: inverse_projection.into(),
+          softness: self.softness,
+        })
+        .expect("Could not load soft particle params into cpu buffer.");
+      cmds = cmds
+        .copy_buffer(proj, self.proj_buffer.clone()).expect("Could not upload soft particle projection.")
+        .copy_buffer(params, self.params_buffer.clone()).expect("Could not upload soft particle params.");
+      self.dirty = false;
+    }
+    cmds = cmds
+      .begin_render_pass(
+        self.framebuffers[image_num].clone(),
+        false,
+        vec![ClearValue::None]
+      ).expect("Could not begin soft particle render pass.");
+    for (vertex_buffer, billboard) in self.queued.drain(..) {
+      cmds = cmds
+        .draw(
+          self.pipeline.pipeline.clone(),
+          dynamic_state,
+          vec![vertex_buffer],
+          (self.proj_set.clone(), depth_set.clone()),
+          billboard
+        ).expect("Could not draw soft particles.");
+    }
+    cmds.end_render_pass().expect("Could not end soft particle render pass.")
+  }
+}
@@ -0,0 +1,233 @@
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::{Device, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::image::{Dimensions, ImmutableImage};
+use vulkano::sync::GpuFuture;
+use nalgebra::Matrix4;
+
+use std::sync::Arc;
+
+use super::super::camera::Camera;
+use super::super::pipelines::ssao::{fs, SsaoPipeline, KERNEL_SIZE};
+
+
+/// The format of the occlusion images.
+const OCCLUSION_FORMAT: Format = Format::R8Unorm;
+
+/// The width and height of the tiled noise texture.
+const NOISE_SIZE: u32 = 4;
+
+
+/// A pseudo random number from 0.0 to 1.0.
+fn random(seed: &mut u32) -> f32 {
+  // xorshift32
+  *seed ^= *seed << 13;
+  *seed ^= *seed >> 17;
+  *seed ^= *seed << 5;
+  *seed as f32 / ::std::u32::MAX as f32
+}
+
+
+/// Screen-space ambient occlusion, drawn from the depth buffer between the
+/// HDR scene pass and tonemapping.
+///
+/// The occlusion is drawn into one image and blurred into another, which the
+/// tonemap pass multiplies the scene by.
+pub(crate) struct Ssao {
+  render_pass: Arc<RenderPassAbstract + Send + Sync>,
+  pipeline: SsaoPipeline,
+  kernel: Arc<CpuAccessibleBuffer<fs::ty::Kernel>>,
+  noise: Arc<ImmutableImage<Format>>,
+  occlusion_framebuffer: Option<Arc<FramebufferAbstract + Send + Sync>>,
+  blur_framebuffer: Option<Arc<FramebufferAbstract + Send + Sync>>,
+  occlusion_set: Option<Arc<DescriptorSet + Send + Sync>>,
+  blur_set: Option<Arc<DescriptorSet + Send + Sync>>,
+  dimensions: [u32; 2],
+  /// The radius of the sampled hemisphere, in view space units.
+  pub radius: f32,
+  /// How dark fully occluded pixels are, from 0.0 to 1.0.
+  pub intensity: f32,
+  /// The projection the scene is drawn with.
+  pub projection: Matrix4<f32>,
+}
+
+
+impl Ssao {
+  pub fn new(device: Arc<Device>, queue: Arc<Queue>, radius: f32, intensity: f32) -> Ssao {
+    let render_pass = Arc::new(
+      single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+          // Every pixel is overwritten.
+          color: {
+            load: DontCare,
+            store: Store,
+            format: OCCLUSION_FORMAT,
+            samples: 1,
+          }
+        },
+        pass: {
+          color: [color],
+          depth_stencil: {}
+        }
+      ).expect("Could not create ssao render pass.")
+    ) as Arc<RenderPassAbstract + Send + Sync>;
+    let pipeline = SsaoPipeline::new(render_pass.clone(), device.clone());
+
+    // Samples in the hemisphere around +z, more of them close to the center.
+    let mut seed = 0x9e37_79b9;
+    let mut samples = [[0.0; 4]; KERNEL_SIZE];
+    for (i, sample) in samples.iter_mut().enumerate() {
+      let x = random(&mut seed) * 2.0 - 1.0;
+      let y = random(&mut seed) * 2.0 - 1.0;
+      let z = random(&mut seed);
+      let length = (x * x + y * y + z * z).sqrt().max(0.0001);
+      let t = i as f32 / KERNEL_SIZE as f32;
+      let scale = (0.1 + 0.9 * t * t) * random(&mut seed);
+      *sample = [x / length * scale, y / length * scale, z / length * scale, 0.0];
+    }
+    let kernel = CpuAccessibleBuffer::from_data(
+      device.clone(),
+      BufferUsage::uniform_buffer(),
+      fs::ty::Kernel { samples }
+    ).expect("Could not create ssao kernel buffer.");
+
+    // Random rotations around the normal, so that fewer kernel samples are
+    // needed. The blur pass removes the resulting pattern.
+    let mut noise_data = vec![];
+    for _ in 0 .. NOISE_SIZE * NOISE_SIZE {
+      noise_data.push((random(&mut seed) * 255.0) as u8);
+      noise_data.push((random(&mut seed) * 255.0) as u8);
+      noise_data.push(128);
+      noise_data.push(255);
+    }
+    let (noise, noise_future) = ImmutableImage::from_iter(
+      noise_data.into_iter(),
+      Dimensions::Dim2d {
+        width: NOISE_SIZE,
+        height: NOISE_SIZE,
+      },
+      Format::R8G8B8A8Unorm,
+      queue
+    ).expect("Could not create ssao noise image.");
+    noise_future
+      .then_signal_fence_and_flush()
+      .expect("Could not flush ssao noise upload.")
+      .wait(None)
+      .expect("Could not wait for ssao noise upload.");
+
+    Ssao {
+      render_pass,
+      pipeline,
+      kernel,
+      noise,
+      occlusion_framebuffer: None,
+      blur_framebuffer: None,
+      occlusion_set: None,
+      blur_set: None,
+      dimensions: [1, 1],
+      radius,
+      intensity,
+      projection: Camera::default().projection(),
+    }
+  }
+
+
+  /// Recreate the occlusion images at the given size, returning the blurred
+  /// occlusion for the tonemap pass to sample.
+  pub fn resize(
+    &mut self,
+    device: Arc<Device>,
+    dimensions: [u32; 2],
+    depth_buffer: Arc<AttachmentImage>
+  ) -> Arc<AttachmentImage> {
+    let occlusion = AttachmentImage::sampled(device.clone(), dimensions, OCCLUSION_FORMAT)
+      .expect("Could not create occlusion image.");
+    let blurred = AttachmentImage::sampled(device, dimensions, OCCLUSION_FORMAT)
+      .expect("Could not create blurred occlusion image.");
+
+    self.occlusion_framebuffer = Some(Arc::new(
+      Framebuffer::start(self.render_pass.clone())
+        .add(occlusion.clone()).expect("Could not add occlusion image to framebuffer.")
+        .build().expect("Could not build occlusion framebuffer.")
+    ));
+    self.blur_framebuffer = Some(Arc::new(
+      Framebuffer::start(self.render_pass.clone())
+        .add(blurred.clone()).expect("Could not add blurred occlusion image to framebuffer.")
+        .build().expect("Could not build blurred occlusion framebuffer.")
+    ));
+    self.occlusion_set = Some(Arc::new(
+      PersistentDescriptorSet::start(self.pipeline.pipeline.clone(), 0)
+        .add_sampled_image(depth_buffer, self.pipeline.sampler.clone()).expect("Could not add depth buffer.")
+        .add_sampled_image(self.noise.clone(), self.pipeline.noise_sampler.clone()).expect("Could not add noise image.")
+        .add_buffer(self.kernel.clone()).expect("Could not add ssao kernel.")
+        .build()
+        .expect("Could not build occlusion set.")
+    ));
+    self.blur_set = Some(Arc::new(
+      PersistentDescriptorSet::start(self.pipeline.blur_pipeline.clone(), 0)
+        .add_sampled_image(occlusion, self.pipeline.sampler.clone()).expect("Could not add occlusion image.")
+        .build()
+        .expect("Could not build blur set.")
+    ));
+    self.dimensions = dimensions;
+    blurred
+  }
+
+
+  /// Record the occlusion and blur passes. This must be recorded after the
+  /// scene render pass has ended.
+  pub fn occlude(
+    &self,
+    cmds: AutoCommandBufferBuilder,
+    dynamic_state: &DynamicState
+  ) -> AutoCommandBufferBuilder {
+    let expect_msg = "Could not get occlusion images - maybe the framebuffers were not created.";
+    let push_constants = fs::ty::Ssao {
+      projection: self.projection.into(),
+      inverse_projection: self
+        .projection
+        .try_inverse()
+        .unwrap_or(Matrix4::identity())
+        .into(),
+      noise_scale: [
+        self.dimensions[0] as f32 / NOISE_SIZE as f32,
+        self.dimensions[1] as f32 / NOISE_SIZE as f32,
+      ],
+      radius: self.radius,
+      intensity: self.intensity,
+    };
+    let vertex_buffer = self.pipeline.vertex_buffer.clone() as Arc<BufferAccess + Send + Sync>;
+    cmds
+      .begin_render_pass(
+        self.occlusion_framebuffer.clone().expect(expect_msg),
+        false,
+        vec![ClearValue::None]
+      ).expect("Could not begin occlusion render pass.")
+      .draw(
+        self.pipeline.pipeline.clone(),
+        dynamic_state,
+        vec![vertex_buffer.clone()],
+        self.occlusion_set.clone().expect(expect_msg),
+        push_constants
+      ).expect("Could not draw occlusion pass.")
+      .end_render_pass().expect("Could not end occlusion render pass.")
+      .begin_render_pass(
+        self.blur_framebuffer.clone().expect(expect_msg),
+        false,
+        vec![ClearValue::None]
+      ).expect("Could not begin blur render pass.")
+      .draw(
+        self.pipeline.blur_pipeline.clone(),
+        dynamic_state,
+        vec![vertex_buffer],
+        self.blur_set.clone().expect(expect_msg),
+        ()
+      ).expect("Could not draw blur pass.")
+      .end_render_pass().expect("Could not end blur render pass.")
+  }
+}
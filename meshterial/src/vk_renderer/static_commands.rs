@@ -0,0 +1,25 @@
+use vulkano::command_buffer::AutoCommandBuffer;
+
+use std::sync::Arc;
+
+
+/// Draw commands recorded once with `VkRenderer::record_static` and executed
+/// every frame with `VkRenderer::execute_static`, for scenes that don't
+/// change, so that their draws aren't recorded again each frame.
+///
+/// The commands are a secondary command buffer that can be in use by more
+/// than one frame at a time. They keep the buffers, images and descriptor
+/// sets they draw with alive, but vulkano doesn't synchronize secondary
+/// command buffers with the rest of the frame yet, so buffers the commands
+/// read, like a camera's projection uniform, must not be written while they
+/// are in use. Record the commands again with new buffers instead.
+///
+/// The commands bake in the viewport, scissor and background, and
+/// `execute_static` returns `VkRendererError::StaleStaticCommands` once the
+/// framebuffers are rebuilt, eg. after the window is resized. Record them
+/// again when that happens.
+pub struct StaticCommands {
+  pub(crate) commands: Arc<AutoCommandBuffer>,
+  /// The renderer's framebuffer generation when these were recorded.
+  pub(crate) generation: u64,
+}
@@ -0,0 +1,38 @@
+use std::fmt;
+
+
+/// Counts of the work submitted during one frame. See
+/// `VkRenderer::last_frame_stats`.
+///
+/// Only work recorded through the renderer's `draw` and `copy_buffer` methods
+/// (and the helpers built on them) is counted. Commands added directly with
+/// `with_command_builder` can be counted with `record_draw` and
+/// `record_buffer_upload`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderStats {
+  pub draw_calls: usize,
+  pub vertices: usize,
+  pub buffer_uploads: usize,
+}
+
+
+impl RenderStats {
+  /// The number of triangles drawn, assuming every draw was a triangle list.
+  pub fn triangles(&self) -> usize {
+    self.vertices / 3
+  }
+}
+
+
+impl fmt::Display for RenderStats {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{} draw calls, {} vertices (~{} triangles), {} buffer uploads",
+      self.draw_calls,
+      self.vertices,
+      self.triangles(),
+      self.buffer_uploads
+    )
+  }
+}
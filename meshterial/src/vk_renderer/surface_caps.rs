@@ -0,0 +1,59 @@
+use vulkano::format::Format;
+use vulkano::swapchain::{Capabilities, ColorSpace, CompositeAlpha, PresentMode, SurfaceTransform};
+
+
+/// What the window's surface supports for its swapchain, eg. to list the
+/// present modes and resolutions in a graphics settings menu. See
+/// `VkRenderer::surface_capabilities`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceCapabilities {
+  pub min_image_count: u32,
+  /// None if there is no limit other than memory.
+  pub max_image_count: Option<u32>,
+  /// The size of the surface, or None if the swapchain decides it.
+  pub current_extent: Option<[u32; 2]>,
+  pub min_image_extent: [u32; 2],
+  pub max_image_extent: [u32; 2],
+  pub present_modes: Vec<PresentMode>,
+  pub supported_transforms: Vec<SurfaceTransform>,
+  pub current_transform: SurfaceTransform,
+  /// How the swapchain images' alpha can be composited with the rest of the
+  /// desktop. A window can only be see-through with something other than
+  /// `CompositeAlpha::Opaque`.
+  pub composite_alpha: Vec<CompositeAlpha>,
+  pub formats: Vec<(Format, ColorSpace)>,
+}
+
+
+impl SurfaceCapabilities {
+  pub fn new(caps: &Capabilities) -> SurfaceCapabilities {
+    SurfaceCapabilities {
+      min_image_count: caps.min_image_count,
+      max_image_count: caps.max_image_count,
+      current_extent: caps.current_extent,
+      min_image_extent: caps.min_image_extent,
+      max_image_extent: caps.max_image_extent,
+      present_modes: caps.present_modes.iter().collect(),
+      supported_transforms: caps.supported_transforms.iter().collect(),
+      current_transform: caps.current_transform,
+      composite_alpha: caps.supported_composite_alpha.iter().collect(),
+      formats: caps.supported_formats.clone(),
+    }
+  }
+
+
+  pub fn supports_present_mode(&self, mode: PresentMode) -> bool {
+    self.present_modes.contains(&mode)
+  }
+
+
+  pub fn supports_composite_alpha(&self, alpha: CompositeAlpha) -> bool {
+    self.composite_alpha.contains(&alpha)
+  }
+
+
+  /// Whether or not a swapchain can be `[width, height]` pixels.
+  pub fn supports_extent(&self, extent: [u32; 2]) -> bool {
+    (0 .. 2).all(|i| self.min_image_extent[i] <= extent[i] && extent[i] <= self.max_image_extent[i])
+  }
+}
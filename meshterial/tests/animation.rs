@@ -0,0 +1,111 @@
+//! Sampling keyframe tracks and clips, which doesn't need a device.
+use meshterial::animation::{AnimationClip, Interpolation, Track, Wrap};
+
+
+fn approx(a: f32, b: f32) -> bool {
+  (a - b).abs() < 1.0e-5
+}
+
+
+/// A clip with one linear track from 0.0 at t = 0 to 10.0 at t = 2.
+fn ramp(wrap: Wrap) -> AnimationClip<f32> {
+  AnimationClip::new("ramp", wrap).with_track(
+    Track::new(Interpolation::Linear)
+      .with_keyframe(0.0, 0.0)
+      .with_keyframe(2.0, 10.0)
+  )
+}
+
+
+#[test]
+fn keyframes_are_kept_sorted() {
+  let track = Track::new(Interpolation::Linear)
+    .with_keyframe(2.0, 20.0f32)
+    .with_keyframe(0.0, 0.0)
+    .with_keyframe(1.0, 10.0);
+  let times: Vec<f32> = track.keyframes().iter().map(|k| k.time).collect();
+  assert_eq!(times, vec![0.0, 1.0, 2.0]);
+  assert_eq!(track.end_time(), 2.0);
+}
+
+
+#[test]
+fn step_holds_the_previous_keyframe() {
+  let track = Track::new(Interpolation::Step)
+    .with_keyframe(0.0, 1.0f32)
+    .with_keyframe(1.0, 2.0)
+    .with_keyframe(2.0, 3.0);
+  assert_eq!(track.sample(0.0), Some(1.0));
+  assert_eq!(track.sample(0.99), Some(1.0));
+  assert_eq!(track.sample(1.0), Some(2.0));
+  assert_eq!(track.sample(1.5), Some(2.0));
+  assert_eq!(track.sample(2.0), Some(3.0));
+}
+
+
+#[test]
+fn linear_interpolates_between_keyframes() {
+  let track = Track::new(Interpolation::Linear)
+    .with_keyframe(0.0, 0.0f32)
+    .with_keyframe(1.0, 10.0)
+    .with_keyframe(3.0, 30.0);
+  assert!(approx(track.sample(0.25).unwrap(), 2.5));
+  assert!(approx(track.sample(1.0).unwrap(), 10.0));
+  assert!(approx(track.sample(2.0).unwrap(), 20.0));
+}
+
+
+#[test]
+fn tracks_clamp_outside_their_keyframes() {
+  let track = Track::new(Interpolation::Linear)
+    .with_keyframe(1.0, 5.0f32)
+    .with_keyframe(2.0, 7.0);
+  assert_eq!(track.sample(-3.0), Some(5.0));
+  assert_eq!(track.sample(10.0), Some(7.0));
+  assert_eq!(Track::<f32>::new(Interpolation::Linear).sample(0.0), None);
+}
+
+
+#[test]
+fn loop_wraps_times_after_the_end() {
+  let clip = ramp(Wrap::Loop);
+  assert_eq!(clip.duration(), 2.0);
+  assert!(approx(clip.local_time(2.5), 0.5));
+  assert!(approx(clip.local_time(7.0), 1.0));
+  assert!(approx(clip.sample(2.5).unwrap()[0], 2.5));
+}
+
+
+#[test]
+fn loop_wraps_negative_times() {
+  let clip = ramp(Wrap::Loop);
+  assert!(approx(clip.local_time(-0.5), 1.5));
+  assert!(approx(clip.local_time(-4.5), 1.5));
+  assert!(approx(clip.sample(-0.5).unwrap()[0], 7.5));
+}
+
+
+#[test]
+fn clamp_holds_the_ends() {
+  let clip = ramp(Wrap::Clamp);
+  assert_eq!(clip.local_time(-1.0), 0.0);
+  assert_eq!(clip.local_time(5.0), 2.0);
+  assert!(approx(clip.sample(-1.0).unwrap()[0], 0.0));
+  assert!(approx(clip.sample(5.0).unwrap()[0], 10.0));
+  assert!(approx(clip.sample(1.0).unwrap()[0], 5.0));
+}
+
+
+#[test]
+fn zero_length_clips_sample_their_only_keyframe() {
+  let clip = AnimationClip::new("pose", Wrap::Loop)
+    .with_track(Track::new(Interpolation::Linear).with_keyframe(0.0, 4.0f32));
+  assert_eq!(clip.duration(), 0.0);
+  assert_eq!(clip.local_time(3.0), 0.0);
+  assert_eq!(clip.local_time(-3.0), 0.0);
+  assert_eq!(clip.sample(3.0), Some(vec![4.0]));
+
+  let empty = AnimationClip::<f32>::new("empty", Wrap::Clamp);
+  assert_eq!(empty.duration(), 0.0);
+  assert_eq!(empty.sample(1.0), Some(vec![]));
+}
@@ -0,0 +1,58 @@
+//! Parsing TexturePacker atlases, which doesn't need a device.
+use nalgebra::Vector2;
+
+use meshterial::textures::atlas::{Atlas, AtlasError, AtlasFrame};
+
+use std::path::Path;
+
+
+fn fixture() -> Atlas {
+  Atlas::open(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/atlas.json"))
+    .expect("Could not open fixture atlas.")
+}
+
+
+#[test]
+fn atlas_hash_frames() {
+  let atlas = fixture();
+  assert_eq!(atlas.frames.len(), 2);
+  assert_eq!(atlas.image, Some("atlas.png".to_string()));
+  assert_eq!(atlas.size, Some(Vector2::new(64, 64)));
+  assert_eq!(
+    atlas.frames["coin.png"],
+    AtlasFrame { x: 32, y: 16, width: 16, height: 16 }
+  );
+}
+
+
+#[test]
+fn atlas_frame_uv_rect() {
+  let atlas = fixture();
+  let size = atlas.size.expect("Fixture has no size.");
+  assert_eq!(atlas.frames["hero.png"].uv_rect(size), [0.0, 0.0, 0.5, 1.0]);
+  assert_eq!(atlas.frames["coin.png"].uv_rect(size), [0.5, 0.25, 0.75, 0.5]);
+}
+
+
+#[test]
+fn atlas_array_frames() {
+  let atlas = Atlas::parse(r#"{
+    "frames": [
+      {"filename": "a", "frame": {"x": 1, "y": 2, "w": 3, "h": 4}}
+    ]
+  }"#).expect("Could not parse array atlas.");
+  assert_eq!(atlas.frames["a"], AtlasFrame { x: 1, y: 2, width: 3, height: 4 });
+  assert_eq!(atlas.image, None);
+}
+
+
+#[test]
+fn atlas_rejects_rotated_frames() {
+  let result = Atlas::parse(r#"{
+    "frames": {"a": {"frame": {"x": 0, "y": 0, "w": 1, "h": 1}, "rotated": true}}
+  }"#);
+  match result {
+    Err(AtlasError::RotatedFrame(name)) => assert_eq!(name, "a"),
+    other => panic!("Expected a rotated frame error, got {:?}", other),
+  }
+}
@@ -0,0 +1,133 @@
+//! Bounding volumes and frustum culling, which don't need a device.
+use meshterial::bounds::{Aabb, BoundingSphere, Frustum};
+use nalgebra::{Matrix4, Perspective3, Vector3, Vector4};
+
+
+fn approx(a: f32, b: f32) -> bool {
+  (a - b).abs() < 1.0e-5
+}
+
+
+fn cube(center: [f32; 3], half: f32) -> Aabb {
+  let center = Vector3::from(center);
+  let half = Vector3::new(half, half, half);
+  Aabb { min: center - half, max: center + half }
+}
+
+
+/// A camera at the origin looking down -z with a 90 degree field of view, so
+/// at a depth of 10 it sees -10 to 10 along x and y.
+fn perspective_frustum() -> Frustum {
+  let proj = Perspective3::new(1.0, std::f32::consts::FRAC_PI_2, 1.0, 100.0);
+  Frustum::from_matrix(proj.as_matrix())
+}
+
+
+#[test]
+fn identity_frustum_is_the_unit_cube() {
+  let frustum = Frustum::from_matrix(&Matrix4::identity());
+  let expected = [
+    Vector4::new(1.0, 0.0, 0.0, 1.0),
+    Vector4::new(-1.0, 0.0, 0.0, 1.0),
+    Vector4::new(0.0, 1.0, 0.0, 1.0),
+    Vector4::new(0.0, -1.0, 0.0, 1.0),
+    Vector4::new(0.0, 0.0, 1.0, 1.0),
+    Vector4::new(0.0, 0.0, -1.0, 1.0),
+  ];
+  for (plane, expected) in frustum.planes.iter().zip(expected.iter()) {
+    assert!((plane - expected).norm() < 1.0e-5, "{:?} != {:?}", plane, expected);
+  }
+}
+
+
+#[test]
+fn perspective_planes_are_normalized() {
+  for plane in perspective_frustum().planes.iter() {
+    assert!(approx(Vector3::new(plane.x, plane.y, plane.z).norm(), 1.0));
+  }
+}
+
+
+#[test]
+fn boxes_inside_the_frustum_intersect() {
+  let frustum = perspective_frustum();
+  assert!(frustum.intersects_aabb(&cube([0.0, 0.0, -10.0], 1.0)));
+  assert!(frustum.intersects_aabb(&cube([5.0, -5.0, -50.0], 2.0)));
+}
+
+
+#[test]
+fn boxes_outside_the_frustum_do_not_intersect() {
+  let frustum = perspective_frustum();
+  // Right of, above, behind, too near and too far.
+  assert!(!frustum.intersects_aabb(&cube([30.0, 0.0, -10.0], 1.0)));
+  assert!(!frustum.intersects_aabb(&cube([0.0, 30.0, -10.0], 1.0)));
+  assert!(!frustum.intersects_aabb(&cube([0.0, 0.0, 5.0], 1.0)));
+  assert!(!frustum.intersects_aabb(&cube([0.0, 0.0, -0.25], 0.5)));
+  assert!(!frustum.intersects_aabb(&cube([0.0, 0.0, -200.0], 1.0)));
+}
+
+
+#[test]
+fn boxes_straddling_a_plane_intersect() {
+  let frustum = perspective_frustum();
+  // Across the right plane, which is at x = 10 when z = -10.
+  assert!(frustum.intersects_aabb(&cube([10.0, 0.0, -10.0], 2.0)));
+  // Across the near and far planes.
+  assert!(frustum.intersects_aabb(&cube([0.0, 0.0, -1.0], 0.5)));
+  assert!(frustum.intersects_aabb(&cube([0.0, 0.0, -100.0], 5.0)));
+}
+
+
+#[test]
+fn frustum_from_a_view_projection_is_in_world_space() {
+  // The same camera moved 100 units along +x.
+  let view = Matrix4::new_translation(&Vector3::new(-100.0, 0.0, 0.0));
+  let proj = Perspective3::new(1.0, std::f32::consts::FRAC_PI_2, 1.0, 100.0);
+  let frustum = Frustum::from_matrix(&(proj.as_matrix() * view));
+  assert!(frustum.intersects_aabb(&cube([100.0, 0.0, -10.0], 1.0)));
+  assert!(!frustum.intersects_aabb(&cube([0.0, 0.0, -10.0], 1.0)));
+}
+
+
+#[test]
+fn spheres_are_culled_by_distance_to_each_plane() {
+  let frustum = perspective_frustum();
+  let sphere = |center: [f32; 3], radius: f32| BoundingSphere { center: Vector3::from(center), radius };
+  assert!(frustum.intersects_sphere(&sphere([0.0, 0.0, -10.0], 1.0)));
+  assert!(frustum.intersects_sphere(&sphere([11.0, 0.0, -10.0], 2.0)));
+  assert!(!frustum.intersects_sphere(&sphere([30.0, 0.0, -10.0], 2.0)));
+  assert!(!frustum.intersects_sphere(&sphere([0.0, 0.0, 5.0], 1.0)));
+}
+
+
+#[test]
+fn bounding_sphere_contains_its_points() {
+  let points = [
+    [1.0, 0.0, 0.0],
+    [-1.0, 0.0, 0.0],
+    [0.0, 2.0, 0.0],
+    [0.0, 0.0, -3.0],
+    [0.5, 0.5, 0.5],
+  ];
+  let sphere = BoundingSphere::from_points(points.iter().cloned()).expect("Points should have a sphere.");
+  for p in points.iter() {
+    let p = Vector3::from(*p);
+    assert!((p - sphere.center).norm() <= sphere.radius + 1.0e-5, "{:?} is outside {:?}", p, sphere);
+  }
+  assert!(BoundingSphere::from_points(Vec::new()).is_none());
+}
+
+
+#[test]
+fn bounding_sphere_from_aabb_and_transformed() {
+  let sphere = BoundingSphere::from_aabb(&cube([1.0, 2.0, 3.0], 1.0));
+  assert_eq!(sphere.center, Vector3::new(1.0, 2.0, 3.0));
+  assert!(approx(sphere.radius, 3.0f32.sqrt()));
+
+  let transform = Matrix4::new_translation(&Vector3::new(1.0, 0.0, 0.0))
+    * Matrix4::new_nonuniform_scaling(&Vector3::new(1.0, 4.0, 2.0));
+  let moved = sphere.transformed(&transform);
+  assert!((moved.center - Vector3::new(2.0, 8.0, 6.0)).norm() < 1.0e-5);
+  assert!(approx(moved.radius, 4.0 * 3.0f32.sqrt()));
+}
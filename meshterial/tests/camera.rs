@@ -0,0 +1,53 @@
+//! Framing bounds with the camera.
+use meshterial::bounds::Aabb;
+use meshterial::Camera;
+use nalgebra::{Point3, Vector3};
+
+
+/// The corners of the box in normalized device coordinates.
+fn projected_corners(camera: &Camera, aabb: &Aabb) -> Vec<Point3<f32>> {
+  let view_projection = camera.view_projection();
+  (0 .. 8)
+    .map(|i| {
+      let corner = Point3::new(
+        if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+        if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+        if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+      );
+      view_projection.transform_point(&corner)
+    })
+    .collect()
+}
+
+
+fn assert_framed(camera: &Camera, aabb: &Aabb) {
+  for p in projected_corners(camera, aabb) {
+    assert!(p.x.abs() <= 1.0 && p.y.abs() <= 1.0, "{:?} is outside of the view", p);
+    assert!(p.z.abs() <= 1.0, "{:?} is clipped", p);
+  }
+}
+
+
+#[test]
+fn framing_keeps_the_view_direction() {
+  let aabb = Aabb { min: Vector3::new(9.0, -1.0, -1.0), max: Vector3::new(11.0, 1.0, 1.0) };
+  let mut camera = Camera::default();
+  let forward = camera.forward();
+  camera.frame_bounds(&aabb);
+  assert_eq!(camera.target, aabb.center());
+  assert!((camera.forward() - forward).norm() < 1e-5);
+  assert_framed(&camera, &aabb);
+}
+
+
+#[test]
+fn framing_a_wide_box_fits_it_horizontally() {
+  let aabb = Aabb { min: Vector3::new(-50.0, -1.0, -1.0), max: Vector3::new(50.0, 1.0, 1.0) };
+  let mut camera = Camera::default();
+  camera.position = Vector3::new(0.0, 0.0, 10.0);
+  camera.set_dimensions([400, 800]);
+  camera.frame_bounds(&aabb);
+  assert_framed(&camera, &aabb);
+  // It was far enough that the far plane had to move.
+  assert!(camera.far > 100.0);
+}
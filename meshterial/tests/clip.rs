@@ -0,0 +1,95 @@
+//! Cutting geometry with a clip plane. Without a vulkan device the GPU test
+//! prints a message and passes.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use nalgebra::Matrix4;
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+
+use meshterial::pipelines::clip::ClipPlaneUniform;
+use meshterial::pipelines::color3d::{self, Color3DPipeline, VertexColor3};
+use meshterial::pipelines::fog::{FogParams, FogUniform};
+
+use std::sync::Arc;
+
+use common::Headless;
+
+
+fn vertex(x: f32, y: f32) -> VertexColor3 {
+  VertexColor3 { position: [x, y, 0.5], color: [1.0, 1.0, 1.0, 1.0] }
+}
+
+
+#[test]
+fn no_clip_plane_keeps_everything() {
+  let uniform = ClipPlaneUniform::from(None);
+  assert_eq!(uniform.plane, [0.0, 0.0, 0.0, 1.0]);
+  let plane = [1.0, 0.0, 0.0, -2.0];
+  assert_eq!(ClipPlaneUniform::from(Some(plane)).plane, plane);
+}
+
+
+#[test]
+fn clip_plane_discards_the_negative_side() {
+  let headless = match Headless::new([32, 32]) {
+    Some(headless) => headless,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let pipeline = Color3DPipeline::new(
+    headless.render_pass.clone(),
+    headless.device.clone(),
+    headless.physical()
+  );
+  headless.upload(Matrix4::identity(), pipeline.proj_uniform_device_buffer.clone());
+  let fog = CpuAccessibleBuffer::from_data(
+    headless.device.clone(),
+    BufferUsage::uniform_buffer(),
+    FogUniform::from(&FogParams::default())
+  ).expect("Could not create fog buffer.");
+  // Keep everything with x >= 0, the right half of the screen.
+  let clip = CpuAccessibleBuffer::from_data(
+    headless.device.clone(),
+    BufferUsage::uniform_buffer(),
+    ClipPlaneUniform::from(Some([1.0, 0.0, 0.0, 0.0]))
+  ).expect("Could not create clip plane buffer.");
+  let set = Arc::new(
+    PersistentDescriptorSet::start(pipeline.pipeline.clone(), 0)
+      .add_buffer(pipeline.proj_uniform_device_buffer.clone()).expect("Could not add projection buffer.")
+      .add_buffer(fog).expect("Could not add fog buffer.")
+      .add_buffer(clip).expect("Could not add clip plane buffer.")
+      .build()
+      .expect("Could not build projection set.")
+  );
+
+  // A quad covering the screen, wound like the color3d golden triangle.
+  let vertices = CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    vec![
+      vertex(-1.0, 1.0), vertex(1.0, 1.0), vertex(-1.0, -1.0),
+      vertex(-1.0, -1.0), vertex(1.0, 1.0), vertex(1.0, -1.0),
+    ].into_iter()
+  ).expect("Could not create vertex buffer.");
+  let push_constants = color3d::vs::ty::ModelView {
+    model: Matrix4::identity().into(),
+    view: Matrix4::identity().into(),
+  };
+  let rendered = headless.render([0.0, 0.0, 0.0, 1.0], |cmds, dynamic_state| {
+    cmds
+      .draw(
+        pipeline.pipeline.clone(),
+        dynamic_state,
+        vec![vertices as Arc<BufferAccess + Send + Sync>],
+        set,
+        push_constants
+      ).expect("Could not draw quad.")
+  });
+  assert_eq!(rendered.get_pixel(8, 16).data, [0, 0, 0, 255]);
+  assert_eq!(rendered.get_pixel(24, 16).data, [255, 255, 255, 255]);
+}
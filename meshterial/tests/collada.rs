@@ -0,0 +1,68 @@
+//! Loading Collada files and merging material libraries, which doesn't need a
+//! device.
+use meshterial::loaders::collada;
+use meshterial::loaders::MaterialLibrary;
+use meshterial::pipelines::phong::Material;
+
+use std::path::Path;
+
+
+fn material(diffuse: f32) -> Material {
+  Material {
+    emission: [0.0, 0.0, 0.0, 1.0],
+    ambient: [0.1, 0.1, 0.1, 1.0],
+    diffuse: [diffuse, diffuse, diffuse, 1.0],
+    specular: [0.5, 0.5, 0.5, 1.0],
+    shininess: 16.0,
+    double_sided: 0,
+  }
+}
+
+
+#[test]
+fn loads_the_fixture() {
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/test.dae");
+  let model = collada::load(&path).expect("Could not load test.dae.");
+  assert!(!model.vertices.is_empty());
+  for (effect, vertices) in model.vertices.iter() {
+    assert!(model.materials.contains_key(effect), "no material for {}", effect);
+    assert!(!vertices.is_empty());
+    assert_eq!(vertices.len() % 3, 0);
+  }
+  let stats = model.stats();
+  assert!(stats.triangle_count > 0);
+  assert_eq!(stats.material_count, model.vertices.len());
+}
+
+
+#[test]
+fn shares_identical_materials() {
+  let mut library = MaterialLibrary::new();
+  assert_eq!(library.insert("rock", "stone", material(0.5)), "stone");
+  assert_eq!(library.insert("wall", "stone", material(0.5)), "stone");
+  assert_eq!(library.len(), 1);
+}
+
+
+#[test]
+fn namespaces_conflicting_materials() {
+  let mut library = MaterialLibrary::new();
+  assert_eq!(library.insert("rock", "stone", material(0.5)), "stone");
+  assert_eq!(library.insert("wall", "stone", material(0.8)), "wall/stone");
+  assert_eq!(library.names(), vec!["stone".to_string(), "wall/stone".to_string()]);
+  let diffuse = library.get("wall/stone").expect("Could not get wall/stone.").diffuse;
+  assert_eq!(diffuse, [0.8, 0.8, 0.8, 1.0]);
+
+  // Loading the same file again shares its namespaced material.
+  assert_eq!(library.insert("wall", "stone", material(0.8)), "wall/stone");
+  assert_eq!(library.len(), 2);
+}
+
+
+#[test]
+fn reads_double_sided_effects() {
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/double_sided.dae");
+  let model = collada::load(&path).expect("Could not load double_sided.dae.");
+  assert_eq!(model.materials["paper-effect"].double_sided, 1);
+  assert_eq!(model.materials["card-effect"].double_sided, 0);
+}
@@ -0,0 +1,428 @@
+//! Offscreen rendering and image comparison shared by the integration tests.
+//!
+//! `Headless` renders into an offscreen image without a window, so scenes
+//! can be drawn with the pipelines directly and read back as PNGs. The
+//! benchmarks include this module too, so that they draw the same scenes.
+#![allow(dead_code)]
+use image::{Rgba, RgbaImage};
+use nalgebra::{Matrix4, Vector3};
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer, DynamicState};
+use vulkano::device::{Device, DeviceExtensions, Features, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::image::ImageUsage;
+use vulkano::instance::{Instance, InstanceExtensions, PhysicalDevice};
+use vulkano::descriptor::descriptor_set::DescriptorSet;
+use vulkano::pipeline::viewport::{Scissor, Viewport};
+use vulkano::sync::GpuFuture;
+
+use meshterial::pipelines::phong::{Light, Material, PhongPipeline, PhongPipelineConfig, VertexPhong};
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+
+/// The format of the offscreen image.
+pub const FORMAT: Format = Format::R8G8B8A8Unorm;
+
+
+/// A device, queue and offscreen color + depth target.
+pub struct Headless {
+  pub instance: Arc<Instance>,
+  pub device: Arc<Device>,
+  pub queue: Arc<Queue>,
+  pub render_pass: Arc<RenderPassAbstract + Send + Sync>,
+  pub dimensions: [u32; 2],
+  image: Arc<AttachmentImage>,
+  framebuffer: Arc<FramebufferAbstract + Send + Sync>,
+}
+
+
+impl Headless {
+  /// Returns None if there is no vulkan device to render with, eg. in CI
+  /// without a GPU or software renderer, in which case the test should be
+  /// skipped.
+  pub fn new(dimensions: [u32; 2]) -> Option<Headless> {
+    let instance = Instance::new(None, &InstanceExtensions::none(), None).ok()?;
+    let (device, queue) = {
+      let physical = PhysicalDevice::enumerate(&instance).next()?;
+      let family = physical
+        .queue_families()
+        .find(|q| q.supports_graphics())?;
+      let (device, mut queues) = Device::new(
+        physical,
+        &Features::none(),
+        &DeviceExtensions::none(),
+        [(family, 0.5)].iter().cloned()
+      ).ok()?;
+      (device, queues.next()?)
+    };
+
+    let render_pass = Arc::new(
+      single_pass_renderpass!(
+        device.clone(),
+        attachments: {
+          color: {
+            load: Clear,
+            store: Store,
+            format: FORMAT,
+            samples: 1,
+          },
+          depth: {
+            load: Clear,
+            store: DontCare,
+            format: Format::D16Unorm,
+            samples: 1,
+          }
+        },
+        pass: {
+          color: [color],
+          depth_stencil: {depth}
+        }
+      ).expect("Could not create headless render pass.")
+    ) as Arc<RenderPassAbstract + Send + Sync>;
+
+    let image = AttachmentImage::with_usage(
+      device.clone(),
+      dimensions,
+      FORMAT,
+      ImageUsage {
+        color_attachment: true,
+        transfer_source: true,
+        .. ImageUsage::none()
+      }
+    ).expect("Could not create headless image.");
+    let depth_buffer = AttachmentImage::transient(device.clone(), dimensions, Format::D16Unorm)
+      .expect("Could not create headless depth buffer.");
+    let framebuffer = Arc::new(
+      Framebuffer::start(render_pass.clone())
+        .add(image.clone()).expect("Could not add image to framebuffer.")
+        .add(depth_buffer).expect("Could not add depth buffer to framebuffer.")
+        .build().expect("Could not build headless framebuffer.")
+    );
+
+    Some(Headless {
+      instance,
+      device,
+      queue,
+      render_pass,
+      dimensions,
+      image,
+      framebuffer,
+    })
+  }
+
+
+  /// The physical device the device was created from.
+  pub fn physical(&self) -> PhysicalDevice {
+    self.device.physical_device()
+  }
+
+
+  /// A dynamic state covering the whole image.
+  pub fn dynamic_state(&self) -> DynamicState {
+    DynamicState {
+      line_width: None,
+      viewports: Some(vec![Viewport {
+        origin: [0.0, 0.0],
+        dimensions: [self.dimensions[0] as f32, self.dimensions[1] as f32],
+        depth_range: 0.0 .. 1.0,
+      }]),
+      scissors: Some(vec![Scissor {
+        origin: [0, 0],
+        dimensions: self.dimensions,
+      }]),
+    }
+  }
+
+
+  /// Write `data` into a device local uniform buffer, waiting until it is
+  /// written.
+  pub fn upload<T>(&self, data: T, dest: Arc<DeviceLocalBuffer<T>>)
+    where T: Send + Sync + 'static
+  {
+    let src = CpuAccessibleBuffer::from_data(self.device.clone(), BufferUsage::transfer_source(), data)
+      .expect("Could not create upload buffer.");
+    let command_buffer =
+      AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.queue.family())
+        .expect("Could not create a new command buffer builder.")
+        .copy_buffer(src, dest)
+        .expect("Could not copy upload buffer.")
+        .build()
+        .expect("Could not build upload command buffer.");
+    self.execute(command_buffer);
+  }
+
+
+  /// Start a command buffer that clears the image to `clear_color` and
+  /// begins the render pass.
+  pub fn begin(&self, clear_color: [f32; 4]) -> AutoCommandBufferBuilder {
+    AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.queue.family())
+      .expect("Could not create a new command buffer builder.")
+      .begin_render_pass(
+        self.framebuffer.clone(),
+        false,
+        vec![ClearValue::Float(clear_color), 1f32.into()]
+      ).expect("Could not begin headless render pass.")
+  }
+
+
+  /// Submit a command buffer and wait for it to finish.
+  pub fn execute<C>(&self, command_buffer: C)
+    where C: CommandBuffer + Send + Sync + 'static
+  {
+    command_buffer
+      .execute(self.queue.clone())
+      .expect("Could not execute headless command buffer.")
+      .then_signal_fence_and_flush()
+      .expect("Could not flush headless command buffer.")
+      .wait(None)
+      .expect("Could not wait for headless command buffer.");
+  }
+
+
+  /// Clear the image to `clear_color`, record draws with `draw` and read the
+  /// result back.
+  pub fn render<F>(&self, clear_color: [f32; 4], draw: F) -> RgbaImage
+    where F: FnOnce(AutoCommandBufferBuilder, &DynamicState) -> AutoCommandBufferBuilder
+  {
+    let width = self.dimensions[0];
+    let height = self.dimensions[1];
+    let buffer = CpuAccessibleBuffer::from_iter(
+      self.device.clone(),
+      BufferUsage::all(),
+      (0 .. width * height * 4).map(|_| 0u8)
+    ).expect("Could not create readback buffer.");
+    let dynamic_state = self.dynamic_state();
+    let command_buffer = draw(self.begin(clear_color), &dynamic_state)
+      .end_render_pass().expect("Could not end headless render pass.")
+      .copy_image_to_buffer(self.image.clone(), buffer.clone())
+      .expect("Could not copy image to readback buffer.")
+      .build()
+      .expect("Could not build headless command buffer.");
+    self.execute(command_buffer);
+
+    assert_eq!(buffer.len(), (width * height * 4) as usize);
+    let pixels = buffer.read().expect("Could not read readback buffer.").to_vec();
+    RgbaImage::from_raw(width, height, pixels).expect("Could not make image from readback.")
+  }
+}
+
+
+/// The path of a golden image in `tests/golden`.
+pub fn golden_path(name: &str) -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR"))
+    .join("tests")
+    .join("golden")
+    .join(format!("{}.png", name))
+}
+
+
+/// Compare a rendered image to the golden image at `golden`, panicking if
+/// their sizes differ or any channel of any pixel differs by more than
+/// `tolerance`. On failure the rendered image is saved next to the golden as
+/// `<name>.actual.png`.
+///
+/// With `MESHTERIAL_UPDATE_GOLDENS` set the rendered image is saved as the
+/// golden instead. Without it a missing golden fails the test, so that a
+/// checkout without its goldens can't pass by writing them.
+pub fn assert_image_matches(rendered: &RgbaImage, golden: &Path, tolerance: u8) {
+  if env::var_os("MESHTERIAL_UPDATE_GOLDENS").is_some() {
+    rendered.save(golden).expect("Could not save golden image.");
+    println!("Wrote golden image {}", golden.display());
+    return;
+  }
+  if !golden.exists() {
+    let actual_path = golden.with_extension("actual.png");
+    rendered.save(&actual_path).expect("Could not save rendered image.");
+    panic!(
+      "There is no golden image {}, see {} - run with MESHTERIAL_UPDATE_GOLDENS=1 to write it",
+      golden.display(),
+      actual_path.display()
+    );
+  }
+
+  let expected = image::open(golden)
+    .expect(&format!("Could not open golden image {}", golden.display()))
+    .to_rgba();
+  let actual_path = golden.with_extension("actual.png");
+  if expected.dimensions() != rendered.dimensions() {
+    rendered.save(&actual_path).expect("Could not save rendered image.");
+    panic!(
+      "{} is {:?} but the rendered image is {:?}, see {}",
+      golden.display(),
+      expected.dimensions(),
+      rendered.dimensions(),
+      actual_path.display()
+    );
+  }
+
+  let differs = |a: &Rgba<u8>, b: &Rgba<u8>| {
+    a.data
+      .iter()
+      .zip(b.data.iter())
+      .any(|(x, y)| (*x as i16 - *y as i16).abs() > tolerance as i16)
+  };
+  let mismatched = expected
+    .pixels()
+    .zip(rendered.pixels())
+    .filter(|(a, b)| differs(a, b))
+    .count();
+  if mismatched > 0 {
+    rendered.save(&actual_path).expect("Could not save rendered image.");
+    panic!(
+      "{} pixels differ from {} by more than {}, see {}",
+      mismatched,
+      golden.display(),
+      tolerance,
+      actual_path.display()
+    );
+  }
+}
+
+
+/// A unit cube centered on the origin, with flat normals.
+pub fn cube() -> Vec<VertexPhong> {
+  let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+    // normal, u, v
+    ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+    ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+    ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+    ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+  ];
+  let mut vertices = vec![];
+  for (n, u, v) in faces.iter() {
+    let corner = |su: f32, sv: f32| VertexPhong {
+      position: [
+        0.5 * (n[0] + su * u[0] + sv * v[0]),
+        0.5 * (n[1] + su * u[1] + sv * v[1]),
+        0.5 * (n[2] + su * u[2] + sv * v[2]),
+      ],
+      normal: *n,
+    };
+    vertices.extend(vec![
+      corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0),
+      corner(-1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0),
+    ]);
+  }
+  vertices
+}
+
+
+/// A sphere of radius 0.5 centered on the origin, with smooth normals.
+pub fn sphere(rings: usize, segments: usize) -> Vec<VertexPhong> {
+  let point = |ring: usize, segment: usize| {
+    let theta = ring as f32 / rings as f32 * ::std::f32::consts::PI;
+    let phi = segment as f32 / segments as f32 * 2.0 * ::std::f32::consts::PI;
+    let normal = [theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin()];
+    VertexPhong {
+      position: [normal[0] * 0.5, normal[1] * 0.5, normal[2] * 0.5],
+      normal,
+    }
+  };
+  let mut vertices = vec![];
+  for ring in 0 .. rings {
+    for segment in 0 .. segments {
+      let a = point(ring, segment);
+      let b = point(ring + 1, segment);
+      let c = point(ring + 1, segment + 1);
+      let d = point(ring, segment + 1);
+      vertices.extend(vec![a.clone(), b, c.clone(), a, c, d]);
+    }
+  }
+  vertices
+}
+
+
+/// `count` unit cubes laid out in a square grid on the xy plane, with a
+/// spacing of 1.5, centered on the origin.
+pub fn cube_grid(count: usize) -> Vec<Vector3<f32>> {
+  let side = (count as f32).sqrt().ceil().max(1.0) as usize;
+  let offset = (side - 1) as f32 * 0.75;
+  (0 .. count)
+    .map(|i| Vector3::new(
+      (i % side) as f32 * 1.5 - offset,
+      (i / side) as f32 * 1.5 - offset,
+      0.0
+    ))
+    .collect()
+}
+
+
+/// A phong pipeline with one material named "cube" and a white light, looking
+/// at a cube at the origin.
+pub struct PhongScene {
+  pub pipeline: PhongPipeline,
+  pub cube: Arc<CpuAccessibleBuffer<[VertexPhong]>>,
+  pub sets: (
+    Arc<DescriptorSet + Send + Sync>,
+    Arc<DescriptorSet + Send + Sync>,
+    Arc<DescriptorSet + Send + Sync>,
+  ),
+}
+
+
+impl PhongScene {
+  /// The camera is folded into the projection, so the modelview of each draw
+  /// is only its model matrix. `distance` is how far the camera is from the
+  /// origin.
+  pub fn new(headless: &Headless, distance: f32) -> PhongScene {
+    PhongScene::with_config(headless, distance, PhongPipelineConfig::new())
+  }
+
+
+  /// A scene whose pipeline is built from `config`, with the "cube" material
+  /// added to it.
+  pub fn with_config(headless: &Headless, distance: f32, config: PhongPipelineConfig) -> PhongScene {
+    let pipeline = config
+      .with_material("cube")
+      .build(
+        headless.render_pass.clone(),
+        headless.device.clone(),
+        headless.physical()
+      ).expect("Could not build phong pipeline.");
+    let projection = Matrix4::new_perspective(1.0, ::std::f32::consts::FRAC_PI_2, 0.1, distance * 4.0)
+      * Matrix4::new_translation(&Vector3::new(0.0, 0.0, -distance));
+    headless.upload(projection, pipeline.proj.device_buffer.clone());
+    let material = Material {
+      emission: [0.0, 0.0, 0.0, 1.0],
+      ambient: [0.1, 0.1, 0.1, 1.0],
+      diffuse: [0.8, 0.4, 0.2, 1.0],
+      specular: [0.5, 0.5, 0.5, 1.0],
+      shininess: 16.0,
+      double_sided: 0,
+    };
+    let (material_buffer, material_set) = {
+      let material = pipeline
+        .materials
+        .get("cube")
+        .expect("Could not get cube material.");
+      (material.device_buffer.clone(), material.desc_set.clone())
+    };
+    headless.upload(material, material_buffer);
+    let light = Light {
+      position: [2.0, 2.0, 2.0],
+      _dummy0: [0, 0, 0, 0],
+      intensity: [1.0, 1.0, 1.0],
+    };
+    headless.upload(light, pipeline.light.device_buffer.clone());
+
+    let cube = CpuAccessibleBuffer::from_iter(
+      headless.device.clone(),
+      BufferUsage::all(),
+      cube().into_iter()
+    ).expect("Could not create cube vertex buffer.");
+    let sets = (
+      pipeline.proj.desc_set.clone(),
+      material_set,
+      pipeline.light.desc_set.clone()
+    );
+    PhongScene { pipeline, cube, sets }
+  }
+}
@@ -0,0 +1,64 @@
+//! Debug drawing, which is laid out on the CPU.
+use meshterial::bounds::Aabb;
+use meshterial::{Camera, DebugDraw};
+use nalgebra::{Point3, Vector3};
+
+use std::f32::INFINITY;
+
+
+const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+
+#[test]
+fn aabb_draws_twelve_edges() {
+  let mut debug = DebugDraw::new();
+  debug.aabb(
+    &Aabb { min: Vector3::new(-1.0, -1.0, -1.0), max: Vector3::new(1.0, 2.0, 3.0) },
+    RED
+  );
+  let vertices = debug.vertices(&Camera::default(), 600);
+  assert_eq!(vertices.len(), 24);
+  // Every edge runs along exactly one axis.
+  for edge in vertices.chunks(2) {
+    let a = Vector3::from(edge[0].position);
+    let b = Vector3::from(edge[1].position);
+    let axes = (0 .. 3).filter(|&i| a[i] != b[i]).count();
+    assert_eq!(axes, 1);
+  }
+}
+
+
+#[test]
+fn text_keeps_its_pixel_size() {
+  let camera = Camera::default();
+  let height = |position: Vector3<f32>| {
+    let mut debug = DebugDraw::new();
+    debug.text(position, "I");
+    let vertices = debug.vertices(&camera, 600);
+    assert!(!vertices.is_empty());
+    let ys = vertices.iter().map(|v| {
+      camera
+        .view_projection()
+        .transform_point(&Point3::from_coordinates(Vector3::from(v.position)))
+        .y
+    });
+    let (low, high) = ys.fold((INFINITY, -INFINITY), |(l, h), y| (l.min(y), h.max(y)));
+    high - low
+  };
+  let near = height(Vector3::zeros());
+  let far = height(camera.position + (Vector3::zeros() - camera.position) * 4.0);
+  assert!((near - far).abs() < 1e-3, "{} != {}", near, far);
+}
+
+
+#[test]
+fn flushing_clears_and_skips_text_behind_the_camera() {
+  let camera = Camera::default();
+  let mut debug = DebugDraw::new();
+  debug.text(camera.position - camera.forward(), "behind");
+  assert!(!debug.is_empty());
+  assert!(debug.vertices(&camera, 600).is_empty());
+  debug.line(Vector3::zeros(), Vector3::x(), RED);
+  debug.clear();
+  assert!(debug.is_empty());
+}
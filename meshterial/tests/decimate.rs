@@ -0,0 +1,78 @@
+//! Mesh simplification, which runs on the CPU only.
+use meshterial::pipelines::phong::VertexPhong;
+use meshterial::utils::{decimate, decimate_with_options, DecimateOptions};
+
+
+/// A plane from -1.0 to 1.0 on x and y split into `n` by `n` quads of two
+/// triangles each.
+fn grid(n: usize) -> (Vec<VertexPhong>, Vec<u32>) {
+  let mut vertices = vec![];
+  for y in 0 ..= n {
+    for x in 0 ..= n {
+      vertices.push(VertexPhong {
+        position: [
+          x as f32 / n as f32 * 2.0 - 1.0,
+          y as f32 / n as f32 * 2.0 - 1.0,
+          0.0
+        ],
+        normal: [0.0, 0.0, 1.0],
+      });
+    }
+  }
+  let mut indices = vec![];
+  let row = n as u32 + 1;
+  for y in 0 .. n as u32 {
+    for x in 0 .. n as u32 {
+      let i = y * row + x;
+      indices.extend(vec![i, i + 1, i + row + 1, i, i + row + 1, i + row]);
+    }
+  }
+  (vertices, indices)
+}
+
+
+fn is_boundary(position: &[f32; 3]) -> bool {
+  position[0].abs() == 1.0 || position[1].abs() == 1.0
+}
+
+
+#[test]
+fn decimate_plane_keeps_its_shape() {
+  let (vertices, indices) = grid(10);
+  let (decimated_vertices, decimated_indices) = decimate(&vertices, &indices, 0.25);
+
+  let triangles = decimated_indices.len() / 3;
+  assert!(triangles < indices.len() / 3 / 2, "only decimated to {} triangles", triangles);
+  assert!(triangles > 0);
+  for v in decimated_vertices.iter() {
+    assert!(v.position[2].abs() < 1e-5, "{:?} left the plane", v.position);
+    assert!(v.position[0].abs() <= 1.0 + 1e-5 && v.position[1].abs() <= 1.0 + 1e-5);
+  }
+
+  // Every boundary vertex is still there, so the outline is unchanged.
+  let boundary = vertices.iter().filter(|v| is_boundary(&v.position)).count();
+  let decimated_boundary = decimated_vertices
+    .iter()
+    .filter(|v| is_boundary(&v.position))
+    .count();
+  assert_eq!(boundary, decimated_boundary);
+}
+
+
+#[test]
+fn decimate_without_boundary_preservation_removes_more() {
+  let (vertices, indices) = grid(10);
+  let options = DecimateOptions { preserve_boundary: false };
+  let (_, preserved) = decimate(&vertices, &indices, 0.0);
+  let (_, unpreserved) = decimate_with_options(&vertices, &indices, 0.0, &options);
+  assert!(unpreserved.len() < preserved.len());
+}
+
+
+#[test]
+fn decimate_ratio_of_one_changes_nothing() {
+  let (vertices, indices) = grid(4);
+  let (decimated_vertices, decimated_indices) = decimate(&vertices, &indices, 1.0);
+  assert_eq!(decimated_vertices.len(), vertices.len());
+  assert_eq!(decimated_indices.len(), indices.len());
+}
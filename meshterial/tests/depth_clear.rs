@@ -0,0 +1,94 @@
+//! Clearing depth in the middle of a render pass. Without a vulkan device the
+//! test prints a message and passes.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use nalgebra::Matrix4;
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+
+use meshterial::pipelines::color3d::{self, Color3DPipeline, VertexColor3};
+use meshterial::pipelines::depth::DepthClearPipeline;
+
+use std::sync::Arc;
+
+use common::Headless;
+
+
+/// A quad covering the screen at depth `z`, wound like the color3d golden
+/// triangle.
+fn quad(headless: &Headless, z: f32, color: [f32; 4]) -> Arc<BufferAccess + Send + Sync> {
+  let vertex = |x, y| VertexColor3 { position: [x, y, z], color };
+  CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    vec![
+      vertex(-1.0, 1.0), vertex(1.0, 1.0), vertex(-1.0, -1.0),
+      vertex(-1.0, -1.0), vertex(1.0, 1.0), vertex(1.0, -1.0),
+    ].into_iter()
+  ).expect("Could not create vertex buffer.")
+}
+
+
+fn render_near_then_far(clear_between: bool) -> Option<image::RgbaImage> {
+  let headless = Headless::new([16, 16])?;
+  let pipeline = Color3DPipeline::new(
+    headless.render_pass.clone(),
+    headless.device.clone(),
+    headless.physical()
+  );
+  headless.upload(Matrix4::identity(), pipeline.proj_uniform_device_buffer.clone());
+  let depth_clear = DepthClearPipeline::new(headless.render_pass.clone(), headless.device.clone());
+  let near = quad(&headless, 0.2, [1.0, 0.0, 0.0, 1.0]);
+  let far = quad(&headless, 0.8, [0.0, 1.0, 0.0, 1.0]);
+  let push_constants = || color3d::vs::ty::ModelView {
+    model: Matrix4::identity().into(),
+    view: Matrix4::identity().into(),
+  };
+  Some(headless.render([0.0, 0.0, 0.0, 1.0], |cmds, dynamic_state| {
+    let cmds = cmds
+      .draw(
+        pipeline.pipeline.clone(),
+        dynamic_state,
+        vec![near],
+        pipeline.proj_desc_set.clone(),
+        push_constants()
+      ).expect("Could not draw near quad.");
+    let cmds = if clear_between {
+      cmds
+        .draw(
+          depth_clear.pipeline.clone(),
+          dynamic_state,
+          vec![depth_clear.vertex_buffer.clone() as Arc<BufferAccess + Send + Sync>],
+          (),
+          ()
+        ).expect("Could not clear depth.")
+    } else {
+      cmds
+    };
+    cmds
+      .draw(
+        pipeline.pipeline.clone(),
+        dynamic_state,
+        vec![far],
+        pipeline.proj_desc_set.clone(),
+        push_constants()
+      ).expect("Could not draw far quad.")
+  }))
+}
+
+
+#[test]
+fn clearing_depth_shows_what_is_drawn_behind() {
+  let (hidden, shown) = match (render_near_then_far(false), render_near_then_far(true)) {
+    (Some(hidden), Some(shown)) => (hidden, shown),
+    _ => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  // Without the clear the far quad fails the depth test.
+  assert_eq!(hidden.get_pixel(8, 8).data, [255, 0, 0, 255]);
+  assert_eq!(shown.get_pixel(8, 8).data, [0, 255, 0, 255]);
+}
@@ -0,0 +1,88 @@
+//! Updating regions of a DynamicTexture. Without a vulkan device the tests
+//! print a message and pass.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+
+use meshterial::textures::dynamic::{DynamicTexture, DynamicTextureError};
+
+use common::Headless;
+
+
+const BLUE: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+
+
+fn texels(color: [u8; 4], count: usize) -> Vec<u8> {
+  (0 .. count).flat_map(|_| color.iter().cloned()).collect()
+}
+
+
+#[test]
+fn updated_region_replaces_only_its_texels() {
+  let headless = match Headless::new([1, 1]) {
+    Some(headless) => headless,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let mut texture = DynamicTexture::new(headless.device.clone(), [4, 4]);
+  let readback = CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    (0 .. 4 * 4 * 4).map(|_| 0u8)
+  ).expect("Could not create readback buffer.");
+
+  let cmds = AutoCommandBufferBuilder::primary_one_time_submit(headless.device.clone(), headless.queue.family())
+    .expect("Could not create a new command buffer builder.");
+  let cmds = texture
+    .record_update_region(cmds, [0, 0, 4, 4], &texels(BLUE, 16))
+    .expect("Could not fill texture.");
+  let cmds = texture
+    .record_update_region(cmds, [1, 2, 3, 2], &texels(GREEN, 6))
+    .expect("Could not update texture region.");
+  let command_buffer = cmds
+    .copy_image_to_buffer(texture.image.clone(), readback.clone())
+    .expect("Could not copy texture to readback buffer.")
+    .build()
+    .expect("Could not build command buffer.");
+  headless.execute(command_buffer);
+
+  let read = readback.read().expect("Could not read readback buffer.");
+  for y in 0 .. 4 {
+    for x in 0 .. 4 {
+      let ndx = (y * 4 + x) * 4;
+      let expected = if x >= 1 && y >= 2 { GREEN } else { BLUE };
+      assert_eq!(&read[ndx .. ndx + 4], &expected, "texel ({}, {})", x, y);
+    }
+  }
+}
+
+
+#[test]
+fn invalid_regions_are_rejected() {
+  let headless = match Headless::new([1, 1]) {
+    Some(headless) => headless,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let mut texture = DynamicTexture::new(headless.device.clone(), [4, 4]);
+  let builder = || {
+    AutoCommandBufferBuilder::primary_one_time_submit(headless.device.clone(), headless.queue.family())
+      .expect("Could not create a new command buffer builder.")
+  };
+
+  let outside = texture.record_update_region(builder(), [2, 0, 3, 1], &texels(BLUE, 3));
+  assert_eq!(outside.err(), Some(DynamicTextureError::OutOfBounds([2, 0, 3, 1])));
+  let empty = texture.record_update_region(builder(), [0, 0, 0, 1], &[]);
+  assert_eq!(empty.err(), Some(DynamicTextureError::OutOfBounds([0, 0, 0, 1])));
+  let short = texture.record_update_region(builder(), [0, 0, 2, 2], &texels(BLUE, 3));
+  assert_eq!(short.err(), Some(DynamicTextureError::DataSize(16, 12)));
+}
@@ -0,0 +1,98 @@
+//! Drawing a vertex type of our own with GenericPipeline. Without a vulkan
+//! device the test prints a message and passes.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use nalgebra::Matrix4;
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+
+use meshterial::pipelines::generic::GenericPipeline;
+use meshterial::pipelines::unlit::{self, Color};
+
+use std::sync::Arc;
+
+use common::Headless;
+
+
+/// A vertex with an attribute none of the built-in pipelines have, which the
+/// unlit shaders don't read.
+#[derive(Debug, Clone)]
+struct VertexId {
+  position: [f32; 3],
+  id: u32,
+}
+impl_vertex!(VertexId, position, id);
+
+
+#[test]
+fn generic_pipeline_draws_a_custom_vertex() {
+  let headless = match Headless::new([32, 32]) {
+    Some(headless) => headless,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let vs = unlit::vs::Shader::load(headless.device.clone()).expect("failed to create shader module");
+  let fs = unlit::fs::Shader::load(headless.device.clone()).expect("failed to create shader module");
+  let pipeline = GenericPipeline::<VertexId>::new(
+    headless.render_pass.clone(),
+    headless.device.clone(),
+    vs.main_entry_point(),
+    fs.main_entry_point()
+  );
+
+  let proj: Arc<DeviceLocalBuffer<Matrix4<f32>>> = DeviceLocalBuffer::new(
+    headless.device.clone(),
+    BufferUsage::uniform_buffer_transfer_destination(),
+    headless.physical().queue_families()
+  ).expect("Could not create projection buffer.");
+  headless.upload(Matrix4::identity(), proj.clone());
+  let color: Arc<DeviceLocalBuffer<Color>> = DeviceLocalBuffer::new(
+    headless.device.clone(),
+    BufferUsage::uniform_buffer_transfer_destination(),
+    headless.physical().queue_families()
+  ).expect("Could not create color buffer.");
+  headless.upload(Color { color: [1.0, 0.0, 0.0, 1.0] }, color.clone());
+  let proj_set = Arc::new(
+    PersistentDescriptorSet::start(pipeline.pipeline.clone(), 0)
+      .add_buffer(proj).expect("Could not add projection buffer.")
+      .build()
+      .expect("Could not build projection set.")
+  );
+  let color_set = Arc::new(
+    PersistentDescriptorSet::start(pipeline.pipeline.clone(), 1)
+      .add_buffer(color).expect("Could not add color buffer.")
+      .build()
+      .expect("Could not build color set.")
+  );
+
+  let vertices = CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    vec![
+      VertexId { position: [-1.0, 1.0, 0.5], id: 0 },
+      VertexId { position: [1.0, 1.0, 0.5], id: 1 },
+      VertexId { position: [0.0, -1.0, 0.5], id: 2 },
+    ].into_iter()
+  ).expect("Could not create vertex buffer.");
+  let push_constants = unlit::vs::ty::ModelView {
+    model: Matrix4::identity().into(),
+    view: Matrix4::identity().into(),
+  };
+  let rendered = headless.render([0.0, 0.0, 0.0, 1.0], |cmds, dynamic_state| {
+    cmds
+      .draw(
+        pipeline.pipeline.clone(),
+        dynamic_state,
+        vec![vertices as Arc<BufferAccess + Send + Sync>],
+        (proj_set, color_set),
+        push_constants
+      ).expect("Could not draw triangle.")
+  });
+  assert_eq!(rendered.get_pixel(16, 16).data, [255, 0, 0, 255]);
+}
@@ -0,0 +1,27 @@
+//! Loading glTF materials, which doesn't need a device.
+use meshterial::loaders::gltf;
+
+use std::path::Path;
+
+
+#[test]
+fn reads_each_primitives_material() {
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/double_sided.gltf");
+  let loaded = gltf::load_colored(&path).expect("Could not load double_sided.gltf.");
+  assert_eq!(loaded.vertices.len(), 6);
+  assert_eq!(loaded.stats.material_count, 2);
+
+  let primitives = &loaded.primitives;
+  assert_eq!(primitives.len(), 2);
+  assert_eq!(primitives[0].material_index, Some(0));
+  assert_eq!(primitives[0].vertices, 0 .. 3);
+  assert!(primitives[0].double_sided);
+  assert_eq!(primitives[1].material_index, Some(1));
+  assert_eq!(primitives[1].vertices, 3 .. 6);
+  assert!(!primitives[1].double_sided);
+
+  let paper = primitives[0].to_material();
+  assert_eq!(paper.double_sided, 1);
+  assert_eq!(paper.diffuse, [1.0, 0.5, 0.25, 1.0]);
+  assert_eq!(primitives[1].to_material().double_sided, 0);
+}
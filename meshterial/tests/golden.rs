@@ -0,0 +1,227 @@
+//! Renders small deterministic scenes offscreen and compares them to the
+//! golden images in `tests/golden`.
+//!
+//! A missing golden fails its test. After adding a test, or an intentional
+//! change to how something renders, write the goldens with
+//!
+//! ```text
+//! MESHTERIAL_UPDATE_GOLDENS=1 cargo test --test golden
+//! ```
+//!
+//! and check the new images before committing them. A failing test saves what
+//! it rendered as `tests/golden/<name>.actual.png`. Without a vulkan device
+//! the tests print a message and pass.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use nalgebra::Matrix4;
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImmutableImage};
+use vulkano::sampler::Filter;
+use vulkano::sync::GpuFuture;
+
+use meshterial::pipelines::color3d::{self, Color3DPipeline, VertexColor3};
+use meshterial::pipelines::phong::{vs, PhongPipelineConfig, Shading, SpecularModel};
+use meshterial::pipelines::texture2d::{self, Texture2DPipeline, VertexUV};
+
+use std::sync::Arc;
+
+use common::{assert_image_matches, golden_path, sphere, Headless, PhongScene};
+
+
+const DIMENSIONS: [u32; 2] = [64, 64];
+
+/// Different drivers rasterize and round slightly differently.
+const TOLERANCE: u8 = 2;
+
+
+fn headless() -> Option<Headless> {
+  let headless = Headless::new(DIMENSIONS);
+  if headless.is_none() {
+    println!("No vulkan device available, skipping.");
+  }
+  headless
+}
+
+
+#[test]
+fn color3d_triangle() {
+  let headless = match headless() {
+    Some(headless) => headless,
+    None => return,
+  };
+  let pipeline = Color3DPipeline::new(
+    headless.render_pass.clone(),
+    headless.device.clone(),
+    headless.physical()
+  );
+  headless.upload(Matrix4::identity(), pipeline.proj_uniform_device_buffer.clone());
+  let vertices = CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    vec![
+      VertexColor3 { position: [-0.5, 0.5, 0.5], color: [1.0, 0.0, 0.0, 1.0] },
+      VertexColor3 { position: [0.5, 0.5, 0.5], color: [0.0, 1.0, 0.0, 1.0] },
+      VertexColor3 { position: [0.0, -0.5, 0.5], color: [0.0, 0.0, 1.0, 1.0] },
+    ].into_iter()
+  ).expect("Could not create triangle vertex buffer.");
+  let push_constants = color3d::vs::ty::ModelView {
+    model: Matrix4::identity().into(),
+    view: Matrix4::identity().into(),
+  };
+
+  let rendered = headless.render([0.0, 0.0, 0.0, 1.0], |cmds, dynamic_state| {
+    cmds
+      .draw(
+        pipeline.pipeline.clone(),
+        dynamic_state,
+        vec![vertices as Arc<BufferAccess + Send + Sync>],
+        pipeline.proj_desc_set.clone(),
+        push_constants
+      ).expect("Could not draw triangle.")
+  });
+  assert_image_matches(&rendered, &golden_path("color3d_triangle"), TOLERANCE);
+}
+
+
+#[test]
+fn phong_cube() {
+  let headless = match headless() {
+    Some(headless) => headless,
+    None => return,
+  };
+  let scene = PhongScene::new(&headless, 2.5);
+  let model = Matrix4::from_euler_angles(0.3, 0.4, 0.0);
+  let push_constants = vs::ty::ModelViewNormal::new(&model, &Matrix4::identity());
+
+  let rendered = headless.render([0.0, 0.0, 0.0, 1.0], |cmds, dynamic_state| {
+    cmds
+      .draw(
+        scene.pipeline.pipeline.clone(),
+        dynamic_state,
+        vec![scene.cube.clone() as Arc<BufferAccess + Send + Sync>],
+        scene.sets.clone(),
+        push_constants
+      ).expect("Could not draw cube.")
+  });
+  assert_image_matches(&rendered, &golden_path("phong_cube"), TOLERANCE);
+}
+
+
+/// Draw a low poly sphere with the given shading.
+fn phong_sphere(headless: &Headless, config: PhongPipelineConfig) -> image::RgbaImage {
+  let scene = PhongScene::with_config(headless, 1.5, config);
+  let vertices = CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    sphere(6, 8).into_iter()
+  ).expect("Could not create sphere vertex buffer.");
+  let push_constants = vs::ty::ModelViewNormal::new(&Matrix4::identity(), &Matrix4::identity());
+  headless.render([0.0, 0.0, 0.0, 1.0], |cmds, dynamic_state| {
+    cmds
+      .draw(
+        scene.pipeline.pipeline.clone(),
+        dynamic_state,
+        vec![vertices as Arc<BufferAccess + Send + Sync>],
+        scene.sets.clone(),
+        push_constants
+      ).expect("Could not draw sphere.")
+  })
+}
+
+
+#[test]
+fn phong_smooth_and_flat_sphere() {
+  let headless = match headless() {
+    Some(headless) => headless,
+    None => return,
+  };
+  let smooth = phong_sphere(&headless, PhongPipelineConfig::new().with_shading(Shading::Smooth));
+  let flat = phong_sphere(&headless, PhongPipelineConfig::new().with_shading(Shading::Flat));
+  assert!(*smooth != *flat, "Flat shading rendered the same as smooth shading.");
+  assert_image_matches(&smooth, &golden_path("phong_sphere_smooth"), TOLERANCE);
+  assert_image_matches(&flat, &golden_path("phong_sphere_flat"), TOLERANCE);
+}
+
+
+#[test]
+fn phong_and_blinn_phong_sphere() {
+  let headless = match headless() {
+    Some(headless) => headless,
+    None => return,
+  };
+  let phong = phong_sphere(
+    &headless,
+    PhongPipelineConfig::new().with_specular_model(SpecularModel::Phong)
+  );
+  let blinn_phong = phong_sphere(
+    &headless,
+    PhongPipelineConfig::new().with_specular_model(SpecularModel::BlinnPhong)
+  );
+  assert!(*phong != *blinn_phong, "Blinn-Phong rendered the same as Phong.");
+  assert_image_matches(&phong, &golden_path("phong_sphere_phong_specular"), TOLERANCE);
+  assert_image_matches(&blinn_phong, &golden_path("phong_sphere_blinn_phong_specular"), TOLERANCE);
+}
+
+
+#[test]
+fn texture2d_quad() {
+  let headless = match headless() {
+    Some(headless) => headless,
+    None => return,
+  };
+  let mut pipeline = Texture2DPipeline::new(
+    headless.render_pass.clone(),
+    headless.device.clone(),
+    headless.physical()
+  );
+  headless.upload(Matrix4::identity(), pipeline.proj_uniform_device_buffer.clone());
+
+  // A 2x2 checkerboard, sampled without filtering so its edges are sharp.
+  let (texture, upload) = ImmutableImage::from_iter(
+    vec![
+      255, 255, 255, 255,   0, 0, 0, 255,
+      0, 0, 0, 255,         255, 255, 255, 255,
+    ].into_iter(),
+    Dimensions::Dim2d { width: 2, height: 2 },
+    Format::R8G8B8A8Unorm,
+    headless.queue.clone()
+  ).expect("Could not create checkerboard texture.");
+  upload
+    .then_signal_fence_and_flush()
+    .expect("Could not flush texture upload.")
+    .wait(None)
+    .expect("Could not wait for texture upload.");
+  pipeline.sampler_config.mag_filter = Filter::Nearest;
+  pipeline.sampler_config.min_filter = Filter::Nearest;
+  let texture_set = pipeline.desc_set_for_texture(texture, headless.device.clone());
+
+  let uv = |x: f32, y: f32| VertexUV { position: [x, y], uv: [x * 0.5 + 0.5, y * 0.5 + 0.5] };
+  let vertices = CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    vec![
+      uv(-0.75, -0.75), uv(0.75, -0.75), uv(0.75, 0.75),
+      uv(-0.75, -0.75), uv(0.75, 0.75), uv(-0.75, 0.75),
+    ].into_iter()
+  ).expect("Could not create quad vertex buffer.");
+  let push_constants = texture2d::vs::ty::Matrices {
+    model: Matrix4::identity().into(),
+    view: Matrix4::identity().into(),
+  };
+
+  let rendered = headless.render([0.0, 0.0, 1.0, 1.0], |cmds, dynamic_state| {
+    cmds
+      .draw(
+        pipeline.pipeline.clone(),
+        dynamic_state,
+        vec![vertices as Arc<BufferAccess + Send + Sync>],
+        (pipeline.proj_desc_set.clone(), texture_set),
+        push_constants
+      ).expect("Could not draw quad.")
+  });
+  assert_image_matches(&rendered, &golden_path("texture2d_quad"), TOLERANCE);
+}
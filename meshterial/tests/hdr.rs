@@ -0,0 +1,82 @@
+//! Decoding HDR images and resampling them into cubemaps, which is done on
+//! the CPU.
+use meshterial::textures::hdr::{cubemap_direction, equirect_to_cubemap, HdrImage};
+use nalgebra::Vector3;
+
+
+/// A flat (not run length encoded) Radiance file of RGBE texels.
+fn radiance(width: u32, height: u32, rgbe: &[[u8; 4]]) -> Vec<u8> {
+  let mut bytes = format!(
+    "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+    height,
+    width
+  ).into_bytes();
+  for texel in rgbe {
+    bytes.extend_from_slice(texel);
+  }
+  bytes
+}
+
+
+fn image(width: u32, height: u32, texel: impl Fn(u32, u32) -> f32) -> HdrImage {
+  let mut texels = vec![];
+  for y in 0 .. height {
+    for x in 0 .. width {
+      let v = texel(x, y);
+      texels.push([v, v, v, 1.0]);
+    }
+  }
+  HdrImage { width, height, texels }
+}
+
+
+#[test]
+fn parses_values_above_one() {
+  let bytes = radiance(2, 1, &[[128, 128, 128, 129], [128, 64, 0, 130]]);
+  let image = HdrImage::parse(&bytes).expect("Could not parse hdr.");
+  assert_eq!((image.width, image.height), (2, 1));
+  assert_eq!(image.texels, vec![[1.0, 1.0, 1.0, 1.0], [2.0, 1.0, 0.0, 1.0]]);
+}
+
+
+#[test]
+fn garbage_is_an_error() {
+  assert!(HdrImage::parse(b"not an image").is_err());
+}
+
+
+#[test]
+fn cubemap_face_centers_point_down_their_axes() {
+  let axes = [
+    Vector3::x(), -Vector3::x(),
+    Vector3::y(), -Vector3::y(),
+    Vector3::z(), -Vector3::z(),
+  ];
+  for (face, axis) in axes.iter().enumerate() {
+    assert_eq!(cubemap_direction(face, 1, 1, 3), *axis);
+  }
+}
+
+
+#[test]
+fn top_and_bottom_rows_become_the_y_faces() {
+  let sky = image(4, 2, |_, y| if y == 0 { 8.0 } else { 0.5 });
+  let size = 3;
+  let faces = equirect_to_cubemap(&sky, size);
+  assert_eq!(faces.len(), 6 * 9);
+  let center = |face: usize| faces[face * 9 + 4];
+  assert_eq!(center(2), [8.0, 8.0, 8.0, 1.0]);
+  assert_eq!(center(3), [0.5, 0.5, 0.5, 1.0]);
+}
+
+
+#[test]
+fn the_middle_of_the_map_faces_negative_z() {
+  let panorama = image(4, 2, |x, _| x as f32);
+  // -Z samples halfway between the middle two columns and +X a quarter of
+  // the way further. +Z is on the seam, halfway between the last column and
+  // the first.
+  assert_eq!(panorama.sample_equirect(&-Vector3::z())[0], 1.5);
+  assert_eq!(panorama.sample_equirect(&Vector3::x())[0], 1.5 + 1.0);
+  assert_eq!(panorama.sample_equirect(&Vector3::z())[0], 1.5);
+}
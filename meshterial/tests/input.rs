@@ -0,0 +1,125 @@
+//! Tracking input state from synthetic sdl2 events, which doesn't need a
+//! window.
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::{MouseButton, MouseState, MouseWheelDirection};
+
+use meshterial::InputState;
+
+
+fn key_down(keycode: Keycode, repeat: bool) -> Event {
+  Event::KeyDown {
+    timestamp: 0,
+    window_id: 0,
+    keycode: Some(keycode),
+    scancode: None,
+    keymod: Mod::empty(),
+    repeat,
+  }
+}
+
+
+fn key_up(keycode: Keycode) -> Event {
+  Event::KeyUp {
+    timestamp: 0,
+    window_id: 0,
+    keycode: Some(keycode),
+    scancode: None,
+    keymod: Mod::empty(),
+    repeat: false,
+  }
+}
+
+
+fn motion(x: i32, y: i32, xrel: i32, yrel: i32) -> Event {
+  Event::MouseMotion {
+    timestamp: 0,
+    window_id: 0,
+    which: 0,
+    mousestate: MouseState::from_sdl_state(0),
+    x,
+    y,
+    xrel,
+    yrel,
+  }
+}
+
+
+fn button_down(mouse_btn: MouseButton) -> Event {
+  Event::MouseButtonDown { timestamp: 0, window_id: 0, which: 0, mouse_btn, clicks: 1, x: 5, y: 6 }
+}
+
+
+fn wheel(x: i32, y: i32) -> Event {
+  Event::MouseWheel { timestamp: 0, window_id: 0, which: 0, x, y, direction: MouseWheelDirection::Normal }
+}
+
+
+#[test]
+fn keys_are_pressed_for_one_frame_and_held_until_released() {
+  let mut input = InputState::new();
+  input.update_from_events(&[key_down(Keycode::W, false)]);
+  assert!(input.was_key_pressed(Keycode::W));
+  assert!(input.is_key_down(Keycode::W));
+
+  // Held with repeats: still down, but not pressed again.
+  input.update_from_events(&[key_down(Keycode::W, true)]);
+  assert!(!input.was_key_pressed(Keycode::W));
+  assert!(input.is_key_down(Keycode::W));
+
+  input.update_from_events(&[key_up(Keycode::W)]);
+  assert!(!input.is_key_down(Keycode::W));
+
+  // Pressed and released within one frame still counts as a press.
+  input.update_from_events(&[key_down(Keycode::A, false), key_up(Keycode::A)]);
+  assert!(input.was_key_pressed(Keycode::A));
+  assert!(!input.is_key_down(Keycode::A));
+}
+
+
+#[test]
+fn buttons_are_pressed_for_one_frame() {
+  let mut input = InputState::new();
+  input.begin_frame();
+  input.handle_event(&button_down(MouseButton::Left));
+  assert!(input.was_button_pressed(MouseButton::Left));
+  assert!(input.is_button_down(MouseButton::Left));
+  assert_eq!(input.mouse_position(), [5, 6]);
+
+  input.begin_frame();
+  assert!(!input.was_button_pressed(MouseButton::Left));
+  assert!(input.is_button_down(MouseButton::Left));
+}
+
+
+#[test]
+fn mouse_motion_accumulates_within_a_frame() {
+  let mut input = InputState::new();
+  input.update_from_events(&[motion(10, 10, 3, -1), motion(12, 15, 2, 5)]);
+  assert_eq!(input.mouse_position(), [12, 15]);
+  assert_eq!(input.mouse_delta(), [5, 4]);
+
+  input.update_from_events(&[]);
+  assert_eq!(input.mouse_delta(), [0, 0]);
+  assert_eq!(input.mouse_position(), [12, 15]);
+}
+
+
+#[test]
+fn wheel_accumulates_within_a_frame() {
+  let mut input = InputState::new();
+  input.update_from_events(&[wheel(0, 1), wheel(0, 2), wheel(-1, 0)]);
+  assert_eq!(input.wheel_delta(), [-1, 3]);
+  input.begin_frame();
+  assert_eq!(input.wheel_delta(), [0, 0]);
+}
+
+
+#[test]
+fn losing_focus_releases_everything() {
+  let mut input = InputState::new();
+  input.update_from_events(&[key_down(Keycode::Space, false), button_down(MouseButton::Right)]);
+  input.handle_event(&Event::Window { timestamp: 0, window_id: 0, win_event: WindowEvent::FocusLost });
+  assert!(!input.is_key_down(Keycode::Space));
+  assert!(!input.is_button_down(MouseButton::Right));
+}
@@ -0,0 +1,109 @@
+//! Selecting levels of detail by distance. Without a vulkan device the tests
+//! print a message and pass.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use nalgebra::{Matrix4, Vector3};
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+
+use meshterial::bounds::Aabb;
+use meshterial::lod::Lod;
+use meshterial::Mesh;
+
+use common::Headless;
+
+
+/// A mesh with `len` points, so that levels can be told apart by length.
+fn mesh(headless: &Headless, len: usize) -> Mesh<[f32; 3]> {
+  let vertex_buffer = CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    (0 .. len).map(|_| [0.0f32; 3])
+  ).expect("Could not create vertex buffer.");
+  Mesh { vertex_buffer }
+}
+
+
+/// Levels with 3, 2 and 1 points used from 0, 10 and 50, added out of order.
+fn lod() -> Option<Lod<[f32; 3]>> {
+  let headless = Headless::new([1, 1])?;
+  let bounds = Aabb { min: Vector3::new(-1.0, -1.0, -1.0), max: Vector3::new(1.0, 1.0, 1.0) };
+  Some(
+    Lod::new(bounds)
+      .with_level(50.0, mesh(&headless, 1))
+      .with_level(0.0, mesh(&headless, 3))
+      .with_level(10.0, mesh(&headless, 2))
+  )
+}
+
+
+fn selected_len(lod: &Lod<[f32; 3]>, distance: f32) -> Option<usize> {
+  lod.select(distance).map(|mesh| mesh.len())
+}
+
+
+#[test]
+fn levels_are_sorted_by_distance() {
+  let lod = match lod() {
+    Some(lod) => lod,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let distances: Vec<f32> = lod.levels().iter().map(|(d, _)| *d).collect();
+  assert_eq!(distances, vec![0.0, 10.0, 50.0]);
+}
+
+
+#[test]
+fn selects_by_distance() {
+  let lod = match lod() {
+    Some(lod) => lod,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  // Closer than the first level still uses the first level.
+  assert_eq!(lod.select_index(-1.0), Some(0));
+  assert_eq!(selected_len(&lod, -1.0), Some(3));
+  assert_eq!(selected_len(&lod, 0.0), Some(3));
+  // Between levels uses the nearer one, and a level starts at its distance.
+  assert_eq!(selected_len(&lod, 9.9), Some(3));
+  assert_eq!(selected_len(&lod, 10.0), Some(2));
+  assert_eq!(selected_len(&lod, 30.0), Some(2));
+  // Beyond the last level keeps using the last level.
+  assert_eq!(selected_len(&lod, 50.0), Some(1));
+  assert_eq!(selected_len(&lod, 1000.0), Some(1));
+}
+
+
+#[test]
+fn selects_for_a_camera_position() {
+  let lod = match lod() {
+    Some(lod) => lod,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let camera = Vector3::new(0.0, 0.0, 0.0);
+  let at = |z: f32| Matrix4::new_translation(&Vector3::new(0.0, 0.0, z));
+  assert_eq!(lod.distance(&at(-20.0), &camera), 20.0);
+  assert_eq!(lod.select_for(&at(-5.0), &camera).map(|m| m.len()), Some(3));
+  assert_eq!(lod.select_for(&at(-20.0), &camera).map(|m| m.len()), Some(2));
+  assert_eq!(lod.select_for(&at(100.0), &camera).map(|m| m.len()), Some(1));
+}
+
+
+#[test]
+fn no_levels_selects_nothing() {
+  let bounds = Aabb { min: Vector3::new(0.0, 0.0, 0.0), max: Vector3::new(0.0, 0.0, 0.0) };
+  let lod: Lod<[f32; 3]> = Lod::new(bounds);
+  assert_eq!(lod.select_index(5.0), None);
+  assert!(lod.select(5.0).is_none());
+  assert!(lod.levels().is_empty());
+}
@@ -0,0 +1,54 @@
+//! Merging meshes, which is CPU side geometry processing.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use meshterial::mesh::merge_vertices;
+use nalgebra::{Matrix4, Rotation3, Vector3};
+
+
+#[test]
+fn merging_two_offset_cubes() {
+  let cube = common::cube();
+  let left = Matrix4::new_translation(&Vector3::new(-2.0, 0.0, 0.0));
+  let right = Matrix4::new_translation(&Vector3::new(2.0, 0.0, 0.0));
+  let merged = merge_vertices(&[(&cube[..], left), (&cube[..], right)]);
+  assert_eq!(merged.len(), cube.len() * 2);
+
+  let (first, second) = merged.split_at(cube.len());
+  for ((a, b), original) in first.iter().zip(second.iter()).zip(cube.iter()) {
+    assert_eq!(a.position, [original.position[0] - 2.0, original.position[1], original.position[2]]);
+    assert_eq!(b.position, [original.position[0] + 2.0, original.position[1], original.position[2]]);
+    // Translation doesn't turn normals.
+    assert_eq!(a.normal, original.normal);
+  }
+}
+
+
+#[test]
+fn merging_rotates_normals() {
+  let cube = common::cube();
+  let rotation = Rotation3::from_axis_angle(&Vector3::y_axis(), ::std::f32::consts::FRAC_PI_2)
+    .to_homogeneous();
+  let merged = merge_vertices(&[(&cube[..], rotation)]);
+  for v in merged.iter() {
+    let n = Vector3::from(v.normal);
+    assert!((n.norm() - 1.0).abs() < 1e-5);
+    // Each flat normal still points out of the face its vertex is on.
+    assert!((Vector3::from(v.position).dot(&n) - 0.5).abs() < 1e-5);
+  }
+}
+
+
+#[test]
+fn mirroring_keeps_the_winding() {
+  let cube = common::cube();
+  let mirror = Matrix4::new_nonuniform_scaling(&Vector3::new(-1.0, 1.0, 1.0));
+  let merged = merge_vertices(&[(&cube[..], mirror)]);
+  for triangle in merged.chunks(3) {
+    let p = |i: usize| Vector3::from(triangle[i].position);
+    let face_normal = (p(1) - p(0)).cross(&(p(2) - p(0)));
+    assert!(face_normal.dot(&Vector3::from(triangle[0].normal)) > 0.0);
+  }
+}
@@ -0,0 +1,86 @@
+//! Keeping loaded meshes by path. Without a vulkan device the tests print a
+//! message and pass.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+
+use meshterial::mesh::{Mesh, MeshCache};
+use meshterial::pipelines::phong::VertexPhong;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use common::Headless;
+
+
+fn triangle(headless: &Headless) -> Mesh<VertexPhong> {
+  let vertex_buffer = CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    common::cube().into_iter().take(3)
+  ).expect("Could not create vertex buffer.");
+  Mesh { vertex_buffer }
+}
+
+
+#[test]
+fn repeated_loads_share_one_mesh() {
+  let headless = match Headless::new([1, 1]) {
+    Some(headless) => headless,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let dir = env::temp_dir().join("meshterial_mesh_cache");
+  fs::create_dir_all(dir.join("sub")).expect("Could not create temp dir.");
+  let path = dir.join("triangle.mesh");
+  fs::write(&path, b"").expect("Could not write temp file.");
+
+  let mut cache = MeshCache::new();
+  let mut loads = 0;
+  let first = cache
+    .load(&path, |_| -> Result<_, ()> { loads += 1; Ok(triangle(&headless)) })
+    .expect("Could not load mesh.");
+  // Another spelling of the same file.
+  let other_spelling = dir.join("sub").join("..").join("triangle.mesh");
+  let second = cache
+    .load(&other_spelling, |_| -> Result<_, ()> { loads += 1; Ok(triangle(&headless)) })
+    .expect("Could not load mesh.");
+  assert_eq!(loads, 1);
+  assert!(Arc::ptr_eq(&first, &second));
+  assert_eq!(cache.len(), 1);
+
+  // Unused meshes are only evicted once nothing else holds them.
+  assert_eq!(cache.evict_unused(), 0);
+  drop(first);
+  drop(second);
+  assert_eq!(cache.evict_unused(), 1);
+  assert!(!cache.contains(&path));
+}
+
+
+#[test]
+fn failed_loads_are_not_kept() {
+  let headless = match Headless::new([1, 1]) {
+    Some(headless) => headless,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let path = Path::new("does/not/exist.mesh");
+  let mut cache = MeshCache::new();
+  let failed = cache.load(path, |_| Err("missing"));
+  assert_eq!(failed.err(), Some("missing"));
+  assert!(cache.is_empty());
+
+  cache.load(path, |_| -> Result<_, ()> { Ok(triangle(&headless)) }).expect("Could not load mesh.");
+  assert!(cache.evict(path).is_some());
+  assert!(cache.get(path).is_none());
+}
@@ -0,0 +1,39 @@
+//! Writing meshes as OBJ, which is done on the CPU.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use meshterial::exporters::obj;
+
+
+fn floats(line: &str) -> Vec<f32> {
+  line
+    .split_whitespace()
+    .skip(1)
+    .map(|value| value.parse().expect("Could not parse float."))
+    .collect()
+}
+
+
+#[test]
+fn cube_is_written_as_obj() {
+  let cube = common::cube();
+  let mut bytes = vec![];
+  obj::write_triangles(&mut bytes, &cube).expect("Could not write obj.");
+  let text = String::from_utf8(bytes).expect("OBJ is not utf8.");
+
+  let positions: Vec<&str> = text.lines().filter(|line| line.starts_with("v ")).collect();
+  let normals: Vec<&str> = text.lines().filter(|line| line.starts_with("vn ")).collect();
+  let faces: Vec<&str> = text.lines().filter(|line| line.starts_with("f ")).collect();
+  assert_eq!(positions.len(), 36);
+  assert_eq!(normals.len(), 36);
+  assert_eq!(faces.len(), 12);
+
+  for (vertex, (position, normal)) in cube.iter().zip(positions.iter().zip(normals.iter())) {
+    assert_eq!(floats(position), vertex.position.to_vec());
+    assert_eq!(floats(normal), vertex.normal.to_vec());
+  }
+  assert_eq!(faces[0], "f 1//1 2//2 3//3");
+  assert_eq!(faces[11], "f 34//34 35//35 36//36");
+}
@@ -0,0 +1,52 @@
+//! Pipeline options that don't need a device to check.
+use vulkano::pipeline::blend::{AttachmentBlend, BlendFactor};
+
+use meshterial::pipelines::{BlendMode, CompareOp, PipelineOptions};
+use meshterial::pipelines::log_depth::LogDepthUniform;
+
+
+#[test]
+fn sample_shading_is_clamped() {
+  let shading = |sample_shading| PipelineOptions { sample_shading, ..Default::default() }.min_sample_shading();
+  assert_eq!(shading(None), None);
+  assert_eq!(shading(Some(0.5)), Some(0.5));
+  assert_eq!(shading(Some(2.0)), Some(1.0));
+  assert_eq!(shading(Some(-1.0)), Some(0.0));
+  assert_eq!(shading(Some(std::f32::NAN)), Some(0.0));
+}
+
+
+#[test]
+fn greater_depth_compares_are_reverse_z() {
+  let reverse_z = |depth_compare| PipelineOptions { depth_compare, ..Default::default() }.is_reverse_z();
+  assert!(!reverse_z(CompareOp::Less));
+  assert!(!reverse_z(CompareOp::LessOrEqual));
+  assert!(reverse_z(CompareOp::Greater));
+  assert!(reverse_z(CompareOp::GreaterOrEqual));
+}
+
+
+#[test]
+fn log_depth_is_off_without_a_far_plane() {
+  assert_eq!(LogDepthUniform::from(None).far, 0.0);
+  assert_eq!(LogDepthUniform::from(Some(1.0e7)).far, 1.0e7);
+  assert_eq!(LogDepthUniform::from(Some(-5.0)).far, 0.0);
+}
+
+
+#[test]
+fn blend_modes_map_to_attachment_blends() {
+  assert_eq!(PipelineOptions::default().blend, BlendMode::Alpha);
+  assert_eq!(AttachmentBlend::from(BlendMode::None), AttachmentBlend::pass_through());
+  assert_eq!(AttachmentBlend::from(BlendMode::Alpha), AttachmentBlend::alpha_blending());
+
+  let additive = AttachmentBlend::from(BlendMode::Additive);
+  assert!(additive.enabled);
+  assert_eq!(additive.color_source, BlendFactor::SrcAlpha);
+  assert_eq!(additive.color_destination, BlendFactor::One);
+
+  let multiply = AttachmentBlend::from(BlendMode::Multiply);
+  assert!(multiply.enabled);
+  assert_eq!(multiply.color_source, BlendFactor::DstColor);
+  assert_eq!(multiply.color_destination, BlendFactor::Zero);
+}
@@ -0,0 +1,48 @@
+//! Validating phong pipeline configs, which doesn't need a device.
+use meshterial::pipelines::phong::{PhongPipelineConfig, PhongPipelineConfigError};
+
+
+#[test]
+fn registered_materials_are_valid() {
+  let config = PhongPipelineConfig::new()
+    .with_material("floor")
+    .with_material("wall")
+    .with_transparent_material("glass");
+  assert_eq!(config.validate(), Ok(()));
+  assert_eq!(config.material_names, vec!["floor", "wall", "glass"]);
+  assert_eq!(config.transparent_materials, vec!["glass"]);
+  assert_eq!(PhongPipelineConfig::new().validate(), Ok(()));
+}
+
+
+#[test]
+fn empty_material_names_are_rejected() {
+  let config = PhongPipelineConfig::new().with_material("floor").with_material("");
+  assert_eq!(config.validate(), Err(PhongPipelineConfigError::EmptyMaterialName));
+}
+
+
+#[test]
+fn duplicate_materials_are_rejected() {
+  let config = PhongPipelineConfig::new()
+    .with_material("floor")
+    .with_material("wall")
+    .with_material("floor");
+  assert_eq!(config.validate(), Err(PhongPipelineConfigError::DuplicateMaterial("floor".to_string())));
+
+  let config = PhongPipelineConfig::new()
+    .with_material("glass")
+    .with_transparent_material("glass");
+  assert_eq!(config.validate(), Err(PhongPipelineConfigError::DuplicateMaterial("glass".to_string())));
+}
+
+
+#[test]
+fn transparent_materials_must_be_registered() {
+  let mut config = PhongPipelineConfig::new().with_material("floor");
+  config.transparent_materials.push("glass".to_string());
+  assert_eq!(
+    config.validate(),
+    Err(PhongPipelineConfigError::UnknownTransparentMaterial("glass".to_string()))
+  );
+}
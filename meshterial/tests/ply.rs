@@ -0,0 +1,129 @@
+//! Parsing PLY files, which doesn't need a device.
+use meshterial::loaders::ply::{self, Ply, PlyError};
+
+use std::path::Path;
+
+
+/// The corners of the tetrahedron in `assets/tetrahedron.ply`.
+const CORNERS: [[f32; 3]; 4] = [
+  [0.0, 0.0, 0.0],
+  [1.0, 0.0, 0.0],
+  [0.0, 1.0, 0.0],
+  [0.0, 0.0, 1.0],
+];
+
+/// Its faces, wound to face outward.
+const FACES: [[u32; 3]; 4] = [
+  [0, 2, 1],
+  [0, 1, 3],
+  [0, 3, 2],
+  [1, 2, 3],
+];
+
+
+fn approx(a: [f32; 3], b: [f32; 3]) -> bool {
+  a.iter().zip(b.iter()).all(|(x, y)| (x - y).abs() < 1.0e-5)
+}
+
+
+/// Check that a parsed tetrahedron has the fixture's faces and flat normals.
+fn assert_tetrahedron(ply: Ply) {
+  let vertices = match ply {
+    Ply::Mesh(vertices) => vertices,
+    Ply::Points(_) => panic!("Tetrahedron was parsed as points."),
+  };
+  assert_eq!(vertices.len(), FACES.len() * 3);
+  for (face, triangle) in FACES.iter().zip(vertices.chunks(3)) {
+    for (ndx, vertex) in face.iter().zip(triangle) {
+      assert_eq!(vertex.position, CORNERS[*ndx as usize]);
+    }
+  }
+  // The file has no normals, so each face gets its own flat one.
+  let third = 1.0 / 3.0f32.sqrt();
+  assert!(approx(vertices[0].normal, [0.0, 0.0, -1.0]));
+  assert!(approx(vertices[3].normal, [0.0, -1.0, 0.0]));
+  assert!(approx(vertices[6].normal, [-1.0, 0.0, 0.0]));
+  assert!(approx(vertices[9].normal, [third, third, third]));
+  assert_eq!(vertices[9].normal, vertices[11].normal);
+}
+
+
+/// The tetrahedron in a binary encoding, with `write` putting each value's
+/// bytes in the encoding's order.
+fn binary_tetrahedron(format: &str, write: fn(&mut Vec<u8>, [u8; 4])) -> Vec<u8> {
+  let mut bytes = format!(
+    "ply\nformat {} 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+     property uchar red\nelement face 4\nproperty list uchar int vertex_indices\nend_header\n",
+    format
+  ).into_bytes();
+  let le = |bits: u32| [bits as u8, (bits >> 8) as u8, (bits >> 16) as u8, (bits >> 24) as u8];
+  for corner in CORNERS.iter() {
+    for coord in corner.iter() {
+      write(&mut bytes, le(coord.to_bits()));
+    }
+    bytes.push(255);
+  }
+  for face in FACES.iter() {
+    bytes.push(3);
+    for ndx in face.iter() {
+      write(&mut bytes, le(*ndx));
+    }
+  }
+  bytes
+}
+
+
+#[test]
+fn parses_the_ascii_fixture() {
+  let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/tetrahedron.ply");
+  let ply = ply::load(&path).expect("Could not load tetrahedron.ply.");
+  let stats = ply.stats();
+  assert_eq!(stats.vertex_count, 12);
+  assert_eq!(stats.triangle_count, 4);
+  assert_tetrahedron(ply);
+}
+
+
+#[test]
+fn parses_binary_little_endian() {
+  let bytes = binary_tetrahedron("binary_little_endian", |bytes, le| bytes.extend_from_slice(&le));
+  assert_tetrahedron(ply::parse(&bytes).expect("Could not parse little endian PLY."));
+}
+
+
+#[test]
+fn parses_binary_big_endian() {
+  let bytes = binary_tetrahedron("binary_big_endian", |bytes, le| {
+    bytes.extend(le.iter().rev());
+  });
+  assert_tetrahedron(ply::parse(&bytes).expect("Could not parse big endian PLY."));
+}
+
+
+#[test]
+fn vertices_without_faces_are_points() {
+  let bytes = b"ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\n\
+    property float z\nproperty uchar red\nproperty uchar green\nproperty uchar blue\nend_header\n\
+    0 0 0 255 0 0\n1 2 3 0 255 0\n";
+  let points = match ply::parse(bytes).expect("Could not parse points.") {
+    Ply::Points(points) => points,
+    Ply::Mesh(_) => panic!("Points were parsed as a mesh."),
+  };
+  assert_eq!(points.len(), 2);
+  assert_eq!(points[1].position, [1.0, 2.0, 3.0]);
+  assert_eq!(points[1].color, [0.0, 1.0, 0.0, 1.0]);
+}
+
+
+#[test]
+fn truncated_and_bad_input_is_rejected() {
+  let bytes = binary_tetrahedron("binary_little_endian", |bytes, le| bytes.extend_from_slice(&le));
+  match ply::parse(&bytes[.. bytes.len() - 2]) {
+    Err(PlyError::Truncated) => {}
+    other => panic!("Expected Truncated, got {:?}", other),
+  }
+  match ply::parse(b"obj\n") {
+    Err(PlyError::NotPly) => {}
+    other => panic!("Expected NotPly, got {:?}", other),
+  }
+}
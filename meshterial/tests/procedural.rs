@@ -0,0 +1,54 @@
+//! Generating procedural texture images.
+use meshterial::textures::procedural::{checkerboard_image, solid_image, uv_grid_image, CHECKERS};
+
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+
+#[test]
+fn checkerboard_alternates_colors() {
+  let image = checkerboard_image(64, RED, BLUE);
+  assert_eq!(image.dimensions(), (64, 64));
+  let checker = 64 / CHECKERS;
+  assert_eq!(image.get_pixel(0, 0).data, RED);
+  assert_eq!(image.get_pixel(checker - 1, checker - 1).data, RED);
+  assert_eq!(image.get_pixel(checker, 0).data, BLUE);
+  assert_eq!(image.get_pixel(0, checker).data, BLUE);
+  assert_eq!(image.get_pixel(checker, checker).data, RED);
+}
+
+
+#[test]
+fn small_checkerboard_has_one_texel_checkers() {
+  let image = checkerboard_image(4, RED, BLUE);
+  assert_eq!(image.get_pixel(0, 0).data, RED);
+  assert_eq!(image.get_pixel(1, 0).data, BLUE);
+  assert_eq!(image.get_pixel(1, 1).data, RED);
+}
+
+
+#[test]
+fn solid_is_one_color() {
+  let image = solid_image(8, BLUE);
+  assert!(image.pixels().all(|pixel| pixel.data == BLUE));
+}
+
+
+#[test]
+fn uv_grid_grows_with_u_and_v() {
+  let image = uv_grid_image(64);
+  let checker = 64 / CHECKERS;
+  // Grid lines.
+  assert_eq!(image.get_pixel(0, 5).data, [255, 255, 255, 255]);
+  assert_eq!(image.get_pixel(5, checker).data, [255, 255, 255, 255]);
+  // Red follows U and green follows V. These squares are all the lighter
+  // shade.
+  let top_left = image.get_pixel(1, 1).data;
+  let top_right = image.get_pixel(6 * checker + 1, 1).data;
+  let bottom_left = image.get_pixel(1, 6 * checker + 1).data;
+  assert!(top_right[0] > top_left[0]);
+  assert_eq!(top_right[1], top_left[1]);
+  assert!(bottom_left[1] > top_left[1]);
+  assert_eq!(bottom_left[0], top_left[0]);
+}
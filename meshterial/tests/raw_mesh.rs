@@ -0,0 +1,72 @@
+//! Making meshes from raw vertex bytes. Without a vulkan device the tests
+//! print a message and pass.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use meshterial::mesh::RawMeshError;
+use meshterial::Mesh;
+
+use common::Headless;
+
+
+/// The native endian bytes of two vertices.
+fn vertex_bytes() -> Vec<u8> {
+  let floats: [f32; 12] = [
+    1.0, 2.0, 3.0, 0.0, 0.0, 1.0,
+    -1.0, -2.0, -3.0, 0.0, 1.0, 0.0,
+  ];
+  floats.iter().flat_map(|f| f.to_bits().to_ne_bytes().to_vec()).collect()
+}
+
+
+#[test]
+fn reads_misaligned_bytes() {
+  let headless = match Headless::new([1, 1]) {
+    Some(headless) => headless,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  // Start the vertices one byte into the buffer so that they can't be
+  // aligned to 4 bytes.
+  let mut bytes = vec![0u8];
+  bytes.extend(vertex_bytes());
+  let mesh = Mesh::from_raw_bytes(headless.device.clone(), &bytes[1 ..], 2)
+    .expect("Misaligned bytes should make a mesh.");
+  assert_eq!(mesh.len(), 2);
+  let vertices = mesh.vertex_buffer.read().expect("Could not read vertex buffer.");
+  assert_eq!(vertices[0].position, [1.0, 2.0, 3.0]);
+  assert_eq!(vertices[0].normal, [0.0, 0.0, 1.0]);
+  assert_eq!(vertices[1].position, [-1.0, -2.0, -3.0]);
+  assert_eq!(vertices[1].normal, [0.0, 1.0, 0.0]);
+}
+
+
+#[test]
+fn rejects_the_wrong_number_of_bytes() {
+  let headless = match Headless::new([1, 1]) {
+    Some(headless) => headless,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let bytes = vertex_bytes();
+  let error = Mesh::from_raw_bytes(headless.device.clone(), &bytes[.. 40], 2)
+    .err()
+    .expect("Too few bytes should be an error.");
+  assert_eq!(error, RawMeshError::SizeMismatch { expected: 48, actual: 40 });
+  assert_eq!(error.to_string(), "expected 48 bytes of vertices but got 40");
+
+  let error = Mesh::from_raw_bytes(headless.device.clone(), &bytes, 3)
+    .err()
+    .expect("Too many vertices should be an error.");
+  assert_eq!(error, RawMeshError::SizeMismatch { expected: 72, actual: 48 });
+
+  let empty = Mesh::from_raw_bytes(headless.device.clone(), &[], 0)
+    .expect("No bytes should make an empty mesh.");
+  assert!(empty.is_empty());
+}
@@ -0,0 +1,91 @@
+//! Intersecting rays with triangles, which doesn't need a device.
+use meshterial::ray::Ray;
+use nalgebra::{Matrix4, Vector3};
+
+
+fn approx(a: f32, b: f32) -> bool {
+  (a - b).abs() < 1.0e-5
+}
+
+
+/// The unit right triangle in the z = 0 plane.
+fn triangle() -> [Vector3<f32>; 3] {
+  [Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)]
+}
+
+
+/// A ray looking down -z from one unit above the point `(x, y)`.
+fn down_at(x: f32, y: f32) -> Ray {
+  Ray::new(Vector3::new(x, y, 1.0), Vector3::new(0.0, 0.0, -2.0))
+}
+
+
+#[test]
+fn hits_with_distance_and_barycentrics() {
+  let [a, b, c] = triangle();
+  let (t, u, v) = down_at(0.25, 0.5)
+    .intersect_triangle(&a, &b, &c)
+    .expect("Ray should hit the triangle.");
+  assert!(approx(t, 1.0));
+  assert!(approx(u, 0.25));
+  assert!(approx(v, 0.5));
+
+  // Back faces are hit too.
+  let up = Ray::new(Vector3::new(0.25, 0.25, -3.0), Vector3::new(0.0, 0.0, 1.0));
+  let (t, _, _) = up.intersect_triangle(&a, &b, &c).expect("Ray should hit the back face.");
+  assert!(approx(t, 3.0));
+}
+
+
+#[test]
+fn misses_outside_the_triangle() {
+  let [a, b, c] = triangle();
+  assert_eq!(down_at(0.75, 0.75).intersect_triangle(&a, &b, &c), None);
+  assert_eq!(down_at(-0.1, 0.5).intersect_triangle(&a, &b, &c), None);
+  assert_eq!(down_at(0.5, -0.1).intersect_triangle(&a, &b, &c), None);
+}
+
+
+#[test]
+fn misses_parallel_to_the_triangle() {
+  let [a, b, c] = triangle();
+  let along = Ray::new(Vector3::new(-1.0, 0.25, 0.0), Vector3::new(1.0, 0.0, 0.0));
+  assert_eq!(along.intersect_triangle(&a, &b, &c), None);
+  let above = Ray::new(Vector3::new(-1.0, 0.25, 1.0), Vector3::new(1.0, 0.0, 0.0));
+  assert_eq!(above.intersect_triangle(&a, &b, &c), None);
+}
+
+
+#[test]
+fn misses_behind_the_origin() {
+  let [a, b, c] = triangle();
+  let away = Ray::new(Vector3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, 1.0));
+  assert_eq!(away.intersect_triangle(&a, &b, &c), None);
+}
+
+
+#[test]
+fn picks_the_nearest_triangle() {
+  let [a, b, c] = triangle();
+  let at = |z: f32| vec![[a.x, a.y, z], [b.x, b.y, z], [c.x, c.y, z]];
+  // The farther triangle comes first.
+  let positions: Vec<[f32; 3]> = at(-2.0).into_iter().chain(at(0.5)).collect();
+  let hit = down_at(0.25, 0.25)
+    .intersect_triangles(positions.clone(), &Matrix4::identity())
+    .expect("Ray should hit a triangle.");
+  assert_eq!(hit.triangle, 1);
+  assert!(approx(hit.distance, 0.5));
+  assert!(approx(hit.barycentric[0], 0.5));
+  assert!(approx(hit.barycentric[1], 0.25));
+  assert!(approx(hit.barycentric[2], 0.25));
+
+  // Moving the triangles moves the hits.
+  let model = Matrix4::new_translation(&Vector3::new(0.0, 0.0, -1.0));
+  let hit = down_at(0.25, 0.25)
+    .intersect_triangles(positions.clone(), &model)
+    .expect("Ray should hit a moved triangle.");
+  assert_eq!(hit.triangle, 1);
+  assert!(approx(hit.distance, 1.5));
+
+  assert_eq!(down_at(2.0, 2.0).intersect_triangles(positions, &Matrix4::identity()), None);
+}
@@ -0,0 +1,51 @@
+//! Adding and queueing draws on named render layers.
+use meshterial::render_layers::{RenderLayers, DEFAULT_LAYER};
+
+
+#[test]
+fn starts_with_one_default_layer() {
+  let layers = RenderLayers::new();
+  assert_eq!(layers.layers().len(), 1);
+  assert_eq!(layers.layers()[0].name, DEFAULT_LAYER);
+  assert!(!layers.layers()[0].clear_depth);
+  assert_eq!(layers.queued_count(), 0);
+}
+
+
+#[test]
+fn layers_keep_the_order_they_were_added() {
+  let mut layers = RenderLayers::new()
+    .with_layer("gizmos", true)
+    .with_layer("ui", true);
+  let names: Vec<&str> = layers.layers().iter().map(|layer| layer.name.as_str()).collect();
+  assert_eq!(names, vec![DEFAULT_LAYER, "gizmos", "ui"]);
+  assert!(layers.has_layer("gizmos"));
+  assert!(!layers.has_layer("overlay"));
+
+  layers.layer_mut("ui").expect("Could not find ui layer.").scissor = Some([0, 0, 10, 10]);
+  assert_eq!(layers.layers()[2].scissor, Some([0, 0, 10, 10]));
+}
+
+
+#[test]
+#[should_panic]
+fn duplicate_layer_names_panic() {
+  let _ = RenderLayers::new().with_layer(DEFAULT_LAYER, true);
+}
+
+
+#[test]
+fn draws_are_queued_by_layer() {
+  let mut layers = RenderLayers::new().with_layer("ui", true);
+  layers.draw(DEFAULT_LAYER, |_| {});
+  layers.draw("ui", |_| {});
+  layers.draw("ui", |_| {});
+  assert_eq!(layers.queued_count(), 3);
+}
+
+
+#[test]
+#[should_panic]
+fn drawing_on_an_unknown_layer_panics() {
+  RenderLayers::new().draw("ui", |_| {});
+}
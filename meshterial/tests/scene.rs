@@ -0,0 +1,93 @@
+//! Traversing scene node hierarchies, which doesn't need a device.
+use meshterial::scene::{Node, Scene};
+use nalgebra::{Matrix4, Point3, Vector3};
+
+
+fn approx_eq(a: &Matrix4<f32>, b: &Matrix4<f32>) -> bool {
+  (a - b).iter().all(|x| x.abs() < 1.0e-5)
+}
+
+
+fn translation(x: f32, y: f32, z: f32) -> Matrix4<f32> {
+  Matrix4::new_translation(&Vector3::new(x, y, z))
+}
+
+
+/// A tank with a turret with a barrel, next to a tree.
+fn scene() -> Scene<&'static str> {
+  let turn = Matrix4::from_scaled_axis(Vector3::y() * ::std::f32::consts::FRAC_PI_2);
+  let barrel = Node::new(translation(0.0, 0.0, -2.0) * Matrix4::new_scaling(0.5)).with_item("barrel");
+  let turret = Node::new(translation(0.0, 1.0, 0.0) * turn).with_item("turret").with_child(barrel);
+  let tank = Node::new(translation(10.0, 0.0, 0.0))
+    .with_item("tank")
+    .with_child(turret)
+    .with_child(Node::new(translation(0.0, 0.0, 3.0)).with_item("tracks"));
+  let tree = Node::new(translation(-5.0, 0.0, 0.0))
+    // A group node without an item of its own.
+    .with_child(Node::new(translation(0.0, 4.0, 0.0)).with_item("leaves"));
+  Scene::new().with_node(tank).with_node(tree)
+}
+
+
+#[test]
+fn world_transforms_accumulate_through_parents() {
+  let items = scene().items();
+  let world = |name: &str| {
+    items
+      .iter()
+      .find(|(item, _)| **item == name)
+      .map(|(_, world)| *world)
+      .expect("Item should be in the scene.")
+  };
+  let turn = Matrix4::from_scaled_axis(Vector3::y() * ::std::f32::consts::FRAC_PI_2);
+
+  assert!(approx_eq(&world("tank"), &translation(10.0, 0.0, 0.0)));
+  assert!(approx_eq(&world("turret"), &(translation(10.0, 1.0, 0.0) * turn)));
+  assert!(approx_eq(&world("leaves"), &translation(-5.0, 4.0, 0.0)));
+
+  // The barrel is two levels down, so it is moved by the tank, turned by the
+  // turret and then scaled and moved by its own transform.
+  let barrel = world("barrel");
+  let expected = translation(10.0, 0.0, 0.0)
+    * translation(0.0, 1.0, 0.0)
+    * turn
+    * translation(0.0, 0.0, -2.0)
+    * Matrix4::new_scaling(0.5);
+  assert!(approx_eq(&barrel, &expected));
+  // The turret's quarter turn points the barrel's -z offset along -x.
+  let origin = barrel.transform_point(&Point3::origin());
+  assert!((origin - Point3::new(8.0, 1.0, 0.0)).norm() < 1.0e-5, "{:?}", origin);
+  let tip = barrel.transform_point(&Point3::new(0.0, 0.0, -1.0));
+  assert!((tip - Point3::new(7.5, 1.0, 0.0)).norm() < 1.0e-5, "{:?}", tip);
+}
+
+
+#[test]
+fn items_are_in_draw_order() {
+  // Depth first, parents before children, in the order they were added.
+  let names: Vec<&str> = scene().items().into_iter().map(|(item, _)| *item).collect();
+  assert_eq!(names, vec!["tank", "turret", "barrel", "tracks", "leaves"]);
+}
+
+
+#[test]
+fn visit_includes_nodes_without_items() {
+  let mut visited = vec![];
+  scene().visit(|node, world| {
+    visited.push((node.item, world.transform_point(&Point3::origin())));
+  });
+  let names: Vec<Option<&str>> = visited.iter().map(|(item, _)| *item).collect();
+  assert_eq!(
+    names,
+    vec![Some("tank"), Some("turret"), Some("barrel"), Some("tracks"), None, Some("leaves")]
+  );
+  let (_, tree_origin) = visited[4];
+  assert!((tree_origin - Point3::new(-5.0, 0.0, 0.0)).norm() < 1.0e-5);
+}
+
+
+#[test]
+fn empty_scene_has_no_items() {
+  let scene: Scene<&str> = Scene::new();
+  assert!(scene.items().is_empty());
+}
@@ -0,0 +1,97 @@
+//! Clamping scissor rects to the framebuffer and drawing through them.
+//! Without a vulkan device the drawing test prints a message and passes.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use nalgebra::Matrix4;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+
+use meshterial::clamp_scissor;
+use meshterial::pipelines::color3d::{self, Color3DPipeline, VertexColor3};
+
+use common::Headless;
+
+
+fn scissor(rect: [i32; 4], dimensions: [u32; 2]) -> ([i32; 2], [u32; 2]) {
+  let scissor = clamp_scissor(rect, dimensions);
+  (scissor.origin, scissor.dimensions)
+}
+
+
+#[test]
+fn rects_inside_the_framebuffer_are_unchanged() {
+  assert_eq!(scissor([4, 8, 16, 32], [64, 64]), ([4, 8], [16, 32]));
+  assert_eq!(scissor([0, 0, 64, 64], [64, 64]), ([0, 0], [64, 64]));
+}
+
+
+#[test]
+fn negative_origins_shrink_the_extent() {
+  assert_eq!(scissor([-8, 0, 24, 16], [64, 64]), ([0, 0], [16, 16]));
+  assert_eq!(scissor([4, -10, 8, 30], [64, 64]), ([4, 0], [8, 20]));
+  // Entirely above and to the left.
+  assert_eq!(scissor([-20, -20, 10, 10], [64, 64]), ([0, 0], [0, 0]));
+}
+
+
+#[test]
+fn extents_are_clamped_to_the_framebuffer() {
+  assert_eq!(scissor([48, 60, 32, 32], [64, 64]), ([48, 60], [16, 4]));
+  assert_eq!(scissor([-8, -8, 100, 100], [64, 64]), ([0, 0], [64, 64]));
+  // Entirely below and to the right.
+  assert_eq!(scissor([70, 80, 10, 10], [64, 64]), ([64, 64], [0, 0]));
+  assert_eq!(scissor([4, 4, -10, 8], [64, 64]), ([4, 4], [0, 8]));
+}
+
+
+#[test]
+fn draws_only_inside_the_scissor() {
+  let headless = match Headless::new([32, 32]) {
+    Some(headless) => headless,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let pipeline = Color3DPipeline::new(
+    headless.render_pass.clone(),
+    headless.device.clone(),
+    headless.physical()
+  );
+  headless.upload(Matrix4::identity(), pipeline.proj_uniform_device_buffer.clone());
+  let vertex = |x, y| VertexColor3 { position: [x, y, 0.5], color: [1.0, 0.0, 0.0, 1.0] };
+  let quad = CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    vec![
+      vertex(-1.0, 1.0), vertex(1.0, 1.0), vertex(-1.0, -1.0),
+      vertex(-1.0, -1.0), vertex(1.0, 1.0), vertex(1.0, -1.0),
+    ].into_iter()
+  ).expect("Could not create vertex buffer.");
+
+  let image = headless.render([0.0, 0.0, 0.0, 1.0], |cmds, dynamic_state| {
+    // Hangs off the top left, so only [0, 0, 16, 16] is drawn.
+    let mut dynamic_state = dynamic_state.clone();
+    dynamic_state.scissors = Some(vec![clamp_scissor([-8, -8, 24, 24], headless.dimensions)]);
+    cmds
+      .draw(
+        pipeline.pipeline.clone(),
+        &dynamic_state,
+        vec![quad],
+        pipeline.proj_desc_set.clone(),
+        color3d::vs::ty::ModelView {
+          model: Matrix4::identity().into(),
+          view: Matrix4::identity().into(),
+        }
+      ).expect("Could not draw quad.")
+  });
+
+  for (x, y) in [(0, 0), (8, 8), (15, 15)].iter() {
+    assert_eq!(image.get_pixel(*x, *y).data, [255, 0, 0, 255], "({}, {}) should be drawn", x, y);
+  }
+  for (x, y) in [(16, 0), (0, 16), (16, 16), (31, 31)].iter() {
+    assert_eq!(image.get_pixel(*x, *y).data, [0, 0, 0, 255], "({}, {}) should be cleared", x, y);
+  }
+}
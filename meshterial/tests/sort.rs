@@ -0,0 +1,22 @@
+//! Sorting for transparency, which doesn't need a device.
+use meshterial::Camera;
+use meshterial::utils::sort_back_to_front;
+use nalgebra::Vector3;
+
+
+#[test]
+fn sorts_farthest_first() {
+  let camera = Camera {
+    position: Vector3::new(0.0, 0.0, 10.0),
+    .. Camera::default()
+  };
+  let mut positions = vec![
+    Vector3::new(0.0, 0.0, 5.0),
+    Vector3::new(1.0, 0.0, -20.0),
+    Vector3::new(0.0, 2.0, 0.0),
+  ];
+  sort_back_to_front(&mut positions, &camera.view(), |p| *p);
+  assert_eq!(positions[0].z, -20.0);
+  assert_eq!(positions[1].z, 0.0);
+  assert_eq!(positions[2].z, 5.0);
+}
@@ -0,0 +1,60 @@
+//! Mesh statistics, which are computed on the CPU.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use meshterial::loaders::ply::{self, Ply};
+use meshterial::MeshStats;
+use nalgebra::Vector3;
+
+
+#[test]
+fn cube_stats() {
+  let stats = MeshStats::from_triangle_list(&common::cube(), 1);
+  assert_eq!(stats.vertex_count, 36);
+  assert_eq!(stats.triangle_count, 12);
+  assert_eq!(stats.material_count, 1);
+  let bounds = stats.bounds.expect("A cube has bounds.");
+  assert_eq!(bounds.min, Vector3::new(-0.5, -0.5, -0.5));
+  assert_eq!(bounds.max, Vector3::new(0.5, 0.5, 0.5));
+  assert_eq!(
+    stats.to_string(),
+    "12 tris, 36 vertices, 1 materials, bounds [-0.5, -0.5, -0.5] to [0.5, 0.5, 0.5]"
+  );
+}
+
+
+#[test]
+fn combined_stats_add_up() {
+  let cube = MeshStats::from_triangle_list(&common::cube(), 1);
+  let total = (0 .. 1000).fold(MeshStats::default(), |total, _| total.combine(&cube));
+  assert_eq!(total.triangle_count, 12_000);
+  assert_eq!(total.material_count, 1000);
+  assert_eq!(total.bounds, cube.bounds);
+  assert!(total.to_string().starts_with("12,000 tris, 36,000 vertices, 1000 materials"));
+}
+
+
+#[test]
+fn empty_stats_have_no_bounds() {
+  let stats = MeshStats::from_triangle_list::<meshterial::pipelines::phong::VertexPhong>(&[], 0);
+  assert_eq!(stats, MeshStats::default());
+  assert!(stats.to_string().ends_with("no bounds"));
+}
+
+
+#[test]
+fn ply_points_have_no_triangles() {
+  let bytes = b"ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n1 2 3\n";
+  let loaded = ply::parse(bytes).expect("Could not parse ply.");
+  match loaded {
+    Ply::Points(_) => {}
+    Ply::Mesh(_) => panic!("Expected points."),
+  }
+  let stats = loaded.stats();
+  assert_eq!(stats.vertex_count, 2);
+  assert_eq!(stats.triangle_count, 0);
+  assert_eq!(stats.material_count, 0);
+  assert_eq!(stats.bounds.map(|b| b.max), Some(Vector3::new(1.0, 2.0, 3.0)));
+}
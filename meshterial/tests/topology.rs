@@ -0,0 +1,185 @@
+//! Triangle strips and fans, compared against the same triangles drawn as a
+//! list. Without a vulkan device the tests print a message and pass.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use nalgebra::Matrix4;
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+
+use meshterial::pipelines::color3d::{self, Color3DPipeline, VertexColor3};
+use meshterial::pipelines::{PipelineOptions, Topology, PRIMITIVE_RESTART_INDEX};
+
+use std::sync::Arc;
+
+use common::Headless;
+
+
+const DIMENSIONS: [u32; 2] = [64, 64];
+
+
+fn headless() -> Option<Headless> {
+  let headless = Headless::new(DIMENSIONS);
+  if headless.is_none() {
+    println!("No vulkan device available, skipping.");
+  }
+  headless
+}
+
+
+fn vertex(x: f32, y: f32) -> VertexColor3 {
+  VertexColor3 { position: [x, y, 0.5], color: [1.0, 1.0, 1.0, 1.0] }
+}
+
+
+/// The corners of a quad in strip order, wound like the color3d golden
+/// triangle so that both of the strip's triangles face front.
+fn quad(x: f32, y: f32, size: f32) -> Vec<VertexColor3> {
+  vec![
+    vertex(x, y + size),
+    vertex(x + size, y + size),
+    vertex(x, y),
+    vertex(x + size, y),
+  ]
+}
+
+
+/// The two triangles that a strip of `quad` makes, as a list.
+fn quad_list(x: f32, y: f32, size: f32) -> Vec<VertexColor3> {
+  let q = quad(x, y, size);
+  vec![
+    q[0].clone(), q[1].clone(), q[2].clone(),
+    q[2].clone(), q[1].clone(), q[3].clone(),
+  ]
+}
+
+
+fn pipeline(headless: &Headless, topology: Topology) -> Color3DPipeline {
+  let pipeline = Color3DPipeline::new_with_options(
+    headless.render_pass.clone(),
+    headless.device.clone(),
+    headless.physical(),
+    &PipelineOptions { topology, ..Default::default() }
+  );
+  headless.upload(Matrix4::identity(), pipeline.proj_uniform_device_buffer.clone());
+  pipeline
+}
+
+
+fn render(
+  headless: &Headless,
+  pipeline: &Color3DPipeline,
+  vertices: Vec<VertexColor3>,
+  indices: Option<Vec<u32>>
+) -> image::RgbaImage {
+  let vertices = CpuAccessibleBuffer::from_iter(
+    headless.device.clone(),
+    BufferUsage::all(),
+    vertices.into_iter()
+  ).expect("Could not create vertex buffer.");
+  let indices = indices.map(|indices| {
+    CpuAccessibleBuffer::from_iter(
+      headless.device.clone(),
+      BufferUsage::all(),
+      indices.into_iter()
+    ).expect("Could not create index buffer.")
+  });
+  let push_constants = color3d::vs::ty::ModelView {
+    model: Matrix4::identity().into(),
+    view: Matrix4::identity().into(),
+  };
+  headless.render([0.0, 0.0, 0.0, 1.0], |cmds, dynamic_state| {
+    let vertices = vec![vertices as Arc<BufferAccess + Send + Sync>];
+    match indices {
+      Some(indices) => cmds
+        .draw_indexed(
+          pipeline.pipeline.clone(),
+          dynamic_state,
+          vertices,
+          indices,
+          pipeline.proj_desc_set.clone(),
+          push_constants
+        ).expect("Could not draw indexed."),
+      None => cmds
+        .draw(
+          pipeline.pipeline.clone(),
+          dynamic_state,
+          vertices,
+          pipeline.proj_desc_set.clone(),
+          push_constants
+        ).expect("Could not draw."),
+    }
+  })
+}
+
+
+fn lit_pixels(image: &image::RgbaImage) -> usize {
+  image.pixels().filter(|p| p[0] > 0).count()
+}
+
+
+#[test]
+fn strip_quad_covers_the_same_area_as_a_list() {
+  let headless = match headless() {
+    Some(headless) => headless,
+    None => return,
+  };
+  let strip = pipeline(&headless, Topology::TriangleStrip);
+  let list = pipeline(&headless, Topology::TriangleList);
+
+  let from_strip = render(&headless, &strip, quad(-0.5, -0.5, 1.0), None);
+  let from_list = render(&headless, &list, quad_list(-0.5, -0.5, 1.0), None);
+  // A quad covering half the width and height covers a quarter of the image.
+  let quarter = (DIMENSIONS[0] * DIMENSIONS[1] / 4) as usize;
+  assert_eq!(lit_pixels(&from_strip), quarter);
+  assert!(from_strip == from_list);
+}
+
+
+#[test]
+fn primitive_restart_separates_strips() {
+  let headless = match headless() {
+    Some(headless) => headless,
+    None => return,
+  };
+  let strip = pipeline(&headless, Topology::TriangleStrip);
+  let list = pipeline(&headless, Topology::TriangleList);
+
+  let mut vertices = quad(-0.75, -0.75, 0.5);
+  vertices.extend(quad(0.25, 0.25, 0.5));
+  let indices = vec![0, 1, 2, 3, PRIMITIVE_RESTART_INDEX, 4, 5, 6, 7];
+  let from_strips = render(&headless, &strip, vertices, Some(indices));
+
+  let mut triangles = quad_list(-0.75, -0.75, 0.5);
+  triangles.extend(quad_list(0.25, 0.25, 0.5));
+  let from_list = render(&headless, &list, triangles, None);
+  // Without the restart the strip would also fill the gap between the quads.
+  let sixteenth = (DIMENSIONS[0] * DIMENSIONS[1] / 16) as usize;
+  assert_eq!(lit_pixels(&from_strips), sixteenth * 2);
+  assert!(from_strips == from_list);
+}
+
+
+#[test]
+fn fan_quad_covers_the_same_area_as_a_list() {
+  let headless = match headless() {
+    Some(headless) => headless,
+    None => return,
+  };
+  let fan = pipeline(&headless, Topology::TriangleFan);
+  let list = pipeline(&headless, Topology::TriangleList);
+
+  // A fan around the first corner makes the same two triangles as the strip
+  // would, from the first, second, fourth then third corners.
+  let q = quad(-0.5, -0.5, 1.0);
+  let fan_vertices = vec![q[0].clone(), q[1].clone(), q[3].clone(), q[2].clone()];
+  let from_fan = render(&headless, &fan, fan_vertices, None);
+  let list_vertices = vec![
+    q[0].clone(), q[1].clone(), q[3].clone(),
+    q[0].clone(), q[3].clone(), q[2].clone(),
+  ];
+  let from_list = render(&headless, &list, list_vertices, None);
+  assert!(lit_pixels(&from_fan) > 0);
+  assert!(from_fan == from_list);
+}
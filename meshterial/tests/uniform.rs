@@ -0,0 +1,44 @@
+//! Reading uniforms back from the GPU. Without a vulkan device the test
+//! prints a message and passes.
+#[macro_use]
+extern crate vulkano;
+
+mod common;
+
+use vulkano::buffer::device_local::DeviceLocalBuffer;
+
+use meshterial::pipelines::phong::Light;
+use meshterial::pipelines::uniform::{read_back_buffer, uniform_buffer_usage};
+
+use std::sync::Arc;
+
+use common::Headless;
+
+
+#[test]
+fn uploaded_light_is_read_back() {
+  let headless = match Headless::new([1, 1]) {
+    Some(headless) => headless,
+    None => {
+      println!("No vulkan device available, skipping.");
+      return;
+    }
+  };
+  let buffer: Arc<DeviceLocalBuffer<Light>> = DeviceLocalBuffer::new(
+    headless.device.clone(),
+    uniform_buffer_usage(),
+    headless.physical().queue_families()
+  ).expect("Could not create light buffer.");
+  headless.upload(
+    Light {
+      position: [1.0, 2.0, 3.0],
+      _dummy0: [0, 0, 0, 0],
+      intensity: [0.5, 0.25, 0.125],
+    },
+    buffer.clone()
+  );
+
+  let light = read_back_buffer(headless.device.clone(), headless.queue.clone(), buffer);
+  assert_eq!(light.position, [1.0, 2.0, 3.0]);
+  assert_eq!(light.intensity, [0.5, 0.25, 0.125]);
+}